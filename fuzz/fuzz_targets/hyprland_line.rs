@@ -0,0 +1,12 @@
+//! Whatever a read off `.socket2.sock` holds, split into lines like the listener does.
+
+#![no_main]
+
+use hyoka_core::hyprland;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    for line in data.split(|&x| x == b'\n') {
+        let _ = hyprland::parse_line(line);
+    }
+});
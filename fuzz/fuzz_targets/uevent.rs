@@ -0,0 +1,12 @@
+//! A datagram off the uevent socket, possibly cut short by the buffer.
+
+#![no_main]
+
+use hyoka_core::uevent::Message;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Some(message) = Message::parse(data) {
+        for _ in message.properties() {}
+    }
+});
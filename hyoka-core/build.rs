@@ -2,7 +2,8 @@ use std::{env, fs, path::PathBuf, process::Command};
 
 fn main() {
     let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
-    let mut headers = vec![];
+    // from the system, cursors come from the theme with it where cursor shape is missing
+    let mut headers = vec!["wayland-cursor.h".to_string()];
     let mut sources = vec![];
     for entry in fs::read_dir("protocols").unwrap() {
         let path = entry.unwrap().path();
@@ -62,5 +63,6 @@ fn main() {
 
     println!("cargo:rerun-if-changed=build.rs");
     println!("cargo:rustc-link-lib=dylib=wayland-client");
+    println!("cargo:rustc-link-lib=dylib=wayland-cursor");
     println!("cargo:rustc-link-lib=static=extern");
 }
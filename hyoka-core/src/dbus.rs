@@ -0,0 +1,415 @@
+use std::{
+    borrow::Cow,
+    cell::RefCell,
+    env,
+    ffi::OsStr,
+    fmt::{self, Debug},
+    io,
+    path::Path,
+    rc::Rc,
+    result,
+    time::Duration,
+};
+
+use compio::{
+    io::{AsyncRead as _, AsyncWrite as _},
+    net::UnixStream,
+};
+use dbus::{
+    self, Flags, MessageIterator, MessageType, OwnedMessage, Serial, authentication::Io,
+    marshal::Marshal, signature::MultiSignature, unmarshal,
+};
+use thiserror::Error;
+
+pub use cookie::*;
+pub use method::*;
+pub use property::*;
+pub use subscription::*;
+
+/// A message as read off the bus.
+pub type Raw = OwnedMessage<Box<[u8]>>;
+type Return = Result<Raw>;
+
+/// One bus connection, cheap to clone, every clone writes to the same socket.
+///
+/// Nothing is read unless [`Connection::serve`] runs, replies, signals and method calls all
+/// come in through it.
+#[derive(Clone)]
+pub struct Connection {
+    stream: UnixStream,
+    serial: Rc<RefCell<Serial>>,
+    cookie: Cookie,
+    subscriptions: Subscriptions,
+    objects: Objects,
+}
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("address not found")]
+    AddrNotFound,
+    #[error("failed to parse address")]
+    FailedParseAddr,
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    Authentication(#[from] dbus::authentication::Error<io::Error>),
+    #[error(transparent)]
+    Unmarshal(#[from] dbus::unmarshal::Error),
+    #[error("{name}{}", if let Some(desc) = desc { ": {desc}" } else { "" })]
+    ErrorMessage {
+        name: Box<dbus::String>,
+        desc: Option<Box<dbus::String>>,
+    },
+    #[error("deadline has elapsed")]
+    Elapsed,
+}
+
+pub type Result<T> = result::Result<T, Error>;
+
+fn address(addr: &[u8]) -> Option<Cow<'_, [u8]>> {
+    for addr in addr.get("unix:".len()..)?.split(|&x| x == b',') {
+        if let Some((k, v)) = addr.split_once(|&x| x == b'=') {
+            match k {
+                b"path" => return Some(v.into()),
+                b"abstract" => {
+                    return Some({
+                        let mut res = Vec::with_capacity(v.len() + 1);
+                        res.push(0);
+                        res.extend_from_slice(v);
+                        res.into()
+                    });
+                }
+                _ => continue,
+            }
+        }
+    }
+    None
+}
+
+async fn connect(addr: &[u8]) -> Result<UnixStream> {
+    let addr = address(addr).ok_or(Error::FailedParseAddr)?;
+    let path = Path::new(unsafe { OsStr::from_encoded_bytes_unchecked(addr.as_ref()) });
+    Ok(UnixStream::connect(path).await?)
+}
+
+impl dbus::authentication::Io for Connection {
+    type Error = io::Error;
+
+    #[allow(refining_impl_trait)]
+    async fn read(&mut self) -> result::Result<impl AsRef<[u8]> + 'static, Self::Error> {
+        let buf = Vec::with_capacity(4096);
+        let (n, buf) = self.stream.read(buf).await?;
+        if n == 0 {
+            Err(io::Error::new(io::ErrorKind::BrokenPipe, "peer closed"))?
+        }
+        Ok(buf)
+    }
+    async fn write(&mut self, data: impl AsRef<[u8]> + 'static) -> result::Result<(), Self::Error> {
+        struct Buf<T>(T);
+
+        impl<T: AsRef<[u8]> + 'static> compio::buf::IoBuf for Buf<T> {
+            fn as_init(&self) -> &[u8] {
+                self.0.as_ref()
+            }
+        }
+        let _ = self.stream.write(Buf(data)).await?;
+        Ok(())
+    }
+}
+
+pub const DBUS: dbus::Proxy = dbus::Proxy {
+    name: "org.freedesktop.DBus".into(),
+    path: "/org/freedesktop/DBus".into(),
+    interface: "org.freedesktop.DBus".into(),
+};
+
+impl Connection {
+    /// At `$DBUS_SESSION_BUS_ADDRESS`.
+    pub async fn session() -> Result<Self> {
+        let addr = env::var_os("DBUS_SESSION_BUS_ADDRESS").ok_or(Error::AddrNotFound)?;
+        Self::at(addr.as_encoded_bytes()).await
+    }
+    /// At `$DBUS_SYSTEM_BUS_ADDRESS`, or the usual socket without it.
+    pub async fn system() -> Result<Self> {
+        let addr = env::var_os("DBUS_SYSTEM_BUS_ADDRESS");
+        let addr = addr
+            .as_ref()
+            .map(|x| x.as_encoded_bytes())
+            .unwrap_or(b"unix:path=/run/dbus/system_bus_socket");
+        Self::at(addr).await
+    }
+    /// At a bus address like `unix:path=/run/user/1000/bus`.
+    pub async fn at(addr: &[u8]) -> Result<Self> {
+        Self::new(connect(addr).await?).await
+    }
+    async fn new(stream: UnixStream) -> Result<Self> {
+        let mut this = Self {
+            stream,
+            serial: Default::default(),
+            cookie: Default::default(),
+            subscriptions: Default::default(),
+            objects: Default::default(),
+        };
+        this.authenticate().await?;
+        this.stream
+            .write(this.serial.borrow_mut().method_call(
+                Flags::empty().with_no_reply_expected(),
+                DBUS,
+                "Hello",
+                dbus::Empty,
+            ))
+            .await?;
+        Ok(this)
+    }
+    async fn authenticate(&mut self) -> Result<()> {
+        dbus::authentication::authenticate(self, rustix::process::getuid().as_raw()).await?;
+        Ok(())
+    }
+
+    /// The reply comes once [`Connection::serve`] reads it, or an error after a second.
+    pub async fn method_call<'a>(
+        &mut self,
+        proxy: dbus::Proxy<'_>,
+        member: impl Into<&'a dbus::String>,
+        arguments: impl Marshal + MultiSignature,
+    ) -> Result<Notifier> {
+        let member = member.into();
+        // closes with the reply, or when it is not awaited anymore
+        let span = tracing::debug_span!(
+            "dbus call",
+            name = ?show_bytes(proxy.name.as_bytes()),
+            interface = ?show_bytes(proxy.interface.as_bytes()),
+            member = ?show_bytes(member.as_bytes()),
+        );
+        let mut serial = self.serial.borrow_mut();
+        self.stream
+            .write(serial.method_call(Flags::empty(), proxy, member, arguments))
+            .await?;
+        Ok(self
+            .cookie
+            .wait(serial.clone(), Duration::from_secs(1), span))
+    }
+
+    pub async fn get_property<'a>(
+        &mut self,
+        proxy: dbus::Proxy<'_>,
+        prop: impl Into<&'a dbus::String>,
+    ) -> Result<Notifier> {
+        self.method_call(
+            dbus::Proxy {
+                interface: "org.freedesktop.DBus.Properties".into(),
+                ..proxy
+            },
+            "Get",
+            dbus::multiple_new!(proxy.interface, prop.into()),
+        )
+        .await
+    }
+
+    pub async fn method_call_silent<'a>(
+        &mut self,
+        proxy: dbus::Proxy<'_>,
+        member: impl Into<&'a dbus::String>,
+        arguments: impl Marshal + MultiSignature,
+    ) -> Result<()> {
+        self.stream
+            .write(self.serial.borrow_mut().method_call(
+                Flags::empty().with_no_reply_expected(),
+                proxy,
+                member,
+                arguments,
+            ))
+            .await?;
+        Ok(())
+    }
+
+    /// Installs `rule` on the bus and calls `handler` for every matching signal.
+    ///
+    /// Handlers run inside the read loop, so they must not wait for method replies themselves;
+    /// hand the work to a task of their own for that instead.
+    pub async fn subscribe<F, Fut>(&mut self, rule: MatchRule, handler: F) -> Result<SubscriptionId>
+    where
+        F: FnMut(Rc<Raw>) -> Fut + 'static,
+        Fut: Future<Output = ()> + 'static,
+    {
+        self.method_call_silent(DBUS, "AddMatch", rule.to_string().as_str())
+            .await?;
+        Ok(self.subscriptions.insert(rule, handler))
+    }
+
+    /// Calls `handler` whenever `name` gets a new owner or loses it.
+    pub async fn track_name(
+        &mut self,
+        name: &str,
+        mut handler: impl FnMut(NameOwner) + 'static,
+    ) -> Result<SubscriptionId> {
+        let tracked = name.to_owned();
+        self.subscribe(
+            MatchRule::signal()
+                .sender("org.freedesktop.DBus")
+                .interface("org.freedesktop.DBus")
+                .member("NameOwnerChanged")
+                .arg0(name),
+            move |msg| {
+                // arg0 is only filtered by the bus, other tracked names come through here too
+                if let Ok(dbus::multiple_match!(name, _old, new)) = msg
+                    .as_ref()
+                    .parse::<dbus::multiple_type!(&dbus::String, &dbus::String, &dbus::String)>()
+                    && name.as_bytes() == tracked.as_bytes()
+                {
+                    handler(if new.is_empty() {
+                        NameOwner::Vanished
+                    } else {
+                        NameOwner::Appeared
+                    })
+                }
+                async {}
+            },
+        )
+        .await
+    }
+
+    pub async fn unsubscribe(&mut self, id: SubscriptionId) -> Result<()> {
+        if let Some(rule) = self.subscriptions.remove(id) {
+            self.method_call_silent(DBUS, "RemoveMatch", rule.to_string().as_str())
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Emits a signal from `proxy`.
+    pub async fn signal<'a>(
+        &mut self,
+        proxy: dbus::Proxy<'_>,
+        member: impl Into<&'a dbus::String>,
+        arguments: impl Marshal + MultiSignature,
+    ) -> Result<()> {
+        self.stream
+            .write(self.serial.borrow_mut().signal(proxy, member, arguments))
+            .await?;
+        Ok(())
+    }
+
+    /// Answers `call`, from a handler passed to [`Connection::register`].
+    pub async fn reply(
+        &mut self,
+        call: &Raw,
+        arguments: impl Marshal + MultiSignature,
+    ) -> Result<()> {
+        self.stream
+            .write(
+                self.serial
+                    .borrow_mut()
+                    .method_return(&call.as_ref().header, arguments),
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Serves method calls on the object at `path` with `handler`, replacing any earlier one.
+    ///
+    /// Like signal handlers, it runs inside the read loop and must not wait for replies.
+    pub fn register<F, Fut>(&self, path: impl Into<String>, handler: F)
+    where
+        F: FnMut(Rc<Raw>) -> Fut + 'static,
+        Fut: Future<Output = Result<Call>> + 'static,
+    {
+        self.objects.insert(path.into(), handler);
+    }
+
+    pub fn unregister(&self, path: &str) {
+        self.objects.remove(path);
+    }
+
+    async fn method_called(&mut self, msg: Raw) -> Result<()> {
+        let msg = Rc::new(msg);
+        let (name, desc) = match self.objects.call(msg.clone()).await? {
+            Call::Answered => return Ok(()),
+            Call::UnknownMethod => ("org.freedesktop.DBus.Error.UnknownMethod", "Unknown method"),
+            Call::InvalidArgs => (
+                "org.freedesktop.DBus.Error.InvalidArgs",
+                "Invalid arguments",
+            ),
+        };
+        self.stream
+            .write(
+                self.serial
+                    .borrow_mut()
+                    .error(name, &msg.as_ref().header, desc),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn read_dispatch(&mut self) -> Result<()> {
+        let buf = self.read().await?;
+        for msg in MessageIterator::new(buf.as_ref()) {
+            let msg = msg?;
+            let fields = msg.header.fields;
+            match msg.header.message_type {
+                MessageType::MethodCall => self.method_called(msg.to_owned()).await?,
+                MessageType::MethodReturn => self.cookie.notify(
+                    Serial::from_raw(
+                        fields
+                            .reply_serial
+                            .ok_or(Error::Unmarshal(unmarshal::Error::InvalidHeader))?,
+                    ),
+                    Ok(msg.to_owned()),
+                ),
+                MessageType::Error => self.cookie.notify(
+                    Serial::from_raw(
+                        fields
+                            .reply_serial
+                            .ok_or(Error::Unmarshal(unmarshal::Error::InvalidHeader))?,
+                    ),
+                    Err(Error::ErrorMessage {
+                        name: fields
+                            .error_name
+                            .ok_or(unmarshal::Error::InvalidHeader)?
+                            .to_owned(),
+                        desc: msg.parse::<&dbus::String>().map(ToOwned::to_owned).ok(),
+                    }),
+                ),
+                MessageType::Signal => self.subscriptions.dispatch(msg.to_owned()).await,
+            }
+        }
+        Ok(())
+    }
+
+    /// Waits for the replies pending, cancelling them after `timeout` without any.
+    pub async fn sync(&mut self, timeout: Duration) -> Result<()> {
+        while !self.cookie.is_empty() {
+            match compio::time::timeout(timeout, self.read_dispatch()).await {
+                Ok(x) => x?,
+                Err(_) => self.cookie.cancel_all(),
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads until the connection breaks.
+    pub async fn serve(&mut self) -> Result<()> {
+        loop {
+            self.read_dispatch().await?;
+        }
+    }
+}
+
+fn show_bytes(xs: &[u8]) -> impl Debug {
+    fmt::from_fn(move |f| {
+        Ok(for &x in xs {
+            if x.is_ascii_graphic() {
+                write!(f, "{}", x as char)?;
+            } else {
+                write!(f, "\\{x}")?;
+            }
+        })
+    })
+}
+
+mod cookie;
+mod method;
+#[cfg(feature = "mock")]
+pub mod mock;
+mod property;
+mod subscription;
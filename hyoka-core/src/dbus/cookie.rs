@@ -8,12 +8,14 @@ use std::{
 
 use dbus::Serial;
 use rustc_hash::FxHashMap;
+use tracing::Span;
 
 #[derive(Default, Clone)]
 pub struct Cookie(Rc<RefCell<FxHashMap<Serial, Rc<Shared>>>>);
 
 impl Cookie {
-    pub fn wait(&self, serial: Serial, timeout: Duration) -> Notifier {
+    /// `span` is entered whenever the reply is polled for.
+    pub fn wait(&self, serial: Serial, timeout: Duration, span: Span) -> Notifier {
         let shared = Rc::new(Shared::new());
         self.0.borrow_mut().insert(serial.clone(), shared.clone());
         Notifier {
@@ -23,6 +25,7 @@ impl Cookie {
                 shared,
             },
             timeout,
+            span,
         }
     }
     pub fn notify(&self, serial: Serial, value: super::Return) {
@@ -123,13 +126,15 @@ impl NotifierInner {
 pub struct Notifier {
     inner: NotifierInner,
     timeout: Duration,
+    span: Span,
 }
 
 impl Future for Notifier {
     type Output = <NotifierInner as Future>::Output;
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        let duration = self.timeout;
-        pin::pin!(self.inner.timeout(duration)).poll(cx)
+        let this = &mut *self;
+        let _entered = this.span.enter();
+        pin::pin!(this.inner.timeout(this.timeout)).poll(cx)
     }
 }
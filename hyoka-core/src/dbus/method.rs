@@ -0,0 +1,53 @@
+use std::{cell::RefCell, rc::Rc};
+
+use futures::future::LocalBoxFuture;
+
+use super::{Raw, Result};
+
+/// How a handler passed to [`super::Connection::register`] dealt with a call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Call {
+    /// with [`super::Connection::reply`]
+    Answered,
+    /// answered with `org.freedesktop.DBus.Error.UnknownMethod`
+    UnknownMethod,
+    /// answered with `org.freedesktop.DBus.Error.InvalidArgs`
+    InvalidArgs,
+}
+
+type Handler = Box<dyn FnMut(Rc<Raw>) -> LocalBoxFuture<'static, Result<Call>>>;
+
+/// The objects served on a connection, by path.
+#[derive(Default, Clone)]
+pub struct Objects(Rc<RefCell<Vec<(String, Handler)>>>);
+
+impl Objects {
+    pub fn insert<F, Fut>(&self, path: String, mut handler: F)
+    where
+        F: FnMut(Rc<Raw>) -> Fut + 'static,
+        Fut: Future<Output = Result<Call>> + 'static,
+    {
+        self.remove(&path);
+        self.0
+            .borrow_mut()
+            .push((path, Box::new(move |msg| Box::pin(handler(msg)))));
+    }
+    pub fn remove(&self, path: &str) {
+        self.0.borrow_mut().retain(|(x, _)| x != path);
+    }
+    /// Calls to paths nobody serves are unknown methods.
+    pub async fn call(&self, msg: Rc<Raw>) -> Result<Call> {
+        let path = msg.as_ref().header.fields.path.map(|x| x.as_bytes());
+        // the handler may register objects itself while running
+        let future = self
+            .0
+            .borrow_mut()
+            .iter_mut()
+            .find(|(x, _)| Some(x.as_bytes()) == path)
+            .map(|(_, handler)| handler(msg.clone()));
+        match future {
+            Some(future) => future.await,
+            None => Ok(Call::UnknownMethod),
+        }
+    }
+}
@@ -0,0 +1,339 @@
+//! A bus for a single client, speaking the wire format itself, to test against a [`Connection`]
+//! without a session bus.
+//!
+//! Nothing is routed, whatever the test sends through a [`Peer`] is what the client reads, as if
+//! the bus and every other client were one.
+//!
+//! [`Connection`]: super::Connection
+
+use std::{env, fs, io, mem, path::PathBuf, process};
+
+use compio::{
+    BufResult,
+    io::{AsyncRead as _, AsyncWriteExt as _},
+    net::{UnixListener, UnixStream},
+};
+use dbus::{MessageIterator, unmarshal};
+
+use super::{Raw, Result};
+
+/// Listening in the temporary directory, until dropped.
+pub struct Bus {
+    listener: UnixListener,
+    path: PathBuf,
+}
+
+impl Bus {
+    /// `name` keeps the sockets of tests running at the same time apart.
+    pub async fn new(name: &str) -> io::Result<Self> {
+        let path = env::temp_dir().join(format!("hyoka-{name}-{}.sock", process::id()));
+        fs::remove_file(&path).ok();
+        let listener = UnixListener::bind(&path).await?;
+        Ok(Self { listener, path })
+    }
+    /// For [`super::Connection::at`].
+    pub fn address(&self) -> String {
+        format!("unix:path={}", self.path.display())
+    }
+    /// The next client, once it authenticated and said `Hello`.
+    pub async fn accept(&self) -> Result<Peer> {
+        let (stream, _) = self.listener.accept().await?;
+        let mut peer = Peer {
+            stream,
+            buf: vec![],
+            serial: 0,
+        };
+        peer.authenticate().await?;
+        let hello = peer.receive().await?;
+        assert_eq!(hello.member(), Some(&b"Hello"[..]), "expected Hello first");
+        Ok(peer)
+    }
+}
+
+impl Drop for Bus {
+    fn drop(&mut self) {
+        fs::remove_file(&self.path).ok();
+    }
+}
+
+/// The bus end of a client connection.
+pub struct Peer {
+    stream: UnixStream,
+    /// read but not taken yet
+    buf: Vec<u8>,
+    serial: u32,
+}
+
+/// A message from the client.
+pub struct Incoming {
+    pub serial: u32,
+    pub message: Raw,
+}
+
+impl Incoming {
+    pub fn path(&self) -> Option<&[u8]> {
+        let fields = self.message.as_ref().header.fields;
+        fields.path.map(|x| x.as_bytes())
+    }
+    pub fn interface(&self) -> Option<&[u8]> {
+        let fields = self.message.as_ref().header.fields;
+        fields.interface.map(|x| x.as_bytes())
+    }
+    pub fn member(&self) -> Option<&[u8]> {
+        let fields = self.message.as_ref().header.fields;
+        fields.member.map(|x| x.as_bytes())
+    }
+    pub fn error_name(&self) -> Option<&[u8]> {
+        let fields = self.message.as_ref().header.fields;
+        fields.error_name.map(|x| x.as_bytes())
+    }
+}
+
+impl Peer {
+    /// Answers the SASL handshake for `EXTERNAL`, whatever the credentials.
+    async fn authenticate(&mut self) -> Result<()> {
+        loop {
+            while let Some(end) = self.buf.windows(2).position(|x| x == b"\r\n") {
+                let line: Vec<u8> = self.buf.drain(..end + 2).collect();
+                // the client starts with a nul byte before its first command
+                let line = line.strip_prefix(b"\0").unwrap_or(&line);
+                let mut words = line[..line.len() - 2].split(|&x| x == b' ');
+                let reply: &[u8] = match (words.next(), words.next(), words.next()) {
+                    (Some(b"BEGIN"), ..) => return Ok(()),
+                    (Some(b"AUTH"), Some(b"EXTERNAL"), Some(_)) | (Some(b"DATA"), ..) => {
+                        b"OK 0123456789abcdef0123456789abcdef\r\n"
+                    }
+                    (Some(b"AUTH"), Some(b"EXTERNAL"), None) => b"DATA\r\n",
+                    (Some(b"AUTH"), ..) => b"REJECTED EXTERNAL\r\n",
+                    (Some(b"NEGOTIATE_UNIX_FD"), ..) => b"AGREE_UNIX_FD\r\n",
+                    _ => b"ERROR\r\n",
+                };
+                self.stream.write_all(reply.to_vec()).await.0?;
+            }
+            self.read().await?;
+        }
+    }
+    async fn read(&mut self) -> Result<()> {
+        let BufResult(n, buf) = self.stream.read(Vec::with_capacity(4096)).await;
+        if n? == 0 {
+            Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "client closed",
+            ))?
+        }
+        self.buf.extend_from_slice(&buf);
+        Ok(())
+    }
+    /// The next message the client sent, waiting for it.
+    pub async fn receive(&mut self) -> Result<Incoming> {
+        loop {
+            if let Some(len) = message_len(&self.buf)
+                && self.buf.len() >= len
+            {
+                let rest = self.buf.split_off(len);
+                let bytes = mem::replace(&mut self.buf, rest);
+                let serial = u32_at(&bytes, 8);
+                let message = MessageIterator::new(&bytes[..])
+                    .next()
+                    .ok_or(unmarshal::Error::InvalidHeader)??
+                    .to_owned();
+                return Ok(Incoming { serial, message });
+            }
+            self.read().await?;
+        }
+    }
+    /// Sends `message` to the client, returns its serial.
+    pub async fn send(&mut self, message: Message<'_>) -> Result<u32> {
+        self.serial += 1;
+        self.stream
+            .write_all(message.marshal(self.serial))
+            .await
+            .0?;
+        Ok(self.serial)
+    }
+}
+
+/// The size of the message at the start of `buf`, once its fixed header is in.
+fn message_len(buf: &[u8]) -> Option<usize> {
+    let header = buf.get(..16)?;
+    let fields = u32_at(header, 12) as usize;
+    let body = u32_at(header, 4) as usize;
+    Some((16 + fields).next_multiple_of(8) + body)
+}
+
+/// In the byte order the message starts with.
+fn u32_at(message: &[u8], offset: usize) -> u32 {
+    let bytes = message[offset..offset + 4].try_into().unwrap();
+    match message[0] {
+        b'B' => u32::from_be_bytes(bytes),
+        _ => u32::from_le_bytes(bytes),
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Arg<'a> {
+    Str(&'a str),
+    Path(&'a str),
+    U32(u32),
+    Bool(bool),
+}
+
+impl<'a> From<&'a str> for Arg<'a> {
+    fn from(value: &'a str) -> Self {
+        Self::Str(value)
+    }
+}
+
+impl From<u32> for Arg<'_> {
+    fn from(value: u32) -> Self {
+        Self::U32(value)
+    }
+}
+
+impl From<bool> for Arg<'_> {
+    fn from(value: bool) -> Self {
+        Self::Bool(value)
+    }
+}
+
+impl Arg<'_> {
+    fn signature(self) -> u8 {
+        match self {
+            Self::Str(_) => b's',
+            Self::Path(_) => b'o',
+            Self::U32(_) => b'u',
+            Self::Bool(_) => b'b',
+        }
+    }
+    /// Aligned from the start of `buf`, which has to be 8-aligned in the message.
+    fn marshal(self, buf: &mut Vec<u8>) {
+        buf.resize(buf.len().next_multiple_of(4), 0);
+        match self {
+            Self::Str(x) | Self::Path(x) => {
+                buf.extend((x.len() as u32).to_le_bytes());
+                buf.extend(x.as_bytes());
+                buf.push(0);
+            }
+            Self::U32(x) => buf.extend(x.to_le_bytes()),
+            Self::Bool(x) => buf.extend((x as u32).to_le_bytes()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Kind {
+    MethodCall = 1,
+    MethodReturn,
+    Error,
+    Signal,
+}
+
+/// A message for [`Peer::send`], little-endian with basic arguments only.
+#[derive(Debug, Clone)]
+pub struct Message<'a> {
+    kind: Kind,
+    path: Option<&'a str>,
+    interface: Option<&'a str>,
+    member: Option<&'a str>,
+    error_name: Option<&'a str>,
+    reply_serial: Option<u32>,
+    sender: Option<&'a str>,
+    args: Vec<Arg<'a>>,
+}
+
+impl<'a> Message<'a> {
+    fn new(kind: Kind) -> Self {
+        Self {
+            kind,
+            path: None,
+            interface: None,
+            member: None,
+            error_name: None,
+            reply_serial: None,
+            sender: None,
+            args: vec![],
+        }
+    }
+    pub fn method_call(path: &'a str, interface: &'a str, member: &'a str) -> Self {
+        Self {
+            path: Some(path),
+            interface: Some(interface),
+            member: Some(member),
+            ..Self::new(Kind::MethodCall)
+        }
+    }
+    pub fn signal(path: &'a str, interface: &'a str, member: &'a str) -> Self {
+        Self {
+            kind: Kind::Signal,
+            ..Self::method_call(path, interface, member)
+        }
+    }
+    pub fn method_return(call: &Incoming) -> Self {
+        Self {
+            reply_serial: Some(call.serial),
+            ..Self::new(Kind::MethodReturn)
+        }
+    }
+    pub fn error(call: &Incoming, name: &'a str, desc: &'a str) -> Self {
+        Self {
+            error_name: Some(name),
+            reply_serial: Some(call.serial),
+            args: vec![Arg::Str(desc)],
+            ..Self::new(Kind::Error)
+        }
+    }
+    /// The bus stamps messages with the unique name of who sent them.
+    pub fn sender(self, sender: &'a str) -> Self {
+        Self {
+            sender: Some(sender),
+            ..self
+        }
+    }
+    pub fn arg(mut self, arg: impl Into<Arg<'a>>) -> Self {
+        self.args.push(arg.into());
+        self
+    }
+
+    fn marshal(&self, serial: u32) -> Vec<u8> {
+        let mut body = vec![];
+        let mut signature = vec![];
+        for &arg in &self.args {
+            arg.marshal(&mut body);
+            signature.push(arg.signature());
+        }
+
+        // the array starts right after the fixed header, at 16
+        let mut fields = vec![];
+        let values = [
+            (1, self.path.map(Arg::Path)),
+            (2, self.interface.map(Arg::Str)),
+            (3, self.member.map(Arg::Str)),
+            (4, self.error_name.map(Arg::Str)),
+            (5, self.reply_serial.map(Arg::U32)),
+            (7, self.sender.map(Arg::Str)),
+        ];
+        let values = values
+            .into_iter()
+            .filter_map(|(code, value)| Some((code, value?)));
+        for (code, value) in values {
+            fields.resize(fields.len().next_multiple_of(8), 0);
+            fields.extend([code, 1, value.signature(), 0]);
+            value.marshal(&mut fields);
+        }
+        if !signature.is_empty() {
+            fields.resize(fields.len().next_multiple_of(8), 0);
+            fields.extend([8, 1, b'g', 0, signature.len() as u8]);
+            fields.extend(&signature);
+            fields.push(0);
+        }
+
+        let mut buf = vec![b'l', self.kind as u8, 0, 1];
+        buf.extend((body.len() as u32).to_le_bytes());
+        buf.extend(serial.to_le_bytes());
+        buf.extend((fields.len() as u32).to_le_bytes());
+        buf.extend(fields);
+        buf.resize(buf.len().next_multiple_of(8), 0);
+        buf.extend(body);
+        buf
+    }
+}
@@ -0,0 +1,123 @@
+use futures::{StreamExt as _, channel::mpsc};
+
+use super::{Connection, MatchRule, NameOwner, Raw, Result};
+
+/// A [`dbus::Proxy`] that owns its strings, so it can outlive the message it came from.
+#[derive(Debug, Clone)]
+pub struct OwnedProxy {
+    pub name: Box<dbus::String>,
+    pub path: Box<dbus::ObjectPath>,
+    pub interface: Box<dbus::String>,
+}
+
+impl OwnedProxy {
+    pub fn proxy(&self) -> dbus::Proxy<'_> {
+        dbus::Proxy {
+            name: &self.name,
+            path: &self.path,
+            interface: &self.interface,
+        }
+    }
+}
+
+impl From<dbus::Proxy<'_>> for OwnedProxy {
+    fn from(proxy: dbus::Proxy<'_>) -> Self {
+        Self {
+            name: proxy.name.to_owned(),
+            path: proxy.path.to_owned(),
+            interface: proxy.interface.to_owned(),
+        }
+    }
+}
+
+/// Property types that can be taken out of a `Get` reply.
+pub trait Property: Clone + PartialEq + 'static {
+    fn from_reply(reply: &Raw) -> Option<Self>;
+}
+
+impl Property for String {
+    fn from_reply(reply: &Raw) -> Option<Self> {
+        let value = reply
+            .as_ref()
+            .parse::<dbus::Variant<&dbus::String>>()
+            .ok()?;
+        Some(unsafe { String::from_utf8_unchecked(value.0.to_vec()) })
+    }
+}
+
+macro_rules! impl_property {
+    ($($ty:ty),*) => {
+        $(impl Property for $ty {
+            fn from_reply(reply: &Raw) -> Option<Self> {
+                Some(reply.as_ref().parse::<dbus::Variant<$ty>>().ok()?.0)
+            }
+        })*
+    };
+}
+
+impl_property!(bool, u8, i32, u32, i64, u64, f64);
+
+impl Connection {
+    /// `prop` as it is now, `None` if it cannot be read as a `T`.
+    pub async fn read_property<T: Property>(
+        &mut self,
+        proxy: dbus::Proxy<'_>,
+        prop: &str,
+    ) -> Option<T> {
+        let reply = self.get_property(proxy, prop).await.ok()?;
+        T::from_reply(&reply.await.ok()?)
+    }
+    /// Yields the current value of `prop` and then every change of it.
+    ///
+    /// `PropertiesChanged` only tells that something on the interface changed, the value is
+    /// fetched again and compared against the last one, so invalidated-only properties work too.
+    /// The same happens when a well-known `proxy.name` gets a new owner.
+    pub async fn watch_property<T: Property>(
+        &mut self,
+        proxy: impl Into<OwnedProxy>,
+        prop: &'static str,
+    ) -> Result<impl futures::Stream<Item = T> + use<T>> {
+        let proxy = proxy.into();
+        let (notifier, mut changes) = mpsc::unbounded();
+        // a restarted service starts over with fresh state
+        if !proxy.name.as_bytes().starts_with(b":") {
+            let notifier = notifier.clone();
+            self.track_name(&proxy.name.to_string(), move |owner| {
+                if owner == NameOwner::Appeared {
+                    notifier.unbounded_send(()).ok();
+                }
+            })
+            .await?;
+        }
+        self.subscribe(
+            MatchRule::signal()
+                .sender(proxy.name.to_string())
+                .path(proxy.path.to_string())
+                .interface("org.freedesktop.DBus.Properties")
+                .member("PropertiesChanged")
+                .arg0(proxy.interface.to_string()),
+            move |_| {
+                notifier.unbounded_send(()).ok();
+                async {}
+            },
+        )
+        .await?;
+        let mut connection = self.clone();
+        Ok(crate::stream(async gen move {
+            let mut cache = None;
+            loop {
+                if let Ok(reply) = connection.get_property(proxy.proxy(), prop).await
+                    && let Ok(reply) = reply.await
+                    && let Some(value) = T::from_reply(&reply)
+                    && cache.as_ref() != Some(&value)
+                {
+                    cache = Some(value.clone());
+                    yield value;
+                }
+                if changes.next().await.is_none() {
+                    return;
+                }
+            }
+        }))
+    }
+}
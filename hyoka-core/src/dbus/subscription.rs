@@ -0,0 +1,154 @@
+use std::{
+    cell::{Cell, RefCell},
+    fmt,
+    rc::Rc,
+};
+
+use futures::future::LocalBoxFuture;
+
+use super::Raw;
+
+/// A signal match rule, installed on the bus with `AddMatch`.
+///
+/// `sender` is only compared locally when it is a unique name, signals always carry the unique
+/// name of their sender. `arg0` is left to the bus.
+#[derive(Debug, Clone, Default)]
+pub struct MatchRule {
+    sender: Option<String>,
+    path: Option<String>,
+    interface: Option<String>,
+    member: Option<String>,
+    arg0: Option<String>,
+}
+
+impl MatchRule {
+    pub fn signal() -> Self {
+        Self::default()
+    }
+    pub fn sender(self, sender: impl Into<String>) -> Self {
+        Self {
+            sender: Some(sender.into()),
+            ..self
+        }
+    }
+    pub fn path(self, path: impl Into<String>) -> Self {
+        Self {
+            path: Some(path.into()),
+            ..self
+        }
+    }
+    pub fn interface(self, interface: impl Into<String>) -> Self {
+        Self {
+            interface: Some(interface.into()),
+            ..self
+        }
+    }
+    pub fn member(self, member: impl Into<String>) -> Self {
+        Self {
+            member: Some(member.into()),
+            ..self
+        }
+    }
+    pub fn arg0(self, arg0: impl Into<String>) -> Self {
+        Self {
+            arg0: Some(arg0.into()),
+            ..self
+        }
+    }
+    fn matches(&self, msg: &Raw) -> bool {
+        fn eq(rule: &Option<String>, value: Option<&[u8]>) -> bool {
+            match rule {
+                Some(rule) => value == Some(rule.as_bytes()),
+                None => true,
+            }
+        }
+        let fields = msg.as_ref().header.fields;
+        let sender = match &self.sender {
+            Some(x) if x.starts_with(':') => &self.sender,
+            _ => &None,
+        };
+        eq(sender, fields.sender.map(|x| x.as_bytes()))
+            && eq(&self.path, fields.path.map(|x| x.as_bytes()))
+            && eq(&self.interface, fields.interface.map(|x| x.as_bytes()))
+            && eq(&self.member, fields.member.map(|x| x.as_bytes()))
+    }
+}
+
+impl fmt::Display for MatchRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "type='signal'")?;
+        for (key, value) in [
+            ("sender", &self.sender),
+            ("path", &self.path),
+            ("interface", &self.interface),
+            ("member", &self.member),
+            ("arg0", &self.arg0),
+        ] {
+            if let Some(value) = value {
+                write!(f, ",{key}='{value}'")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SubscriptionId(u64);
+
+/// Ownership change of a bus name, see [`super::Connection::track_name`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NameOwner {
+    /// the name got an owner, either for the first time or after a restart
+    Appeared,
+    Vanished,
+}
+
+type Handler = Box<dyn FnMut(Rc<Raw>) -> LocalBoxFuture<'static, ()>>;
+
+struct Subscription {
+    id: SubscriptionId,
+    rule: MatchRule,
+    handler: Handler,
+}
+
+#[derive(Default, Clone)]
+pub struct Subscriptions {
+    inner: Rc<RefCell<Vec<Subscription>>>,
+    next_id: Rc<Cell<u64>>,
+}
+
+impl Subscriptions {
+    pub fn insert<F, Fut>(&self, rule: MatchRule, mut handler: F) -> SubscriptionId
+    where
+        F: FnMut(Rc<Raw>) -> Fut + 'static,
+        Fut: Future<Output = ()> + 'static,
+    {
+        let id = SubscriptionId(self.next_id.get());
+        self.next_id.set(id.0 + 1);
+        self.inner.borrow_mut().push(Subscription {
+            id,
+            rule,
+            handler: Box::new(move |msg| Box::pin(handler(msg))),
+        });
+        id
+    }
+    pub fn remove(&self, id: SubscriptionId) -> Option<MatchRule> {
+        let mut inner = self.inner.borrow_mut();
+        let idx = inner.iter().position(|x| x.id == id)?;
+        Some(inner.swap_remove(idx).rule)
+    }
+    pub async fn dispatch(&self, msg: Raw) {
+        let msg = Rc::new(msg);
+        // collect first, handlers may subscribe again while running
+        let futures: Vec<_> = self
+            .inner
+            .borrow_mut()
+            .iter_mut()
+            .filter(|x| x.rule.matches(&msg))
+            .map(|x| (x.handler)(msg.clone()))
+            .collect();
+        for future in futures {
+            future.await;
+        }
+    }
+}
@@ -0,0 +1,18 @@
+use std::{io, result};
+
+use thiserror::Error;
+
+use crate::dbus;
+
+/// Errors a module can stop with, the consumer restarts it afterwards.
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    Dbus(#[from] dbus::Error),
+    #[error("{0} closed the connection")]
+    Closed(&'static str),
+}
+
+pub type Result<T> = result::Result<T, Error>;
@@ -0,0 +1,464 @@
+use std::{
+    env, fmt,
+    io::{self, Write},
+    mem,
+    path::PathBuf,
+};
+
+use compio::{
+    BufResult,
+    buf::IoBuf,
+    io::{AsyncRead as _, AsyncReadExt, AsyncWrite as _},
+    net::UnixStream,
+};
+use serde::Deserialize;
+
+use crate::{
+    TinyString,
+    error::{Error, Result},
+    mapping::Mapping,
+    xdg_runtime_dir,
+};
+
+#[derive(Clone)]
+pub struct Context {
+    /// hyprland instance signature
+    his: PathBuf,
+}
+
+impl Context {
+    pub fn new() -> Option<Self> {
+        let mut dir = xdg_runtime_dir();
+        dir.push("hypr");
+        dir.push(env::var_os("HYPRLAND_INSTANCE_SIGNATURE")?);
+        let his = dir;
+        Some(Self { his })
+    }
+    /// The instance whose sockets are in `dir`, instead of the one the environment names.
+    pub fn at(dir: PathBuf) -> Self {
+        Self { his: dir }
+    }
+    /// A connection for a single command or request. Hyprland closes it after answering, which
+    /// is how the end of the answer is known, so it cannot be kept or pooled. Requests needed
+    /// together go in one `[[BATCH]]`.
+    pub async fn controller(&self) -> io::Result<Controller> {
+        Ok(Controller {
+            stream: UnixStream::connect(self.his.join(".socket.sock")).await?,
+        })
+    }
+    async fn listener(&self) -> io::Result<Listener> {
+        Ok(Listener {
+            stream: UnixStream::connect(self.his.join(".socket2.sock")).await?,
+            context: self.clone(),
+        })
+    }
+}
+
+pub struct Listener {
+    stream: UnixStream,
+    /// to look up what the events leave out
+    context: Context,
+}
+
+pub struct Controller {
+    stream: UnixStream,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Event {
+    /// all the workspaces there are, by id with their names
+    Workspaces(Vec<(usize, TinyString)>),
+    Workspace {
+        id: usize,
+    },
+    /// named after its id unless it was given a name
+    CreateWorkspace {
+        id: usize,
+        name: TinyString,
+    },
+    RenameWorkspace {
+        id: usize,
+        name: TinyString,
+    },
+    DestroyWorkspace {
+        id: usize,
+    },
+    ActiveWindow {
+        class: TinyString,
+        title: TinyString,
+    },
+    /// the focused window entered or left fullscreen
+    Fullscreen(bool),
+    /// the keybinding submap entered, empty once back to the default one
+    Submap(TinyString),
+    /// a window on this workspace asked for attention
+    Urgent {
+        id: usize,
+    },
+}
+
+/// An event line as it comes off the socket, before anything is looked up for it.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Line<'a> {
+    Event(Event),
+    /// workspaces move over to the remaining monitors, or back
+    MonitorChange,
+    /// by the window at this address, as hex without `0x`
+    Urgent(&'a str),
+}
+
+/// `EVENT>>DATA`, without the newline. `None` for events not followed and malformed lines.
+pub fn parse_line(line: &[u8]) -> Option<Line<'_>> {
+    /// `ID,NAME`
+    fn parse_workspace(body: &[u8]) -> Option<(usize, TinyString)> {
+        let (id, name) = body.split_once(|&x| x == b',')?;
+        Some((usize::from_ascii(id).ok()?, lossy(name)))
+    }
+    fn lossy(x: &[u8]) -> TinyString {
+        String::from_utf8_lossy(x).as_ref().into()
+    }
+
+    let idx = line.windows(2).position(|x| x == b">>")?;
+    let (event_type, event_body) = (&line[..idx], &line[idx + 2..]);
+    let event = match event_type {
+        b"workspacev2" => Event::Workspace {
+            id: parse_workspace(event_body)?.0,
+        },
+        b"createworkspacev2" => {
+            let (id, name) = parse_workspace(event_body)?;
+            Event::CreateWorkspace { id, name }
+        }
+        b"destroyworkspacev2" => Event::DestroyWorkspace {
+            id: parse_workspace(event_body)?.0,
+        },
+        b"renameworkspace" => {
+            let (id, name) = parse_workspace(event_body)?;
+            Event::RenameWorkspace { id, name }
+        }
+        b"activewindow" => {
+            let (class, title) = event_body.split_once(|&x| x == b',')?;
+            Event::ActiveWindow {
+                class: lossy(class),
+                title: lossy(title),
+            }
+        }
+        b"fullscreen" => Event::Fullscreen(event_body != b"0"),
+        b"submap" => Event::Submap(lossy(event_body)),
+        b"monitoradded" | b"monitorremoved" => return Some(Line::MonitorChange),
+        b"urgent" => return Some(Line::Urgent(str::from_utf8(event_body).ok()?)),
+        _ => return None,
+    };
+    Some(Line::Event(event))
+}
+
+/// Longer event lines are dropped rather than buffered.
+const MAX_LINE: usize = 1 << 16;
+
+impl Listener {
+    pub async fn listen(mut self, mut dispatch: impl AsyncFnMut(Event)) -> Result<()> {
+        let mut buffer = Mapping::page()?;
+        // reads end anywhere under load, the last line may only be complete after the next one
+        let mut pending = Vec::new();
+        // the start of a line too long was dropped, so is the rest of it
+        let mut overlong = false;
+        loop {
+            let BufResult(result, buf) = self.stream.read(buffer).await;
+            buffer = buf;
+            let n = result?;
+            if n == 0 {
+                return Err(Error::Closed("hyprland"));
+            }
+            pending.extend_from_slice(&buffer.as_bytes()[..n]);
+
+            let Some(end) = pending.iter().rposition(|&x| x == b'\n') else {
+                if pending.len() > MAX_LINE {
+                    tracing::warn!("dropping an event longer than {MAX_LINE} bytes");
+                    pending.clear();
+                    overlong = true;
+                }
+                continue;
+            };
+            let mut lines = pending[..end].split(|&x| x == b'\n');
+            if mem::take(&mut overlong) {
+                lines.next();
+            }
+            for line in lines {
+                match parse_line(line) {
+                    Some(Line::Event(e)) => dispatch(e).await,
+                    Some(Line::MonitorChange) => {
+                        if let Err(e) = sync(&self.context, &mut dispatch).await {
+                            tracing::warn!("cannot query hyprland: {e}");
+                        }
+                    }
+                    Some(Line::Urgent(address)) => {
+                        if let Some(id) = client_workspace(&self.context, address).await {
+                            dispatch(Event::Urgent { id }).await
+                        }
+                    }
+                    None => {}
+                }
+            }
+            pending.drain(..=end);
+        }
+    }
+}
+
+/// What `hyprctl dispatch` takes, the ones without arguments act on the focused window.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Dispatcher {
+    Workspace(u8),
+    /// by this many among the existing workspaces
+    RelativeWorkspace(i32),
+    KillActive,
+    ToggleFloating,
+    Fullscreen,
+    /// fullscreen keeping the gaps and bars
+    Maximize,
+    /// takes the focused window along
+    MoveToWorkspace(u8),
+    /// the window at the address, as hex without `0x`, staying on the current workspace
+    MoveWindowToWorkspace(u8, String),
+    /// the window at the address, as hex without `0x`, switching to its workspace
+    FocusWindow(String),
+    /// shell command, started by Hyprland with its window rules applied
+    Exec(String),
+    /// any other, like "movefocus l"
+    Raw(String),
+}
+
+impl fmt::Display for Dispatcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Dispatcher::Workspace(id) => write!(f, "workspace {id}"),
+            Dispatcher::RelativeWorkspace(offset) => write!(f, "workspace e{offset:+}"),
+            Dispatcher::KillActive => f.write_str("killactive"),
+            Dispatcher::ToggleFloating => f.write_str("togglefloating"),
+            Dispatcher::Fullscreen => f.write_str("fullscreen 0"),
+            Dispatcher::Maximize => f.write_str("fullscreen 1"),
+            Dispatcher::MoveToWorkspace(id) => write!(f, "movetoworkspace {id}"),
+            Dispatcher::MoveWindowToWorkspace(id, address) => {
+                write!(f, "movetoworkspacesilent {id},address:0x{address}")
+            }
+            Dispatcher::FocusWindow(address) => write!(f, "focuswindow address:0x{address}"),
+            Dispatcher::Exec(command) => write!(f, "exec {command}"),
+            Dispatcher::Raw(dispatcher) => f.write_str(dispatcher),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub enum Request {
+    ActiveWindow,
+    Clients,
+}
+
+#[derive(Debug)]
+#[must_use]
+pub enum Response {
+    Raw(String),
+}
+
+/// A mapped window, as listed by `clients`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Client {
+    /// as hex without `0x`
+    pub address: String,
+    pub class: TinyString,
+    pub title: String,
+    /// `None` on special workspaces, they have no id of ours
+    pub workspace: Option<usize>,
+}
+
+/// Where the focused window is, in logical pixels.
+#[derive(Debug)]
+pub struct Placement {
+    pub title: String,
+    pub monitor: String,
+    /// position relative to the monitor, then size
+    pub region: [i32; 4],
+}
+
+impl Controller {
+    /// similar to request, but requires no response.
+    pub async fn dispatch(mut self, dispatcher: Dispatcher) -> io::Result<()> {
+        let mut buf = Vec::with_capacity(64);
+        write!(&mut buf, "q/dispatch {dispatcher}")?;
+        self.stream.write(buf).await.0?;
+        Ok(())
+    }
+
+    pub async fn raw_request(mut self, msg: impl IoBuf) -> io::Result<String> {
+        self.stream.write(msg).await.0?;
+
+        let buf = Vec::with_capacity(1024);
+        let BufResult(res, buf) = self.stream.read_to_end(buf).await;
+        res?;
+        Ok(match String::from_utf8(buf) {
+            Ok(x) => x,
+            Err(e) => String::from_utf8_lossy(e.as_bytes()).into_owned(),
+        })
+    }
+
+    /// `None` without a focused window.
+    pub async fn active_window_placement(self) -> io::Result<Option<Placement>> {
+        let res = self.raw_request("[[BATCH]]activewindow;monitors").await?;
+        let mut res = res.split("\n\n\n\n\n");
+        let (Some(window), Some(monitors)) = (res.next(), res.next()) else {
+            return Ok(None);
+        };
+        Ok(parse_placement(window, monitors))
+    }
+
+    pub async fn clients(self) -> io::Result<Vec<Client>> {
+        let Response::Raw(clients) = self.request(Request::Clients).await?;
+        Ok(parse_clients(&clients))
+    }
+
+    pub async fn request(self, req: Request) -> io::Result<Response> {
+        let msg = match req {
+            Request::ActiveWindow => &b"activewindow"[..],
+            Request::Clients => b"clients",
+        };
+        let raw = self.raw_request(msg).await?;
+        Ok(Response::Raw(raw))
+    }
+}
+
+/// Connects to the event socket, sends the current state and then follows the events until the
+/// connection breaks.
+pub async fn run(context: &Context, mut dispatch: impl AsyncFnMut(Event)) -> Result<()> {
+    let listener = context.listener().await?;
+    sync(context, &mut dispatch).await?;
+    listener.listen(dispatch).await
+}
+
+/// Sends the current state, whatever the events said before.
+async fn sync(context: &Context, dispatch: &mut impl AsyncFnMut(Event)) -> io::Result<()> {
+    let init = context.controller().await?;
+    let res = init
+        .raw_request("[[BATCH]]workspaces;activeworkspace;activewindow")
+        .await?;
+    let mut res = res.split("\n\n\n\n\n");
+    if let Some(workspaces) = res.next() {
+        let workspaces = workspaces.split("\n\n").filter_map(|x| {
+            let id = parse_workspace_id(x)?;
+            // `workspace ID 3 (NAME) on monitor DP-1:`
+            let (_, name) = x.split_once(" (")?;
+            let (name, _) = name.split_once(") on monitor ")?;
+            Some((id, name.into()))
+        });
+        dispatch(Event::Workspaces(workspaces.collect())).await;
+    }
+    if let Some(active_workspace) = res.next() {
+        if let Some(id) = parse_workspace_id(active_workspace) {
+            dispatch(Event::Workspace { id }).await;
+        }
+    }
+    if let Some(active_window) = res.next() {
+        let mut required = 3;
+        let [mut class, mut title] = [TinyString::new(), TinyString::new()];
+        let mut fullscreen = false;
+        for line in active_window.split("\n") {
+            if line.starts_with("\tclass") {
+                if let Some(pos) = line.find(' ') {
+                    class = line[pos + 1..].into();
+                    required -= 1;
+                }
+            } else if line.starts_with("\ttitle") {
+                if let Some(pos) = line.find(' ') {
+                    title = line[pos + 1..].into();
+                    required -= 1;
+                }
+            } else if let Some(state) = line.strip_prefix("\tfullscreen: ") {
+                fullscreen = state != "0";
+                required -= 1;
+            }
+            if required == 0 {
+                break;
+            }
+        }
+        dispatch(Event::ActiveWindow { class, title }).await;
+        dispatch(Event::Fullscreen(fullscreen)).await;
+    }
+    Ok(())
+}
+
+pub fn parse_workspace_id(data: &str) -> Option<usize> {
+    let data = data.as_bytes();
+    let span = data.get(13..)?;
+    let pos = span.iter().position(|&x| x == b' ')?;
+    usize::from_ascii(&span[..pos]).ok()
+}
+
+/// The workspace of the window at `address`, as hex without `0x`. Looked up among all the
+/// clients, special workspaces have no id of ours.
+async fn client_workspace(context: &Context, address: &str) -> Option<usize> {
+    let clients = context
+        .controller()
+        .await
+        .ok()?
+        .clients()
+        .await
+        .inspect_err(|e| tracing::warn!("cannot query clients: {e}"))
+        .ok()?;
+    let client = clients.into_iter().find(|x| x.address == address)?;
+    client.workspace
+}
+
+/// The answer to `clients`, those missing a field are left out.
+pub fn parse_clients(clients: &str) -> Vec<Client> {
+    // `Window 55d0f3a1b0c0 -> title:` and its properties, one per line
+    clients
+        .split("\n\n")
+        .filter_map(|x| {
+            let header = x.trim_start_matches('\n').lines().next()?;
+            let (address, title) = header.strip_prefix("Window ")?.split_once(" -> ")?;
+            let field = |name: &str| {
+                x.lines()
+                    .find_map(|x| x.strip_prefix('\t')?.strip_prefix(name)?.strip_prefix(": "))
+            };
+            let workspace = field("workspace")?.split_once(' ')?.0;
+            Some(Client {
+                address: address.into(),
+                class: field("class")?.into(),
+                title: title.strip_suffix(':').unwrap_or(title).into(),
+                workspace: workspace.parse().ok(),
+            })
+        })
+        .collect()
+}
+
+fn parse_placement(window: &str, monitors: &str) -> Option<Placement> {
+    let field = |name: &str| {
+        window
+            .lines()
+            .find_map(|x| x.strip_prefix('\t')?.strip_prefix(name)?.strip_prefix(": "))
+    };
+    let pair = |s: &str, separator: char| -> Option<[i32; 2]> {
+        let (a, b) = s.split_once(separator)?;
+        Some([a.parse().ok()?, b.parse().ok()?])
+    };
+    // in the layout, as are the monitors
+    let [x, y] = pair(field("at")?, ',')?;
+    let [width, height] = pair(field("size")?, ',')?;
+    let monitor_id = field("monitor")?;
+    let (monitor, [left, top]) = monitors.split("\n\n").find_map(|x| {
+        // `Monitor DP-1 (ID 0):` followed by `\t2560x1440@143.99800 at 0x0`
+        let mut lines = x.lines();
+        let (name, id) = lines
+            .next()?
+            .strip_prefix("Monitor ")?
+            .split_once(" (ID ")?;
+        if id.strip_suffix("):")? != monitor_id {
+            return None;
+        }
+        let (_, at) = lines.next()?.split_once(" at ")?;
+        Some((name, pair(at, 'x')?))
+    })?;
+    Some(Placement {
+        title: field("title")?.into(),
+        monitor: monitor.into(),
+        region: [x - left, y - top, width, height],
+    })
+}
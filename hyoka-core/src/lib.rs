@@ -0,0 +1,57 @@
+//! The Wayland client, the session and system bus clients, the Hyprland IPC client and the
+//! kernel uevent parser the bar is built on, for other shell components to reuse.
+//!
+//! Everything here is single-threaded and meant to run on a compio runtime. Wayland events and
+//! module updates come out through `futures` channels or the `dispatch` callbacks passed in.
+
+#![feature(
+    allocator_api,
+    async_iterator,
+    const_convert,
+    const_trait_impl,
+    future_join,
+    gen_blocks,
+    int_from_ascii,
+    map_try_insert,
+    slice_split_once,
+    str_as_str
+)]
+
+use std::{async_iter::AsyncIterator, env, path::PathBuf};
+
+use smallstr::SmallString;
+
+/// Short names, class names and the like, kept inline.
+pub type TinyString = SmallString<[u8; 16]>;
+
+/// Adapts an `async gen` block to a [`futures::Stream`].
+pub fn stream<T: AsyncIterator>(iterator: T) -> impl futures::Stream<Item = T::Item> {
+    #[repr(transparent)]
+    struct Stream<T>(T);
+    impl<T: AsyncIterator> futures::Stream for Stream<T> {
+        type Item = <T as AsyncIterator>::Item;
+
+        fn poll_next(
+            self: std::pin::Pin<&mut Self>,
+            cx: &mut std::task::Context<'_>,
+        ) -> std::task::Poll<Option<Self::Item>> {
+            let inner: std::pin::Pin<&mut T> = unsafe { std::mem::transmute(self) };
+            AsyncIterator::poll_next(std::pin::pin!(inner), cx)
+        }
+    }
+    Stream(iterator)
+}
+
+/// `$XDG_RUNTIME_DIR`, where sockets go.
+pub fn xdg_runtime_dir() -> PathBuf {
+    env::var_os("XDG_RUNTIME_DIR")
+        .map(Into::into)
+        .unwrap_or_else(|| format!("/run/user/{}", rustix::process::getuid()).into())
+}
+
+pub mod dbus;
+pub mod error;
+pub mod hyprland;
+pub mod mapping;
+pub mod uevent;
+pub mod wayland;
@@ -0,0 +1,30 @@
+//! Kernel uevents, as read off a `NETLINK_KOBJECT_UEVENT` socket.
+
+/// `ACTION@DEVPATH` and the `KEY=VALUE` properties after it, all nul-terminated.
+#[derive(Debug, Clone, Copy)]
+pub struct Message<'a> {
+    pub header: &'a str,
+    properties: &'a [u8],
+}
+
+impl<'a> Message<'a> {
+    /// One datagram holds one message. Whatever a short buffer cut off after the last nul is
+    /// left out.
+    pub fn parse(data: &'a [u8]) -> Option<Self> {
+        let end = data.iter().rposition(|&x| x == 0)?;
+        let (header, properties) = match data[..end].split_once(|&x| x == 0) {
+            Some((header, properties)) => (header, properties),
+            None => (&data[..end], &[][..]),
+        };
+        let header = str::from_utf8(header).ok()?;
+        // udev rebroadcasts under a binary header starting with `libudev`
+        header.contains('@').then_some(Self { header, properties })
+    }
+    /// Any property that is not `KEY=VALUE` in UTF-8 is skipped.
+    pub fn properties(&self) -> impl Iterator<Item = (&'a str, &'a str)> + use<'a> {
+        self.properties.split(|&x| x == 0).filter_map(|x| {
+            let (k, v) = x.split_once(|&x| x == b'=')?;
+            Some((str::from_utf8(k).ok()?, str::from_utf8(v).ok()?))
+        })
+    }
+}
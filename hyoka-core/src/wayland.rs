@@ -0,0 +1,1955 @@
+use std::{
+    borrow::Borrow,
+    cell::Cell,
+    ffi::{CStr, c_char, c_void},
+    fmt::{self, Debug, Formatter},
+    io, mem,
+    os::fd::{BorrowedFd, FromRawFd as _, OwnedFd},
+    panic::{self, AssertUnwindSafe},
+    pin::Pin,
+    ptr::{self, NonNull},
+};
+
+use compio::net::PollFd;
+use derive_where::derive_where;
+use iced_core::{Point, mouse};
+use rustix::{io::Errno, path::Arg};
+use thiserror::Error;
+
+pub use notifier::*;
+
+#[allow(dead_code, non_camel_case_types, non_upper_case_globals)]
+pub mod ffi {
+    include!(concat!(env!("OUT_DIR"), "/bindings/wayland.rs"));
+}
+
+#[derive(Debug)]
+pub enum Event {
+    Resize {
+        object: NonNull<c_void>,
+        size: [u32; 2],
+    },
+    Rescale {
+        surface: NonNull<ffi::wl_surface>,
+        factor: u32,
+    },
+    /// How the output the surface is on is turned, buffers in the same orientation can be
+    /// scanned out without the compositor turning them.
+    Retransform {
+        surface: NonNull<ffi::wl_surface>,
+        transform: ffi::wl_output_transform,
+    },
+    /// What the seat has, as a mask of `WL_SEAT_CAPABILITY_*`, sent once it is bound and again
+    /// whenever a pointer, keyboard or touchscreen comes or goes.
+    Capabilities {
+        seat: Object<ffi::wl_seat>,
+        capabilities: u32,
+    },
+    /// The pointer or a touch of `seat` is on `surface` now.
+    Enter {
+        seat: NonNull<ffi::wl_seat>,
+        surface: NonNull<ffi::wl_surface>,
+        serial: u32,
+    },
+    /// Precedes the button press, popups grab with it.
+    Pressed {
+        seat: NonNull<ffi::wl_seat>,
+        serial: u32,
+    },
+    Mouse {
+        seat: NonNull<ffi::wl_seat>,
+        event: mouse::Event,
+    },
+    /// A Linux input event code.
+    KeyPressed {
+        key: u32,
+    },
+    /// The held modifiers as a mask in keymap order, Shift comes first in every usual one.
+    Modifiers(u32),
+    /// The compositor dismissed the popup.
+    PopupDone(NonNull<ffi::xdg_popup>),
+    /// Introduces an offer, its mime types follow before it becomes a selection.
+    DataOffer(Object<ffi::zwlr_data_control_offer_v1>),
+    OfferMime {
+        offer: Object<ffi::zwlr_data_control_offer_v1>,
+        mime: String,
+    },
+    /// `None` when the selection was cleared.
+    Selection {
+        offer: Option<Object<ffi::zwlr_data_control_offer_v1>>,
+        primary: bool,
+    },
+    /// Someone pastes what we offered, it goes into `fd`.
+    SourceSend {
+        source: Object<ffi::zwlr_data_control_source_v1>,
+        fd: OwnedFd,
+    },
+    /// Our offer is no longer the selection.
+    SourceCancelled(Object<ffi::zwlr_data_control_source_v1>),
+    /// Entries per channel of the output's gamma ramp, it can be set from now on.
+    GammaSize {
+        control: Object<ffi::zwlr_gamma_control_v1>,
+        size: u32,
+    },
+    /// Another client controls the output's gamma already, or the output is gone.
+    GammaFailed(Object<ffi::zwlr_gamma_control_v1>),
+    /// No input for the configured time, or input again after that.
+    Idle(bool),
+    /// Like "DP-1", sent once after binding.
+    OutputName {
+        output: Object<ffi::wl_output>,
+        name: String,
+    },
+    /// The output's display turned on or off, by us or anyone else.
+    OutputPower {
+        power: Object<ffi::zwlr_output_power_v1>,
+        on: bool,
+    },
+    /// Another client controls the output's power already, or the output is gone.
+    OutputPowerFailed(Object<ffi::zwlr_output_power_v1>),
+    /// How xdg output sees the output, sent once after creating it and again on changes.
+    XdgOutput {
+        output: Object<ffi::zxdg_output_v1>,
+        change: XdgOutputChange,
+    },
+    /// A connected output, enabled or not, its properties follow.
+    Head(Object<ffi::zwlr_output_head_v1>),
+    HeadChanged {
+        head: Object<ffi::zwlr_output_head_v1>,
+        change: HeadChange,
+    },
+    ModeChanged {
+        mode: Object<ffi::zwlr_output_mode_v1>,
+        change: ModeChange,
+    },
+    /// The heads are complete and consistent, a configuration is based on this serial.
+    HeadsDone(u32),
+    /// `None` when it was cancelled for being based on an outdated serial.
+    Configured {
+        configuration: Object<ffi::zwlr_output_configuration_v1>,
+        applied: Option<bool>,
+    },
+    CallbackDone(Object<ffi::wl_callback>),
+    /// The shm buffer a screencopy frame can be copied into, others are ignored.
+    FrameBuffer {
+        frame: Object<ffi::zwlr_screencopy_frame_v1>,
+        format: u32,
+        size: [u32; 2],
+        stride: u32,
+    },
+    FrameFlags {
+        frame: Object<ffi::zwlr_screencopy_frame_v1>,
+        y_invert: bool,
+    },
+    /// Every buffer type is announced, the copy can begin.
+    FrameBufferDone(Object<ffi::zwlr_screencopy_frame_v1>),
+    FrameReady(Object<ffi::zwlr_screencopy_frame_v1>),
+    FrameFailed(Object<ffi::zwlr_screencopy_frame_v1>),
+    /// An output plugged in after startup, to bind with [`Proxy::bind_output`].
+    OutputAdded {
+        name: u32,
+        version: u32,
+    },
+    /// A seat added after startup, to bind with [`Proxy::bind_seat`].
+    SeatAdded {
+        name: u32,
+        version: u32,
+    },
+    /// A global is gone, an output or a seat if [`Proxy::output`] or [`Proxy::seat`] knows the
+    /// name.
+    GlobalRemoved(u32),
+    /// Handed to an app launched for it, which passes it on to focus its window.
+    ActivationToken {
+        token: Object<ffi::xdg_activation_token_v1>,
+        name: String,
+    },
+}
+
+#[derive(Debug)]
+pub enum HeadChange {
+    Name(String),
+    /// one it supports, its properties follow
+    Mode(Object<ffi::zwlr_output_mode_v1>),
+    Enabled(bool),
+    CurrentMode(Object<ffi::zwlr_output_mode_v1>),
+    /// in the global compositor space
+    Position([i32; 2]),
+    Scale(f64),
+    /// Disconnected.
+    Finished,
+}
+
+#[derive(Debug)]
+pub enum XdgOutputChange {
+    /// in the global compositor space
+    Position([i32; 2]),
+    /// scaled and transformed, as surfaces are laid out on it
+    Size([i32; 2]),
+    /// like "DP-1", also for compositors without wl_output version 4
+    Name(String),
+    /// like "Dell Inc. DELL U2720Q (DP-1)"
+    Description(String),
+}
+
+#[derive(Debug)]
+pub enum ModeChange {
+    Size([i32; 2]),
+    /// mHz
+    Refresh(i32),
+    Finished,
+}
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("cannot connect to the compositor: {0}")]
+    Connect(io::Error),
+    #[error(
+        "the compositor does not support {0}, hyoka needs a compositor with wlr layer shell, \
+         like Hyprland, Sway or river"
+    )]
+    Unsupported(&'static str),
+    #[error("protocol error {code} on {interface}@{id}")]
+    Protocol {
+        interface: String,
+        id: u32,
+        code: u32,
+    },
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+/// Takes the fatal error out of a display that just failed.
+fn display_error(display: *mut ffi::wl_display) -> Error {
+    let errno = unsafe { ffi::wl_display_get_error(display) };
+    if errno != Errno::PROTO.raw_os_error() {
+        return io::Error::from_raw_os_error(errno).into();
+    }
+    let mut interface = ptr::null();
+    let mut id = 0;
+    let code = unsafe { ffi::wl_display_get_protocol_error(display, &mut interface, &mut id) };
+    let interface = match unsafe { interface.as_ref() } {
+        Some(interface) => unsafe { CStr::from_ptr(interface.name) }
+            .to_string_lossy()
+            .into_owned(),
+        None => "unknown".into(),
+    };
+    Error::Protocol {
+        interface,
+        id,
+        code,
+    }
+}
+
+pub struct Daemon {
+    display: NonNull<ffi::wl_display>,
+}
+
+pub struct Proxy {
+    pub globals: Globals,
+    registry: *mut ffi::wl_registry,
+    /// the registry's user data, goes after it
+    _registry_data: Box<Registry>,
+    /// the user data of the pointers and touches, one per seat
+    devices: Vec<Box<Device>>,
+    notifier: Pin<Box<Notifier>>,
+}
+
+/// What the listeners of a seat's pointer and touch work with, their events tell the seat apart.
+struct Device {
+    seat: NonNull<ffi::wl_seat>,
+    notifier: *const Notifier,
+}
+
+impl Device {
+    fn send(&self, event: Event) {
+        unsafe { &*self.notifier }.send(event)
+    }
+    fn enter(&self, surface: NonNull<ffi::wl_surface>, serial: u32) {
+        let seat = self.seat;
+        self.send(Event::Enter {
+            seat,
+            surface,
+            serial,
+        });
+    }
+    fn pressed(&self, serial: u32) {
+        let seat = self.seat;
+        self.send(Event::Pressed { seat, serial });
+    }
+    fn mouse(&self, event: mouse::Event) {
+        let seat = self.seat;
+        self.send(Event::Mouse { seat, event });
+    }
+}
+
+impl Proxy {
+    /// Registers `listener` with the event channel as its user data.
+    pub fn listen<T: Listen>(&mut self, object: &OwnedObject<T>, listener: &'static T::Listener) {
+        let data = &raw mut *self.notifier;
+        unsafe { object.add_listener(listener, data.cast()) }
+    }
+    /// Registers `listener` of a pointer or touch of `seat`, its events come with the seat.
+    pub fn listen_device<T: Listen>(
+        &mut self,
+        seat: Object<ffi::wl_seat>,
+        object: &OwnedObject<T>,
+        listener: &'static T::Listener,
+    ) {
+        let Some(device) = self.devices.iter().find(|x| x.seat == seat.as_non_null()) else {
+            return;
+        };
+        let data = &raw const **device;
+        unsafe { object.add_listener(listener, data.cast_mut().cast()) }
+    }
+    /// Binds an output plugged in after startup, its name follows like those of the others.
+    pub fn bind_output(&mut self, name: u32, version: u32) -> Object<ffi::wl_output> {
+        let interface = unsafe { &ffi::wl_output_interface };
+        let version = version.min(interface.version as u32);
+        let output = Object::from_raw(
+            unsafe { ffi::wl_registry_bind(self.registry, name, interface, version) }.cast(),
+        );
+        let data = &raw mut *self.notifier;
+        unsafe { ffi::wl_output_add_listener(output.as_ptr(), &OUTPUT_LISTENER, data.cast()) };
+        self.globals.outputs.push((name, output.as_non_null()));
+        output
+    }
+    /// The output bound from global `name`, `None` for any other global.
+    pub fn output(&self, name: u32) -> Option<Object<ffi::wl_output>> {
+        self.globals
+            .outputs
+            .iter()
+            .find(|(x, _)| *x == name)
+            .map(|&(_, output)| Object(output))
+    }
+    /// Binds a seat added after startup, its capabilities follow like those of the others.
+    pub fn bind_seat(&mut self, name: u32, version: u32) -> Object<ffi::wl_seat> {
+        let interface = unsafe { &ffi::wl_seat_interface };
+        let version = version.min(interface.version as u32);
+        let seat = Object::from_raw(
+            unsafe { ffi::wl_registry_bind(self.registry, name, interface, version) }.cast(),
+        );
+        self.globals.seats.push((name, seat.as_non_null()));
+        self.add_seat(seat);
+        seat
+    }
+    /// Listens to `seat` and makes room for the user data of its devices.
+    fn add_seat(&mut self, seat: Object<ffi::wl_seat>) {
+        let data = &raw mut *self.notifier;
+        unsafe { ffi::wl_seat_add_listener(seat.as_ptr(), &SEAT_LISTENER, data.cast()) };
+        self.devices.push(Box::new(Device {
+            seat: seat.as_non_null(),
+            notifier: data,
+        }));
+    }
+    /// The seat bound from global `name`, `None` for any other global.
+    pub fn seat(&self, name: u32) -> Option<Object<ffi::wl_seat>> {
+        self.globals
+            .seats
+            .iter()
+            .find(|(x, _)| *x == name)
+            .map(|&(_, seat)| Object(seat))
+    }
+    /// Destroys the seat bound from global `name`, its pointer, keyboard and touch have to be
+    /// gone already.
+    pub fn remove_seat(&mut self, name: u32) {
+        if let Some(index) = self.globals.seats.iter().position(|(x, _)| *x == name) {
+            let (_, seat) = self.globals.seats.remove(index);
+            self.devices.retain(|x| x.seat != seat);
+            unsafe { ffi::wl_seat_destroy(seat.as_ptr()) };
+        }
+    }
+    /// Destroys the output bound from global `name`, nothing may refer to it anymore.
+    pub fn remove_output(&mut self, name: u32) {
+        if let Some(index) = self.globals.outputs.iter().position(|(x, _)| *x == name) {
+            let (_, output) = self.globals.outputs.remove(index);
+            unsafe { ffi::wl_output_destroy(output.as_ptr()) };
+        }
+    }
+    /// Destroys the optional global bound from `name`, what was created from it goes along. A
+    /// required one is only logged. `false` for outputs, seats and globals never bound.
+    pub fn remove_global(&mut self, name: u32) -> bool {
+        self.globals.remove(name)
+    }
+    /// Every object created from the globals must be gone already.
+    pub fn destroy(self) {
+        self.globals.destroy();
+        unsafe { ffi::wl_registry_destroy(self.registry) };
+    }
+}
+
+/// Flushes pending requests and closes the connection, the compositor drops whatever is left.
+pub fn disconnect(display: NonNull<ffi::wl_display>) {
+    unsafe {
+        ffi::wl_display_flush(display.as_ptr());
+        ffi::wl_display_disconnect(display.as_ptr());
+    }
+}
+
+impl Daemon {
+    pub fn display(&self) -> NonNull<ffi::wl_display> {
+        self.display
+    }
+    /// Dispatches events until the connection fails, the display is unusable afterwards.
+    pub async fn run(self) -> Result<(), Error> {
+        let display = self.display.as_ptr();
+
+        unsafe {
+            let fd = ffi::wl_display_get_fd(display);
+            let fd = BorrowedFd::borrow_raw(fd);
+            let fd = PollFd::new(fd)?;
+            loop {
+                while ffi::wl_display_prepare_read(display) != 0 {
+                    if ffi::wl_display_dispatch_pending(display) == -1 {
+                        return Err(display_error(display));
+                    }
+                }
+                ffi::wl_display_flush(display);
+                if let Err(e) = fd.read_ready().await {
+                    ffi::wl_display_cancel_read(display);
+                    return Err(e.into());
+                }
+                if ffi::wl_display_read_events(display) == -1 {
+                    return Err(display_error(display));
+                }
+
+                if ffi::wl_display_dispatch_pending(display) == -1 {
+                    return Err(display_error(display));
+                }
+            }
+        }
+    }
+}
+
+pub extern "C" fn nop() {}
+
+/// Runs the body of a listener, a panic in it is logged instead of unwinding into libwayland,
+/// which would abort.
+fn guard(listener: &str, f: impl FnOnce()) {
+    if let Err(e) = panic::catch_unwind(AssertUnwindSafe(f)) {
+        let message = e
+            .downcast_ref::<&str>()
+            .copied()
+            .or_else(|| e.downcast_ref::<String>().map(|x| x.as_str()))
+            .unwrap_or("unknown");
+        tracing::error!("wayland listener {listener} panicked: {message}");
+    }
+}
+
+#[macro_export]
+macro_rules! nop {
+    () => {
+        unsafe { mem::transmute($crate::wayland::nop as *const ()) }
+    };
+}
+
+/// What the registry listener works with, it lives as long as the registry.
+struct Registry {
+    /// until the startup roundtrip is over, only outputs and seats come and go afterwards
+    builder: Option<GlobalsBuilder>,
+    notifier: *mut Notifier,
+}
+
+const REGISTRY_LISTENER: ffi::wl_registry_listener = ffi::wl_registry_listener {
+    global: Some({
+        extern "C" fn global(
+            data: *mut c_void,
+            registry: *mut ffi::wl_registry,
+            name: u32,
+            interface: *const i8,
+            version: u32,
+        ) {
+            guard("global", || {
+                let data = unsafe { &mut *(data as *mut Registry) };
+                if let Some(globals) = &mut data.builder {
+                    globals.bind(registry, name, interface, version);
+                    return;
+                }
+                let notifier = unsafe { &*data.notifier };
+                let is = |other: &ffi::wl_interface| unsafe {
+                    cstr_eq(
+                        Restrict::from_ptr(interface),
+                        Restrict::from_ptr(other.name),
+                    )
+                };
+                if is(unsafe { &ffi::wl_output_interface }) {
+                    notifier.send(Event::OutputAdded { name, version });
+                } else if is(unsafe { &ffi::wl_seat_interface }) {
+                    notifier.send(Event::SeatAdded { name, version });
+                }
+            });
+        }
+        global
+    }),
+    global_remove: Some({
+        extern "C" fn global_remove(data: *mut c_void, _: *mut ffi::wl_registry, name: u32) {
+            guard("global_remove", || {
+                let data = unsafe { &mut *(data as *mut Registry) };
+                if data.builder.is_some() {
+                    return;
+                }
+                let notifier = unsafe { &*data.notifier };
+                notifier.send(Event::GlobalRemoved(name));
+            });
+        }
+        global_remove
+    }),
+};
+
+const WM_BASE_LISTENER: ffi::xdg_wm_base_listener = ffi::xdg_wm_base_listener {
+    ping: Some({
+        extern "C" fn ping(_: *mut c_void, wm_base: *mut ffi::xdg_wm_base, serial: u32) {
+            guard("ping", || unsafe { ffi::xdg_wm_base_pong(wm_base, serial) });
+        }
+        ping
+    }),
+};
+
+const SEAT_LISTENER: ffi::wl_seat_listener = ffi::wl_seat_listener {
+    capabilities: {
+        extern "C" fn capabilities(data: *mut c_void, seat: *mut ffi::wl_seat, capabilities: u32) {
+            guard("capabilities", || {
+                let notifier = unsafe { &*(data as *const Notifier) };
+                if let Some(seat) = NonNull::new(seat) {
+                    notifier.send(Event::Capabilities {
+                        seat: Object(seat),
+                        capabilities,
+                    });
+                }
+            });
+        }
+        Some(capabilities)
+    },
+    name: nop!(),
+};
+
+pub type Fixed = ffi::wl_fixed_t;
+
+impl Fixed {
+    fn as_f32(self) -> f32 {
+        (self.0 as f32) / 256.0
+    }
+    #[allow(dead_code)]
+    fn as_i32(self) -> i32 {
+        self.0 / 256
+    }
+}
+
+impl Into<f32> for Fixed {
+    fn into(self) -> f32 {
+        self.as_f32()
+    }
+}
+
+/// For [`Proxy::listen_device`].
+pub const POINTER_LISTENERL: ffi::wl_pointer_listener = ffi::wl_pointer_listener {
+    enter: {
+        extern "C" fn enter(
+            data: *mut c_void,
+            _pointer: *mut ffi::wl_pointer,
+            serial: u32,
+            surface: *mut ffi::wl_surface,
+            x: Fixed,
+            y: Fixed,
+        ) {
+            guard("enter", || {
+                let device = unsafe { &*(data as *const Device) };
+                // Sometimes surface is null. Why can surface be null ? idk. It's not nullable in protocol
+                if let Some(surface) = NonNull::new(surface) {
+                    device.enter(surface, serial);
+                    device.mouse(mouse::Event::CursorMoved {
+                        position: Point::new(x.into(), y.into()),
+                    });
+                }
+            });
+        }
+        Some(enter)
+    },
+    leave: {
+        extern "C" fn leave(
+            data: *mut c_void,
+            _pointer: *mut ffi::wl_pointer,
+            _serial: u32,
+            _surface: *mut ffi::wl_surface,
+        ) {
+            guard("leave", || {
+                let device = unsafe { &*(data as *const Device) };
+                device.mouse(mouse::Event::CursorLeft);
+            });
+        }
+        Some(leave)
+    },
+    motion: {
+        extern "C" fn motion(
+            data: *mut c_void,
+            _pointer: *mut ffi::wl_pointer,
+            _time: u32,
+            x: Fixed,
+            y: Fixed,
+        ) {
+            guard("motion", || {
+                let device = unsafe { &*(data as *const Device) };
+                device.mouse(mouse::Event::CursorMoved {
+                    position: Point::new(x.into(), y.into()),
+                });
+            });
+        }
+        Some(motion)
+    },
+    button: {
+        extern "C" fn button(
+            data: *mut c_void,
+            _pointer: *mut ffi::wl_pointer,
+            serial: u32,
+            _time: u32,
+            button: u32,
+            state: u32,
+        ) {
+            guard("button", || {
+                let device = unsafe { &*(data as *const Device) };
+                if state == ffi::WL_POINTER_BUTTON_STATE_PRESSED {
+                    device.pressed(serial);
+                }
+                let button = match button {
+                    0x110 => mouse::Button::Left,
+                    0x111 => mouse::Button::Right,
+                    0x112 => mouse::Button::Middle,
+                    0x115 => mouse::Button::Forward,
+                    0x116 => mouse::Button::Back,
+                    other => mouse::Button::Other(other as _),
+                };
+                let event = match state {
+                    ffi::WL_POINTER_BUTTON_STATE_RELEASED => mouse::Event::ButtonReleased(button),
+                    ffi::WL_POINTER_BUTTON_STATE_PRESSED => mouse::Event::ButtonPressed(button),
+                    _ => return,
+                };
+                device.mouse(event);
+            });
+        }
+        Some(button)
+    },
+    axis: {
+        extern "C" fn axis(
+            data: *mut c_void,
+            _pointer: *mut ffi::wl_pointer,
+            _time: u32,
+            axis: u32,
+            value: Fixed,
+        ) {
+            guard("axis", || {
+                let device = unsafe { &*(data as *const Device) };
+                // positive values scroll down or right here, up or left in iced
+                let value = -value.as_f32();
+                let delta = match axis {
+                    ffi::WL_POINTER_AXIS_VERTICAL_SCROLL => {
+                        mouse::ScrollDelta::Pixels { x: 0.0, y: value }
+                    }
+                    ffi::WL_POINTER_AXIS_HORIZONTAL_SCROLL => {
+                        mouse::ScrollDelta::Pixels { x: value, y: 0.0 }
+                    }
+                    _ => return,
+                };
+                device.mouse(mouse::Event::WheelScrolled { delta });
+            });
+        }
+        Some(axis)
+    },
+    frame: nop!(),
+    axis_source: nop!(),
+    axis_stop: nop!(),
+    axis_discrete: nop!(),
+    axis_value120: nop!(),
+    axis_relative_direction: nop!(),
+};
+
+/// Touches act as the pointer pressing the left button where they go down, every touch point
+/// moves it. For [`Proxy::listen_device`].
+pub const TOUCH_LISTENER: ffi::wl_touch_listener = ffi::wl_touch_listener {
+    down: {
+        extern "C" fn down(
+            data: *mut c_void,
+            _touch: *mut ffi::wl_touch,
+            serial: u32,
+            _time: u32,
+            surface: *mut ffi::wl_surface,
+            _id: i32,
+            x: Fixed,
+            y: Fixed,
+        ) {
+            guard("down", || {
+                let device = unsafe { &*(data as *const Device) };
+                let Some(surface) = NonNull::new(surface) else {
+                    return;
+                };
+                device.enter(surface, serial);
+                device.mouse(mouse::Event::CursorMoved {
+                    position: Point::new(x.into(), y.into()),
+                });
+                device.pressed(serial);
+                device.mouse(mouse::Event::ButtonPressed(mouse::Button::Left));
+            });
+        }
+        Some(down)
+    },
+    up: {
+        extern "C" fn up(
+            data: *mut c_void,
+            _touch: *mut ffi::wl_touch,
+            _serial: u32,
+            _time: u32,
+            _id: i32,
+        ) {
+            guard("up", || {
+                let device = unsafe { &*(data as *const Device) };
+                device.mouse(mouse::Event::ButtonReleased(mouse::Button::Left));
+                device.mouse(mouse::Event::CursorLeft);
+            });
+        }
+        Some(up)
+    },
+    motion: {
+        extern "C" fn motion(
+            data: *mut c_void,
+            _touch: *mut ffi::wl_touch,
+            _time: u32,
+            _id: i32,
+            x: Fixed,
+            y: Fixed,
+        ) {
+            guard("motion", || {
+                let device = unsafe { &*(data as *const Device) };
+                device.mouse(mouse::Event::CursorMoved {
+                    position: Point::new(x.into(), y.into()),
+                });
+            });
+        }
+        Some(motion)
+    },
+    cancel: {
+        extern "C" fn cancel(data: *mut c_void, _touch: *mut ffi::wl_touch) {
+            guard("cancel", || {
+                // the compositor took the touch over, it is no click
+                let device = unsafe { &*(data as *const Device) };
+                device.mouse(mouse::Event::CursorLeft);
+            });
+        }
+        Some(cancel)
+    },
+    frame: nop!(),
+    shape: nop!(),
+    orientation: nop!(),
+};
+
+/// Key presses and modifiers, the keymap is never needed.
+pub const KEYBOARD_LISTENER: ffi::wl_keyboard_listener = ffi::wl_keyboard_listener {
+    keymap: {
+        extern "C" fn keymap(
+            _data: *mut c_void,
+            _keyboard: *mut ffi::wl_keyboard,
+            _format: u32,
+            fd: i32,
+            _size: u32,
+        ) {
+            guard("keymap", || {
+                drop(unsafe { OwnedFd::from_raw_fd(fd) });
+            });
+        }
+        Some(keymap)
+    },
+    enter: nop!(),
+    leave: {
+        extern "C" fn leave(
+            data: *mut c_void,
+            _keyboard: *mut ffi::wl_keyboard,
+            _serial: u32,
+            _surface: *mut ffi::wl_surface,
+        ) {
+            guard("leave", || {
+                let notifier = unsafe { &*(data as *const Notifier) };
+                // no more modifier updates until the next enter
+                notifier.send(Event::Modifiers(0));
+            });
+        }
+        Some(leave)
+    },
+    key: {
+        extern "C" fn key(
+            data: *mut c_void,
+            _keyboard: *mut ffi::wl_keyboard,
+            _serial: u32,
+            _time: u32,
+            key: u32,
+            state: u32,
+        ) {
+            guard("key", || {
+                if state != ffi::WL_KEYBOARD_KEY_STATE_PRESSED {
+                    return;
+                }
+                let notifier = unsafe { &*(data as *const Notifier) };
+                notifier.send(Event::KeyPressed { key });
+            });
+        }
+        Some(key)
+    },
+    modifiers: {
+        extern "C" fn modifiers(
+            data: *mut c_void,
+            _keyboard: *mut ffi::wl_keyboard,
+            _serial: u32,
+            depressed: u32,
+            _latched: u32,
+            _locked: u32,
+            _group: u32,
+        ) {
+            guard("modifiers", || {
+                let notifier = unsafe { &*(data as *const Notifier) };
+                notifier.send(Event::Modifiers(depressed));
+            });
+        }
+        Some(modifiers)
+    },
+    repeat_info: nop!(),
+};
+
+pub const XDG_SURFACE_LISTENER: ffi::xdg_surface_listener = ffi::xdg_surface_listener {
+    configure: {
+        extern "C" fn configure(_: *mut c_void, surface: *mut ffi::xdg_surface, serial: u32) {
+            guard("configure", || unsafe {
+                ffi::xdg_surface_ack_configure(surface, serial)
+            });
+        }
+        Some(configure)
+    },
+};
+
+pub const XDG_POPUP_LISTENER: ffi::xdg_popup_listener = ffi::xdg_popup_listener {
+    configure: {
+        extern "C" fn configure(
+            data: *mut c_void,
+            popup: *mut ffi::xdg_popup,
+            _x: i32,
+            _y: i32,
+            width: i32,
+            height: i32,
+        ) {
+            guard("configure", || {
+                let notifier = unsafe { &*(data as *const Notifier) };
+                let size = [width as u32, height as u32];
+                if let Some(object) = NonNull::new(popup as _) {
+                    notifier.send(Event::Resize { object, size });
+                }
+            });
+        }
+        Some(configure)
+    },
+    popup_done: {
+        extern "C" fn popup_done(data: *mut c_void, popup: *mut ffi::xdg_popup) {
+            guard("popup_done", || {
+                let notifier = unsafe { &*(data as *const Notifier) };
+                if let Some(popup) = NonNull::new(popup) {
+                    notifier.send(Event::PopupDone(popup));
+                }
+            });
+        }
+        Some(popup_done)
+    },
+    repositioned: nop!(),
+};
+
+pub const DATA_DEVICE_LISTENER: ffi::zwlr_data_control_device_v1_listener =
+    ffi::zwlr_data_control_device_v1_listener {
+        data_offer: {
+            extern "C" fn data_offer(
+                data: *mut c_void,
+                _device: *mut ffi::zwlr_data_control_device_v1,
+                offer: *mut ffi::zwlr_data_control_offer_v1,
+            ) {
+                guard("data_offer", || {
+                    // its mime types come next, the listener has to be there first
+                    unsafe {
+                        ffi::zwlr_data_control_offer_v1_add_listener(
+                            offer,
+                            &DATA_OFFER_LISTENER,
+                            data,
+                        )
+                    };
+                    let notifier = unsafe { &*(data as *const Notifier) };
+                    notifier.send(Event::DataOffer(Object::from_raw(offer)));
+                });
+            }
+            Some(data_offer)
+        },
+        selection: {
+            extern "C" fn selection(
+                data: *mut c_void,
+                _device: *mut ffi::zwlr_data_control_device_v1,
+                offer: *mut ffi::zwlr_data_control_offer_v1,
+            ) {
+                guard("selection", || {
+                    let notifier = unsafe { &*(data as *const Notifier) };
+                    notifier.send(Event::Selection {
+                        offer: NonNull::new(offer).map(Object),
+                        primary: false,
+                    });
+                });
+            }
+            Some(selection)
+        },
+        finished: nop!(),
+        primary_selection: {
+            extern "C" fn primary_selection(
+                data: *mut c_void,
+                _device: *mut ffi::zwlr_data_control_device_v1,
+                offer: *mut ffi::zwlr_data_control_offer_v1,
+            ) {
+                guard("primary_selection", || {
+                    let notifier = unsafe { &*(data as *const Notifier) };
+                    notifier.send(Event::Selection {
+                        offer: NonNull::new(offer).map(Object),
+                        primary: true,
+                    });
+                });
+            }
+            Some(primary_selection)
+        },
+    };
+
+const DATA_OFFER_LISTENER: ffi::zwlr_data_control_offer_v1_listener =
+    ffi::zwlr_data_control_offer_v1_listener {
+        offer: {
+            extern "C" fn offer(
+                data: *mut c_void,
+                offer: *mut ffi::zwlr_data_control_offer_v1,
+                mime_type: *const c_char,
+            ) {
+                guard("offer", || {
+                    let notifier = unsafe { &*(data as *const Notifier) };
+                    let mime = unsafe { CStr::from_ptr(mime_type) };
+                    notifier.send(Event::OfferMime {
+                        offer: Object::from_raw(offer),
+                        mime: mime.to_string_lossy().into_owned(),
+                    });
+                });
+            }
+            Some(offer)
+        },
+    };
+
+pub const DATA_SOURCE_LISTENER: ffi::zwlr_data_control_source_v1_listener =
+    ffi::zwlr_data_control_source_v1_listener {
+        send: {
+            extern "C" fn send(
+                data: *mut c_void,
+                source: *mut ffi::zwlr_data_control_source_v1,
+                _mime_type: *const c_char,
+                fd: i32,
+            ) {
+                guard("send", || {
+                    let notifier = unsafe { &*(data as *const Notifier) };
+                    notifier.send(Event::SourceSend {
+                        source: Object::from_raw(source),
+                        fd: unsafe { OwnedFd::from_raw_fd(fd) },
+                    });
+                });
+            }
+            Some(send)
+        },
+        cancelled: {
+            extern "C" fn cancelled(
+                data: *mut c_void,
+                source: *mut ffi::zwlr_data_control_source_v1,
+            ) {
+                guard("cancelled", || {
+                    let notifier = unsafe { &*(data as *const Notifier) };
+                    notifier.send(Event::SourceCancelled(Object::from_raw(source)));
+                });
+            }
+            Some(cancelled)
+        },
+    };
+
+pub const GAMMA_CONTROL_LISTENER: ffi::zwlr_gamma_control_v1_listener =
+    ffi::zwlr_gamma_control_v1_listener {
+        gamma_size: {
+            extern "C" fn gamma_size(
+                data: *mut c_void,
+                control: *mut ffi::zwlr_gamma_control_v1,
+                size: u32,
+            ) {
+                guard("gamma_size", || {
+                    let notifier = unsafe { &*(data as *const Notifier) };
+                    notifier.send(Event::GammaSize {
+                        control: Object::from_raw(control),
+                        size,
+                    });
+                });
+            }
+            Some(gamma_size)
+        },
+        failed: {
+            extern "C" fn failed(data: *mut c_void, control: *mut ffi::zwlr_gamma_control_v1) {
+                guard("failed", || {
+                    let notifier = unsafe { &*(data as *const Notifier) };
+                    notifier.send(Event::GammaFailed(Object::from_raw(control)));
+                });
+            }
+            Some(failed)
+        },
+    };
+
+pub const SCREENCOPY_FRAME_LISTENER: ffi::zwlr_screencopy_frame_v1_listener =
+    ffi::zwlr_screencopy_frame_v1_listener {
+        buffer: {
+            extern "C" fn buffer(
+                data: *mut c_void,
+                frame: *mut ffi::zwlr_screencopy_frame_v1,
+                format: u32,
+                width: u32,
+                height: u32,
+                stride: u32,
+            ) {
+                guard("buffer", || {
+                    let notifier = unsafe { &*(data as *const Notifier) };
+                    notifier.send(Event::FrameBuffer {
+                        frame: Object::from_raw(frame),
+                        format,
+                        size: [width, height],
+                        stride,
+                    });
+                });
+            }
+            Some(buffer)
+        },
+        flags: {
+            extern "C" fn flags(
+                data: *mut c_void,
+                frame: *mut ffi::zwlr_screencopy_frame_v1,
+                flags: u32,
+            ) {
+                guard("flags", || {
+                    let notifier = unsafe { &*(data as *const Notifier) };
+                    notifier.send(Event::FrameFlags {
+                        frame: Object::from_raw(frame),
+                        y_invert: flags & ffi::ZWLR_SCREENCOPY_FRAME_V1_FLAGS_Y_INVERT != 0,
+                    });
+                });
+            }
+            Some(flags)
+        },
+        ready: {
+            extern "C" fn ready(
+                data: *mut c_void,
+                frame: *mut ffi::zwlr_screencopy_frame_v1,
+                _tv_sec_hi: u32,
+                _tv_sec_lo: u32,
+                _tv_nsec: u32,
+            ) {
+                guard("ready", || {
+                    let notifier = unsafe { &*(data as *const Notifier) };
+                    notifier.send(Event::FrameReady(Object::from_raw(frame)));
+                });
+            }
+            Some(ready)
+        },
+        failed: {
+            extern "C" fn failed(data: *mut c_void, frame: *mut ffi::zwlr_screencopy_frame_v1) {
+                guard("failed", || {
+                    let notifier = unsafe { &*(data as *const Notifier) };
+                    notifier.send(Event::FrameFailed(Object::from_raw(frame)));
+                });
+            }
+            Some(failed)
+        },
+        damage: nop!(),
+        linux_dmabuf: nop!(),
+        buffer_done: {
+            extern "C" fn buffer_done(
+                data: *mut c_void,
+                frame: *mut ffi::zwlr_screencopy_frame_v1,
+            ) {
+                guard("buffer_done", || {
+                    let notifier = unsafe { &*(data as *const Notifier) };
+                    notifier.send(Event::FrameBufferDone(Object::from_raw(frame)));
+                });
+            }
+            Some(buffer_done)
+        },
+    };
+
+pub const IDLE_NOTIFICATION_LISTENER: ffi::ext_idle_notification_v1_listener =
+    ffi::ext_idle_notification_v1_listener {
+        idled: {
+            extern "C" fn idled(data: *mut c_void, _: *mut ffi::ext_idle_notification_v1) {
+                guard("idled", || {
+                    let notifier = unsafe { &*(data as *const Notifier) };
+                    notifier.send(Event::Idle(true));
+                });
+            }
+            Some(idled)
+        },
+        resumed: {
+            extern "C" fn resumed(data: *mut c_void, _: *mut ffi::ext_idle_notification_v1) {
+                guard("resumed", || {
+                    let notifier = unsafe { &*(data as *const Notifier) };
+                    notifier.send(Event::Idle(false));
+                });
+            }
+            Some(resumed)
+        },
+    };
+
+pub const ACTIVATION_TOKEN_LISTENER: ffi::xdg_activation_token_v1_listener =
+    ffi::xdg_activation_token_v1_listener {
+        done: {
+            extern "C" fn done(
+                data: *mut c_void,
+                token: *mut ffi::xdg_activation_token_v1,
+                name: *const c_char,
+            ) {
+                guard("done", || {
+                    let notifier = unsafe { &*(data as *const Notifier) };
+                    let name = unsafe { CStr::from_ptr(name) };
+                    notifier.send(Event::ActivationToken {
+                        token: Object::from_raw(token),
+                        name: name.to_string_lossy().into_owned(),
+                    });
+                });
+            }
+            Some(done)
+        },
+    };
+
+/// Only the name is of interest, the geometry comes with the surfaces placed on it.
+const OUTPUT_LISTENER: ffi::wl_output_listener = ffi::wl_output_listener {
+    geometry: nop!(),
+    mode: nop!(),
+    done: nop!(),
+    scale: nop!(),
+    name: {
+        extern "C" fn name(data: *mut c_void, output: *mut ffi::wl_output, name: *const c_char) {
+            guard("name", || {
+                let notifier = unsafe { &*(data as *const Notifier) };
+                let name = unsafe { CStr::from_ptr(name) };
+                notifier.send(Event::OutputName {
+                    output: Object::from_raw(output),
+                    name: name.to_string_lossy().into_owned(),
+                });
+            });
+        }
+        Some(name)
+    },
+    description: nop!(),
+};
+
+pub const OUTPUT_POWER_LISTENER: ffi::zwlr_output_power_v1_listener =
+    ffi::zwlr_output_power_v1_listener {
+        mode: {
+            extern "C" fn mode(
+                data: *mut c_void,
+                power: *mut ffi::zwlr_output_power_v1,
+                mode: u32,
+            ) {
+                guard("mode", || {
+                    let notifier = unsafe { &*(data as *const Notifier) };
+                    notifier.send(Event::OutputPower {
+                        power: Object::from_raw(power),
+                        on: mode == ffi::ZWLR_OUTPUT_POWER_V1_MODE_ON,
+                    });
+                });
+            }
+            Some(mode)
+        },
+        failed: {
+            extern "C" fn failed(data: *mut c_void, power: *mut ffi::zwlr_output_power_v1) {
+                guard("failed", || {
+                    let notifier = unsafe { &*(data as *const Notifier) };
+                    notifier.send(Event::OutputPowerFailed(Object::from_raw(power)));
+                });
+            }
+            Some(failed)
+        },
+    };
+
+fn xdg_output_changed(
+    data: *mut c_void,
+    output: *mut ffi::zxdg_output_v1,
+    change: XdgOutputChange,
+) {
+    let notifier = unsafe { &*(data as *const Notifier) };
+    notifier.send(Event::XdgOutput {
+        output: Object::from_raw(output),
+        change,
+    });
+}
+
+pub const XDG_OUTPUT_LISTENER: ffi::zxdg_output_v1_listener = ffi::zxdg_output_v1_listener {
+    logical_position: {
+        extern "C" fn logical_position(
+            data: *mut c_void,
+            output: *mut ffi::zxdg_output_v1,
+            x: i32,
+            y: i32,
+        ) {
+            guard("logical_position", || {
+                xdg_output_changed(data, output, XdgOutputChange::Position([x, y]))
+            });
+        }
+        Some(logical_position)
+    },
+    logical_size: {
+        extern "C" fn logical_size(
+            data: *mut c_void,
+            output: *mut ffi::zxdg_output_v1,
+            width: i32,
+            height: i32,
+        ) {
+            guard("logical_size", || {
+                xdg_output_changed(data, output, XdgOutputChange::Size([width, height]))
+            });
+        }
+        Some(logical_size)
+    },
+    // deprecated for wl_output.done since version 3, the values are taken as they come
+    done: nop!(),
+    name: {
+        extern "C" fn name(
+            data: *mut c_void,
+            output: *mut ffi::zxdg_output_v1,
+            name: *const c_char,
+        ) {
+            guard("name", || {
+                let name = unsafe { CStr::from_ptr(name) }
+                    .to_string_lossy()
+                    .into_owned();
+                xdg_output_changed(data, output, XdgOutputChange::Name(name))
+            });
+        }
+        Some(name)
+    },
+    description: {
+        extern "C" fn description(
+            data: *mut c_void,
+            output: *mut ffi::zxdg_output_v1,
+            description: *const c_char,
+        ) {
+            guard("description", || {
+                let description = unsafe { CStr::from_ptr(description) };
+                let description = description.to_string_lossy().into_owned();
+                xdg_output_changed(data, output, XdgOutputChange::Description(description))
+            });
+        }
+        Some(description)
+    },
+};
+
+const OUTPUT_MANAGER_LISTENER: ffi::zwlr_output_manager_v1_listener =
+    ffi::zwlr_output_manager_v1_listener {
+        head: {
+            extern "C" fn head(
+                data: *mut c_void,
+                _manager: *mut ffi::zwlr_output_manager_v1,
+                head: *mut ffi::zwlr_output_head_v1,
+            ) {
+                guard("head", || {
+                    // its properties come next, the listener has to be there first
+                    unsafe { ffi::zwlr_output_head_v1_add_listener(head, &HEAD_LISTENER, data) };
+                    let notifier = unsafe { &*(data as *const Notifier) };
+                    notifier.send(Event::Head(Object::from_raw(head)));
+                });
+            }
+            Some(head)
+        },
+        done: {
+            extern "C" fn done(
+                data: *mut c_void,
+                _manager: *mut ffi::zwlr_output_manager_v1,
+                serial: u32,
+            ) {
+                guard("done", || {
+                    let notifier = unsafe { &*(data as *const Notifier) };
+                    notifier.send(Event::HeadsDone(serial));
+                });
+            }
+            Some(done)
+        },
+        finished: nop!(),
+    };
+
+fn head_changed(data: *mut c_void, head: *mut ffi::zwlr_output_head_v1, change: HeadChange) {
+    let notifier = unsafe { &*(data as *const Notifier) };
+    notifier.send(Event::HeadChanged {
+        head: Object::from_raw(head),
+        change,
+    });
+}
+
+const HEAD_LISTENER: ffi::zwlr_output_head_v1_listener = ffi::zwlr_output_head_v1_listener {
+    name: {
+        extern "C" fn name(
+            data: *mut c_void,
+            head: *mut ffi::zwlr_output_head_v1,
+            name: *const c_char,
+        ) {
+            guard("name", || {
+                let name = unsafe { CStr::from_ptr(name) }
+                    .to_string_lossy()
+                    .into_owned();
+                head_changed(data, head, HeadChange::Name(name));
+            });
+        }
+        Some(name)
+    },
+    description: nop!(),
+    physical_size: nop!(),
+    mode: {
+        extern "C" fn mode(
+            data: *mut c_void,
+            head: *mut ffi::zwlr_output_head_v1,
+            mode: *mut ffi::zwlr_output_mode_v1,
+        ) {
+            guard("mode", || {
+                unsafe { ffi::zwlr_output_mode_v1_add_listener(mode, &MODE_LISTENER, data) };
+                head_changed(data, head, HeadChange::Mode(Object::from_raw(mode)));
+            });
+        }
+        Some(mode)
+    },
+    enabled: {
+        extern "C" fn enabled(
+            data: *mut c_void,
+            head: *mut ffi::zwlr_output_head_v1,
+            enabled: i32,
+        ) {
+            guard("enabled", || {
+                head_changed(data, head, HeadChange::Enabled(enabled != 0));
+            });
+        }
+        Some(enabled)
+    },
+    current_mode: {
+        extern "C" fn current_mode(
+            data: *mut c_void,
+            head: *mut ffi::zwlr_output_head_v1,
+            mode: *mut ffi::zwlr_output_mode_v1,
+        ) {
+            guard("current_mode", || {
+                head_changed(data, head, HeadChange::CurrentMode(Object::from_raw(mode)));
+            });
+        }
+        Some(current_mode)
+    },
+    position: {
+        extern "C" fn position(
+            data: *mut c_void,
+            head: *mut ffi::zwlr_output_head_v1,
+            x: i32,
+            y: i32,
+        ) {
+            guard("position", || {
+                head_changed(data, head, HeadChange::Position([x, y]));
+            });
+        }
+        Some(position)
+    },
+    transform: nop!(),
+    scale: {
+        extern "C" fn scale(data: *mut c_void, head: *mut ffi::zwlr_output_head_v1, scale: Fixed) {
+            guard("scale", || {
+                head_changed(data, head, HeadChange::Scale(scale.0 as f64 / 256.0));
+            });
+        }
+        Some(scale)
+    },
+    finished: {
+        extern "C" fn finished(data: *mut c_void, head: *mut ffi::zwlr_output_head_v1) {
+            guard("finished", || {
+                head_changed(data, head, HeadChange::Finished);
+            });
+        }
+        Some(finished)
+    },
+    make: nop!(),
+    model: nop!(),
+    serial_number: nop!(),
+    adaptive_sync: nop!(),
+};
+
+fn mode_changed(data: *mut c_void, mode: *mut ffi::zwlr_output_mode_v1, change: ModeChange) {
+    let notifier = unsafe { &*(data as *const Notifier) };
+    notifier.send(Event::ModeChanged {
+        mode: Object::from_raw(mode),
+        change,
+    });
+}
+
+const MODE_LISTENER: ffi::zwlr_output_mode_v1_listener = ffi::zwlr_output_mode_v1_listener {
+    size: {
+        extern "C" fn size(
+            data: *mut c_void,
+            mode: *mut ffi::zwlr_output_mode_v1,
+            width: i32,
+            height: i32,
+        ) {
+            guard("size", || {
+                mode_changed(data, mode, ModeChange::Size([width, height]));
+            });
+        }
+        Some(size)
+    },
+    refresh: {
+        extern "C" fn refresh(
+            data: *mut c_void,
+            mode: *mut ffi::zwlr_output_mode_v1,
+            refresh: i32,
+        ) {
+            guard("refresh", || {
+                mode_changed(data, mode, ModeChange::Refresh(refresh));
+            });
+        }
+        Some(refresh)
+    },
+    preferred: nop!(),
+    finished: {
+        extern "C" fn finished(data: *mut c_void, mode: *mut ffi::zwlr_output_mode_v1) {
+            guard("finished", || {
+                mode_changed(data, mode, ModeChange::Finished);
+            });
+        }
+        Some(finished)
+    },
+};
+
+fn configured(
+    data: *mut c_void,
+    configuration: *mut ffi::zwlr_output_configuration_v1,
+    applied: Option<bool>,
+) {
+    let notifier = unsafe { &*(data as *const Notifier) };
+    notifier.send(Event::Configured {
+        configuration: Object::from_raw(configuration),
+        applied,
+    });
+}
+
+pub const OUTPUT_CONFIGURATION_LISTENER: ffi::zwlr_output_configuration_v1_listener =
+    ffi::zwlr_output_configuration_v1_listener {
+        succeeded: {
+            extern "C" fn succeeded(
+                data: *mut c_void,
+                configuration: *mut ffi::zwlr_output_configuration_v1,
+            ) {
+                guard("succeeded", || {
+                    configured(data, configuration, Some(true));
+                });
+            }
+            Some(succeeded)
+        },
+        failed: {
+            extern "C" fn failed(
+                data: *mut c_void,
+                configuration: *mut ffi::zwlr_output_configuration_v1,
+            ) {
+                guard("failed", || {
+                    configured(data, configuration, Some(false));
+                });
+            }
+            Some(failed)
+        },
+        cancelled: {
+            extern "C" fn cancelled(
+                data: *mut c_void,
+                configuration: *mut ffi::zwlr_output_configuration_v1,
+            ) {
+                guard("cancelled", || {
+                    configured(data, configuration, None);
+                });
+            }
+            Some(cancelled)
+        },
+    };
+
+pub const SURFACE_LISTENER: ffi::wl_surface_listener = ffi::wl_surface_listener {
+    enter: nop!(),
+    leave: nop!(),
+    // enter: {
+    //     extern "C" fn enter(
+    //         data: *mut c_void,
+    //         surface: *mut ffi::wl_surface,
+    //         output: *mut ffi::wl_output,
+    //     ) {
+    //         dbg!(output);
+    //     }
+    //     Some(enter)
+    // },
+    // leave: {
+    //     extern "C" fn leave(
+    //         data: *mut c_void,
+    //         surface: *mut ffi::wl_surface,
+    //         output: *mut ffi::wl_output,
+    //     ) {
+    //         dbg!(output);
+    //     }
+    //     Some(leave)
+    // },
+    preferred_buffer_scale: {
+        extern "C" fn scale(data: *mut c_void, surface: *mut ffi::wl_surface, scale: i32) {
+            guard("scale", || {
+                let notifier = unsafe { &*(data as *const Notifier) };
+                if let Some(surface) = NonNull::new(surface) {
+                    notifier.send(Event::Rescale {
+                        surface,
+                        factor: scale as _,
+                    });
+                }
+            });
+        }
+        Some(scale)
+    },
+    preferred_buffer_transform: {
+        extern "C" fn transform(data: *mut c_void, surface: *mut ffi::wl_surface, transform: u32) {
+            guard("transform", || {
+                let notifier = unsafe { &*(data as *const Notifier) };
+                if let Some(surface) = NonNull::new(surface) {
+                    notifier.send(Event::Retransform {
+                        surface,
+                        transform: transform as _,
+                    });
+                }
+            });
+        }
+        Some(transform)
+    },
+};
+
+pub const LAYER_SURFACE_LISTENER: ffi::zwlr_layer_surface_v1_listener =
+    ffi::zwlr_layer_surface_v1_listener {
+        configure: {
+            extern "C" fn configure(
+                data: *mut c_void,
+                surface: *mut ffi::zwlr_layer_surface_v1,
+                serial: u32,
+                width: u32,
+                height: u32,
+            ) {
+                guard("configure", || {
+                    unsafe { ffi::zwlr_layer_surface_v1_ack_configure(surface, serial) };
+                    let notifier = unsafe { &*(data as *const Notifier) };
+                    if let Some(object) = NonNull::new(surface as _) {
+                        notifier.send(Event::Resize {
+                            object,
+                            size: [width, height],
+                        });
+                    }
+                });
+            }
+            Some(configure)
+        },
+        closed: nop!(),
+    };
+
+pub const CALLBACK_LISTENER: ffi::wl_callback_listener = ffi::wl_callback_listener {
+    done: {
+        extern "C" fn done(
+            data: *mut c_void,
+            callback: *mut ffi::wl_callback,
+            _callback_data: u32,
+        ) {
+            guard("done", || {
+                let notifier = unsafe { &*(data as *const Notifier) };
+                notifier.send(Event::CallbackDone(Object::from_raw(callback)));
+            });
+        }
+        Some(done)
+    },
+};
+
+/// Handled in place, `data` points to the `Cell<bool>` telling whether the buffer is busy.
+pub const BUFFER_LISTENER: ffi::wl_buffer_listener = ffi::wl_buffer_listener {
+    release: {
+        extern "C" fn release(data: *mut c_void, _: *mut ffi::wl_buffer) {
+            guard("release", || {
+                let busy = unsafe { &*(data as *const Cell<bool>) };
+                busy.set(false);
+            });
+        }
+        Some(release)
+    },
+};
+
+#[derive_where(Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Object<T>(NonNull<T>);
+
+impl<T> Debug for Object<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let proxy = self.0.cast().as_ptr();
+        let name = unsafe { ffi::wl_proxy_get_class(proxy) };
+        let name = unsafe { CStr::from_ptr(name) };
+        let name = name.as_str().map_err(|_| fmt::Error)?;
+        f.debug_tuple(name)
+            .field(&unsafe { ffi::wl_proxy_get_id(proxy) })
+            .finish()?;
+
+        write!(f, "at {:?}", self.0)?;
+        Ok(())
+    }
+}
+
+impl<T> Object<T> {
+    fn from_raw(object: *mut T) -> Self {
+        Self(NonNull::new(object).unwrap())
+    }
+    pub fn as_ptr(self) -> *mut T {
+        self.0.as_ptr()
+    }
+    pub fn as_non_null(self) -> NonNull<T> {
+        self.0
+    }
+}
+
+unsafe impl<T> Send for Object<T> {}
+
+#[derive_where(Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct OwnedObject<T: Interface>(Object<T>);
+
+impl<T: Interface> OwnedObject<T> {
+    /// Takes ownership of a freshly created proxy, panics on null.
+    pub fn from_raw(object: *mut T) -> Self {
+        Self(Object::from_raw(object))
+    }
+    pub fn as_ptr(&self) -> *mut T {
+        self.0.as_ptr()
+    }
+    pub fn as_non_null(&self) -> NonNull<T> {
+        self.0.as_non_null()
+    }
+}
+
+impl<T: Listen> OwnedObject<T> {
+    /// # Safety
+    ///
+    /// `data` has to stay valid for as long as the object lives.
+    pub unsafe fn add_listener(&self, listener: &'static T::Listener, data: *mut c_void) {
+        unsafe { T::add_listener(self.as_ptr(), listener, data) }
+    }
+}
+
+/// A proxy type together with the request that destroys it.
+pub trait Interface {
+    fn drop(ptr: *mut Self);
+}
+
+/// A proxy type that emits events.
+pub trait Listen: Interface {
+    type Listener: 'static;
+    unsafe fn add_listener(ptr: *mut Self, listener: &'static Self::Listener, data: *mut c_void);
+}
+
+macro_rules! interfaces {
+    ($($interface:ident: $destroy:ident $(, $listener:ident)?;)*) => {
+        $(
+            impl Interface for ffi::$interface {
+                fn drop(ptr: *mut Self) {
+                    unsafe { ffi::$destroy(ptr) }
+                }
+            }
+            $(impl Listen for ffi::$interface {
+                type Listener = ffi::$listener;
+                unsafe fn add_listener(
+                    ptr: *mut Self,
+                    listener: &'static Self::Listener,
+                    data: *mut c_void,
+                ) {
+                    concat_idents::concat_idents!(add_listener = $interface, _add_listener {
+                        unsafe { ffi::add_listener(ptr, listener, data) };
+                    });
+                }
+            })?
+        )*
+    };
+}
+
+interfaces! {
+    ext_idle_notification_v1: ext_idle_notification_v1_destroy, ext_idle_notification_v1_listener;
+    wl_buffer: wl_buffer_destroy, wl_buffer_listener;
+    wl_callback: wl_callback_destroy, wl_callback_listener;
+    wl_keyboard: wl_keyboard_release, wl_keyboard_listener;
+    wl_pointer: wl_pointer_release, wl_pointer_listener;
+    wl_shm_pool: wl_shm_pool_destroy;
+    wl_surface: wl_surface_destroy, wl_surface_listener;
+    wl_touch: wl_touch_release, wl_touch_listener;
+    wp_cursor_shape_device_v1: wp_cursor_shape_device_v1_destroy;
+    xdg_activation_token_v1: xdg_activation_token_v1_destroy, xdg_activation_token_v1_listener;
+    xdg_popup: xdg_popup_destroy, xdg_popup_listener;
+    xdg_positioner: xdg_positioner_destroy;
+    xdg_surface: xdg_surface_destroy, xdg_surface_listener;
+    zwlr_data_control_device_v1: zwlr_data_control_device_v1_destroy,
+        zwlr_data_control_device_v1_listener;
+    zwlr_data_control_offer_v1: zwlr_data_control_offer_v1_destroy;
+    zwlr_data_control_source_v1: zwlr_data_control_source_v1_destroy,
+        zwlr_data_control_source_v1_listener;
+    zwlr_gamma_control_v1: zwlr_gamma_control_v1_destroy, zwlr_gamma_control_v1_listener;
+    zwlr_layer_surface_v1: zwlr_layer_surface_v1_destroy, zwlr_layer_surface_v1_listener;
+    zwlr_output_configuration_head_v1: zwlr_output_configuration_head_v1_destroy;
+    zwlr_output_configuration_v1: zwlr_output_configuration_v1_destroy,
+        zwlr_output_configuration_v1_listener;
+    zwlr_output_head_v1: zwlr_output_head_v1_destroy;
+    zwlr_output_mode_v1: zwlr_output_mode_v1_destroy;
+    zwlr_output_power_v1: zwlr_output_power_v1_destroy, zwlr_output_power_v1_listener;
+    zwlr_screencopy_frame_v1: zwlr_screencopy_frame_v1_destroy,
+        zwlr_screencopy_frame_v1_listener;
+    zxdg_output_v1: zxdg_output_v1_destroy, zxdg_output_v1_listener;
+}
+
+impl<T: Interface> Drop for OwnedObject<T> {
+    fn drop(&mut self) {
+        T::drop(self.0.0.as_ptr())
+    }
+}
+
+impl<T: Interface> Borrow<Object<T>> for OwnedObject<T> {
+    fn borrow(&self) -> &Object<T> {
+        &self.0
+    }
+}
+
+pub type Callback = OwnedObject<ffi::wl_callback>;
+
+pub fn new() -> Result<(Daemon, Proxy, Events), Error> {
+    let display = NonNull::new(unsafe { ffi::wl_display_connect(ptr::null_mut()) })
+        .ok_or_else(|| Error::Connect(io::Error::last_os_error()))?;
+    let (notifier, events) = notifier::channel();
+    let mut notifier = Box::pin(notifier);
+    let registry = unsafe { ffi::wl_display_get_registry(display.as_ptr()) };
+    let mut registry_data = Box::new(Registry {
+        builder: Some(GlobalsBuilder::default()),
+        notifier: &raw mut *notifier,
+    });
+    unsafe {
+        ffi::wl_registry_add_listener(
+            registry,
+            &REGISTRY_LISTENER,
+            (&raw mut *registry_data).cast(),
+        )
+    };
+    unsafe { ffi::wl_display_roundtrip(display.as_ptr()) };
+    let globals = registry_data.builder.take().unwrap().build()?;
+    if let Some(wm_base) = globals.wm_base() {
+        unsafe { ffi::xdg_wm_base_add_listener(wm_base, &WM_BASE_LISTENER, ptr::null_mut()) };
+    }
+    // their names come after the roundtrip, in answer to binding them
+    for output in globals.outputs() {
+        let data = &raw mut *notifier;
+        unsafe { ffi::wl_output_add_listener(output.as_ptr(), &OUTPUT_LISTENER, data.cast()) };
+    }
+    // and the heads
+    if let Some(manager) = globals.output_manager() {
+        let data = &raw mut *notifier;
+        unsafe {
+            ffi::zwlr_output_manager_v1_add_listener(manager, &OUTPUT_MANAGER_LISTENER, data.cast())
+        };
+    }
+    let mut proxy = Proxy {
+        globals,
+        registry,
+        _registry_data: registry_data,
+        devices: vec![],
+        notifier,
+    };
+    // and so do the capabilities of the seats
+    for seat in proxy.globals.seats().collect::<Vec<_>>() {
+        proxy.add_seat(seat);
+    }
+    Ok((Daemon { display }, proxy, events))
+}
+
+macro_rules! use_globals {
+    (
+        $($vis:vis $name:ident: $interface:ident),* $(,)?;
+        optional { $($opt_vis:vis $opt_name:ident: $opt_interface:ident),* $(,)? }
+        many { $($many_vis:vis $many_name:ident: $many_interface:ident),* $(,)? }
+    ) => {
+        #[derive(Default)]
+        struct GlobalsBuilder {
+            $($name: *mut ffi::$interface,)*
+            $($opt_name: Option<(u32, NonNull<ffi::$opt_interface>)>,)*
+            $($many_name: Vec<(u32, NonNull<ffi::$many_interface>)>,)*
+            required: Vec<(u32, &'static str)>,
+        }
+
+        impl GlobalsBuilder {
+            fn build(self) -> Result<Globals, Error> {
+                Ok(Globals {
+                    $($name: NonNull::new(self.$name).ok_or(Error::Unsupported(stringify!($interface)))?,)*
+                    $($opt_name: self.$opt_name,)*
+                    $($many_name: self.$many_name,)*
+                    required: self.required,
+                })
+            }
+            fn bind(
+                &mut self,
+                registry: *mut ffi::wl_registry,
+                name: u32,
+                interface_name: *const i8,
+                version: u32,
+            ) {
+                $(
+                    let interface = unsafe { &concat_idents::concat_idents!(interface = $interface, _interface { ffi::interface }) };
+                    if unsafe {
+                        cstr_eq(
+                            Restrict::from_ptr(interface_name),
+                            Restrict::from_ptr(interface.name),
+                        )
+                    } {
+                        self.$name = unsafe { ffi::wl_registry_bind(registry, name, interface, version) }.cast();
+                        self.required.push((name, stringify!($interface)));
+                        return;
+                    }
+                )*
+                $(
+                    let interface = unsafe { &concat_idents::concat_idents!(interface = $opt_interface, _interface { ffi::interface }) };
+                    if unsafe {
+                        cstr_eq(
+                            Restrict::from_ptr(interface_name),
+                            Restrict::from_ptr(interface.name),
+                        )
+                    } {
+                        // newer than the generated code would send events it has no listener slot for
+                        let version = version.min(interface.version as u32);
+                        let global = unsafe { ffi::wl_registry_bind(registry, name, interface, version) };
+                        self.$opt_name = NonNull::new(global.cast()).map(|x| (name, x));
+                        return;
+                    }
+                )*
+                $(
+                    let interface = unsafe { &concat_idents::concat_idents!(interface = $many_interface, _interface { ffi::interface }) };
+                    if unsafe {
+                        cstr_eq(
+                            Restrict::from_ptr(interface_name),
+                            Restrict::from_ptr(interface.name),
+                        )
+                    } {
+                        let version = version.min(interface.version as u32);
+                        let global = unsafe { ffi::wl_registry_bind(registry, name, interface, version) };
+                        self.$many_name.extend(NonNull::new(global.cast()).map(|x| (name, x)));
+                        return;
+                    }
+                )*
+            }
+        }
+
+        pub struct Globals {
+            $($vis $name: NonNull<ffi::$interface>,)*
+            $(
+                /// with the name of its global
+                $opt_vis $opt_name: Option<(u32, NonNull<ffi::$opt_interface>)>,
+            )*
+            $(
+                /// with the names of their globals
+                $many_vis $many_name: Vec<(u32, NonNull<ffi::$many_interface>)>,
+            )*
+            /// the names of the required globals, for telling when one goes
+            required: Vec<(u32, &'static str)>,
+        }
+
+        impl Globals {
+            $($vis fn $name(&self) -> *mut ffi::$interface {
+                self.$name.as_ptr()
+            })*
+            $($opt_vis fn $opt_name(&self) -> Option<*mut ffi::$opt_interface> {
+                self.$opt_name.map(|(_, x)| x.as_ptr())
+            })*
+            $($many_vis fn $many_name(&self) -> impl Iterator<Item = Object<ffi::$many_interface>> {
+                self.$many_name.iter().map(|&(_, x)| Object(x))
+            })*
+            /// Destroys the optional global bound from `name`, a required one stays as it cannot
+            /// be done without. `false` for any other global.
+            fn remove(&mut self, name: u32) -> bool {
+                $(if let Some((x, global)) = self.$opt_name
+                    && x == name
+                {
+                    tracing::warn!(concat!("the compositor removed ", stringify!($opt_interface)));
+                    self.$opt_name = None;
+                    concat_idents::concat_idents!(destroy = $opt_interface, _destroy {
+                        unsafe { ffi::destroy(global.as_ptr()) }
+                    });
+                    return true;
+                })*
+                match self.required.iter().find(|(x, _)| *x == name) {
+                    Some((_, interface)) => {
+                        tracing::error!("the compositor removed {interface}, which is required");
+                        true
+                    }
+                    None => false,
+                }
+            }
+            fn destroy(self) {
+                $(concat_idents::concat_idents!(destroy = $interface, _destroy {
+                    unsafe { ffi::destroy(self.$name.as_ptr()) }
+                });)*
+                $(if let Some((_, global)) = self.$opt_name {
+                    concat_idents::concat_idents!(destroy = $opt_interface, _destroy {
+                        unsafe { ffi::destroy(global.as_ptr()) }
+                    });
+                })*
+                $(for (_, global) in self.$many_name {
+                    concat_idents::concat_idents!(destroy = $many_interface, _destroy {
+                        unsafe { ffi::destroy(global.as_ptr()) }
+                    });
+                })*
+            }
+        }
+    };
+}
+
+use_globals! {
+    pub compositer: wl_compositor,
+    pub layer_shell: zwlr_layer_shell_v1,
+    pub shm: wl_shm;
+    optional {
+        pub activation: xdg_activation_v1,
+        // cursors are loaded from the theme without it
+        pub cursor_shape_manager: wp_cursor_shape_manager_v1,
+        pub data_control_manager: zwlr_data_control_manager_v1,
+        pub gamma_control_manager: zwlr_gamma_control_manager_v1,
+        pub idle_notifier: ext_idle_notifier_v1,
+        pub output_power_manager: zwlr_output_power_manager_v1,
+        pub output_manager: zwlr_output_manager_v1,
+        pub screencopy_manager: zwlr_screencopy_manager_v1,
+        // for names and logical geometry of outputs
+        pub xdg_output_manager: zxdg_output_manager_v1,
+        // for tooltips and menus
+        pub wm_base: xdg_wm_base,
+    }
+    many {
+        pub outputs: wl_output,
+        pub seats: wl_seat,
+    }
+}
+
+#[repr(C)]
+struct Restrict<T: 'static>(&'static T);
+impl<T: 'static> Restrict<T> {
+    fn from_ptr(ptr: *const T) -> Self {
+        Self(unsafe { mem::transmute(ptr) })
+    }
+    fn as_ptr(self) -> *const T {
+        unsafe { mem::transmute(self) }
+    }
+}
+
+unsafe fn cstr_eq(x: Restrict<c_char>, y: Restrict<c_char>) -> bool {
+    let mut ps = [x, y].map(Restrict::as_ptr);
+
+    loop {
+        let [x, y] = ps.map(|p| unsafe { *p });
+        match (x == 0, y == 0) {
+            (true, true) => return true,
+            (false, false) if x == y => {
+                ps = ps.map(|p| unsafe { p.add(1) });
+                continue;
+            }
+            _ => return false,
+        }
+    }
+}
+
+mod notifier;
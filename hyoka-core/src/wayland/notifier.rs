@@ -0,0 +1,169 @@
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    pin::Pin,
+    rc::Rc,
+    task::{Context, Poll, Waker},
+};
+
+use futures::Stream;
+use iced_core::mouse::{self, ScrollDelta};
+
+use super::Event;
+
+/// Input queued past this many events is dropped until the consumer catches up.
+const CAPACITY: usize = 256;
+
+#[derive(Default)]
+struct Queue {
+    events: VecDeque<Event>,
+    waker: Option<Waker>,
+    /// either end is gone
+    closed: bool,
+    /// input dropped since the queue last ran empty
+    dropped: usize,
+}
+
+/// The listeners' end of the event queue, passed to them as their user data, or along with the
+/// seat to those of pointers and touches.
+///
+/// Sending never blocks nor fails, listeners run inside libwayland and must return. Pointer
+/// motion and scrolling coalesce with the same event of the same seat queued last, a rescale to
+/// the factor already queued for the surface is dropped, and so is input once [`CAPACITY`]
+/// events wait.
+/// Everything else carries objects or state that cannot be lost and is always queued, the
+/// compositor only sends it as often as things change.
+pub struct Notifier(Rc<RefCell<Queue>>);
+
+/// The consumer's end, ending once the [`Notifier`] is dropped with the [`super::Proxy`].
+pub struct Events(Rc<RefCell<Queue>>);
+
+pub fn channel() -> (Notifier, Events) {
+    let queue = Rc::new(RefCell::new(Queue::default()));
+    (Notifier(queue.clone()), Events(queue))
+}
+
+impl Notifier {
+    pub fn send(&self, event: Event) {
+        let mut queue = self.0.borrow_mut();
+        let Queue {
+            events,
+            waker,
+            closed,
+            dropped,
+        } = &mut *queue;
+        if *closed {
+            return;
+        }
+        match (events.back_mut(), &event) {
+            // only where the pointer ends up matters
+            (
+                Some(Event::Mouse {
+                    seat,
+                    event: mouse::Event::CursorMoved { position },
+                }),
+                Event::Mouse {
+                    seat: new_seat,
+                    event: mouse::Event::CursorMoved { position: new },
+                },
+            ) if seat == new_seat => {
+                *position = *new;
+                return;
+            }
+            (
+                Some(Event::Mouse {
+                    seat,
+                    event:
+                        mouse::Event::WheelScrolled {
+                            delta: ScrollDelta::Pixels { x, y },
+                        },
+                }),
+                Event::Mouse {
+                    seat: new_seat,
+                    event:
+                        mouse::Event::WheelScrolled {
+                            delta: ScrollDelta::Pixels { x: dx, y: dy },
+                        },
+                },
+            ) if seat == new_seat => {
+                *x += dx;
+                *y += dy;
+                return;
+            }
+            _ => {}
+        }
+        if let Event::Rescale { surface, factor } = event {
+            let queued = events.iter().rev().find_map(|x| match x {
+                Event::Rescale {
+                    surface: queued,
+                    factor,
+                } if *queued == surface => Some(*factor),
+                _ => None,
+            });
+            if queued == Some(factor) {
+                return;
+            }
+        }
+        let input = matches!(
+            event,
+            Event::Mouse {
+                event: mouse::Event::CursorMoved { .. } | mouse::Event::WheelScrolled { .. },
+                ..
+            }
+        );
+        if input && events.len() >= CAPACITY {
+            if *dropped == 0 {
+                tracing::warn!("wayland events are not consumed, dropping input");
+            }
+            *dropped += 1;
+            return;
+        }
+        events.push_back(event);
+        let waker = waker.take();
+        drop(queue);
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+impl Drop for Notifier {
+    fn drop(&mut self) {
+        let mut queue = self.0.borrow_mut();
+        queue.closed = true;
+        let waker = queue.waker.take();
+        drop(queue);
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+impl Stream for Events {
+    type Item = Event;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut queue = self.0.borrow_mut();
+        if let Some(event) = queue.events.pop_front() {
+            return Poll::Ready(Some(event));
+        }
+        if queue.closed {
+            return Poll::Ready(None);
+        }
+        if queue.dropped > 0 {
+            tracing::debug!("dropped {} input events", queue.dropped);
+            queue.dropped = 0;
+        }
+        queue.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl Drop for Events {
+    fn drop(&mut self) {
+        let mut queue = self.0.borrow_mut();
+        queue.closed = true;
+        // fds of pastes nobody answers anymore are closed with them
+        queue.events.clear();
+    }
+}
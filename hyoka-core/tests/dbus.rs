@@ -0,0 +1,229 @@
+//! `Connection` against the fake bus of `dbus::mock`.
+
+use std::time::Duration;
+
+use compio::runtime::{self, Runtime};
+use futures::{StreamExt as _, channel::mpsc, future};
+use hyoka_core::dbus::{
+    Call, Connection, Error, MatchRule,
+    mock::{Bus, Message, Peer},
+};
+
+fn proxy() -> dbus::Proxy<'static> {
+    dbus::Proxy {
+        name: "org.example.Echo".into(),
+        path: "/org/example/Echo".into(),
+        interface: "org.example.Echo".into(),
+    }
+}
+
+/// `name` keeps the socket apart from those of other tests.
+fn run(name: &str, f: impl AsyncFnOnce(Connection, Peer)) {
+    Runtime::new().unwrap().block_on(async {
+        let bus = Bus::new(&format!("dbus-{name}")).await.unwrap();
+        let address = bus.address();
+        let (connection, peer) =
+            future::join(Connection::at(address.as_bytes()), bus.accept()).await;
+        f(connection.unwrap(), peer.unwrap()).await
+    })
+}
+
+/// Reads in the background, like the daemons do.
+fn serve(connection: &Connection) {
+    let mut connection = connection.clone();
+    runtime::spawn(async move { connection.serve().await }).detach();
+}
+
+fn string(msg: &hyoka_core::dbus::Raw) -> String {
+    let s = msg.as_ref().parse::<&dbus::String>().unwrap();
+    String::from_utf8(s.as_bytes().into()).unwrap()
+}
+
+#[test]
+fn replies_go_by_serial() {
+    run("serial", async |mut connection, mut peer| {
+        serve(&connection);
+        let a = connection.method_call(proxy(), "Echo", "a").await.unwrap();
+        let b = connection.method_call(proxy(), "Echo", "b").await.unwrap();
+        let call_a = peer.receive().await.unwrap();
+        let call_b = peer.receive().await.unwrap();
+        assert_eq!(call_a.member(), Some(&b"Echo"[..]));
+        assert_eq!(call_a.path(), Some(&b"/org/example/Echo"[..]));
+        assert_eq!(string(&call_b.message), "b");
+
+        peer.send(Message::method_return(&call_b).arg("to b"))
+            .await
+            .unwrap();
+        peer.send(Message::method_return(&call_a).arg("to a"))
+            .await
+            .unwrap();
+        assert_eq!(string(&a.await.unwrap()), "to a");
+        assert_eq!(string(&b.await.unwrap()), "to b");
+    });
+}
+
+#[test]
+fn error_replies() {
+    run("error", async |mut connection, mut peer| {
+        serve(&connection);
+        let reply = connection.method_call(proxy(), "Echo", "a").await.unwrap();
+        let call = peer.receive().await.unwrap();
+        peer.send(Message::error(&call, "org.example.Error.Nope", "nope"))
+            .await
+            .unwrap();
+        match reply.await {
+            Err(Error::ErrorMessage { name, desc }) => {
+                assert_eq!(name.as_bytes(), b"org.example.Error.Nope");
+                assert_eq!(desc.unwrap().as_bytes(), b"nope");
+            }
+            res => panic!("expected an error reply, got {:?}", res.map(|_| ())),
+        }
+    });
+}
+
+#[test]
+fn unanswered_calls_time_out() {
+    run("timeout", async |mut connection, mut peer| {
+        serve(&connection);
+        let reply = connection.method_call(proxy(), "Echo", "a").await.unwrap();
+        let late = peer.receive().await.unwrap();
+        assert!(matches!(reply.await, Err(Error::Elapsed)));
+
+        // a reply nobody waits for anymore is dropped, the connection goes on
+        peer.send(Message::method_return(&late).arg("late"))
+            .await
+            .unwrap();
+        let reply = connection.method_call(proxy(), "Echo", "b").await.unwrap();
+        let call = peer.receive().await.unwrap();
+        peer.send(Message::method_return(&call).arg("on time"))
+            .await
+            .unwrap();
+        assert_eq!(string(&reply.await.unwrap()), "on time");
+    });
+}
+
+#[test]
+fn sync_reads_pending_replies() {
+    run("sync", async |mut connection, mut peer| {
+        let reply = connection.method_call(proxy(), "Echo", "a").await.unwrap();
+        let call = peer.receive().await.unwrap();
+        peer.send(Message::method_return(&call).arg("a"))
+            .await
+            .unwrap();
+        connection.sync(Duration::from_secs(1)).await.unwrap();
+        assert_eq!(string(&reply.await.unwrap()), "a");
+    });
+}
+
+#[test]
+fn sync_cancels_after_timeout() {
+    run("sync-timeout", async |mut connection, mut peer| {
+        let reply = connection.method_call(proxy(), "Echo", "a").await.unwrap();
+        peer.receive().await.unwrap();
+        connection.sync(Duration::from_millis(50)).await.unwrap();
+        assert!(matches!(reply.await, Err(Error::Elapsed)));
+    });
+}
+
+#[test]
+fn dropped_calls_are_cancelled() {
+    run("cancel", async |mut connection, mut peer| {
+        let reply = connection.method_call(proxy(), "Echo", "a").await.unwrap();
+        drop(reply);
+        peer.receive().await.unwrap();
+        // nothing is pending, so it does not wait for the timeout
+        compio::time::timeout(
+            Duration::from_millis(500),
+            connection.sync(Duration::from_secs(5)),
+        )
+        .await
+        .expect("sync waited for a cancelled call")
+        .unwrap();
+    });
+}
+
+#[test]
+fn signals_reach_matching_subscribers() {
+    run("signals", async |mut connection, mut peer| {
+        serve(&connection);
+        let (sender, mut signals) = mpsc::unbounded();
+        let rule = MatchRule::signal()
+            .sender(":1.5")
+            .interface("org.example.Echo")
+            .member("Changed");
+        let expected = rule.to_string();
+        connection
+            .subscribe(rule, move |msg| {
+                sender.unbounded_send(string(&msg)).unwrap();
+                async {}
+            })
+            .await
+            .unwrap();
+        let add_match = peer.receive().await.unwrap();
+        assert_eq!(add_match.member(), Some(&b"AddMatch"[..]));
+        assert_eq!(string(&add_match.message), expected);
+
+        let signal = |sender, member, arg| {
+            Message::signal("/org/example/Echo", "org.example.Echo", member)
+                .sender(sender)
+                .arg(arg)
+        };
+        peer.send(signal(":1.6", "Changed", "other sender"))
+            .await
+            .unwrap();
+        peer.send(signal(":1.5", "Moved", "other member"))
+            .await
+            .unwrap();
+        peer.send(signal(":1.5", "Changed", "matched"))
+            .await
+            .unwrap();
+        assert_eq!(signals.next().await.unwrap(), "matched");
+    });
+}
+
+#[test]
+fn registered_objects_answer_calls() {
+    run("objects", async |connection, mut peer| {
+        serve(&connection);
+        let replies = connection.clone();
+        connection.register("/org/example/Echo", move |msg| {
+            let mut connection = replies.clone();
+            async move {
+                let message = msg.as_ref();
+                if message.header.fields.member.map(|x| x.as_bytes()) != Some(&b"Echo"[..]) {
+                    return Ok(Call::UnknownMethod);
+                }
+                let Ok(arg) = message.parse::<&dbus::String>() else {
+                    return Ok(Call::InvalidArgs);
+                };
+                connection.reply(&msg, arg).await?;
+                Ok(Call::Answered)
+            }
+        });
+
+        let call = |path, member| Message::method_call(path, "org.example.Echo", member);
+        peer.send(call("/org/example/Echo", "Echo").arg("hi"))
+            .await
+            .unwrap();
+        let reply = peer.receive().await.unwrap();
+        assert_eq!(reply.error_name(), None);
+        assert_eq!(string(&reply.message), "hi");
+
+        for (msg, error) in [
+            (call("/org/example/Echo", "Echo").arg(1u32), "InvalidArgs"),
+            (
+                call("/org/example/Echo", "Shout").arg("hi"),
+                "UnknownMethod",
+            ),
+            (
+                call("/org/example/Elsewhere", "Echo").arg("hi"),
+                "UnknownMethod",
+            ),
+        ] {
+            peer.send(msg).await.unwrap();
+            let reply = peer.receive().await.unwrap();
+            let expected = format!("org.freedesktop.DBus.Error.{error}");
+            assert_eq!(reply.error_name(), Some(expected.as_bytes()));
+        }
+    });
+}
@@ -0,0 +1,264 @@
+//! `hyprland::run` against fake `.socket.sock` and `.socket2.sock` listeners, and single event
+//! lines.
+
+use std::{
+    collections::HashMap,
+    fs,
+    io::{Read as _, Write as _},
+    os::unix::net::UnixListener,
+    path::PathBuf,
+    process, thread,
+    time::Duration,
+};
+
+use compio::runtime::Runtime;
+use hyoka_core::{
+    error::Error,
+    hyprland::{self, Client, Context, Dispatcher, Event, Line},
+};
+
+/// Between the replies of a `[[BATCH]]`, as hyprland separates them.
+const BATCH: &str = "\n\n\n\n\n";
+
+/// A hyprland instance directory with scripted replies and events.
+struct Fixture {
+    dir: PathBuf,
+}
+
+impl Fixture {
+    /// Answers each request found in `replies` once per connection, like hyprland does, and
+    /// sends `events` to the first listener before closing on it, a write per chunk.
+    fn new(
+        name: &str,
+        replies: HashMap<&'static str, String>,
+        events: &'static [&'static str],
+    ) -> Self {
+        let dir = std::env::temp_dir().join(format!("hyoka-{name}-{}", process::id()));
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(&dir).unwrap();
+
+        let requests = UnixListener::bind(dir.join(".socket.sock")).unwrap();
+        thread::spawn(move || {
+            for stream in requests.incoming() {
+                let mut stream = stream.unwrap();
+                let mut buf = [0; 4096];
+                let n = stream.read(&mut buf).unwrap();
+                let request = str::from_utf8(&buf[..n]).unwrap();
+                let reply = replies
+                    .get(request)
+                    .unwrap_or_else(|| panic!("unexpected request {request:?}"));
+                stream.write_all(reply.as_bytes()).unwrap();
+            }
+        });
+
+        let listeners = UnixListener::bind(dir.join(".socket2.sock")).unwrap();
+        thread::spawn(move || {
+            let (mut stream, _) = listeners.accept().unwrap();
+            for chunk in events {
+                stream.write_all(chunk.as_bytes()).unwrap();
+                // for the listener to read each on its own
+                thread::sleep(Duration::from_millis(20));
+            }
+        });
+
+        Self { dir }
+    }
+    /// Runs until the event socket closes, with everything dispatched until then.
+    fn run(&self) -> (hyoka_core::error::Result<()>, Vec<Event>) {
+        let context = Context::at(self.dir.clone());
+        let mut events = vec![];
+        let res = Runtime::new()
+            .unwrap()
+            .block_on(hyprland::run(&context, async |e| events.push(e)));
+        (res, events)
+    }
+}
+
+impl Drop for Fixture {
+    fn drop(&mut self) {
+        fs::remove_dir_all(&self.dir).ok();
+    }
+}
+
+fn state() -> String {
+    let workspaces = [
+        "workspace ID 1 (1) on monitor DP-1:\n\tmonitorID: 0\n\twindows: 2",
+        "workspace ID 3 (web) on monitor DP-1:\n\tmonitorID: 0\n\twindows: 1",
+    ]
+    .join("\n\n");
+    let active_workspace = "workspace ID 3 (web) on monitor DP-1:\n\tmonitorID: 0\n\twindows: 1";
+    let active_window = "Window 55d0f3a1b0c0 -> Firefox:\n\tmapped: 1\n\tworkspace: 3 (web)\n\
+                         \tfullscreen: 0\n\tclass: firefox\n\ttitle: Firefox";
+    [&*workspaces, active_workspace, active_window].join(BATCH)
+}
+
+fn synced() -> [Event; 4] {
+    [
+        Event::Workspaces(vec![(1, "1".into()), (3, "web".into())]),
+        Event::Workspace { id: 3 },
+        Event::ActiveWindow {
+            class: "firefox".into(),
+            title: "Firefox".into(),
+        },
+        Event::Fullscreen(false),
+    ]
+}
+
+fn replies() -> HashMap<&'static str, String> {
+    HashMap::from([
+        ("[[BATCH]]workspaces;activeworkspace;activewindow", state()),
+        (
+            "clients",
+            "Window 55d0f3a1b0c0 -> Firefox:\n\tworkspace: 3 (web)\n\tclass: firefox\n\n\
+             Window 55d0f3a1b0d8 -> foot:\n\tworkspace: -98 (special:term)\n\tclass: foot\n"
+                .into(),
+        ),
+    ])
+}
+
+#[test]
+fn initial_state() {
+    let fixture = Fixture::new("hyprland-initial-state", replies(), &[]);
+    let (res, events) = fixture.run();
+    assert!(matches!(res, Err(Error::Closed("hyprland"))), "{res:?}");
+    assert_eq!(events, synced());
+}
+
+#[test]
+fn events() {
+    let fixture = Fixture::new(
+        "hyprland-events",
+        replies(),
+        &["workspacev2>>1,1\n\
+         createworkspacev2>>4,4\n\
+         renameworkspace>>4,music\n\
+         activewindow>>foot,~/src\n\
+         fullscreen>>1\n\
+         submap>>resize\n\
+         submap>>\n\
+         urgent>>55d0f3a1b0c0\n\
+         destroyworkspacev2>>4,music\n\
+         configreloaded>>\n"],
+    );
+    let (res, events) = fixture.run();
+    assert!(matches!(res, Err(Error::Closed("hyprland"))), "{res:?}");
+    let expected = synced().into_iter().chain([
+        Event::Workspace { id: 1 },
+        Event::CreateWorkspace {
+            id: 4,
+            name: "4".into(),
+        },
+        Event::RenameWorkspace {
+            id: 4,
+            name: "music".into(),
+        },
+        Event::ActiveWindow {
+            class: "foot".into(),
+            title: "~/src".into(),
+        },
+        Event::Fullscreen(true),
+        Event::Submap("resize".into()),
+        Event::Submap("".into()),
+        Event::Urgent { id: 3 },
+        Event::DestroyWorkspace { id: 4 },
+    ]);
+    assert_eq!(events, expected.collect::<Vec<_>>());
+}
+
+#[test]
+fn monitor_change_resyncs() {
+    let fixture = Fixture::new(
+        "hyprland-monitor-change",
+        replies(),
+        &["monitoradded>>DP-2\n"],
+    );
+    let (res, events) = fixture.run();
+    assert!(matches!(res, Err(Error::Closed("hyprland"))), "{res:?}");
+    let expected = synced().into_iter().chain(synced());
+    assert_eq!(events, expected.collect::<Vec<_>>());
+}
+
+#[test]
+fn events_split_across_reads() {
+    let fixture = Fixture::new(
+        "hyprland-split",
+        replies(),
+        &[
+            "workspa",
+            "cev2>>1,1\nactivewindow>>foot,~/s",
+            "rc\nfullscreen>>1",
+            "\n",
+        ],
+    );
+    let (res, events) = fixture.run();
+    assert!(matches!(res, Err(Error::Closed("hyprland"))), "{res:?}");
+    let expected = synced().into_iter().chain([
+        Event::Workspace { id: 1 },
+        Event::ActiveWindow {
+            class: "foot".into(),
+            title: "~/src".into(),
+        },
+        Event::Fullscreen(true),
+    ]);
+    assert_eq!(events, expected.collect::<Vec<_>>());
+}
+
+#[test]
+fn malformed_lines_are_skipped() {
+    for line in [
+        &b""[..],
+        b">>",
+        b"workspacev2>",
+        b"workspacev2>>",
+        b"workspacev2>>x,1",
+        b"createworkspacev2>>4",
+        b"activewindow>>no comma",
+        b"urgent>>\xff",
+    ] {
+        assert_eq!(hyprland::parse_line(line), None, "{line:?}");
+    }
+}
+
+#[test]
+fn clients() {
+    let clients = &replies()["clients"];
+    let client = |address: &str, class: &str, title: &str, workspace| Client {
+        address: address.into(),
+        class: class.into(),
+        title: title.into(),
+        workspace,
+    };
+    assert_eq!(
+        hyprland::parse_clients(clients),
+        [
+            client("55d0f3a1b0c0", "firefox", "Firefox", Some(3)),
+            client("55d0f3a1b0d8", "foot", "foot", None),
+        ],
+    );
+    // one without a workspace is not a window of any
+    let clients = hyprland::parse_clients("Window 55d0f3a1b0c0 -> Firefox:\n\tclass: firefox\n");
+    assert!(clients.is_empty(), "{clients:?}");
+}
+
+#[test]
+fn window_dispatchers() {
+    assert_eq!(
+        Dispatcher::FocusWindow("55d0f3a1b0c0".into()).to_string(),
+        "focuswindow address:0x55d0f3a1b0c0",
+    );
+    assert_eq!(
+        Dispatcher::MoveWindowToWorkspace(4, "55d0f3a1b0c0".into()).to_string(),
+        "movetoworkspacesilent 4,address:0x55d0f3a1b0c0",
+    );
+}
+
+#[test]
+fn invalid_utf8_is_replaced() {
+    assert_eq!(
+        hyprland::parse_line(b"activewindow>>foot,caf\xc3"),
+        Some(Line::Event(Event::ActiveWindow {
+            class: "foot".into(),
+            title: "caf\u{fffd}".into(),
+        })),
+    );
+}
@@ -0,0 +1,44 @@
+//! `uevent::Message` on datagrams as the kernel sends them, and cut short.
+
+use hyoka_core::uevent::Message;
+
+const CHANGE: &[u8] = b"change@/devices/LNXSYSTM:00/PNP0C0A:00/power_supply/BAT0\0\
+                        ACTION=change\0SUBSYSTEM=power_supply\0POWER_SUPPLY_TYPE=Battery\0\
+                        POWER_SUPPLY_CAPACITY=42\0";
+
+#[test]
+fn properties() {
+    let message = Message::parse(CHANGE).unwrap();
+    assert_eq!(
+        message.header,
+        "change@/devices/LNXSYSTM:00/PNP0C0A:00/power_supply/BAT0"
+    );
+    assert_eq!(
+        message.properties().collect::<Vec<_>>(),
+        [
+            ("ACTION", "change"),
+            ("SUBSYSTEM", "power_supply"),
+            ("POWER_SUPPLY_TYPE", "Battery"),
+            ("POWER_SUPPLY_CAPACITY", "42"),
+        ],
+    );
+}
+
+#[test]
+fn truncated() {
+    // the capacity lost its last digit and its nul
+    let message = Message::parse(&CHANGE[..CHANGE.len() - 2]).unwrap();
+    assert_eq!(
+        message.properties().last(),
+        Some(("POWER_SUPPLY_TYPE", "Battery"))
+    );
+    assert!(Message::parse(&CHANGE[..20]).is_none());
+    assert!(Message::parse(b"").is_none());
+}
+
+#[test]
+fn malformed() {
+    assert!(Message::parse(b"libudev\0\xfe\xed\xca\xfe\0").is_none());
+    let message = Message::parse(b"add@/x\0NOVALUE\0K=\xff\0A=b\0").unwrap();
+    assert_eq!(message.properties().collect::<Vec<_>>(), [("A", "b")]);
+}
@@ -0,0 +1,119 @@
+//! The overflow policy of the wayland event queue, without a compositor.
+
+use std::ptr::NonNull;
+
+use futures::{FutureExt as _, StreamExt as _};
+use hyoka_core::wayland::{self, Event, Events};
+use iced_core::{
+    Point,
+    mouse::{self, ScrollDelta},
+};
+
+/// Never dereferenced, only compared.
+fn fake<T>(address: usize) -> NonNull<T> {
+    NonNull::new(address as *mut _).unwrap()
+}
+
+fn moved_on(seat: usize, x: f32) -> Event {
+    Event::Mouse {
+        seat: fake(seat),
+        event: mouse::Event::CursorMoved {
+            position: Point::new(x, 0.0),
+        },
+    }
+}
+
+fn moved(x: f32) -> Event {
+    moved_on(8, x)
+}
+
+fn scrolled(y: f32) -> Event {
+    Event::Mouse {
+        seat: fake(8),
+        event: mouse::Event::WheelScrolled {
+            delta: ScrollDelta::Pixels { x: 0.0, y },
+        },
+    }
+}
+
+fn pressed(serial: u32) -> Event {
+    Event::Pressed {
+        seat: fake(8),
+        serial,
+    }
+}
+
+/// What is queued, as its debug output since events do not compare.
+fn drain(events: &mut Events) -> Vec<String> {
+    let mut res = vec![];
+    while let Some(Some(event)) = events.next().now_or_never() {
+        res.push(format!("{event:?}"));
+    }
+    res
+}
+
+#[test]
+fn input_coalesces() {
+    let (notifier, mut events) = wayland::channel();
+    notifier.send(moved(1.0));
+    notifier.send(moved(2.0));
+    notifier.send(pressed(7));
+    notifier.send(moved(3.0));
+    notifier.send(scrolled(1.0));
+    notifier.send(scrolled(2.5));
+    let expected = [moved(2.0), pressed(7), moved(3.0), scrolled(3.5)];
+    assert_eq!(drain(&mut events), expected.map(|x| format!("{x:?}")));
+}
+
+#[test]
+fn seats_move_apart() {
+    let (notifier, mut events) = wayland::channel();
+    notifier.send(moved_on(8, 1.0));
+    notifier.send(moved_on(16, 2.0));
+    notifier.send(moved_on(16, 3.0));
+    let expected = [moved_on(8, 1.0), moved_on(16, 3.0)];
+    assert_eq!(drain(&mut events), expected.map(|x| format!("{x:?}")));
+}
+
+#[test]
+fn duplicate_rescales_are_dropped() {
+    let (notifier, mut events) = wayland::channel();
+    let [a, b] = [8, 16].map(fake);
+    let rescale = |surface, factor| Event::Rescale { surface, factor };
+    for event in [
+        rescale(a, 2),
+        rescale(b, 2),
+        rescale(a, 2),
+        rescale(a, 1),
+        rescale(a, 2),
+    ] {
+        notifier.send(event);
+    }
+    let expected = [rescale(a, 2), rescale(b, 2), rescale(a, 1), rescale(a, 2)];
+    assert_eq!(drain(&mut events), expected.map(|x| format!("{x:?}")));
+}
+
+#[test]
+fn only_input_is_dropped_once_full() {
+    let (notifier, mut events) = wayland::channel();
+    for serial in 0..1000 {
+        notifier.send(pressed(serial));
+        notifier.send(moved(serial as f32));
+    }
+    let queued = drain(&mut events);
+    let pressed = queued.iter().filter(|x| x.starts_with("Pressed")).count();
+    assert_eq!(pressed, 1000);
+    assert!(queued.len() < 2000, "{} events queued", queued.len());
+}
+
+#[test]
+fn ends_with_the_notifier() {
+    let (notifier, mut events) = wayland::channel();
+    notifier.send(pressed(1));
+    drop(notifier);
+    assert!(matches!(
+        events.next().now_or_never(),
+        Some(Some(Event::Pressed { serial: 1, .. }))
+    ));
+    assert!(matches!(events.next().now_or_never(), Some(None)));
+}
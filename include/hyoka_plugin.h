@@ -0,0 +1,41 @@
+/* Bar items for hyoka, loaded from $XDG_CONFIG_HOME/hyoka/plugins/*.so and shown by the
+ * "plugins" module in file name order. */
+#ifndef HYOKA_PLUGIN_H
+#define HYOKA_PLUGIN_H
+
+#include <stdint.h>
+
+#define HYOKA_PLUGIN_ABI 1
+
+enum hyoka_action {
+	HYOKA_ACTION_CLICK,
+	HYOKA_ACTION_MIDDLE_CLICK,
+	HYOKA_ACTION_RIGHT_CLICK,
+	HYOKA_ACTION_SCROLL_UP,
+	HYOKA_ACTION_SCROLL_DOWN,
+};
+
+struct hyoka_host {
+	const void *data;
+	/* Replaces the output of the item, from any thread. Either a json object
+	 * {"text", "tooltip", "class"} or up to three lines in that order, like custom modules
+	 * print. */
+	void (*update)(const void *data, const char *output);
+};
+
+struct hyoka_plugin {
+	uint32_t abi;
+	const char *name;
+	/* Called once on the main thread, the host stays valid until deinit. Returns the state
+	 * passed to the others, NULL on failure. Must not block. */
+	void *(*init)(const struct hyoka_host *host);
+	/* On the main thread, must not block. */
+	void (*action)(void *state, uint32_t action);
+	/* Stops whatever calls update before returning. */
+	void (*deinit)(void *state);
+};
+
+/* The only symbol looked up, the result must live as long as the library. */
+const struct hyoka_plugin *hyoka_plugin(void);
+
+#endif
@@ -0,0 +1,698 @@
+use std::{env, fs, io, path::PathBuf, time::Duration};
+
+use chrono::NaiveTime;
+use iced::Color;
+use rustc_hash::FxHashMap;
+use serde::{Deserialize, Deserializer, de};
+
+use crate::modules::hyprland::Dispatcher;
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub updates: Updates,
+    pub custom: Vec<Custom>,
+    /// only read at startup
+    pub scripts: Vec<Script>,
+    pub systemd: Systemd,
+    pub render: Render,
+    pub icons: Icons,
+    pub colors: Colors,
+    pub layout: Layout,
+    /// which outputs get a bar, and with which modules; every output shows `layout` when empty
+    pub bars: Vec<BarProfile>,
+    pub bar: Bar,
+    pub tooltip: Tooltip,
+    /// by module name, each button set here replaces what the module does by default
+    pub actions: FxHashMap<String, Actions>,
+    /// shown side by side by the clock module, one with the default formats when empty
+    pub clock: Vec<Clock>,
+    pub world_clock: WorldClock,
+    pub timer: Timer,
+    pub battery: Battery,
+    pub fans: Fans,
+    pub processes: Processes,
+    pub vpn: Vpn,
+    pub clipboard: Clipboard,
+    pub screenshot: Screenshot,
+    pub night_light: NightLight,
+    pub idle: Idle,
+    pub workspaces: Workspaces,
+    pub effects: Effects,
+    /// only read at startup
+    pub log: Log,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct Updates {
+    /// seconds between two checks
+    pub interval: u64,
+    /// AUR helper queried with `-Qua`, e.g. "paru" or "yay"
+    pub aur: Option<String>,
+    /// spawned when the module is clicked
+    pub command: Vec<String>,
+}
+
+impl Default for Updates {
+    fn default() -> Self {
+        Self {
+            interval: 30 * 60,
+            aur: None,
+            command: ["foot", "sudo", "pacman", "-Syu"].map(Into::into).into(),
+        }
+    }
+}
+
+impl Updates {
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(self.interval)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Custom {
+    /// shell command producing the module output
+    pub exec: String,
+    /// seconds between two runs of `exec`; without it `exec` keeps running and every line
+    /// it prints replaces the output
+    pub interval: Option<u64>,
+    pub on_click: Option<String>,
+    pub on_middle_click: Option<String>,
+    pub on_right_click: Option<String>,
+    pub on_scroll_up: Option<String>,
+    pub on_scroll_down: Option<String>,
+}
+
+/// A module defined by a Rhai script, see [`crate::modules::script::run`].
+#[derive(Debug, Clone, Deserialize)]
+pub struct Script {
+    /// relative to the directory of this file
+    pub path: PathBuf,
+    /// seconds between two calls of its `view`; without it the output changes only after
+    /// the script handles a click or scroll
+    pub interval: Option<u64>,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct Systemd {
+    /// system units to watch, e.g. "nginx.service"
+    pub units: Vec<String>,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct Render {
+    /// only read at startup
+    pub backend: Backend,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Backend {
+    /// software rendering into shared memory
+    #[default]
+    TinySkia,
+    /// Vulkan or GLES, cheaper for wide bars at high scale factors
+    Wgpu,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct Log {
+    /// like `RUST_LOG`, e.g. "info,hyoka::consumer=debug"; `--log` and `RUST_LOG` take
+    /// precedence, debug enables the spans timing wayland dispatch, dbus calls and redraws
+    pub filter: String,
+    pub output: LogOutput,
+    /// daily files kept with `output = "file"`
+    pub keep: usize,
+}
+
+impl Default for Log {
+    fn default() -> Self {
+        Self {
+            filter: "info".into(),
+            output: LogOutput::default(),
+            keep: 7,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LogOutput {
+    #[default]
+    Stderr,
+    /// stderr when journald is not there
+    Journal,
+    /// `$XDG_STATE_HOME/hyoka/hyoka.<date>.log`, a new one every day
+    File,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct Icons {
+    /// icon theme, the desktop's (settings portal, then GTK settings) when unset
+    pub theme: Option<String>,
+    /// themes tried in order when `theme` misses an icon
+    pub fallback: Vec<String>,
+    /// icons kept loaded, each name at each size and scale counts
+    pub cache: usize,
+}
+
+impl Default for Icons {
+    fn default() -> Self {
+        Self {
+            theme: None,
+            fallback: vec![],
+            cache: 128,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct Bar {
+    /// pixels between the bar and the output edges: top, right, bottom, left
+    pub margin: [i32; 4],
+    /// of the background corners
+    pub radius: f32,
+    pub border: BarBorder,
+    /// urgent modules stay highlighted instead of blinking
+    pub steady_urgency: bool,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct BarBorder {
+    pub width: f32,
+    /// the palette's primary color when unset
+    #[serde(deserialize_with = "optional_color")]
+    pub color: Option<Color>,
+}
+
+/// Formats in strftime syntax, an empty one hides its part.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct Clock {
+    pub date: String,
+    pub time: String,
+    pub weekday: String,
+    /// of month and day names, e.g. "ja_JP"; `LC_TIME` when unset
+    pub locale: Option<String>,
+    /// from the tz database, e.g. "Asia/Tokyo"; the local one when unset
+    pub timezone: Option<String>,
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Self {
+            date: "%Y %b %d".into(),
+            time: "%H:%M:%S".into(),
+            weekday: "%a".into(),
+            locale: None,
+            timezone: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct Timer {
+    /// counted down by the timer module
+    pub minutes: u64,
+    /// shell command run when the time is up, e.g. `notify-send "Time is up"`
+    pub on_expire: Option<String>,
+}
+
+impl Default for Timer {
+    fn default() -> Self {
+        Self {
+            minutes: 25,
+            on_expire: None,
+        }
+    }
+}
+
+impl Timer {
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs(self.minutes * 60)
+    }
+}
+
+/// Sounds and commands, by the event playing them.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct Effects {
+    /// percent the battery counts as low at, while discharging
+    pub low_battery_level: u8,
+    pub low_battery: Effect,
+    /// a tray item or an urgent window
+    pub attention: Effect,
+    /// as well as `timer.on_expire`
+    pub timer_expired: Effect,
+}
+
+impl Default for Effects {
+    fn default() -> Self {
+        Self {
+            low_battery_level: 10,
+            low_battery: Effect::default(),
+            attention: Effect::default(),
+            timer_expired: Effect::default(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct Effect {
+    /// event id in the freedesktop sound theme, e.g. "battery-caution", played by
+    /// `canberra-gtk-play`
+    pub sound: Option<String>,
+    /// shell command, e.g. a haptic feedback tool
+    pub command: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct Battery {
+    /// percent the charge stops at while the limit is on, where the battery takes a threshold
+    pub charge_limit: u8,
+}
+
+impl Default for Battery {
+    fn default() -> Self {
+        Self { charge_limit: 80 }
+    }
+}
+
+/// The fans module, which shows the fastest fan and lists every one in its tooltip.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct Fans {
+    /// rpm from which the fastest fan is shown in the warning color
+    pub warning: Option<u32>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct Processes {
+    /// listed by the popup of the cpu and memory modules
+    pub count: usize,
+}
+
+impl Default for Processes {
+    fn default() -> Self {
+        Self { count: 10 }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct Vpn {
+    /// prints the public IP address, run only when asked for it
+    pub ip_command: Vec<String>,
+}
+
+impl Default for Vpn {
+    fn default() -> Self {
+        Self {
+            ip_command: ["curl", "-s", "https://api.ipify.org"]
+                .map(Into::into)
+                .into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct Clipboard {
+    /// entries kept for the clipboard module's popup
+    pub history: usize,
+}
+
+impl Default for Clipboard {
+    fn default() -> Self {
+        Self { history: 50 }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct Screenshot {
+    /// run when the screenshot module is clicked, whatever it prints is taken for a PNG image
+    pub command: Vec<String>,
+    /// puts the printed image on the clipboard, and so into its history
+    pub copy: bool,
+}
+
+impl Default for Screenshot {
+    fn default() -> Self {
+        Self {
+            command: ["sh", "-c", r#"grim -g "$(slurp)" -"#]
+                .map(Into::into)
+                .into(),
+            copy: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct NightLight {
+    /// kelvin, 6500 is neutral
+    pub temperature: u32,
+    /// local "HH:MM" at which it turns on by itself, together with `sunrise`
+    pub sunset: Option<String>,
+    /// and off
+    pub sunrise: Option<String>,
+}
+
+impl Default for NightLight {
+    fn default() -> Self {
+        Self {
+            temperature: 4000,
+            sunset: None,
+            sunrise: None,
+        }
+    }
+}
+
+impl NightLight {
+    /// Whether it is on at `now` by the schedule, `None` without one.
+    pub fn scheduled(&self, now: NaiveTime) -> Option<bool> {
+        let parse = |x: &Option<String>| {
+            NaiveTime::parse_from_str(x.as_deref()?, "%H:%M")
+                .inspect_err(|e| tracing::warn!("invalid night light time {x:?}: {e}"))
+                .ok()
+        };
+        let (sunset, sunrise) = (parse(&self.sunset)?, parse(&self.sunrise)?);
+        Some(if sunset <= sunrise {
+            (sunset..sunrise).contains(&now)
+        } else {
+            now >= sunset || now < sunrise
+        })
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct Idle {
+    /// seconds without input after which sensors and update checks pause, 0 never
+    pub timeout: u32,
+}
+
+impl Default for Idle {
+    fn default() -> Self {
+        Self { timeout: 300 }
+    }
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct Workspaces {
+    /// by workspace name, shown instead of it, e.g. a glyph for "web"
+    pub icons: FxHashMap<String, String>,
+    /// ids from 1 to 10 shown like existing workspaces even when Hyprland has not created them
+    pub persistent: Vec<usize>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct WorldClock {
+    /// from the tz database, e.g. "America/New_York"
+    pub zones: Vec<String>,
+    pub format: String,
+}
+
+impl Default for WorldClock {
+    fn default() -> Self {
+        Self {
+            zones: vec![],
+            format: "%a %H:%M".into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct Tooltip {
+    /// milliseconds the pointer rests on an item before its tooltip opens
+    pub delay: u64,
+    /// milliseconds a tooltip stays after the pointer left its item, entering another one
+    /// meanwhile shows that one's tooltip right away
+    pub grace: u64,
+}
+
+impl Default for Tooltip {
+    fn default() -> Self {
+        Self {
+            delay: 300,
+            grace: 150,
+        }
+    }
+}
+
+impl Tooltip {
+    pub fn delay(&self) -> Duration {
+        Duration::from_millis(self.delay)
+    }
+    pub fn grace(&self) -> Duration {
+        Duration::from_millis(self.grace)
+    }
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct Actions {
+    pub click: Option<Action>,
+    pub middle_click: Option<Action>,
+    pub right_click: Option<Action>,
+    /// compositors only tell the focused surface about modifiers, the bar takes keyboard focus
+    /// when clicked as soon as any module sets this
+    pub shift_click: Option<Action>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Action {
+    /// shell command
+    Exec(String),
+    /// opens or closes the calendar
+    Calendar,
+    /// puts the date on the clipboard
+    CopyDate,
+    /// the focused one
+    CloseWindow,
+    /// e.g. `{ hyprland = { move-to-workspace = 3 } }` or `{ hyprland = { raw = "movefocus l" } }`
+    Hyprland(Dispatcher),
+    /// the failed systemd units
+    RestartUnits,
+    /// runs `updates.command`
+    Update,
+    /// starts, pauses or resumes the timer
+    TimerToggle,
+    TimerReset,
+    /// starts or stops the stopwatch
+    StopwatchToggle,
+    StopwatchReset,
+    /// opens or closes the processes using the most CPU
+    TopCpu,
+    /// or memory
+    TopMemory,
+    /// looks the public IP address up with `vpn.ip_command`
+    PublicIp,
+    /// puts the public IP address on the clipboard
+    CopyIp,
+    /// opens or closes the clipboard history
+    ClipboardHistory,
+    /// runs `screenshot.command`
+    Screenshot,
+    /// interrupts the screen recorders running
+    StopRecording,
+    /// turns the night light on or off until the next sunset or sunrise
+    NightLight,
+    /// mutes or unmutes the default source
+    MicrophoneMute,
+    /// opens or closes the list of outputs
+    Monitors,
+    /// opens or closes every workspace with the windows on it
+    WorkspaceOverview,
+    /// opens or closes what can be done with the focused window
+    WindowMenu,
+    /// opens or closes the paired bluetooth devices
+    BluetoothMenu,
+    /// opens or closes the battery menu, with the charge limit toggle
+    BatteryMenu,
+}
+
+/// The bars of some outputs, the first profile naming an output wins.
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct BarProfile {
+    /// like "DP-1", as the compositor names them, or as it describes them with xdg output like
+    /// "Dell Inc. DELL U2720Q (DP-1)"
+    pub output: Vec<String>,
+    /// the top level one when unset
+    pub layout: Option<Layout>,
+}
+
+/// Module names per section of the bar, in order.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct Layout {
+    pub left: Vec<String>,
+    /// kept in the middle of the bar regardless of how wide the sides are
+    pub center: Vec<String>,
+    pub right: Vec<String>,
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Self {
+            left: ["logo", "workspaces", "title"].map(Into::into).into(),
+            center: vec![],
+            right: [
+                "custom", "tray", "units", "updates", "lock", "battery", "clock",
+            ]
+            .map(Into::into)
+            .into(),
+        }
+    }
+}
+
+impl Layout {
+    pub fn contains(&self, module: &str) -> bool {
+        [&self.left, &self.center, &self.right]
+            .into_iter()
+            .flatten()
+            .any(|x| x == module)
+    }
+}
+
+impl Config {
+    /// Of the bars of `profile`, an index into `bars`, or of every bar without profiles.
+    pub fn layout(&self, profile: usize) -> &Layout {
+        self.bars
+            .get(profile)
+            .and_then(|x| x.layout.as_ref())
+            .unwrap_or(&self.layout)
+    }
+    /// Of all the bars there may be.
+    pub fn layouts(&self) -> impl Iterator<Item = &Layout> {
+        (0..self.bars.len().max(1)).map(|x| self.layout(x))
+    }
+    /// Whether any bar shows `module`.
+    pub fn uses(&self, module: &str) -> bool {
+        self.layouts().any(|x| x.contains(module))
+    }
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct Colors {
+    /// used while the desktop prefers dark or has no preference, built-in when unset
+    pub dark: Option<Palette>,
+    /// used while the desktop prefers light, built-in when unset
+    pub light: Option<Palette>,
+    /// generated palettes which replace both, reloaded whenever the generator runs again
+    pub import: Option<Import>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Import {
+    /// `$XDG_CACHE_HOME/wal/colors.json`
+    Pywal,
+    /// the output of `matugen --json hex` saved to this file
+    Matugen(PathBuf),
+}
+
+/// Colors as `"#rrggbb"` or `"#rrggbbaa"`.
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct Palette {
+    #[serde(deserialize_with = "color")]
+    pub background: Color,
+    #[serde(deserialize_with = "color")]
+    pub text: Color,
+    #[serde(deserialize_with = "color")]
+    pub primary: Color,
+    #[serde(deserialize_with = "color")]
+    pub success: Color,
+    #[serde(deserialize_with = "color")]
+    pub warning: Color,
+    #[serde(deserialize_with = "color")]
+    pub danger: Color,
+}
+
+fn color<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+    let s = String::deserialize(deserializer)?;
+    Color::parse(&s).ok_or_else(|| de::Error::custom(format!("invalid color {s:?}")))
+}
+
+fn optional_color<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Color>, D::Error> {
+    color(deserializer).map(Some)
+}
+
+pub fn xdg_config_home() -> PathBuf {
+    env::var_os("XDG_CONFIG_HOME")
+        .map(Into::into)
+        .unwrap_or_else(|| PathBuf::from(env::var_os("HOME").unwrap_or_default()).join(".config"))
+}
+
+pub fn xdg_cache_home() -> PathBuf {
+    env::var_os("XDG_CACHE_HOME")
+        .map(Into::into)
+        .unwrap_or_else(|| PathBuf::from(env::var_os("HOME").unwrap_or_default()).join(".cache"))
+}
+
+pub fn xdg_state_home() -> PathBuf {
+    env::var_os("XDG_STATE_HOME")
+        .map(Into::into)
+        .unwrap_or_else(|| {
+            PathBuf::from(env::var_os("HOME").unwrap_or_default()).join(".local/state")
+        })
+}
+
+/// `$XDG_DATA_HOME` followed by `$XDG_DATA_DIRS`, in order of precedence.
+pub fn xdg_data_dirs() -> Vec<PathBuf> {
+    let home = env::var_os("XDG_DATA_HOME")
+        .map(Into::into)
+        .unwrap_or_else(|| {
+            PathBuf::from(env::var_os("HOME").unwrap_or_default()).join(".local/share")
+        });
+    let dirs = env::var_os("XDG_DATA_DIRS")
+        .filter(|x| !x.is_empty())
+        .unwrap_or_else(|| "/usr/local/share:/usr/share".into());
+    let mut res = vec![home];
+    res.extend(env::split_paths(&dirs));
+    res
+}
+
+pub fn path() -> PathBuf {
+    xdg_config_home().join("hyoka").join("config.toml")
+}
+
+/// Missing or malformed config falls back to defaults, the bar should always come up.
+pub fn load() -> Config {
+    try_load().unwrap_or_default()
+}
+
+/// Like [`load`], but `None` when the file is there and broken, so a reload can keep the
+/// running configuration.
+pub fn try_load() -> Option<Config> {
+    let path = path();
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Some(Config::default()),
+        Err(e) => {
+            tracing::warn!("cannot read {path:?}: {e}");
+            return None;
+        }
+    };
+    toml::from_str(&text)
+        .inspect_err(|e| tracing::warn!("cannot parse {path:?}: {e}"))
+        .ok()
+}
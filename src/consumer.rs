@@ -1,17 +1,30 @@
+use std::{iter, pin::pin};
+
 use crate::{
-    Split,
-    consumer::program::{Message, Runner},
-    modules::{self, battery, dbus::TrayEvent, hyprland, polling, uevent},
+    config::{self, Config},
+    consumer::{
+        program::{Message, Runner},
+        supervisor::{Slot, supervise},
+    },
+    modules::{
+        self, audio, battery, clock, custom,
+        dbus::{BluetoothDevice, BluetoothEvent, SessionEvent, SettingEvent, TrayEvent, UnitState},
+        hyprland, ipc, network, palette, plugin, polling, reload, script, updates,
+    },
     wayland,
 };
 use derive_more::From;
 use futures::{
     SinkExt as _, StreamExt as _,
     channel::mpsc::{self, Sender},
+    future::{self, Either},
 };
 use iced::Theme;
-use iced_tiny_skia::Renderer;
+use iced_renderer::Renderer;
 use rustc_hash::FxHashMap;
+use rustix::process::Signal;
+
+const MAX_BATCH: usize = 64;
 
 #[derive(Debug, From)]
 enum Event {
@@ -25,7 +38,46 @@ enum AppEvent {
     Hyprland(hyprland::Event),
     Battery(BatteryEvent),
     Tray(TrayEvent),
+    Session(SessionEvent),
+    Setting(SettingEvent),
+    Unit(UnitState),
+    Bluetooth(BluetoothEvent),
+    /// paired devices, looked up again after connecting or disconnecting one
+    #[from(skip)]
+    BluetoothDevices(Vec<BluetoothDevice>),
     Polling(polling::Event),
+    Updates(updates::Updates),
+    Custom(custom::Event),
+    Plugin(plugin::Event),
+    Script(script::Event),
+    Vpn(network::Vpn),
+    Microphone(Option<audio::Microphone>),
+    Reload(Config),
+    Palette(palette::Palettes),
+    Ipc(ipc::Command),
+    /// an icon requested earlier finished loading
+    IconLoaded,
+    /// the hover delay or grace period started as this generation ran out
+    #[from(skip)]
+    HoverTimer(u64),
+    /// as printed by `vpn.ip_command`, put on the clipboard as well if asked to
+    #[from(skip)]
+    PublicIp {
+        ip: String,
+        copy: bool,
+    },
+    /// `screenshot.command` succeeded, with the image it printed if any
+    #[from(skip)]
+    Screenshot(Option<clipboard::Content>),
+    /// the confirmation shown after this generation of screenshot is over
+    #[from(skip)]
+    ScreenshotShown(u64),
+    /// the selection as of this generation
+    #[from(skip)]
+    Clipboard {
+        generation: u64,
+        content: clipboard::Content,
+    },
 }
 
 #[derive(Debug)]
@@ -34,12 +86,17 @@ enum BatteryEvent {
     PowerOffline,
     Capacity(u8),
     Status(battery::Status),
+    Plugged,
 }
 
-pub async fn run() {
+/// Runs the bar until it is asked to quit, or until the compositor connection fails.
+///
+/// Everything is torn down either way, so on error the caller can simply run it again.
+pub async fn run() -> Result<(), wayland::Error> {
+    let config = config::load();
     let (mut notifier, mut receiver) = mpsc::channel(4);
 
-    let (wayland_daemon, wayland_proxy, mut wayland_events) = wayland::new();
+    let (wayland_daemon, wayland_proxy, mut wayland_events) = wayland::new()?;
     let mut sender = notifier.clone();
     let wayland = async move {
         loop {
@@ -51,94 +108,143 @@ pub async fn run() {
     };
 
     let mut sender = notifier.clone();
-    let (hyprland_daemon, hyprctl) = hyprland::new().await.split();
-    let init = if let Some(x) = hyprctl.as_ref() {
-        Some(x.controller().await)
-    } else {
-        None
-    };
+    let hyprctl = hyprland::Context::new();
     let hyprland = async {
-        match hyprland_daemon {
-            Some(daemon) => {
-                daemon
-                    .run(init.unwrap(), async |event| {
-                        sender
-                            .send(Event::App(AppEvent::Hyprland(event)))
-                            .await
-                            .unwrap();
-                    })
-                    .await
-            }
-            None => {}
-        }
-    };
-
-    let mut events = notifier.clone();
-    let uevent = uevent::new();
-    let uevent = async {
-        uevent
-            .serve(async move |e| match e {
-                uevent::Event::PowerOnline => {
-                    events.send(BatteryEvent::PowerOnline.into()).await.unwrap()
-                }
-                uevent::Event::PowerOffline => events
-                    .send(BatteryEvent::PowerOffline.into())
+        let Some(context) = &hyprctl else {
+            return;
+        };
+        supervise("hyprland", async || {
+            hyprland::run(context, async |event| {
+                sender
+                    .send(Event::App(AppEvent::Hyprland(event)))
                     .await
-                    .unwrap(),
-                uevent::Event::BatCapacity(x) => {
-                    events.send(BatteryEvent::Capacity(x).into()).await.unwrap()
-                }
-                uevent::Event::BatStatus(x) => {
-                    events.send(BatteryEvent::Status(x).into()).await.unwrap()
-                }
+                    .unwrap();
             })
-            .await;
+            .await
+        })
+        .await
     };
 
     let mut sender = notifier.clone();
     let (polling_controller, mut signals) = mpsc::channel(1);
-    let polling = polling::run(&mut signals, async |e| {
+    let polling = polling::run(
+        clock::needs_seconds(&config),
+        polling::Sensors::of(&config),
+        &mut signals,
+        async |e| {
+            sender.send(e.into()).await.unwrap();
+        },
+    );
+
+    let mut sender = notifier.clone();
+    let (updates_controller, mut updates_signals) = mpsc::channel(1);
+    let updates = updates::run(&config.updates, &mut updates_signals, async |e| {
         sender.send(e.into()).await.unwrap();
     });
 
+    let mut sender = notifier.clone();
+    let (script_controller, mut script_actions) = mpsc::channel(1);
+    let scripts = script::run(&config.scripts, &mut script_actions, async |e| {
+        sender.send(e.into()).await.unwrap();
+    });
+
+    let (plugins, plugin_updates) = plugin::load();
+    let mut sender = notifier.clone();
+    let plugin = plugin::run(plugin_updates, async |e| {
+        sender.send(e.into()).await.unwrap();
+    });
+
+    let mut sender = notifier.clone();
+    let reload = reload::run(async |config| {
+        sender.send(AppEvent::Reload(config).into()).await.unwrap();
+    });
+
+    let mut sender = notifier.clone();
+    let palette = palette::run(config.colors.import.as_ref(), async |palettes| {
+        sender.send(palettes.into()).await.unwrap();
+    });
+
+    let mut sender = notifier.clone();
+    let ipc = ipc::serve(async |command| {
+        sender.send(AppEvent::Ipc(command).into()).await.unwrap();
+    });
+
     let sender = notifier.clone();
-    let (dbus_daemon, dbus_proxy) = modules::dbus::new(Dispatcher(sender)).await.split();
-    let dbus = async {
-        if let Some(daemon) = dbus_daemon {
-            daemon.serve().await;
-        }
-    };
+    let dbus_proxy = Slot::default();
+    let dbus = supervise("session bus", async || {
+        let (daemon, proxy) = modules::dbus::new(Dispatcher(sender.clone())).await?;
+        dbus_proxy.set(Some(proxy));
+        let res = daemon.serve().await;
+        dbus_proxy.set(None);
+        Ok(res?)
+    });
+
+    let sender = notifier.clone();
+    let system_proxy = Slot::default();
+    let system = supervise("system bus", async || {
+        let (daemon, proxy) =
+            modules::dbus::system(Dispatcher(sender.clone()), config.systemd.units.clone()).await?;
+        system_proxy.set(Some(proxy));
+        let res = daemon.serve().await;
+        system_proxy.set(None);
+        Ok(res?)
+    });
 
     notifier.flush().await.unwrap();
+    let display = wayland_daemon.display();
     let mut runner = Runner::new(
         wayland_proxy,
-        wayland_daemon.display(),
-        hyprctl,
-        dbus_proxy,
+        display,
+        hyprctl.clone(),
+        dbus_proxy.clone(),
+        system_proxy.clone(),
         polling_controller,
+        updates_controller,
+        script_controller,
+        notifier.clone(),
+        plugins,
+        config.clone(),
     );
-    let consumer = async move {
-        loop {
-            // TODO: dispatch all pending events at once
-            match receiver.next().await.unwrap() {
-                Event::Wayland(event) => {
-                    runner.dispatch_wayland_event(event).await;
+    let consumer = async {
+        while !runner.quitting() {
+            let first = receiver.next().await.unwrap();
+            // whatever queued up meanwhile shares one redraw, bounded so a chatty source
+            // cannot keep the bar from painting
+            let pending = iter::from_fn(|| receiver.try_next().ok().flatten());
+            for event in iter::once(first).chain(pending).take(MAX_BATCH) {
+                match event {
+                    Event::Wayland(event) => {
+                        runner.dispatch_wayland_event(event).await;
+                    }
+                    Event::App(event) => runner.dispatch_app_event(event).await,
                 }
-                Event::App(event) => runner.dispatch_app_event(event),
             }
+            runner.refresh();
         }
     };
 
-    std::future::join!(
-        wayland_daemon.run(),
-        wayland,
-        consumer,
-        hyprland,
-        uevent,
-        polling,
-        dbus
-    )
-    .await;
+    let signals = async {
+        let terminate = pin!(compio::signal::unix::signal(Signal::TERM.as_raw()));
+        let interrupt = pin!(compio::signal::unix::signal(Signal::INT.as_raw()));
+        future::select(terminate, interrupt).await;
+        tracing::info!("shutting down");
+    };
+
+    let modules = std::future::join!(
+        wayland, hyprland, polling, updates, scripts, plugin, reload, palette, ipc, dbus, system
+    );
+
+    let done = async {
+        future::select(pin!(consumer), future::select(pin!(signals), pin!(modules))).await;
+    };
+    // dropping the losers closes the module sockets
+    let res = match future::select(pin!(done), pin!(wayland_daemon.run())).await {
+        Either::Left(((), _)) => Ok(()),
+        Either::Right((res, _)) => res,
+    };
+    runner.shutdown();
+    wayland::disconnect(display);
+    res
 }
 
 type Callbacks = FxHashMap<wayland::Callback, Box<dyn FnOnce(&mut Runner)>>;
@@ -153,6 +259,26 @@ impl modules::dbus::Dispatcher for Dispatcher {
                 .send(Event::App(AppEvent::Tray(tray_event)))
                 .await
                 .unwrap(),
+            modules::dbus::Event::Session(session_event) => self
+                .0
+                .send(Event::App(AppEvent::Session(session_event)))
+                .await
+                .unwrap(),
+            modules::dbus::Event::Setting(setting_event) => self
+                .0
+                .send(Event::App(AppEvent::Setting(setting_event)))
+                .await
+                .unwrap(),
+            modules::dbus::Event::Unit(unit_state) => self
+                .0
+                .send(Event::App(AppEvent::Unit(unit_state)))
+                .await
+                .unwrap(),
+            modules::dbus::Event::Bluetooth(bluetooth_event) => self
+                .0
+                .send(Event::App(AppEvent::Bluetooth(bluetooth_event)))
+                .await
+                .unwrap(),
         }
     }
 }
@@ -160,5 +286,17 @@ impl modules::dbus::Dispatcher for Dispatcher {
 type UserInterface<'ui> = iced_runtime::UserInterface<'ui, Message, Theme, Renderer>;
 type Element<'ui> = iced::Element<'ui, Message, Theme, Renderer>;
 
+mod activation;
+mod clipboard;
+mod effects;
+mod gpu;
+mod hud;
+mod icon;
+mod night_light;
+mod outputs;
+mod preview;
 mod program;
+mod registry;
+mod seat;
+mod supervisor;
 mod window;
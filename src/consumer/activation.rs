@@ -0,0 +1,67 @@
+use std::ptr::NonNull;
+
+use crate::{
+    consumer::seat::Press,
+    wayland::{self, Object, OwnedObject, ffi},
+};
+
+type Token = ffi::xdg_activation_token_v1;
+
+/// What to start once the token is there.
+pub type Launch = Box<dyn FnOnce(Option<&str>)>;
+
+/// Tokens from xdg activation for the apps we launch, compositors preventing focus stealing
+/// only focus their windows with one.
+pub struct Activation {
+    display: NonNull<ffi::wl_display>,
+    manager: *mut ffi::xdg_activation_v1,
+    pending: Vec<(OwnedObject<Token>, Launch)>,
+}
+
+impl Activation {
+    /// `None` without xdg activation.
+    pub fn new(wayland: &wayland::Proxy, display: NonNull<ffi::wl_display>) -> Option<Self> {
+        Some(Self {
+            display,
+            manager: wayland.globals.activation()?,
+            pending: vec![],
+        })
+    }
+    /// Requests a token for `press` on `surface`, `launch` gets it when done.
+    ///
+    /// Without them the compositor still hands one out, it may just not focus anything with it.
+    pub fn request(
+        &mut self,
+        wayland: &mut wayland::Proxy,
+        press: Option<Press>,
+        surface: Option<NonNull<ffi::wl_surface>>,
+        launch: Launch,
+    ) {
+        let token = OwnedObject::from_raw(unsafe {
+            ffi::xdg_activation_v1_get_activation_token(self.manager)
+        });
+        wayland.listen(&token, &wayland::ACTIVATION_TOKEN_LISTENER);
+        unsafe {
+            if let Some(Press { seat, serial }) = press {
+                ffi::xdg_activation_token_v1_set_serial(token.as_ptr(), serial, seat.as_ptr());
+            }
+            if let Some(surface) = surface {
+                ffi::xdg_activation_token_v1_set_surface(token.as_ptr(), surface.as_ptr());
+            }
+            ffi::xdg_activation_token_v1_commit(token.as_ptr());
+            ffi::wl_display_flush(self.display.as_ptr());
+        }
+        self.pending.push((token, launch));
+    }
+    pub fn done(&mut self, token: Object<Token>, name: &str) {
+        let Some(index) = self
+            .pending
+            .iter()
+            .position(|(x, _)| x.as_ptr() == token.as_ptr())
+        else {
+            return;
+        };
+        let (_, launch) = self.pending.swap_remove(index);
+        launch(Some(name));
+    }
+}
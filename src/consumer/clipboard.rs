@@ -0,0 +1,315 @@
+use std::{
+    collections::VecDeque,
+    ffi::CStr,
+    fs::File,
+    io::{self, Read as _, Write as _},
+    os::fd::{AsRawFd as _, OwnedFd},
+    ptr::NonNull,
+    rc::Rc,
+};
+
+use futures::{SinkExt as _, channel::mpsc::Sender};
+use iced::widget::image;
+use rustc_hash::FxHashMap;
+use rustix::pipe::{PipeFlags, pipe_with};
+
+use crate::{
+    consumer::{AppEvent, Event},
+    wayland::{self, Object, OwnedObject, ffi},
+};
+
+/// Plain text in order of preference, all of them offered for ours.
+const TEXT: [&CStr; 5] = [
+    c"text/plain;charset=utf-8",
+    c"text/plain",
+    c"UTF8_STRING",
+    c"STRING",
+    c"TEXT",
+];
+
+const PNG: &CStr = c"image/png";
+/// bytes, larger images are left out of the history
+const MAX_IMAGE: u64 = 8 << 20;
+
+type Offer = ffi::zwlr_data_control_offer_v1;
+type Source = ffi::zwlr_data_control_source_v1;
+
+/// The regular selection through wlr data control, which needs no keyboard focus.
+pub struct Clipboard {
+    display: NonNull<ffi::wl_display>,
+    manager: *mut ffi::zwlr_data_control_manager_v1,
+    device: OwnedObject<ffi::zwlr_data_control_device_v1>,
+    /// announced but not the selection yet, with their mime types
+    offers: FxHashMap<OwnedObject<Offer>, Vec<String>>,
+    selection: Option<OwnedObject<Offer>>,
+    /// the selection, read in the background whenever it changes
+    current: Option<Entry>,
+    /// earlier selections, the latest first
+    history: VecDeque<Entry>,
+    /// counts selections, a read finishing after the next one began is stale
+    generation: u64,
+    /// ours, while it is the selection
+    source: Option<(OwnedObject<Source>, Entry)>,
+    events: Sender<Event>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Entry {
+    Text(Rc<str>),
+    Image(Rc<Image>),
+}
+
+#[derive(Debug)]
+pub struct Image {
+    png: Vec<u8>,
+    pub handle: image::Handle,
+}
+
+impl PartialEq for Entry {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Entry::Text(a), Entry::Text(b)) => a == b,
+            (Entry::Image(a), Entry::Image(b)) => a.png == b.png,
+            _ => false,
+        }
+    }
+}
+
+impl Entry {
+    fn mimes(&self) -> &'static [&'static CStr] {
+        match self {
+            Entry::Text(_) => &TEXT,
+            Entry::Image(_) => &[PNG],
+        }
+    }
+    fn bytes(&self) -> &[u8] {
+        match self {
+            Entry::Text(text) => text.as_bytes(),
+            Entry::Image(image) => &image.png,
+        }
+    }
+}
+
+/// A selection as read, built on the blocking pool.
+#[derive(Debug)]
+pub enum Content {
+    Text(String),
+    Image { png: Vec<u8>, handle: image::Handle },
+}
+
+impl From<Content> for Entry {
+    fn from(content: Content) -> Self {
+        match content {
+            Content::Text(text) => Entry::Text(text.into()),
+            Content::Image { png, handle } => Entry::Image(Rc::new(Image { png, handle })),
+        }
+    }
+}
+
+impl Clipboard {
+    /// `None` without data control, on compositors other than wlroots-based ones.
+    pub fn new(
+        wayland: &mut wayland::Proxy,
+        display: NonNull<ffi::wl_display>,
+        events: Sender<Event>,
+    ) -> Option<Self> {
+        let manager = wayland.globals.data_control_manager()?;
+        // the history follows the selection of the first seat
+        let seat = wayland.globals.seats().next()?;
+        let device = OwnedObject::from_raw(unsafe {
+            ffi::zwlr_data_control_manager_v1_get_data_device(manager, seat.as_ptr())
+        });
+        wayland.listen(&device, &wayland::DATA_DEVICE_LISTENER);
+        Some(Self {
+            display,
+            manager,
+            device,
+            offers: FxHashMap::default(),
+            selection: None,
+            current: None,
+            history: VecDeque::new(),
+            generation: 0,
+            source: None,
+            events,
+        })
+    }
+    /// The selection as text, `None` until it is read or for anything else.
+    pub fn text(&self) -> Option<Rc<str>> {
+        match &self.current {
+            Some(Entry::Text(text)) => Some(text.clone()),
+            _ => None,
+        }
+    }
+    pub fn history(&self) -> &VecDeque<Entry> {
+        &self.history
+    }
+    pub fn offer(&mut self, offer: Object<Offer>) {
+        self.offers
+            .insert(OwnedObject::from_raw(offer.as_ptr()), vec![]);
+    }
+    pub fn add_mime(&mut self, offer: Object<Offer>, mime: String) {
+        if let Some(mimes) = self.offers.get_mut(&offer) {
+            mimes.push(mime);
+        }
+    }
+    pub fn select(&mut self, offer: Option<Object<Offer>>, primary: bool) {
+        let offer = offer.and_then(|x| self.offers.remove_entry(&x));
+        if primary {
+            // unused, only destroyed
+            return;
+        }
+        self.generation += 1;
+        self.current = None;
+        self.selection = None;
+        let Some((offer, mimes)) = offer else {
+            return;
+        };
+        if let Some(mime) = TEXT
+            .into_iter()
+            .chain([PNG])
+            .find(|x| mimes.iter().any(|m| m.as_bytes() == x.to_bytes()))
+        {
+            self.receive(&offer, mime);
+        }
+        self.selection = Some(offer);
+    }
+    fn receive(&mut self, offer: &OwnedObject<Offer>, mime: &'static CStr) {
+        let Ok((reader, writer)) = pipe_with(PipeFlags::CLOEXEC)
+            .inspect_err(|e| tracing::warn!("cannot read the clipboard: {e}"))
+        else {
+            return;
+        };
+        unsafe {
+            ffi::zwlr_data_control_offer_v1_receive(
+                offer.as_ptr(),
+                mime.as_ptr(),
+                writer.as_raw_fd(),
+            );
+            ffi::wl_display_flush(self.display.as_ptr());
+        }
+        // the source has its own copy, ours would keep the pipe from ever ending
+        drop(writer);
+        let generation = self.generation;
+        let mut events = self.events.clone();
+        compio::runtime::spawn(async move {
+            let content = compio::runtime::spawn_blocking(move || read(reader, mime)).await;
+            let content = match content {
+                Ok(Ok(Some(content))) => content,
+                Ok(Ok(None)) | Err(_) => return,
+                Ok(Err(e)) => {
+                    tracing::warn!("cannot read the clipboard: {e}");
+                    return;
+                }
+            };
+            events
+                .send(
+                    AppEvent::Clipboard {
+                        generation,
+                        content,
+                    }
+                    .into(),
+                )
+                .await
+                .ok();
+        })
+        .detach();
+    }
+    /// Keeps at most `keep` earlier selections.
+    pub fn received(&mut self, generation: u64, content: Content, keep: usize) {
+        if generation != self.generation {
+            return;
+        }
+        let entry = Entry::from(content);
+        // picked again from the history, or copied twice
+        self.history.retain(|x| *x != entry);
+        self.history.push_front(entry.clone());
+        self.history.truncate(keep);
+        self.current = Some(entry);
+    }
+    /// Makes `entry` the selection.
+    pub fn set(&mut self, wayland: &mut wayland::Proxy, entry: Entry) {
+        let source = OwnedObject::from_raw(unsafe {
+            ffi::zwlr_data_control_manager_v1_create_data_source(self.manager)
+        });
+        wayland.listen(&source, &wayland::DATA_SOURCE_LISTENER);
+        unsafe {
+            for mime in entry.mimes() {
+                ffi::zwlr_data_control_source_v1_offer(source.as_ptr(), mime.as_ptr());
+            }
+            ffi::zwlr_data_control_device_v1_set_selection(self.device.as_ptr(), source.as_ptr());
+            ffi::wl_display_flush(self.display.as_ptr());
+        }
+        // destroys the one replaced, there is nothing left to send for it
+        self.source = Some((source, entry));
+    }
+    /// Writes our selection into `fd` for whoever pastes it.
+    pub fn send(&self, source: Object<Source>, fd: OwnedFd) {
+        let Some((_, entry)) = self
+            .source
+            .as_ref()
+            .filter(|(x, _)| x.as_ptr() == source.as_ptr())
+        else {
+            return;
+        };
+        let bytes = entry.bytes().to_vec();
+        compio::runtime::spawn(async move {
+            let written =
+                compio::runtime::spawn_blocking(move || File::from(fd).write_all(&bytes)).await;
+            if let Ok(Err(e)) = written {
+                tracing::warn!("cannot write the clipboard: {e}");
+            }
+        })
+        .detach();
+    }
+    pub fn cancelled(&mut self, source: Object<Source>) {
+        if self
+            .source
+            .as_ref()
+            .is_some_and(|(x, _)| x.as_ptr() == source.as_ptr())
+        {
+            self.source = None;
+        }
+    }
+}
+
+/// All of `reader`, `None` for images too large to keep or that cannot be decoded.
+fn read(reader: OwnedFd, mime: &CStr) -> io::Result<Option<Content>> {
+    let mut bytes = vec![];
+    File::from(reader)
+        .take(MAX_IMAGE + 1)
+        .read_to_end(&mut bytes)?;
+    if mime != PNG {
+        return Ok(Some(Content::Text(
+            String::from_utf8_lossy(&bytes).into_owned(),
+        )));
+    }
+    if bytes.len() as u64 > MAX_IMAGE {
+        return Ok(None);
+    }
+    Ok(image(bytes))
+}
+
+/// A PNG image ready for [`Clipboard::set`], blocks while decoding it.
+pub fn image(png: Vec<u8>) -> Option<Content> {
+    let handle = decode_png(&png)?;
+    Some(Content::Image { png, handle })
+}
+
+fn decode_png(bytes: &[u8]) -> Option<image::Handle> {
+    let mut decoder = png::Decoder::new(io::Cursor::new(bytes));
+    // whatever it holds, as 8-bit RGBA
+    decoder.set_transformations(
+        png::Transformations::normalize_to_color8() | png::Transformations::ALPHA,
+    );
+    let mut reader = decoder
+        .read_info()
+        .inspect_err(|e| tracing::warn!("cannot decode the copied image: {e}"))
+        .ok()?;
+    let mut buf = vec![0; reader.output_buffer_size()?];
+    let info = reader.next_frame(&mut buf).ok()?;
+    if info.color_type != png::ColorType::Rgba {
+        return None;
+    }
+    buf.truncate(info.buffer_size());
+    Some(image::Handle::from_rgba(info.width, info.height, buf))
+}
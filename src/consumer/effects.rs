@@ -0,0 +1,35 @@
+//! Sounds and commands for events of the bar, each event with its own.
+
+use crate::{config, spawn::spawn};
+
+/// What an effect is configured for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trigger {
+    /// the battery dropped to its low level while discharging
+    LowBattery,
+    /// a tray item or a window on another workspace asks for attention
+    Attention,
+    /// the timer ran out
+    TimerExpired,
+}
+
+/// Plays the sound and runs the command configured for `trigger`, neither waited for.
+pub fn fire(config: &config::Effects, trigger: Trigger) {
+    let effect = match trigger {
+        Trigger::LowBattery => &config.low_battery,
+        Trigger::Attention => &config.attention,
+        Trigger::TimerExpired => &config.timer_expired,
+    };
+    tracing::debug!("{trigger:?}: {effect:?}");
+    if let Some(sound) = &effect.sound {
+        // by event id of the freedesktop sound theme, libcanberra picks the file
+        spawn("canberra-gtk-play", ["--id", sound], None)
+            .inspect_err(|e| tracing::warn!("cannot play {sound:?}: {e}"))
+            .ok();
+    }
+    if let Some(command) = &effect.command {
+        spawn("sh", ["-c", command], None)
+            .inspect_err(|e| tracing::warn!("cannot run {command:?}: {e}"))
+            .ok();
+    }
+}
@@ -0,0 +1,161 @@
+use std::{ffi::c_void, ptr::NonNull};
+
+use iced::{Color, Font, Pixels};
+use iced_wgpu::{Engine, graphics::Shell, wgpu};
+use raw_window_handle::{
+    RawDisplayHandle, RawWindowHandle, WaylandDisplayHandle, WaylandWindowHandle,
+};
+use thiserror::Error;
+
+use crate::{
+    consumer::window::{Viewport, WlSurface},
+    wayland,
+};
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("no usable adapter: {0}")]
+    Adapter(#[from] wgpu::RequestAdapterError),
+    #[error("cannot open device: {0}")]
+    Device(#[from] wgpu::RequestDeviceError),
+    #[error("cannot create surface: {0}")]
+    Surface(#[from] wgpu::CreateSurfaceError),
+}
+
+/// Shared by every wgpu backed window, the compositor gets dmabufs instead of shm buffers.
+pub struct Gpu {
+    instance: wgpu::Instance,
+    adapter: wgpu::Adapter,
+    device: wgpu::Device,
+    engine: Engine,
+}
+
+/// Supported everywhere Vulkan or GLES present to Wayland.
+const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Bgra8Unorm;
+
+impl Gpu {
+    pub fn new() -> Result<Self, Error> {
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
+            backends: wgpu::Backends::VULKAN | wgpu::Backends::GL,
+            ..Default::default()
+        });
+        // native adapters resolve right away, nothing to wait for
+        let adapter =
+            futures::executor::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::LowPower,
+                ..Default::default()
+            }))?;
+        let (device, queue) = futures::executor::block_on(
+            adapter.request_device(&wgpu::DeviceDescriptor::default()),
+        )?;
+        let engine = Engine::new(
+            &adapter,
+            device.clone(),
+            queue,
+            FORMAT,
+            None,
+            Shell::headless(),
+        );
+        Ok(Self {
+            instance,
+            adapter,
+            device,
+            engine,
+        })
+    }
+    pub fn renderer(&self, font: Font, size: Pixels) -> iced_wgpu::Renderer {
+        iced_wgpu::Renderer::new(self.engine.clone(), font, size)
+    }
+    pub fn create_surface(
+        &self,
+        display: NonNull<wayland::ffi::wl_display>,
+        surface: WlSurface,
+        viewport: Viewport,
+    ) -> Result<Surface, Error> {
+        let target = wgpu::SurfaceTargetUnsafe::RawHandle {
+            raw_display_handle: RawDisplayHandle::Wayland(WaylandDisplayHandle::new(
+                display.cast::<c_void>(),
+            )),
+            raw_window_handle: RawWindowHandle::Wayland(WaylandWindowHandle::new(
+                surface.cast::<c_void>(),
+            )),
+        };
+        // the window drops its target before the wl_surface goes away
+        let surface = unsafe { self.instance.create_surface_unsafe(target) }?;
+        let capabilities = surface.get_capabilities(&self.adapter);
+        let alpha_mode = [
+            wgpu::CompositeAlphaMode::PreMultiplied,
+            wgpu::CompositeAlphaMode::PostMultiplied,
+        ]
+        .into_iter()
+        .find(|x| capabilities.alpha_modes.contains(x))
+        .unwrap_or(capabilities.alpha_modes[0]);
+        let surface = Surface {
+            surface,
+            device: self.device.clone(),
+            alpha_mode,
+        };
+        surface.configure(viewport);
+        Ok(surface)
+    }
+}
+
+pub struct Surface {
+    surface: wgpu::Surface<'static>,
+    device: wgpu::Device,
+    alpha_mode: wgpu::CompositeAlphaMode,
+}
+
+impl Surface {
+    pub fn configure(&self, viewport: Viewport) {
+        let [width, height] = viewport.buffer_size();
+        self.surface.configure(
+            &self.device,
+            &wgpu::SurfaceConfiguration {
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+                format: FORMAT,
+                width,
+                height,
+                // frame callbacks pace the redraws already
+                present_mode: wgpu::PresentMode::AutoNoVsync,
+                desired_maximum_frame_latency: 1,
+                alpha_mode: self.alpha_mode,
+                view_formats: vec![],
+            },
+        );
+    }
+    pub fn present(
+        &self,
+        renderer: &mut iced_wgpu::Renderer,
+        viewport: Viewport,
+        background: Color,
+    ) {
+        let frame = match self.surface.get_current_texture() {
+            Ok(frame) => frame,
+            Err(wgpu::SurfaceError::Outdated | wgpu::SurfaceError::Lost) => {
+                self.configure(viewport);
+                match self.surface.get_current_texture() {
+                    Ok(frame) => frame,
+                    Err(e) => {
+                        tracing::warn!("cannot get a frame: {e}");
+                        return;
+                    }
+                }
+            }
+            Err(e) => {
+                tracing::warn!("cannot get a frame: {e}");
+                return;
+            }
+        };
+        let view = frame
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        renderer.present(
+            Some(background),
+            FORMAT,
+            &view,
+            &viewport.to_iced_viewport(),
+        );
+        frame.present();
+    }
+}
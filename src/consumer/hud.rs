@@ -0,0 +1,98 @@
+use std::time::Duration;
+
+use iced::{
+    Alignment, Background, Border, Color, Length, Rectangle,
+    widget::{container, stack, text},
+};
+use iced_core::{Renderer as _, renderer::Quad};
+use iced_renderer::Renderer;
+use iced_tiny_skia::graphics;
+
+use crate::consumer::{Element, icon};
+
+const DAMAGE: Color = Color::from_rgb(1.0, 0.0, 0.3);
+
+/// What the frames of a bar cost, drawn over it while `hyoka msg toggle-hud` has it on.
+#[derive(Default)]
+pub struct Hud {
+    /// of the frame before, this one is only measured once it is shown
+    pub timings: Timings,
+    /// as of this frame
+    pub icons: icon::Stats,
+    /// of the last frame, as the ui drew it without the outlines
+    layers: Option<Vec<iced_tiny_skia::Layer>>,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Timings {
+    /// building the ui and handling the redraw event
+    pub layout: Duration,
+    /// drawing the ui and rasterizing, and presenting with wgpu
+    pub draw: Duration,
+    /// damaging and committing the surface
+    pub commit: Duration,
+}
+
+impl Hud {
+    /// The timings in the right corner, over `view`.
+    pub fn view<'a>(&self, view: Element<'a>) -> Element<'a> {
+        let ms = |x: Duration| x.as_secs_f64() * 1000.0;
+        let Timings {
+            layout,
+            draw,
+            commit,
+        } = self.timings;
+        let icon::Stats { hits, misses, len } = self.icons;
+        let label = text(format!(
+            "layout {:.2} ms  draw {:.2} ms  commit {:.2} ms  icons {len} ({hits} hits {misses} \
+             misses)",
+            ms(layout),
+            ms(draw),
+            ms(commit),
+        ))
+        .size(11);
+        let label = container(label)
+            .padding([1, 4])
+            .style(|_| container::Style {
+                background: Some(Color::from_rgba(0.0, 0.0, 0.0, 0.7).into()),
+                text_color: Some(Color::WHITE),
+                ..Default::default()
+            });
+        let overlay = container(label)
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .align_x(Alignment::End)
+            .align_y(Alignment::Center);
+        stack![view, overlay].into()
+    }
+    /// Outlines what changed against the last frame, once the ui is drawn.
+    ///
+    /// Only tiny-skia tracks damage, wgpu repaints the whole surface every frame.
+    pub fn outline(&mut self, renderer: &mut Renderer) {
+        let Renderer::Secondary(tiny_skia) = renderer else {
+            return;
+        };
+        let layers = tiny_skia.layers();
+        let damage = match &self.layers {
+            Some(old) => graphics::damage::diff(
+                old,
+                layers,
+                |layer| vec![layer.bounds],
+                iced_tiny_skia::Layer::damage,
+            ),
+            None => vec![],
+        };
+        self.layers = Some(layers.to_vec());
+        for bounds in damage {
+            renderer.fill_quad(outline(bounds), Background::Color(Color::TRANSPARENT));
+        }
+    }
+}
+
+fn outline(bounds: Rectangle) -> Quad {
+    Quad {
+        bounds,
+        border: Border::default().color(DAMAGE).width(1.0),
+        ..Default::default()
+    }
+}
@@ -0,0 +1,421 @@
+use std::{
+    cell::{OnceCell, RefCell},
+    fs,
+    hash::RandomState,
+    io,
+    num::NonZero,
+    path::{Path, PathBuf},
+    rc::Rc,
+    sync::{Arc, Mutex, OnceLock},
+};
+
+use futures::{SinkExt as _, channel::mpsc::Sender};
+use iced::{
+    Length, Theme,
+    theme::Palette,
+    widget::{image, svg},
+};
+use lru::LruCache;
+use rustix::{
+    fs::{Mode, OFlags},
+    mm::{MapFlags, ProtFlags},
+    path::Arg as _,
+};
+
+use crate::{
+    TinyString,
+    config::{self, xdg_config_home},
+    consumer::{AppEvent, Element, Event, icon::cache::Cache},
+    mapping::Mapping,
+};
+
+/// logical pixels, the largest an icon is shown at
+const SIZE: u16 = 64;
+/// shown for windows whose icon cannot be found
+const GENERIC_APP: &str = "application-x-executable";
+
+#[derive(Debug, Clone)]
+pub enum Handle {
+    Pixmap(image::Handle),
+    Svg(svg::Handle),
+}
+
+impl Handle {
+    pub fn load(self) -> Element<'static> {
+        self.load_size(24)
+    }
+    pub fn load_size(self, size: impl Into<Length> + Copy) -> Element<'static> {
+        match self {
+            Handle::Pixmap(handle) => image(handle).width(size).into(),
+            Handle::Svg(handle) => svg(handle).width(size).height(size).into(),
+        }
+    }
+}
+
+/// Empty until the lookup finishes, then shared by everyone who asked for the same name.
+#[derive(Debug, Clone)]
+pub struct Icon(Rc<Slot>);
+
+#[derive(Debug)]
+struct Slot {
+    name: TinyString,
+    symbolic: bool,
+    /// looked up through the desktop entries as well
+    app: bool,
+    handle: OnceCell<Option<Handle>>,
+}
+
+impl Icon {
+    pub fn get(&self) -> Option<Handle> {
+        self.0.handle.get()?.clone()
+    }
+}
+
+/// The configured theme, or the desktop's, followed by the configured fallbacks.
+pub fn themes(config: &config::Icons, desktop: Option<&str>) -> Arc<[String]> {
+    let theme = match &config.theme {
+        Some(theme) => Some(theme.clone()),
+        None => desktop.map(ToOwned::to_owned).or_else(gtk_theme),
+    };
+    theme
+        .into_iter()
+        .chain(config.fallback.iter().cloned())
+        .collect()
+}
+
+/// `gtk-icon-theme-name` from the GTK settings, for desktops without a settings portal.
+fn gtk_theme() -> Option<String> {
+    ["gtk-4.0", "gtk-3.0"].into_iter().find_map(|dir| {
+        let text = fs::read_to_string(xdg_config_home().join(dir).join("settings.ini")).ok()?;
+        text.lines().find_map(|line| {
+            let (key, value) = line.split_once('=')?;
+            (key.trim() == "gtk-icon-theme-name").then(|| value.trim().trim_matches('"').to_owned())
+        })
+    })
+}
+
+/// Resolves and decodes icons on the blocking pool, [`AppEvent::IconLoaded`] tells the
+/// runner to redraw once one is ready.
+///
+/// Clones share the loaded icons, so anything showing icons can hold one.
+#[derive(Clone)]
+pub struct Loader {
+    memory: Rc<RefCell<Memory>>,
+    /// of the densest output, icons are resolved for it
+    scale: u16,
+    context: Context,
+    events: Sender<Event>,
+}
+
+struct Memory {
+    /// by name, size and scale
+    cache: LruCache<(TinyString, u16, u16), Icon, RandomState>,
+    stats: Stats,
+}
+
+/// How well the loaded icons serve requests, shown by the hud.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Stats {
+    pub hits: u64,
+    pub misses: u64,
+    /// loaded right now
+    pub len: usize,
+}
+
+fn capacity(capacity: usize) -> NonZero<usize> {
+    NonZero::new(capacity).unwrap_or(NonZero::<usize>::MIN)
+}
+
+impl Loader {
+    pub fn new(events: Sender<Event>, themes: Arc<[String]>, capacity: usize) -> Self {
+        let memory = Memory {
+            cache: LruCache::with_hasher(self::capacity(capacity), RandomState::new()),
+            stats: Stats::default(),
+        };
+        Self {
+            memory: Rc::new(RefCell::new(memory)),
+            scale: 1,
+            context: Context {
+                themes,
+                disk: Arc::new(Mutex::new(Cache::load())),
+                apps: Default::default(),
+            },
+            events,
+        }
+    }
+    /// Forgets every loaded icon, callers [`reload`](Self::reload) the ones they hold.
+    ///
+    /// Lookups still in flight fill slots nobody reads anymore, harmless.
+    pub fn reset(&mut self, themes: Arc<[String]>) {
+        self.context.themes = themes;
+        // picks up applications installed since
+        self.context.apps = Default::default();
+        self.memory.borrow_mut().cache.clear();
+    }
+    /// Drops the least recently used icons beyond `capacity`.
+    pub fn resize(&self, capacity: usize) {
+        self.memory
+            .borrow_mut()
+            .cache
+            .resize(self::capacity(capacity));
+    }
+    pub fn stats(&self) -> Stats {
+        let memory = self.memory.borrow();
+        Stats {
+            len: memory.cache.len(),
+            ..memory.stats
+        }
+    }
+    pub fn themes(&self) -> Arc<[String]> {
+        self.context.themes.clone()
+    }
+    /// Whether `scale` is new, callers [`reload`](Self::reload) the icons they hold for it.
+    pub fn rescale(&mut self, scale: u16) -> bool {
+        let scale = scale.max(1);
+        scale != std::mem::replace(&mut self.scale, scale)
+    }
+    pub fn reload(&mut self, icon: &Icon, theme: &Theme) -> Icon {
+        let Slot {
+            name,
+            symbolic,
+            app,
+            ..
+        } = &*icon.0;
+        self.request(name, *symbolic, *app, theme)
+    }
+    pub fn load(&mut self, name: &TinyString, symbolic: bool, theme: &Theme) -> Icon {
+        self.request(name, symbolic, false, theme)
+    }
+    /// Like [`load`](Self::load) for a window class, which often names no icon itself.
+    pub fn load_app(&mut self, class: &TinyString, theme: &Theme) -> Icon {
+        self.request(class, false, true, theme)
+    }
+    fn request(&mut self, name: &TinyString, symbolic: bool, app: bool, theme: &Theme) -> Icon {
+        // tray items name symbolic icons without asking for them to be tinted
+        let symbolic = symbolic || is_symbolic(name.as_bytes());
+        let key = (name.clone(), SIZE, self.scale);
+        let mut memory = self.memory.borrow_mut();
+        if let Some(icon) = memory.cache.get(&key).cloned() {
+            memory.stats.hits += 1;
+            return icon;
+        }
+        memory.stats.misses += 1;
+        let icon = Icon(Rc::new(Slot {
+            name: name.clone(),
+            symbolic,
+            app,
+            handle: OnceCell::new(),
+        }));
+        memory.cache.put(key, icon.clone());
+        drop(memory);
+        let slot = icon.clone();
+        let name = name.clone();
+        let scale = self.scale;
+        let theme = theme.clone();
+        let context = self.context.clone();
+        let mut events = self.events.clone();
+        compio::runtime::spawn(async move {
+            let handle = compio::runtime::spawn_blocking(move || {
+                context.lookup(&name, symbolic, app, scale, &theme)
+            })
+            .await
+            .unwrap_or_default();
+            let found = handle.is_some();
+            slot.0.handle.set(handle).ok();
+            if found {
+                events.send(AppEvent::IconLoaded.into()).await.ok();
+            }
+        })
+        .detach();
+        icon
+    }
+}
+
+/// Everything a lookup needs on the blocking pool.
+#[derive(Clone)]
+struct Context {
+    themes: Arc<[String]>,
+    disk: Arc<Mutex<Cache>>,
+    /// scanned by the first app icon missing from the themes
+    apps: Arc<OnceLock<desktop::Index>>,
+}
+
+impl Context {
+    /// Prefers the `@2x` directories of themes having them at scale 2 and up.
+    fn find(&self, name: &str, scale: u16) -> Option<PathBuf> {
+        if self.themes.is_empty() {
+            // hicolor only
+            return cosmic_freedesktop_icons::lookup(name)
+                .with_size(SIZE)
+                .with_scale(scale)
+                .find();
+        }
+        self.themes.iter().find_map(|x| {
+            cosmic_freedesktop_icons::lookup(name)
+                .with_size(SIZE)
+                .with_scale(scale)
+                .with_theme(x)
+                .find()
+        })
+    }
+    /// The icon named by the desktop entry of the window class `class`.
+    fn find_app(&self, class: &str, scale: u16) -> Option<PathBuf> {
+        let icon = self.apps.get_or_init(desktop::Index::scan).icon(class)?;
+        if Path::new(icon).is_absolute() {
+            Some(icon.into())
+        } else {
+            self.find(icon, scale)
+        }
+    }
+    fn lookup(
+        &self,
+        name: &str,
+        symbolic: bool,
+        app: bool,
+        scale: u16,
+        theme: &Theme,
+    ) -> Option<Handle> {
+        let key = self.themes.join(":");
+        // svgs are rasterized and cached at the pixels they take on the output
+        let pixels = SIZE * scale;
+        if let Some(path) = self.disk.lock().unwrap().get(name, &key, pixels) {
+            return load(&path, symbolic || symbolic_file(&path), theme);
+        }
+        let path = match self.find(name, scale) {
+            Some(path) => Some(path),
+            None if app => self.find_app(name, scale),
+            None => None,
+        };
+        let Some(path) = path else {
+            // not cached, the application may install its icon later
+            return app
+                .then(|| self.find(GENERIC_APP, scale))
+                .flatten()
+                .and_then(|path| load(&path, false, theme));
+        };
+        let symbolic = symbolic || symbolic_file(&path);
+        let mut png = None;
+        let handle = if !symbolic && path.extension()? == "svg" {
+            let tree = parse_svg(&path)?;
+            png = cache::rasterize(name, &key, pixels, &tree);
+            Handle::Svg(svg::Handle::from_tree(tree))
+        } else {
+            load(&path, symbolic, theme)?
+        };
+        self.disk
+            .lock()
+            .unwrap()
+            .insert(name, &key, pixels, path, png);
+        Some(handle)
+    }
+}
+
+/// Drawn in `currentColor` by the theme spec, black on the bar unless tinted.
+fn is_symbolic(name: &[u8]) -> bool {
+    name.ends_with(b"-symbolic")
+}
+
+/// Themes without a full color variant resolve other names to the symbolic one.
+fn symbolic_file(path: &Path) -> bool {
+    path.file_stem()
+        .is_some_and(|x| is_symbolic(x.as_encoded_bytes()))
+}
+
+fn load(path: &Path, symbolic: bool, theme: &Theme) -> Option<Handle> {
+    match path.extension()?.as_encoded_bytes() {
+        b"svg" if symbolic => load_symbolic(path, theme).map(Handle::Svg),
+        b"svg" => parse_svg(path).map(|tree| Handle::Svg(svg::Handle::from_tree(tree))),
+        b"png" => load_png(path).map(Handle::Pixmap),
+        _ => None,
+    }
+}
+
+fn parse_svg(path: &Path) -> Option<usvg::Tree> {
+    let fd = rustix::fs::open(path, OFlags::CLOEXEC, Mode::empty()).ok()?;
+    let mapping = Mapping::map(fd, ProtFlags::READ, MapFlags::PRIVATE).ok()?;
+    let text = unsafe { str::from_utf8_unchecked(mapping.as_bytes()) };
+    usvg::Tree::from_str(text, &usvg::Options::default())
+        .inspect_err(|err| tracing::warn!("cannot parse {path:?}: {err:?}"))
+        .ok()
+}
+
+fn load_symbolic(path: impl AsRef<Path>, theme: &Theme) -> Option<svg::Handle> {
+    let path = path.as_ref();
+    let data = fs::read_to_string(path)
+        .ok()?
+        .replace("currentColor", &theme.palette().text.to_string());
+    let tree = usvg::Tree::from_str(
+        &data,
+        &usvg::Options {
+            style_sheet: Some(theme.css_injection()),
+            ..Default::default()
+        },
+    )
+    .inspect_err(|err| tracing::warn!("cannot parse {path:?}: {err:?}"))
+    .ok()?;
+    Some(svg::Handle::from_tree(tree))
+}
+
+fn load_png(path: impl AsRef<Path>) -> Option<image::Handle> {
+    let path = path.as_ref();
+    let path = path.as_cow_c_str().unwrap();
+    let fd = rustix::fs::open(path.as_c_str(), OFlags::CLOEXEC, Mode::empty()).ok()?;
+    let data = Mapping::map(fd, ProtFlags::READ, MapFlags::PRIVATE).ok()?;
+    let cursor = io::Cursor::new(data.as_bytes());
+    let decoder = png::Decoder::new(cursor);
+
+    let mut reader = decoder.read_info().ok()?;
+    let len = reader.output_buffer_size()?;
+    let buf = Mapping::anon(len, ProtFlags::READ | ProtFlags::WRITE, MapFlags::PRIVATE).ok()?;
+    let info = reader
+        .next_frame(buf.as_bytes_mut())
+        .inspect_err(|e| tracing::warn!("cannot decode {path:?}: {e}"))
+        .ok()?;
+    match info.color_type {
+        png::ColorType::Rgba => {}
+        x => {
+            tracing::warn!("{path:?} has unsupported color type: {x:?}");
+            return None;
+        }
+    }
+    match info.bit_depth {
+        png::BitDepth::Eight => {}
+        x => {
+            tracing::warn!("{path:?} has unsupported {}-bit depth", x as u32);
+            return None;
+        }
+    }
+    let handle = image::Handle::from_rgba(info.width, info.height, buf);
+    Some(handle)
+}
+
+trait StyleSheet {
+    fn css_injection(&self) -> String;
+}
+// foreground’, ‘success’, ‘warning’, ‘error’, ‘accent’
+impl StyleSheet for Theme {
+    fn css_injection(&self) -> String {
+        let Palette {
+            text,
+            primary,
+            success,
+            warning,
+            danger,
+            ..
+        } = self.palette();
+        format!(
+            concat!(
+                "* {{ fill:{} }}",
+                ".foreground {{ fill:{} }}",
+                ".success {{ fill:{} }}",
+                ".warning {{ fill:{} }}",
+                ".error {{ fill:{} }}",
+                ".accent {{ fill:{} }}",
+            ),
+            text, text, success, warning, danger, primary
+        )
+    }
+}
+
+mod cache;
+mod desktop;
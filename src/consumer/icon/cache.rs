@@ -0,0 +1,129 @@
+use std::{
+    fs, io,
+    os::unix::fs::MetadataExt as _,
+    path::{Path, PathBuf},
+};
+
+use rustc_hash::FxHashMap;
+use serde::{Deserialize, Serialize};
+
+use crate::config::xdg_cache_home;
+
+fn dir() -> PathBuf {
+    xdg_cache_home().join("hyoka")
+}
+
+fn key(name: &str, theme: &str, size: u16) -> String {
+    format!("{theme}/{size}/{name}")
+}
+
+fn mtime(path: &Path) -> Option<i64> {
+    Some(fs::metadata(path).ok()?.mtime())
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Entry {
+    path: PathBuf,
+    /// of `path`, a theme update invalidates the entry
+    mtime: i64,
+    /// rasterized copy of an svg `path`
+    png: Option<PathBuf>,
+}
+
+/// Where icons were found on previous runs, a cold lookup probes hundreds of directories.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Cache {
+    entries: FxHashMap<String, Entry>,
+}
+
+impl Cache {
+    pub fn load() -> Self {
+        let path = dir().join("icons.json");
+        let data = match fs::read(&path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Self::default(),
+            Err(e) => {
+                tracing::warn!("cannot read {path:?}: {e}");
+                return Self::default();
+            }
+        };
+        serde_json::from_slice(&data)
+            .inspect_err(|e| tracing::warn!("cannot parse {path:?}: {e}"))
+            .unwrap_or_default()
+    }
+    /// The file to decode, `None` when the icon has to be looked up again.
+    pub fn get(&self, name: &str, theme: &str, size: u16) -> Option<PathBuf> {
+        let entry = self.entries.get(&key(name, theme, size))?;
+        if mtime(&entry.path)? != entry.mtime {
+            return None;
+        }
+        let png = entry.png.as_ref().filter(|x| x.exists());
+        Some(png.unwrap_or(&entry.path).clone())
+    }
+    pub fn insert(
+        &mut self,
+        name: &str,
+        theme: &str,
+        size: u16,
+        path: PathBuf,
+        png: Option<PathBuf>,
+    ) {
+        let Some(mtime) = mtime(&path) else {
+            return;
+        };
+        self.entries
+            .insert(key(name, theme, size), Entry { path, mtime, png });
+        if let Err(e) = self.save() {
+            tracing::warn!("cannot write icon cache: {e}");
+        }
+    }
+    fn save(&self) -> io::Result<()> {
+        let dir = dir();
+        fs::create_dir_all(&dir)?;
+        let tmp = dir.join("icons.json.tmp");
+        fs::write(&tmp, serde_json::to_vec(self)?)?;
+        fs::rename(tmp, dir.join("icons.json"))
+    }
+}
+
+/// Renders `tree` into a `size` pixels square png, svgs are parsed and rasterized again on
+/// every launch otherwise.
+pub fn rasterize(name: &str, theme: &str, size: u16, tree: &usvg::Tree) -> Option<PathBuf> {
+    let size = u32::from(size);
+    let mut pixmap = tiny_skia::Pixmap::new(size, size)?;
+    let (width, height) = (tree.size().width(), tree.size().height());
+    let scale = size as f32 / width.max(height);
+    let transform = tiny_skia::Transform::from_scale(scale, scale).post_translate(
+        (size as f32 - width * scale) / 2.0,
+        (size as f32 - height * scale) / 2.0,
+    );
+    resvg::render(tree, transform, &mut pixmap.as_mut());
+    let data: Vec<u8> = pixmap
+        .pixels()
+        .iter()
+        .flat_map(|x| {
+            let x = x.demultiply();
+            [x.red(), x.green(), x.blue(), x.alpha()]
+        })
+        .collect();
+
+    let dir = dir().join("icons");
+    let path = dir.join(format!("{theme}-{size}-{}.png", name.replace('/', "_")));
+    let res = fs::create_dir_all(&dir).and_then(|()| {
+        let mut encoder =
+            png::Encoder::new(io::BufWriter::new(fs::File::create(&path)?), size, size);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder
+            .write_header()
+            .and_then(|mut writer| writer.write_image_data(&data))
+            .map_err(io::Error::other)
+    });
+    match res {
+        Ok(()) => Some(path),
+        Err(e) => {
+            tracing::warn!("cannot write {path:?}: {e}");
+            None
+        }
+    }
+}
@@ -0,0 +1,98 @@
+use std::{fs, path::Path};
+
+use rustc_hash::FxHashMap;
+
+use crate::config::xdg_data_dirs;
+
+/// Window classes to the `Icon` of their desktop entry.
+#[derive(Debug, Default)]
+pub struct Index {
+    /// by `StartupWMClass`
+    classes: FxHashMap<String, String>,
+    /// by desktop file id, and by its last component for reverse-DNS ids
+    ids: FxHashMap<String, String>,
+}
+
+impl Index {
+    pub fn scan() -> Self {
+        let mut index = Self::default();
+        // earlier directories take precedence, so never overwrite
+        for dir in xdg_data_dirs() {
+            let Ok(entries) = fs::read_dir(dir.join("applications")) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                index.add(&entry.path());
+            }
+        }
+        index
+    }
+    pub fn icon(&self, class: &str) -> Option<&str> {
+        let class = class.to_lowercase();
+        self.classes
+            .get(&class)
+            .or_else(|| self.ids.get(&class))
+            .map(String::as_str)
+    }
+    fn add(&mut self, path: &Path) {
+        if path.extension().is_none_or(|x| x != "desktop") {
+            return;
+        }
+        let Some(id) = path.file_stem().and_then(|x| x.to_str()) else {
+            return;
+        };
+        let Ok(text) = fs::read_to_string(path) else {
+            return;
+        };
+        let Some(Entry { icon, class }) = parse(&text) else {
+            return;
+        };
+        let id = id.to_lowercase();
+        if let Some((_, name)) = id.rsplit_once('.') {
+            self.ids
+                .entry(name.to_owned())
+                .or_insert_with(|| icon.to_owned());
+        }
+        self.ids.entry(id).or_insert_with(|| icon.to_owned());
+        if let Some(class) = class {
+            self.classes
+                .entry(class.to_lowercase())
+                .or_insert_with(|| icon.to_owned());
+        }
+    }
+}
+
+struct Entry<'a> {
+    icon: &'a str,
+    class: Option<&'a str>,
+}
+
+/// The `[Desktop Entry]` group, `None` without an icon or for hidden entries.
+fn parse(text: &str) -> Option<Entry<'_>> {
+    let mut icon = None;
+    let mut class = None;
+    let mut group = false;
+    for line in text.lines() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            group = line == "[Desktop Entry]";
+            continue;
+        }
+        if !group {
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        match key.trim_end() {
+            "Icon" => icon = Some(value.trim_start()),
+            "StartupWMClass" => class = Some(value.trim_start()),
+            "Hidden" if value.trim_start() == "true" => return None,
+            _ => {}
+        }
+    }
+    Some(Entry {
+        icon: icon.filter(|x| !x.is_empty())?,
+        class,
+    })
+}
@@ -0,0 +1,104 @@
+use std::{
+    fs::File,
+    io::{self, Seek as _, Write as _},
+    os::fd::AsRawFd as _,
+    ptr::NonNull,
+};
+
+use rustix::fs::{MemfdFlags, memfd_create};
+
+use crate::wayland::{self, Object, OwnedObject, ffi};
+
+type Control = ffi::zwlr_gamma_control_v1;
+
+/// Warmer colors through wlr gamma control, on every output at once.
+pub struct NightLight {
+    display: NonNull<ffi::wl_display>,
+    manager: *mut ffi::zwlr_gamma_control_manager_v1,
+    /// one per output while on, the compositor restores the gamma as they are destroyed
+    controls: Vec<OwnedObject<Control>>,
+    /// kelvin
+    temperature: u32,
+}
+
+impl NightLight {
+    /// `None` without gamma control, on compositors other than wlroots-based ones.
+    pub fn new(wayland: &wayland::Proxy, display: NonNull<ffi::wl_display>) -> Option<Self> {
+        Some(Self {
+            display,
+            manager: wayland.globals.gamma_control_manager()?,
+            controls: vec![],
+            temperature: 0,
+        })
+    }
+    pub fn on(&self) -> bool {
+        !self.controls.is_empty()
+    }
+    /// Takes the gamma of every output, the ramps are set once their sizes are known.
+    pub fn enable(&mut self, wayland: &mut wayland::Proxy, temperature: u32) {
+        self.temperature = temperature;
+        if self.on() {
+            // the sizes are known only to the listener, start over to set them again
+            self.controls.clear();
+        }
+        let outputs: Vec<_> = wayland.globals.outputs().collect();
+        for output in outputs {
+            let control = OwnedObject::from_raw(unsafe {
+                ffi::zwlr_gamma_control_manager_v1_get_gamma_control(self.manager, output.as_ptr())
+            });
+            wayland.listen(&control, &wayland::GAMMA_CONTROL_LISTENER);
+            self.controls.push(control);
+        }
+        unsafe { ffi::wl_display_flush(self.display.as_ptr()) };
+    }
+    pub fn disable(&mut self) {
+        self.controls.clear();
+        unsafe { ffi::wl_display_flush(self.display.as_ptr()) };
+    }
+    pub fn sized(&mut self, control: Object<Control>, size: u32) {
+        if !self.controls.iter().any(|x| x.as_ptr() == control.as_ptr()) {
+            return;
+        }
+        if let Err(e) = set_gamma(control, size, whitepoint(self.temperature)) {
+            tracing::warn!("cannot set the gamma: {e}");
+        }
+        unsafe { ffi::wl_display_flush(self.display.as_ptr()) };
+    }
+    pub fn failed(&mut self, control: Object<Control>) {
+        let len = self.controls.len();
+        self.controls.retain(|x| x.as_ptr() != control.as_ptr());
+        if self.controls.len() < len {
+            tracing::warn!("cannot control the gamma of an output, is gammastep running?");
+        }
+    }
+}
+
+/// Ramps scaled by `white`, red first then green and blue, passed through a memfd.
+fn set_gamma(control: Object<Control>, size: u32, white: [f64; 3]) -> io::Result<()> {
+    let mut file = File::from(memfd_create(c"gamma", MemfdFlags::CLOEXEC)?);
+    let last = size.saturating_sub(1).max(1) as f64;
+    let ramps: Vec<u8> = white
+        .into_iter()
+        .flat_map(|channel| {
+            (0..size).map(move |i| (i as f64 / last * channel * u16::MAX as f64) as u16)
+        })
+        .flat_map(u16::to_ne_bytes)
+        .collect();
+    file.write_all(&ramps)?;
+    file.rewind()?;
+    unsafe { ffi::zwlr_gamma_control_v1_set_gamma(control.as_ptr(), file.as_raw_fd()) };
+    Ok(())
+}
+
+/// Relative red, green and blue of a blackbody at `kelvin`, after Tanner Helland's fit.
+fn whitepoint(kelvin: u32) -> [f64; 3] {
+    // the fit holds up to 6600 K, nothing bluer than daylight is wanted at night
+    let t = kelvin.clamp(1000, 6500) as f64 / 100.0;
+    let green = (99.470_802_586_1 * t.ln() - 161.119_568_166_1) / 255.0;
+    let blue = if t <= 19.0 {
+        0.0
+    } else {
+        (138.517_731_223_1 * (t - 10.0).ln() - 305.044_792_730_7) / 255.0
+    };
+    [1.0, green.clamp(0.0, 1.0), blue.clamp(0.0, 1.0)]
+}
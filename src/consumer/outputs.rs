@@ -0,0 +1,406 @@
+use std::{mem, ptr::NonNull};
+
+use crate::wayland::{self, HeadChange, ModeChange, Object, OwnedObject, XdgOutputChange, ffi};
+
+type Power = ffi::zwlr_output_power_v1;
+type Head = ffi::zwlr_output_head_v1;
+type Mode = ffi::zwlr_output_mode_v1;
+type Configuration = ffi::zwlr_output_configuration_v1;
+type XdgOutput = ffi::zxdg_output_v1;
+
+/// The outputs bound, with their display power where wlr output power management is there,
+/// their logical geometry where xdg output is, and every connected one where wlr output
+/// management is.
+pub struct Outputs {
+    display: NonNull<ffi::wl_display>,
+    list: Vec<Output>,
+    manager: Option<*mut ffi::zwlr_output_manager_v1>,
+    heads: Vec<HeadState>,
+    /// of the latest consistent state of `heads`, configurations are based on it
+    serial: Option<u32>,
+    /// applied and not answered yet
+    configuration: Option<OwnedObject<Configuration>>,
+}
+
+struct Output {
+    wl_output: Object<ffi::wl_output>,
+    /// like "DP-1", once the compositor told
+    name: Option<String>,
+    /// like "Dell Inc. DELL U2720Q (DP-1)", from xdg output
+    description: Option<String>,
+    xdg_output: Option<OwnedObject<XdgOutput>>,
+    /// in the global compositor space, from xdg output
+    position: Option<[i32; 2]>,
+    /// as surfaces are laid out on it, from xdg output
+    size: Option<[i32; 2]>,
+    power: Option<OwnedObject<Power>>,
+    /// as last reported, assumed until then
+    on: bool,
+}
+
+impl Output {
+    fn new(wayland: &mut wayland::Proxy, wl_output: Object<ffi::wl_output>) -> Self {
+        let power = wayland.globals.output_power_manager().map(|manager| {
+            let power = OwnedObject::from_raw(unsafe {
+                ffi::zwlr_output_power_manager_v1_get_output_power(manager, wl_output.as_ptr())
+            });
+            wayland.listen(&power, &wayland::OUTPUT_POWER_LISTENER);
+            power
+        });
+        let xdg_output = wayland.globals.xdg_output_manager().map(|manager| {
+            let xdg_output = OwnedObject::from_raw(unsafe {
+                ffi::zxdg_output_manager_v1_get_xdg_output(manager, wl_output.as_ptr())
+            });
+            wayland.listen(&xdg_output, &wayland::XDG_OUTPUT_LISTENER);
+            xdg_output
+        });
+        Self {
+            wl_output,
+            name: None,
+            description: None,
+            xdg_output,
+            position: None,
+            size: None,
+            power,
+            on: true,
+        }
+    }
+}
+
+/// An output as wlr output management sees it, disabled ones included.
+struct HeadState {
+    head: OwnedObject<Head>,
+    name: String,
+    enabled: bool,
+    /// supported ones
+    modes: Vec<ModeState>,
+    current_mode: Option<Object<Mode>>,
+    position: [i32; 2],
+    scale: f64,
+}
+
+struct ModeState {
+    mode: OwnedObject<Mode>,
+    size: [i32; 2],
+    /// mHz
+    refresh: i32,
+}
+
+impl HeadState {
+    fn current_mode(&self) -> Option<&ModeState> {
+        let current = self.current_mode?;
+        self.modes
+            .iter()
+            .find(|x| x.mode.as_ptr() == current.as_ptr())
+    }
+}
+
+/// An output as listed by the monitors menu.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Monitor {
+    pub name: String,
+    /// `None` when its power cannot be controlled
+    pub power: Option<(Object<ffi::wl_output>, bool)>,
+    /// `None` without wlr output management
+    pub enabled: Option<bool>,
+    pub scale: Option<f64>,
+    /// like "2560×1440 @ 144 Hz"
+    pub mode: Option<String>,
+}
+
+impl Outputs {
+    pub fn new(wayland: &mut wayland::Proxy, display: NonNull<ffi::wl_display>) -> Self {
+        let outputs: Vec<_> = wayland.globals.outputs().collect();
+        let list = outputs
+            .into_iter()
+            .map(|wl_output| Output::new(wayland, wl_output))
+            .collect();
+        Self {
+            display,
+            list,
+            manager: wayland.globals.output_manager(),
+            heads: vec![],
+            serial: None,
+            configuration: None,
+        }
+    }
+    /// Plugged in after startup.
+    pub fn added(&mut self, wayland: &mut wayland::Proxy, wl_output: Object<ffi::wl_output>) {
+        self.list.push(Output::new(wayland, wl_output));
+    }
+    /// Unplugged, before the output is destroyed.
+    pub fn removed(&mut self, wl_output: Object<ffi::wl_output>) {
+        self.list.retain(|x| x.wl_output != wl_output);
+    }
+    pub fn named(&mut self, wl_output: Object<ffi::wl_output>, name: String) {
+        if let Some(output) = self.list.iter_mut().find(|x| x.wl_output == wl_output) {
+            output.name = Some(name);
+        }
+    }
+    /// Whether the compositor names the output `name`, or describes it so with xdg output.
+    pub fn is_named(&self, wl_output: Object<ffi::wl_output>, name: &str) -> bool {
+        self.list.iter().any(|x| {
+            x.wl_output == wl_output
+                && (x.name.as_deref() == Some(name) || x.description.as_deref() == Some(name))
+        })
+    }
+    /// The output when this named it, which wl_output before version 4 never does.
+    pub fn xdg_output_changed(
+        &mut self,
+        xdg_output: Object<XdgOutput>,
+        change: XdgOutputChange,
+    ) -> Option<Object<ffi::wl_output>> {
+        let output = self.list.iter_mut().find(|x| {
+            x.xdg_output
+                .as_ref()
+                .is_some_and(|x| x.as_ptr() == xdg_output.as_ptr())
+        })?;
+        match change {
+            XdgOutputChange::Position(position) => output.position = Some(position),
+            XdgOutputChange::Size(size) => output.size = Some(size),
+            XdgOutputChange::Name(name) if output.name.is_none() => {
+                output.name = Some(name);
+                return Some(output.wl_output);
+            }
+            XdgOutputChange::Name(_) => {}
+            XdgOutputChange::Description(description) => output.description = Some(description),
+        }
+        None
+    }
+    /// Named like "DP-1" by the compositor.
+    pub fn by_name(&self, name: &str) -> Option<Object<ffi::wl_output>> {
+        self.list
+            .iter()
+            .find(|x| x.name.as_deref() == Some(name))
+            .map(|x| x.wl_output)
+    }
+    /// Without output power management from now on.
+    pub fn drop_power(&mut self) {
+        for output in &mut self.list {
+            output.power = None;
+        }
+    }
+    /// Without xdg output from now on, what it told is kept.
+    pub fn drop_xdg_outputs(&mut self) {
+        for output in &mut self.list {
+            output.xdg_output = None;
+        }
+    }
+    /// Without output management from now on, only the outputs bound are listed.
+    pub fn drop_heads(&mut self) {
+        self.configuration = None;
+        self.heads.clear();
+        self.serial = None;
+        self.manager = None;
+    }
+    fn by_power(&mut self, power: Object<Power>) -> Option<&mut Output> {
+        self.list.iter_mut().find(|x| {
+            x.power
+                .as_ref()
+                .is_some_and(|x| x.as_ptr() == power.as_ptr())
+        })
+    }
+    /// Whether anything changed.
+    pub fn powered(&mut self, power: Object<Power>, on: bool) -> bool {
+        self.by_power(power)
+            .is_some_and(|output| mem::replace(&mut output.on, on) != on)
+    }
+    pub fn power_failed(&mut self, power: Object<Power>) {
+        if let Some(output) = self.by_power(power) {
+            tracing::warn!("cannot control the power of {:?}", output.name);
+            output.power = None;
+        }
+    }
+    pub fn set_power(&mut self, wl_output: Object<ffi::wl_output>, on: bool) -> Option<()> {
+        let output = self.list.iter().find(|x| x.wl_output == wl_output)?;
+        let mode = match on {
+            true => ffi::ZWLR_OUTPUT_POWER_V1_MODE_ON,
+            false => ffi::ZWLR_OUTPUT_POWER_V1_MODE_OFF,
+        };
+        unsafe {
+            ffi::zwlr_output_power_v1_set_mode(output.power.as_ref()?.as_ptr(), mode);
+            ffi::wl_display_flush(self.display.as_ptr());
+        }
+        Some(())
+    }
+    fn power(&self, name: &str) -> Option<(Object<ffi::wl_output>, bool)> {
+        self.list
+            .iter()
+            .find(|x| x.power.is_some() && x.name.as_deref() == Some(name))
+            .map(|x| (x.wl_output, x.on))
+    }
+    /// The heads where there are any, as they include disabled outputs.
+    pub fn monitors(&self) -> Vec<Monitor> {
+        if self.heads.is_empty() {
+            let mut list: Vec<_> = self.list.iter().collect();
+            // left to right as laid out, where xdg output tells
+            list.sort_by_key(|x| x.position.map(|[x, y]| [y, x]));
+            return list
+                .into_iter()
+                .map(|x| Monitor {
+                    name: x.name.clone().unwrap_or_else(|| "Unknown".into()),
+                    power: x.power.is_some().then_some((x.wl_output, x.on)),
+                    enabled: None,
+                    scale: None,
+                    // logical, the mode itself is only known to output management
+                    mode: x.size.map(|[width, height]| format!("{width}×{height}")),
+                })
+                .collect();
+        }
+        self.heads
+            .iter()
+            .map(|x| Monitor {
+                name: x.name.clone(),
+                power: x.enabled.then(|| self.power(&x.name)).flatten(),
+                enabled: Some(x.enabled),
+                scale: x.enabled.then_some(x.scale),
+                mode: x.current_mode().map(|mode| {
+                    let [width, height] = mode.size;
+                    let hz = (mode.refresh as f64 / 1000.0).round();
+                    format!("{width}×{height} @ {hz} Hz")
+                }),
+            })
+            .collect()
+    }
+    pub fn head(&mut self, head: Object<Head>) {
+        self.heads.push(HeadState {
+            head: OwnedObject::from_raw(head.as_ptr()),
+            name: String::new(),
+            enabled: false,
+            modes: vec![],
+            current_mode: None,
+            position: [0, 0],
+            scale: 1.0,
+        });
+    }
+    pub fn head_changed(&mut self, head: Object<Head>, change: HeadChange) {
+        let Some(index) = self
+            .heads
+            .iter()
+            .position(|x| x.head.as_ptr() == head.as_ptr())
+        else {
+            return;
+        };
+        let state = &mut self.heads[index];
+        match change {
+            HeadChange::Name(name) => state.name = name,
+            HeadChange::Mode(mode) => state.modes.push(ModeState {
+                mode: OwnedObject::from_raw(mode.as_ptr()),
+                size: [0, 0],
+                refresh: 0,
+            }),
+            HeadChange::Enabled(enabled) => {
+                state.enabled = enabled;
+                if !enabled {
+                    state.current_mode = None;
+                }
+            }
+            HeadChange::CurrentMode(mode) => state.current_mode = Some(mode),
+            HeadChange::Position(position) => state.position = position,
+            HeadChange::Scale(scale) => state.scale = scale,
+            HeadChange::Finished => {
+                self.heads.remove(index);
+            }
+        }
+    }
+    pub fn mode_changed(&mut self, mode: Object<Mode>, change: ModeChange) {
+        if let ModeChange::Finished = change {
+            for head in &mut self.heads {
+                head.modes.retain(|x| x.mode.as_ptr() != mode.as_ptr());
+                if head.current_mode == Some(mode) {
+                    head.current_mode = None;
+                }
+            }
+            return;
+        }
+        let Some(state) = self
+            .heads
+            .iter_mut()
+            .flat_map(|x| &mut x.modes)
+            .find(|x| x.mode.as_ptr() == mode.as_ptr())
+        else {
+            return;
+        };
+        match change {
+            ModeChange::Size(size) => state.size = size,
+            ModeChange::Refresh(refresh) => state.refresh = refresh,
+            ModeChange::Finished => {}
+        }
+    }
+    pub fn heads_done(&mut self, serial: u32) {
+        self.serial = Some(serial);
+    }
+    /// Applies every head as it is except the one named `name`, all of them at once.
+    pub fn configure(
+        &mut self,
+        wayland: &mut wayland::Proxy,
+        name: &str,
+        enabled: bool,
+        scale: f64,
+    ) -> Option<()> {
+        let manager = self.manager?;
+        let configuration = OwnedObject::from_raw(unsafe {
+            ffi::zwlr_output_manager_v1_create_configuration(manager, self.serial?)
+        });
+        wayland.listen(&configuration, &wayland::OUTPUT_CONFIGURATION_LISTENER);
+        for head in &self.heads {
+            let (enabled, scale) = match head.name == name {
+                true => (enabled, scale),
+                false => (head.enabled, head.scale),
+            };
+            if !enabled {
+                unsafe {
+                    ffi::zwlr_output_configuration_v1_disable_head(
+                        configuration.as_ptr(),
+                        head.head.as_ptr(),
+                    )
+                };
+                continue;
+            }
+            // owned by the configuration, gone with it
+            let config_head: OwnedObject<ffi::zwlr_output_configuration_head_v1> =
+                OwnedObject::from_raw(unsafe {
+                    ffi::zwlr_output_configuration_v1_enable_head(
+                        configuration.as_ptr(),
+                        head.head.as_ptr(),
+                    )
+                });
+            unsafe {
+                // none when turned on again, the compositor picks one then
+                if let Some(mode) = head.current_mode {
+                    ffi::zwlr_output_configuration_head_v1_set_mode(
+                        config_head.as_ptr(),
+                        mode.as_ptr(),
+                    );
+                }
+                let [x, y] = head.position;
+                ffi::zwlr_output_configuration_head_v1_set_position(config_head.as_ptr(), x, y);
+                ffi::zwlr_output_configuration_head_v1_set_scale(
+                    config_head.as_ptr(),
+                    ffi::wl_fixed_t((scale * 256.0) as i32),
+                );
+            }
+        }
+        unsafe {
+            ffi::zwlr_output_configuration_v1_apply(configuration.as_ptr());
+            ffi::wl_display_flush(self.display.as_ptr());
+        }
+        self.configuration = Some(configuration);
+        Some(())
+    }
+    pub fn configured(&mut self, configuration: Object<Configuration>, applied: Option<bool>) {
+        if self
+            .configuration
+            .as_ref()
+            .is_none_or(|x| x.as_ptr() != configuration.as_ptr())
+        {
+            return;
+        }
+        self.configuration = None;
+        match applied {
+            Some(true) => {}
+            Some(false) => tracing::warn!("the compositor rejected the output configuration"),
+            None => tracing::warn!("the outputs changed before the configuration was applied"),
+        }
+    }
+}
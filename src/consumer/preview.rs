@@ -0,0 +1,200 @@
+use std::{
+    os::fd::{AsRawFd as _, OwnedFd},
+    ptr::NonNull,
+};
+
+use iced::widget::image;
+use rustix::{
+    fs::{MemfdFlags, ftruncate, memfd_create},
+    mm::{MapFlags, ProtFlags},
+};
+
+use crate::{
+    mapping::Mapping,
+    wayland::{self, Object, OwnedObject, ffi},
+};
+
+type Frame = ffi::zwlr_screencopy_frame_v1;
+
+/// pixels, previews are scaled down to it
+const WIDTH: u32 = 320;
+
+/// Snapshots of part of an output through wlr screencopy, one at a time.
+pub struct Preview {
+    display: NonNull<ffi::wl_display>,
+    manager: *mut ffi::zwlr_screencopy_manager_v1,
+    capture: Option<Capture>,
+}
+
+/// Fields drop in declaration order, the frame goes before its buffer.
+struct Capture {
+    frame: OwnedObject<Frame>,
+    /// the shm one offered, if it is in a format we read
+    format: Option<Format>,
+    buffer: Option<(OwnedObject<ffi::wl_buffer>, Mapping<usize>)>,
+    y_invert: bool,
+}
+
+#[derive(Clone, Copy)]
+struct Format {
+    format: u32,
+    size: [u32; 2],
+    stride: u32,
+}
+
+impl Preview {
+    /// `None` without screencopy, on compositors other than wlroots-based ones.
+    pub fn new(wayland: &wayland::Proxy, display: NonNull<ffi::wl_display>) -> Option<Self> {
+        Some(Self {
+            display,
+            manager: wayland.globals.screencopy_manager()?,
+            capture: None,
+        })
+    }
+    /// Starts over with `[x, y, width, height]` of `output`, in logical pixels.
+    pub fn capture(
+        &mut self,
+        wayland: &mut wayland::Proxy,
+        output: Object<ffi::wl_output>,
+        [x, y, width, height]: [i32; 4],
+    ) {
+        let frame = OwnedObject::from_raw(unsafe {
+            ffi::zwlr_screencopy_manager_v1_capture_output_region(
+                self.manager,
+                0,
+                output.as_ptr(),
+                x,
+                y,
+                width,
+                height,
+            )
+        });
+        wayland.listen(&frame, &wayland::SCREENCOPY_FRAME_LISTENER);
+        unsafe { ffi::wl_display_flush(self.display.as_ptr()) };
+        self.capture = Some(Capture {
+            frame,
+            format: None,
+            buffer: None,
+            y_invert: false,
+        });
+    }
+    fn current(&mut self, frame: Object<Frame>) -> Option<&mut Capture> {
+        self.capture
+            .as_mut()
+            .filter(|x| x.frame.as_ptr() == frame.as_ptr())
+    }
+    pub fn buffer(&mut self, frame: Object<Frame>, format: u32, size: [u32; 2], stride: u32) {
+        let Some(capture) = self.current(frame) else {
+            return;
+        };
+        if [ffi::WL_SHM_FORMAT_ARGB8888, ffi::WL_SHM_FORMAT_XRGB8888].contains(&format) {
+            capture.format = Some(Format {
+                format,
+                size,
+                stride,
+            });
+        }
+    }
+    pub fn flags(&mut self, frame: Object<Frame>, y_invert: bool) {
+        if let Some(capture) = self.current(frame) {
+            capture.y_invert = y_invert;
+        }
+    }
+    /// Copies into a buffer of the format offered.
+    pub fn buffer_done(&mut self, wayland: &wayland::Proxy, frame: Object<Frame>) {
+        let display = self.display;
+        let Some(capture) = self.current(frame) else {
+            return;
+        };
+        let Some(format) = capture.format else {
+            tracing::warn!("cannot preview the window, no buffer format in common");
+            self.capture = None;
+            return;
+        };
+        match shm_buffer(wayland, format) {
+            Ok(buffer) => {
+                unsafe {
+                    ffi::zwlr_screencopy_frame_v1_copy(capture.frame.as_ptr(), buffer.0.as_ptr());
+                    ffi::wl_display_flush(display.as_ptr());
+                }
+                capture.buffer = Some(buffer);
+            }
+            Err(e) => {
+                tracing::warn!("cannot preview the window: {e}");
+                self.capture = None;
+            }
+        }
+    }
+    /// The snapshot scaled down, once it is complete.
+    pub fn ready(&mut self, frame: Object<Frame>) -> Option<image::Handle> {
+        self.current(frame)?;
+        let Capture {
+            format,
+            buffer,
+            y_invert,
+            ..
+        } = self.capture.take()?;
+        let (Some(format), Some((_, pixels))) = (format, buffer) else {
+            return None;
+        };
+        Some(scale_down(pixels.as_bytes(), format, y_invert))
+    }
+    pub fn failed(&mut self, frame: Object<Frame>) {
+        if self.current(frame).is_some() {
+            tracing::warn!("cannot preview the window, the capture failed");
+            self.capture = None;
+        }
+    }
+}
+
+fn shm_buffer(
+    wayland: &wayland::Proxy,
+    Format {
+        format,
+        size: [width, height],
+        stride,
+    }: Format,
+) -> rustix::io::Result<(OwnedObject<ffi::wl_buffer>, Mapping<usize>)> {
+    let len = stride * height;
+    let fd: OwnedFd = memfd_create(c"hyoka-preview", MemfdFlags::CLOEXEC)?;
+    ftruncate(&fd, len as _)?;
+    let pixels = Mapping::map(&fd, ProtFlags::READ, MapFlags::SHARED)?;
+    let pool: OwnedObject<ffi::wl_shm_pool> = OwnedObject::from_raw(unsafe {
+        ffi::wl_shm_create_pool(wayland.globals.shm(), fd.as_raw_fd(), len as _)
+    });
+    // the buffer keeps the memory, the pool can go right away
+    let buffer = OwnedObject::from_raw(unsafe {
+        ffi::wl_shm_pool_create_buffer(
+            pool.as_ptr(),
+            0,
+            width as _,
+            height as _,
+            stride as _,
+            format,
+        )
+    });
+    Ok((buffer, pixels))
+}
+
+/// To at most [`WIDTH`] wide by sampling the nearest pixel, from little-endian ARGB or XRGB to
+/// RGBA.
+fn scale_down(pixels: &[u8], format: Format, y_invert: bool) -> image::Handle {
+    let [width, height] = format.size;
+    let factor = (width as f32 / WIDTH as f32).max(1.0);
+    let [w, h] = [width, height].map(|x| ((x as f32 / factor) as u32).max(1));
+    let opaque = format.format == ffi::WL_SHM_FORMAT_XRGB8888;
+    let mut rgba = Vec::with_capacity((w * h * 4) as usize);
+    for y in 0..h {
+        let mut sy = ((y as f32 * factor) as u32).min(height - 1);
+        if y_invert {
+            sy = height - 1 - sy;
+        }
+        let row = &pixels[(sy * format.stride) as usize..];
+        for x in 0..w {
+            let sx = ((x as f32 * factor) as u32).min(width - 1) as usize;
+            let [b, g, r, a]: [u8; 4] = row[sx * 4..sx * 4 + 4].try_into().unwrap();
+            rgba.extend([r, g, b, if opaque { u8::MAX } else { a }]);
+        }
+    }
+    image::Handle::from_rgba(w, h, rgba)
+}
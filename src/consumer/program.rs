@@ -1,101 +1,271 @@
 use std::{
+    borrow::Cow,
     cell::Cell,
-    fs, io,
-    num::NonZero,
-    path::Path,
+    collections::VecDeque,
+    iter, mem,
     ptr::{self, NonNull},
     rc::Rc,
+    time::Duration,
 };
 
 use ahash::AHashMap;
-use arrayvec::ArrayVec;
-use derive_more::{Deref, From};
+use chrono::Locale;
+use chrono_tz::Tz;
 use futures::{SinkExt as _, channel::mpsc::Sender};
 use iced::{
     Alignment, Border, Center, Color, Font, Length, Padding, Pixels, Point, Size, Theme, color,
     font::{Family, Stretch, Style, Weight},
-    mouse::Cursor,
+    mouse::{Cursor, ScrollDelta},
     theme::Palette,
-    widget::{self, button, container, image, mouse_area, row, svg, text},
+    widget::{self, button, container, mouse_area, row, svg, text},
 };
 use iced_core::{layout::Limits, text::Shaping, widget::Tree};
-use iced_tiny_skia::Renderer;
-use lru::LruCache;
-use rustc_hash::FxHashMap;
-use rustix::{
-    fs::{Mode, OFlags},
-    mm::{MapFlags, ProtFlags},
-    path::Arg as _,
-};
+use iced_renderer::Renderer;
+use rustc_hash::{FxHashMap, FxHashSet};
+use rustix::process::{Pid, Signal};
 
 use crate::{
     TinyString,
+    config::{self, Config},
     consumer::{
-        AppEvent, BatteryEvent, Dispatcher, Element,
-        window::{Role, Tag, Window, WindowManager},
+        AppEvent, BatteryEvent, Dispatcher, Element, Event,
+        activation::{Activation, Launch},
+        clipboard::{self, Clipboard, Entry},
+        effects::{self, Trigger},
+        gpu,
+        icon::{self, Handle, Icon},
+        night_light::NightLight,
+        outputs::{Monitor, Outputs},
+        preview::Preview,
+        registry::Registry,
+        seat::{Press, Seat},
+        supervisor::Slot,
+        window::{Role, Surface, Tag, Window, WindowManager, WlSurface},
     },
-    mapping::Mapping,
     modules::{
         self,
+        audio::Microphone,
         battery::{self, Battery},
-        clock::Clock,
-        dbus::{Tray, TrayEvent},
-        hyprland, polling,
+        clock::{self, Clock},
+        custom,
+        dbus::{
+            BluetoothDevice, BluetoothEvent, ColorScheme, SessionEvent, SettingEvent, Tray,
+            TrayEvent, TrayStatus,
+        },
+        hwmon, hyprland, ipc,
+        network::Vpn,
+        palette,
+        plugin::{self, Plugin},
+        polling,
+        procfs::{self, Process, Sort},
+        script,
+        timer::{self, Countdown, Stopwatch},
+        updates,
     },
-    wayland,
+    spawn::{spawn, spawn_attached},
+    wayland::{self, Object, OwnedObject},
 };
+use urgency::Urgency;
 
 const BAR_HEIGHT: u32 = 35;
+/// pixels of scrolling per step, one notch of a mouse wheel
+const SCROLL_STEP: f32 = 15.0;
+/// from `linux/input-event-codes.h`
+const KEY_ESC: u32 = 1;
+const KEY_BACKSPACE: u32 = 14;
+const KEY_ENTER: u32 = 28;
+const KEY_SPACE: u32 = 57;
 const WORKSPACE_MAX: usize = 10;
+/// how long the screenshot module says what became of the latest one
+const SCREENSHOT_DONE: Duration = Duration::from_secs(2);
+/// names accepted by `hyoka msg set-module` and by the layout
+const MODULES: &[&str] = &[
+    "logo",
+    "workspaces",
+    "submap",
+    "title",
+    "custom",
+    "plugins",
+    "scripts",
+    "tray",
+    "units",
+    "updates",
+    "lock",
+    "battery",
+    "clock",
+    "timer",
+    "stopwatch",
+    "fans",
+    "gpu",
+    "cpu",
+    "memory",
+    "vpn",
+    "microphone",
+    "bluetooth",
+    "clipboard",
+    "screenshot",
+    "recording",
+    "night_light",
+    "monitors",
+];
 
 #[derive(Debug, Clone)]
 pub enum Message {
     Hello,
-    Workspace { id: u8 },
+    Workspace {
+        id: u8,
+    },
+    WorkspaceScroll(f32),
     WindowInfo,
     Battery,
     TrayTooltip(Tray),
     TrayAction(Tray),
     CloseTooltip,
+    Calendar,
+    WorldClock,
     BatteryStop,
+    /// opens or closes the battery menu
+    BatteryMenu,
+    /// turns the charge limit on or off
+    ChargeLimit(bool),
+    Updates,
+    UpdatesAction,
+    Units,
+    RestartUnits,
+    CustomTooltip(usize),
+    Custom(usize, custom::Action),
+    CustomScroll(usize, f32),
+    PluginTooltip(usize),
+    Plugin(usize, custom::Action),
+    PluginScroll(usize, f32),
+    ScriptTooltip(usize),
+    Script(usize, custom::Action),
+    ScriptScroll(usize, f32),
+    /// entering an item, shown once the pointer rests on it
+    Hover(Box<Message>),
+    /// on a module, whatever its action for the button is
+    Click(&'static str, Button),
+    Hyprland(hyprland::Dispatcher),
+    CopyDate,
+    Exec(String),
+    TimerToggle,
+    TimerReset,
+    StopwatchToggle,
+    StopwatchReset,
+    /// makes the text the selection
+    Copy(String),
+    Fans,
+    Gpu,
+    /// opens or closes the top processes
+    Processes(Sort),
+    Vpn,
+    Microphone,
+    MicrophoneMute,
+    PublicIp,
+    CopyIp,
+    /// opens or closes the clipboard history
+    ClipboardHistory,
+    /// makes an earlier selection the selection again
+    ClipboardPick(Entry),
+    Screenshot,
+    Recording,
+    StopRecording,
+    NightLight,
+    /// opens or closes the list of outputs
+    Monitors,
+    /// opens or closes every workspace with the windows on it
+    WorkspaceOverview,
+    /// a window of the overview pressed, by address and with its workspace
+    OverviewPress(String, u8),
+    /// the pointer released over a workspace of the overview
+    OverviewRelease(u8),
+    /// opens or closes what can be done with the focused window
+    WindowMenu,
+    /// from the window menu, closing it
+    WindowAction(hyprland::Dispatcher),
+    /// opens or closes the paired bluetooth devices
+    BluetoothMenu,
+    BluetoothPower(bool),
+    /// the device at the object path connected or not
+    BluetoothConnect(String, bool),
+    OutputPower(Object<wayland::ffi::wl_output>, bool),
+    /// the named output enabled or not, at the scale
+    ConfigureOutput(String, bool, f64),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Button {
+    Left,
+    Middle,
+    Right,
+}
+
+impl Message {
+    fn hover(message: Message) -> Self {
+        Self::Hover(Box::new(message))
+    }
 }
 
 type Callbacks = FxHashMap<wayland::Callback, Box<dyn FnOnce(&mut Runner)>>;
 
+#[derive(PartialEq)]
 struct BitSet(u16);
 
 impl BitSet {
     fn new() -> Self {
         BitSet(0)
     }
-    fn set(&mut self, idx: usize) {
-        self.0 |= 1 << idx;
+    fn set(&mut self, idx: usize) -> bool {
+        self.0.update(self.0 | 1 << idx)
     }
-    fn unset(&mut self, idx: usize) {
-        self.0 &= !(1 << idx);
+    fn unset(&mut self, idx: usize) -> bool {
+        self.0.update(self.0 & !(1 << idx))
     }
     fn get(&self, idx: usize) -> bool {
         (self.0 & (1 << idx)) != 0
     }
 }
 
+/// Where workspace `id` is on the bar, `None` for those it does not show.
+fn workspace_index(id: usize) -> Option<usize> {
+    id.checked_sub(1).filter(|&idx| idx < WORKSPACE_MAX)
+}
+
 struct WindowInfo {
+    /// untruncated, the icon is looked up by it
+    app_class: TinyString,
     class: TinyString,
     title: TinyString,
-    icon: Option<Handle>,
+    icon: Option<Icon>,
 }
 
-struct TrayItem {
-    icon: Option<Handle>,
+/// A workspace of the overview.
+struct OverviewTile {
+    id: u8,
+    label: String,
+    focused: bool,
+    windows: Vec<OverviewWindow>,
+}
+
+struct OverviewWindow {
+    /// as hex without `0x`
+    address: String,
+    /// shown without an icon
+    class: TinyString,
+    icon: Option<Icon>,
 }
 
-#[derive(From, Default, Deref)]
-struct Attr<T>(T);
+struct TrayItem {
+    icon: Icon,
+    attention_icon: Option<Icon>,
+    status: TrayStatus,
+}
 
-impl<T: PartialEq> Attr<T> {
-    fn update(&mut self, value: T) -> bool {
-        if &self.0 != &value {
-            self.0 = value;
+/// Change detection for anything the bar shows, an event that changes nothing draws nothing.
+trait Update: PartialEq + Sized {
+    fn update(&mut self, value: Self) -> bool {
+        if *self != value {
+            *self = value;
             true
         } else {
             false
@@ -103,68 +273,120 @@ impl<T: PartialEq> Attr<T> {
     }
 }
 
+impl<T: PartialEq> Update for T {}
+
 struct BatteryStatus {
-    device: Rc<Battery>,
-    charging: Attr<Option<bool>>,
-    status: Attr<battery::Status>,
-    capacity: Attr<u8>,
+    device: Battery,
+    charging: Option<bool>,
+    status: battery::Status,
+    capacity: u8,
+    /// as of the latest change, for the effect to play once
+    low: bool,
 }
 
 impl BatteryStatus {
     fn new() -> Option<Self> {
         let device = Battery::new()?;
         Some(Self {
-            charging: None.into(),
-            status: device.status().into(),
-            capacity: device.capacity().into(),
-            device: Rc::new(device),
+            charging: None,
+            status: device.status(),
+            capacity: device.capacity(),
+            low: false,
+            device,
         })
     }
+    /// Whether the icon changes.
+    fn update(&mut self, level: battery::Level) -> bool {
+        self.capacity.update(level.capacity) | self.status.update(level.status)
+    }
+    /// Whether it just dropped to `level` percent or under, while discharging.
+    fn became_low(&mut self, level: u8) -> bool {
+        let low = !self.charging() && self.capacity <= level;
+        self.low.update(low) && low
+    }
     fn charged(&self) -> bool {
-        self.status.0 == battery::Status::Full || self.capacity.0 >= 99
+        self.status == battery::Status::Full || self.capacity >= 99
     }
     fn charging(&self) -> bool {
-        self.charging.0 == Some(true) || self.status.0 == battery::Status::Charging
+        self.charging == Some(true) || self.status == battery::Status::Charging
     }
     fn icon(&self) -> String {
         if self.charged() {
             "battery-level-100-charged-symbolic".into()
         } else {
-            let level = self.capacity.0 / 10 * 10;
+            let level = self.capacity / 10 * 10;
             let state = if self.charging() { "-charging" } else { "" };
             format!("battery-level-{level}{state}-symbolic")
         }
     }
 }
 
-#[derive(Debug, Clone)]
-enum Handle {
-    Pixmap(image::Handle),
-    Svg(svg::Handle),
-}
-
-impl Handle {
-    fn load(self) -> Element<'static> {
-        self.load_size(24)
-    }
-    fn load_size(self, size: impl Into<Length> + Copy) -> Element<'static> {
-        match self {
-            Handle::Pixmap(handle) => image(handle).width(size).into(),
-            Handle::Svg(handle) => svg(handle).width(size).height(size).into(),
-        }
-    }
-}
-
 enum TooltipText {
     WindowInfo(String),
+    /// the title over a snapshot of the window, once it is taken
+    WindowPreview {
+        title: String,
+        image: Option<widget::image::Handle>,
+    },
     Simple(TinyString),
+    Multiline(String),
+    /// with the charge limit toggle in the menu
+    Battery {
+        info: battery::Info,
+        menu: bool,
+    },
+    Tray {
+        icon: Option<Icon>,
+        title: String,
+        body: String,
+    },
+    Calendar(Clock, Locale),
+    /// zone and time
+    WorldClock(Vec<[String; 2]>),
+    Fans(String),
+    Gpu(String),
+    Processes(Sort, Vec<Process>),
+    /// with the latest link qualities of the Wi-Fi
+    Vpn {
+        text: String,
+        signal: Vec<u8>,
+    },
+    /// the search typed so far and the entries matching it
+    ClipboardHistory {
+        query: String,
+        entries: Vec<Entry>,
+    },
+    Monitors(Vec<Monitor>),
+    WorkspaceOverview(Vec<OverviewTile>),
+    /// for the focused window, with the other workspaces by id and label
+    WindowMenu(Vec<(u8, String)>),
+    Bluetooth {
+        powered: bool,
+        devices: Vec<BluetoothDevice>,
+    },
 }
 
 impl TooltipText {
     fn view(&self) -> Element<'_> {
         match self {
             TooltipText::WindowInfo(s) => tooltip_text(s.trim_end(), 13.0, Shaping::Auto),
+            TooltipText::WindowPreview { title, image } => window_preview(title, image.as_ref()),
             TooltipText::Simple(s) => tooltip_text(s, 10.0, Shaping::Basic),
+            TooltipText::Multiline(s) => tooltip_text(s, 13.0, Shaping::Basic),
+            TooltipText::Battery { info, menu } => battery_tooltip(info, *menu),
+            TooltipText::Tray { icon, title, body } => {
+                tray_tooltip(icon.as_ref().and_then(Icon::get), title, body)
+            }
+            TooltipText::Calendar(clock, locale) => calendar(clock, *locale),
+            TooltipText::WorldClock(zones) => world_clock(zones),
+            TooltipText::Processes(sort, processes) => top_processes(*sort, processes),
+            TooltipText::ClipboardHistory { query, entries } => clipboard_history(query, entries),
+            TooltipText::Monitors(monitors) => monitors_menu(monitors),
+            TooltipText::WorkspaceOverview(tiles) => workspace_overview(tiles),
+            TooltipText::WindowMenu(workspaces) => window_menu(workspaces),
+            TooltipText::Bluetooth { powered, devices } => bluetooth_menu(*powered, devices),
+            TooltipText::Vpn { text, signal } => network_tooltip(text, signal),
+            TooltipText::Fans(s) | TooltipText::Gpu(s) => tooltip_text(s, 13.0, Shaping::Basic),
         }
     }
 }
@@ -172,36 +394,206 @@ impl TooltipText {
 struct Tooltip {
     text: TooltipText,
     window: Window,
+    /// a menu, it holds the pointer and keyboard until dismissed
+    grab: bool,
+}
+
+/// A configured clock and its parts as of the latest tick.
+struct ClockFace {
+    config: config::Clock,
+    locale: Locale,
+    timezone: Option<Tz>,
+    /// date, time and weekday
+    text: [String; 3],
+}
+
+impl ClockFace {
+    fn new(config: config::Clock) -> Self {
+        for format in [&config.date, &config.time, &config.weekday] {
+            if !clock::valid_format(format) {
+                tracing::warn!("invalid clock format {format:?}");
+            }
+        }
+        let mut face = Self {
+            locale: clock::locale(config.locale.as_deref()),
+            timezone: config.timezone.as_deref().and_then(clock::timezone),
+            config,
+            text: Default::default(),
+        };
+        face.text = face.render(&Clock::now());
+        face
+    }
+    fn render(&self, clock: &Clock) -> [String; 3] {
+        [&self.config.date, &self.config.time, &self.config.weekday].map(|x| {
+            clock
+                .format_in(self.timezone, x, self.locale)
+                .unwrap_or_default()
+        })
+    }
+}
+
+/// The configured zones, labeled with their city.
+fn world_zones(config: &config::WorldClock) -> Vec<(String, Tz)> {
+    config
+        .zones
+        .iter()
+        .filter_map(|name| {
+            let tz = clock::timezone(name)?;
+            let city = name.rsplit('/').next().unwrap_or(name);
+            Some((city.replace('_', " "), tz))
+        })
+        .collect()
+}
+
+fn clock_faces(config: &[config::Clock]) -> Vec<ClockFace> {
+    if config.is_empty() {
+        return vec![ClockFace::new(Default::default())];
+    }
+    config.iter().cloned().map(ClockFace::new).collect()
+}
+
+/// Tooltips wait for the pointer to rest before opening, and linger a moment after it left.
+enum Hover {
+    Idle,
+    Opening(Message),
+    Closing,
 }
 
 pub struct Runner {
     pub wayland: wayland::Proxy,
     hyprctl: Option<hyprland::Context>,
-    dbus: Option<modules::dbus::Proxy<Dispatcher>>,
+    dbus: Slot<modules::dbus::Proxy<Dispatcher>>,
+    system: Slot<modules::dbus::Proxy<Dispatcher>>,
     polling: Sender<polling::Signal>,
+    updates: Sender<updates::Signal>,
+    config: Config,
 
     pub display: NonNull<wayland::ffi::wl_display>,
-    window_manager: WindowManager,
+    pub window_manager: WindowManager,
     pub callbacks: Callbacks,
     tooltip: Option<Tooltip>,
-    pub pointer: NonNull<wayland::ffi::wl_pointer>,
-    pub cursor_shape_device: NonNull<wayland::ffi::wp_cursor_shape_device_v1>,
+    hover: Hover,
+    /// bumped to cancel the running hover timer
+    hover_timer: u64,
+    events: Sender<Event>,
+    /// `None` without idle notify or with `idle.timeout` at 0
+    idle_notification: Option<OwnedObject<wayland::ffi::ext_idle_notification_v1>>,
+    /// `None` without data control, copying falls back to `wl-copy` then
+    pub clipboard: Option<Clipboard>,
+    /// the latest button press or touch, menus grab with it
+    press: Option<Press>,
+    /// `None` without xdg activation, apps are launched without a token then
+    activation: Option<Activation>,
+    /// picks the shift-click actions
+    shift: bool,
     pub theme: Theme,
+    /// set up once, switching backends takes a restart
+    pub gpu: Option<gpu::Gpu>,
+    disabled_modules: FxHashSet<&'static str>,
+    /// the background sources of modules in the layout
+    registry: Registry,
+    /// frame timings and damage drawn over the bars, for `hyoka msg toggle-hud`
+    pub hud: bool,
+    quit: bool,
+    /// pixels scrolled short of a whole [`SCROLL_STEP`]
+    scrolled: f32,
+    /// by `hyoka msg toggle-visibility`
+    hidden: bool,
+    /// the focused window is fullscreen, the bar is hidden meanwhile
+    fullscreen: bool,
+    /// Set by app events, cleared by [`Runner::refresh`] once per batch.
+    outdated: bool,
+    tooltip_outdated: bool,
 
     workspaces: BitSet,
     workspace_focused: usize,
+    /// as Hyprland has them, mostly just the id
+    workspace_names: [TinyString; WORKSPACE_MAX],
+    /// with windows asking for attention, until focused
+    urgent_workspaces: BitSet,
+    /// the window pressed in the overview and its workspace, until the pointer is released
+    overview_press: Option<(String, u8)>,
+    /// the active keybinding submap, empty for the default one
+    submap: TinyString,
     window: WindowInfo,
 
     tray_items: AHashMap<Tray, TrayItem>,
 
-    battery_icon: Option<Handle>,
+    battery_icon: Option<Icon>,
     battery_status: Option<BatteryStatus>,
 
-    date: ArrayVec<u8, 12>,
-    time: [u8; 8],
-    weekday: &'static str,
+    lock_icon: Option<Icon>,
+
+    pending_updates: Vec<String>,
+    updates_icon: Option<Icon>,
+
+    custom: Vec<custom::Output>,
+    plugins: Vec<Plugin>,
+    /// of each plugin, as last sent
+    plugin_outputs: Vec<custom::Output>,
+    /// takes clicks and scrolls to the scripts
+    scripts: Sender<(usize, custom::Action)>,
+    script_outputs: Vec<custom::Output>,
+
+    failed_units: Vec<String>,
+    /// of battery, units, tray and workspaces, as of the latest event
+    urgency: Urgency,
 
-    icon_cache: LruCache<TinyString, Option<Handle>, ahash::RandomState>,
+    clocks: Vec<ClockFace>,
+    world_zones: Vec<(String, Tz)>,
+    /// polling ticks every second rather than every minute
+    seconds: bool,
+
+    countdown: Countdown,
+    timer_icon: Option<Icon>,
+    stopwatch: Stopwatch,
+    stopwatch_icon: Option<Icon>,
+    /// as of the latest tick, empty unless the fans module is in the layout
+    fans: Vec<hwmon::Reading>,
+    /// as of the latest tick, `None` unless the gpu module is in the layout
+    gpu_usage: Option<modules::gpu::Reading>,
+    /// percent
+    cpu: Option<u8>,
+    memory: Option<procfs::Memory>,
+    /// the VPN interfaces that are up
+    vpn: Vec<String>,
+    vpn_icon: Option<Icon>,
+    /// looked up on demand
+    public_ip: Option<String>,
+    /// link quality of the Wi-Fi by tick, the latest last
+    wifi_signal: VecDeque<u8>,
+    /// `None` without a default source
+    microphone: Option<Microphone>,
+    microphone_icon: Option<Icon>,
+    /// whether the adapter is on, `None` without one
+    bluetooth: Option<bool>,
+    bluetooth_icon: Option<Icon>,
+    clipboard_icon: Option<Icon>,
+    screenshot_icon: Option<Icon>,
+    /// "Copied" or "Saved" for a moment after a screenshot, shown instead of the icon
+    screenshot_done: Option<&'static str>,
+    /// bumped by every screenshot, ends the confirmation of the previous one early
+    screenshots: u64,
+    /// pids of the screen recorders, as of the latest tick
+    recorders: Vec<u32>,
+    /// the recording dot fades every other second
+    pulse: bool,
+    /// `None` without gamma control
+    night_light: Option<NightLight>,
+    night_light_icon: Option<Icon>,
+    /// as of the latest tick, toggling by hand lasts until this changes
+    night_scheduled: Option<bool>,
+    outputs: Outputs,
+    monitors_icon: Option<Icon>,
+    /// `None` without screencopy
+    preview: Option<Preview>,
+
+    pub icons: icon::Loader,
+    /// from the settings portal, used unless the config names a theme
+    desktop_icon_theme: Option<String>,
+    /// from the settings portal, picks the palette
+    color_scheme: ColorScheme,
+    imported_palettes: palette::Palettes,
 }
 
 impl Runner {
@@ -209,88 +601,85 @@ impl Runner {
         mut wayland: wayland::Proxy,
         display: NonNull<wayland::ffi::wl_display>,
         hyprctl: Option<hyprland::Context>,
-        dbus: Option<modules::dbus::Proxy<Dispatcher>>,
+        dbus: Slot<modules::dbus::Proxy<Dispatcher>>,
+        system: Slot<modules::dbus::Proxy<Dispatcher>>,
         polling: Sender<polling::Signal>,
+        updates: Sender<updates::Signal>,
+        scripts: Sender<(usize, custom::Action)>,
+        events: Sender<Event>,
+        plugins: Vec<Plugin>,
+        config: Config,
     ) -> Self {
-        let mut window_manager = WindowManager::default();
-        let surface =
-            unsafe { wayland::ffi::wl_compositor_create_surface(wayland.globals.compositer()) };
-        unsafe {
-            wayland::ffi::wl_surface_add_listener(
-                surface,
-                &wayland::SURFACE_LISTENER,
-                &raw mut *wayland.notifier as _,
-            );
+        let gpu = match config.render.backend {
+            config::Backend::TinySkia => None,
+            config::Backend::Wgpu => gpu::Gpu::new()
+                .inspect_err(|e| tracing::error!("{e}, falling back to software rendering"))
+                .ok(),
         };
-        let layer_surface = unsafe {
-            wayland::ffi::zwlr_layer_shell_v1_get_layer_surface(
-                wayland.globals.layer_shell(),
-                surface,
-                ptr::null_mut(),
-                wayland::ffi::ZWLR_LAYER_SHELL_V1_LAYER_TOP,
-                c"hyoka".as_ptr(),
-            )
-        };
-        unsafe {
-            wayland::ffi::zwlr_layer_surface_v1_add_listener(
-                layer_surface,
-                &wayland::LAYER_SURFACE_LISTENER,
-                &raw mut *wayland.notifier as _,
-            );
-            wayland::ffi::zwlr_layer_surface_v1_set_size(layer_surface, 0, BAR_HEIGHT);
-            wayland::ffi::zwlr_layer_surface_v1_set_anchor(
-                layer_surface,
-                wayland::ffi::ZWLR_LAYER_SURFACE_V1_ANCHOR_TOP
-                    | wayland::ffi::ZWLR_LAYER_SURFACE_V1_ANCHOR_LEFT
-                    | wayland::ffi::ZWLR_LAYER_SURFACE_V1_ANCHOR_RIGHT,
-            );
-            wayland::ffi::zwlr_layer_surface_v1_set_exclusive_zone(layer_surface, 35);
-            wayland::ffi::wl_surface_commit(surface);
-
-            wayland::ffi::wl_display_flush(display.as_ptr());
-        }
-        window_manager.create_window(
-            NonNull::new(surface).unwrap(),
-            Role::Layer {
-                layer_surface: NonNull::new(layer_surface).unwrap(),
-            },
-            Tag::Bar,
-            renderer(),
-        );
+        let window_manager = WindowManager::default();
 
-        let pointer = unsafe { wayland::ffi::wl_seat_get_pointer(wayland.globals.seat()) };
-        unsafe {
-            wayland::ffi::wl_pointer_add_listener(
-                pointer,
-                &wayland::POINTER_LISTENERL,
-                &raw mut *wayland.notifier as _,
-            )
-        };
-        let cursor_shape_device = unsafe {
-            wayland::ffi::wp_cursor_shape_manager_v1_get_pointer(
-                wayland.globals.cursor_shape_manager(),
-                pointer,
-            )
-        };
+        let clipboard = Clipboard::new(&mut wayland, display, events.clone());
+        let night_light = NightLight::new(&wayland, display);
+        let outputs = Outputs::new(&mut wayland, display);
+        let preview = Preview::new(&wayland, display);
+        let activation = Activation::new(&wayland, display);
+        let idle_notification = idle_notification(&mut wayland, config.idle.timeout);
 
-        let now = Clock::now();
+        check_layout(&config);
+        let icons = icon::Loader::new(
+            events.clone(),
+            icon::themes(&config.icons, None),
+            config.icons.cache,
+        );
+        let registry = Registry::new(events.clone());
+        let theme = theme(
+            &config.colors,
+            &Default::default(),
+            ColorScheme::NoPreference,
+        );
+        let clocks = clock_faces(&config.clock);
         let mut res = Self {
             wayland,
             display,
             hyprctl,
             dbus,
+            system,
             polling,
+            updates,
+            config,
 
             tooltip: None,
+            hover: Hover::Idle,
+            hover_timer: 0,
+            events,
             window_manager,
-            theme: theme(),
-            pointer: NonNull::new(pointer).unwrap(),
-            cursor_shape_device: NonNull::new(cursor_shape_device).unwrap(),
+            theme,
+            color_scheme: ColorScheme::NoPreference,
+            imported_palettes: Default::default(),
+            gpu,
+            disabled_modules: Default::default(),
+            registry,
+            hud: false,
+            quit: false,
+            scrolled: 0.0,
+            hidden: false,
+            fullscreen: false,
+            outdated: false,
+            tooltip_outdated: false,
+            clipboard,
+            press: None,
+            activation,
+            shift: false,
             callbacks: Default::default(),
 
             workspaces: BitSet::new(),
             workspace_focused: usize::MAX,
+            workspace_names: Default::default(),
+            urgent_workspaces: BitSet::new(),
+            overview_press: None,
+            submap: TinyString::new(),
             window: WindowInfo {
+                app_class: TinyString::new(),
                 class: TinyString::new(),
                 title: TinyString::new(),
                 icon: None,
@@ -298,17 +687,82 @@ impl Runner {
             tray_items: AHashMap::with_hasher(ahash::RandomState::with_seeds(114, 514, 1919, 810)),
             battery_icon: None,
             battery_status: BatteryStatus::new(),
-            date: now.date(),
-            time: now.time(),
-            weekday: now.weekday(),
-            icon_cache: LruCache::with_hasher(NonZero::new(16).unwrap(), ahash::RandomState::new()),
+            lock_icon: None,
+            pending_updates: vec![],
+            updates_icon: None,
+            custom: vec![],
+            plugin_outputs: plugins.iter().map(|_| Default::default()).collect(),
+            plugins,
+            scripts,
+            script_outputs: vec![],
+            failed_units: vec![],
+            urgency: Urgency::default(),
+            clocks,
+            world_zones: world_zones(&config.world_clock),
+            seconds: clock::needs_seconds(&config),
+            countdown: Countdown::Idle,
+            timer_icon: None,
+            stopwatch: Stopwatch::default(),
+            stopwatch_icon: None,
+            fans: vec![],
+            gpu_usage: None,
+            cpu: None,
+            memory: None,
+            vpn: vec![],
+            vpn_icon: None,
+            public_ip: None,
+            wifi_signal: VecDeque::new(),
+            microphone: None,
+            microphone_icon: None,
+            bluetooth: None,
+            bluetooth_icon: None,
+            clipboard_icon: None,
+            screenshot_icon: None,
+            screenshot_done: None,
+            screenshots: 0,
+            recorders: vec![],
+            pulse: false,
+            night_light,
+            idle_notification,
+            night_light_icon: None,
+            night_scheduled: None,
+            outputs,
+            monitors_icon: None,
+            preview,
+            icons,
+            desktop_icon_theme: None,
         };
         res.reload_battery_icon();
+        res.reload_vpn_icon();
+        res.timer_icon = Some(res.load_icon(&"alarm-symbolic".into(), true));
+        res.stopwatch_icon = Some(res.load_icon(&"stopwatch-symbolic".into(), true));
+        res.clipboard_icon = Some(res.load_icon(&"edit-paste-symbolic".into(), true));
+        res.screenshot_icon = Some(res.load_icon(&"camera-photo-symbolic".into(), true));
+        res.reload_night_light_icon();
+        res.monitors_icon = Some(res.load_icon(&"video-display-symbolic".into(), true));
+        res.update_registry();
+        // creates the bars
+        res.update_visibility();
         res
     }
+    pub fn quitting(&self) -> bool {
+        self.quit
+    }
+    /// Destroys every Wayland object created so far, the caller closes the connection.
+    pub fn shutdown(mut self) {
+        self.close_tooltip();
+        self.callbacks.clear();
+        self.window_manager.clear();
+        drop(self.clipboard);
+        drop(self.night_light);
+        drop(self.idle_notification);
+        drop(self.outputs);
+        drop(self.preview);
+        self.wayland.destroy();
+    }
     pub fn view(&self, tag: Tag) -> Element<'_> {
         match tag {
-            Tag::Bar => self.bar(),
+            Tag::Bar(profile) => self.bar(profile),
             Tag::Tooltip => match self.tooltip {
                 Some(Tooltip { ref text, .. }) => text.view(),
                 None => "".into(),
@@ -321,282 +775,1932 @@ impl Runner {
         }
     }
     fn set_tooltip(&mut self, text: TooltipText) -> Option<()> {
-        self.close_tooltip();
-        let w = self.window_manager.focused()?.clone();
-        let state = w.state.borrow();
-        if let Cursor::Available(Point { x, .. }) = state.cursor {
-            self.tooltip = popup(
-                &mut self.wayland,
-                &mut self.window_manager,
-                self.display,
-                text.view(),
-                [x as _, BAR_HEIGHT + 1],
-                &w.surface().role,
-            )
-            .cloned()
-            .map(|window| Tooltip { text, window });
+        // hovering the bar leaves an open menu alone
+        if self.tooltip.as_ref().is_some_and(|x| x.grab) {
+            return None;
+        }
+        self.open_popup(text, None)
+    }
+    /// Like a tooltip, but closed by a click elsewhere or Escape instead of leaving its item.
+    fn open_menu(&mut self, text: TooltipText) -> Option<()> {
+        let press = self.press?;
+        self.cancel_hover();
+        self.open_popup(text, Some(press))
+    }
+    fn close_menu(&mut self) {
+        if self.tooltip.as_ref().is_some_and(|x| x.grab) {
+            self.close_tooltip();
+        }
+    }
+    fn open_popup(&mut self, text: TooltipText, grab: Option<Press>) -> Option<()> {
+        let Some(w) = self.window_manager.focused().cloned() else {
+            self.close_tooltip();
+            return None;
+        };
+        let Cursor::Available(Point { x, .. }) = w.state.borrow().cursor else {
+            self.close_tooltip();
+            return Some(());
+        };
+        let anchor = [x as _, BAR_HEIGHT + 1];
+        if grab.is_none()
+            && let Some(tooltip) = &mut self.tooltip
+            && !tooltip.grab
+        {
+            // only the content changes, the surface moves over instead of being replaced
+            let size = measure(text.view(), &mut tooltip.window.state.borrow_mut().renderer);
+            if size.width * size.height == 0.0 {
+                self.close_tooltip();
+                return None;
+            }
+            tooltip.text = text;
+            reposition(&tooltip.window.surface().role, size, anchor);
+            self.tooltip_outdated = true;
+            self.outdated = true;
+            return Some(());
         }
+        self.close_tooltip();
+        self.tooltip = popup(
+            &mut self.wayland,
+            &mut self.window_manager,
+            self.display,
+            text.view(),
+            anchor,
+            &w.surface().role,
+            grab,
+            renderer(self.gpu.as_ref()),
+        )
+        .cloned()
+        .map(|window| Tooltip {
+            text,
+            window,
+            grab: grab.is_some(),
+        });
         Some(())
     }
     pub async fn update(&mut self, message: Message) -> Option<()> {
         match message {
             Message::Hello => {}
             Message::Workspace { id } => {
-                self.hyprctl
-                    .as_mut()?
-                    .controller()
-                    .await
-                    .command(hyprland::Command::Workspace(id))
-                    .await;
+                self.hyprland_dispatch(hyprland::Dispatcher::Workspace(id))
+                    .await?
+            }
+            Message::WorkspaceScroll(y) => {
+                let steps = self.scroll_steps(y);
+                if steps == 0 {
+                    return None;
+                }
+                // scrolling up goes back
+                self.hyprland_dispatch(hyprland::Dispatcher::RelativeWorkspace(-steps))
+                    .await?;
             }
             Message::WindowInfo => {
+                if self.window_preview().await.is_some() {
+                    return Some(());
+                }
                 let res = match self
                     .hyprctl
                     .as_mut()?
                     .controller()
                     .await
+                    .inspect_err(|e| tracing::warn!("cannot connect to hyprland: {e}"))
+                    .ok()?
                     .request(hyprland::Request::ActiveWindow)
                     .await
+                    .inspect_err(|e| tracing::warn!("cannot query active window: {e}"))
+                    .ok()?
                 {
                     hyprland::Response::Raw(s) => s,
                 };
                 self.set_tooltip(TooltipText::WindowInfo(res.replace('\t', "        ")));
             }
             Message::Battery => {
-                self.set_tooltip(TooltipText::Simple(
-                    self.battery_status.as_ref()?.device.info().tooltip(),
-                ));
+                self.set_tooltip(TooltipText::Battery {
+                    info: self.battery_status.as_ref()?.device.info(),
+                    menu: false,
+                });
                 self.polling
-                    .send(polling::Signal::Battery(
-                        self.battery_status.as_ref()?.device.clone(),
-                    ))
+                    .send(polling::Signal::BatteryTooltip(true))
                     .await
                     .unwrap();
             }
             Message::TrayTooltip(service) => {
-                let content =
-                    TinyString::from_string(self.dbus.as_mut()?.tray_tooltip(service).await?);
-                self.set_tooltip(TooltipText::Simple(content));
+                let tooltip = self.dbus.get()?.tray_tooltip(service.clone()).await?;
+                if tooltip.title.is_empty() && tooltip.body.is_empty() {
+                    return None;
+                }
+                let icon = if tooltip.icon_name.is_empty() {
+                    Some(self.tray_items.get(&service)?.icon.clone())
+                } else {
+                    Some(self.load_icon(&tooltip.icon_name.as_str().into(), false))
+                };
+                self.set_tooltip(TooltipText::Tray {
+                    icon,
+                    title: tooltip.title,
+                    body: strip_markup(&tooltip.body),
+                });
             }
             Message::BatteryStop => {
-                self.close_tooltip();
+                self.leave();
+                // the menu keeps showing the battery until dismissed
+                if let Some(Tooltip {
+                    text: TooltipText::Battery { menu: true, .. },
+                    ..
+                }) = self.tooltip
+                {
+                    return None;
+                }
                 self.polling
-                    .send(polling::Signal::BatteryStop)
+                    .send(polling::Signal::BatteryTooltip(false))
                     .await
                     .unwrap();
             }
-            Message::TrayAction(service) => self.dbus.as_mut()?.tray_action(service).await,
-            Message::CloseTooltip => self.close_tooltip(),
-        }
-        None
-    }
-    pub async fn dispatch_wayland_event(&mut self, event: wayland::Event) -> Option<()> {
-        match event {
-            wayland::Event::Resize { object, size } => {
-                self.window_manager
-                    .find_by_object(object)?
-                    .clone()
-                    .resize(size, self);
+            Message::TrayAction(service) => {
+                let mut dbus = self.dbus.get()?;
+                self.launch(Box::new(move |token| {
+                    let token = token.map(str::to_owned);
+                    compio::runtime::spawn(async move {
+                        dbus.tray_action(service, token.as_deref()).await
+                    })
+                    .detach();
+                }));
             }
-            wayland::Event::Rescale { surface, factor } => {
-                self.window_manager
-                    .find_by_object(surface)?
-                    .clone()
-                    .rescale(factor, self);
+            Message::Updates => {
+                self.set_tooltip(TooltipText::Multiline(self.pending_updates.join("\n")));
             }
-            wayland::Event::Enter { surface, serial } => {
-                let win = self.window_manager.find_by_object(surface)?.clone();
-                win.mouse(iced::mouse::Event::CursorEntered, self).await;
-                win.enter(serial);
-                self.window_manager.focused = Some(surface);
+            Message::UpdatesAction => {
+                let (program, args) = self.config.updates.command.split_first()?;
+                let (program, args) = (program.clone(), args.to_vec());
+                let mut refresh = self.updates.clone();
+                self.launch(Box::new(move |token| {
+                    let mut child = match spawn_attached(&program, args, token) {
+                        Ok(x) => x,
+                        Err(e) => {
+                            tracing::warn!("cannot spawn {program:?}: {e}");
+                            return;
+                        }
+                    };
+                    compio::runtime::spawn(async move {
+                        compio::runtime::spawn_blocking(move || child.wait())
+                            .await
+                            .ok();
+                        refresh.send(updates::Signal::Refresh).await.ok();
+                    })
+                    .detach();
+                }));
             }
-            wayland::Event::Mouse(event) => {
-                let window = self.window_manager.focused()?;
-                window.clone().mouse(event, self).await;
-                match event {
-                    iced::mouse::Event::CursorLeft => {
-                        self.window_manager.focused.take();
-                    }
-                    _ => (),
+            Message::Units => {
+                self.set_tooltip(TooltipText::Multiline(self.failed_units.join("\n")));
+            }
+            Message::RestartUnits => {
+                let mut system = self.system.get()?;
+                for unit in &self.failed_units {
+                    system.restart_unit(unit).await;
                 }
             }
-            wayland::Event::CallbackDone(cb) => self.callbacks.remove(&cb).unwrap()(self),
-        }
-        Some(())
-    }
-    pub fn dispatch_app_event(&mut self, event: AppEvent) {
-        let mut update_tooltip = false;
-        match event {
-            AppEvent::Hyprland(event) => match event {
-                hyprland::Event::Workspace { id } => self.workspace_focused = id - 1,
-                hyprland::Event::CreateWorkspace { id } => self.workspaces.set(id - 1),
-                hyprland::Event::DestroyWorkspace { id } => self.workspaces.unset(id - 1),
-                hyprland::Event::ActiveWindow { class, title } => {
-                    self.window = WindowInfo {
-                        icon: if class.is_empty() {
-                            None
-                        } else {
-                            self.load_icon(&class, false)
-                        },
-                        class: truncate(class.clone(), 15, "…"),
-                        title: truncate(title.clone(), 50, "…"),
-                    }
+            Message::CustomTooltip(index) => {
+                let tooltip = self.custom.get(index)?.tooltip.clone()?;
+                self.set_tooltip(TooltipText::Multiline(tooltip));
+            }
+            Message::Custom(index, action) => self.run_custom(index, action)?,
+            Message::CustomScroll(index, y) => {
+                let steps = self.scroll_steps(y);
+                let action = if steps > 0 {
+                    custom::Action::ScrollUp
+                } else {
+                    custom::Action::ScrollDown
+                };
+                for _ in 0..steps.unsigned_abs() {
+                    self.run_custom(index, action)?;
+                }
+            }
+            Message::ScriptTooltip(index) => {
+                let tooltip = self.script_outputs.get(index)?.tooltip.clone()?;
+                self.set_tooltip(TooltipText::Multiline(tooltip));
+            }
+            Message::Script(index, action) => self.scripts.send((index, action)).await.unwrap(),
+            Message::ScriptScroll(index, y) => {
+                let steps = self.scroll_steps(y);
+                let action = if steps > 0 {
+                    custom::Action::ScrollUp
+                } else {
+                    custom::Action::ScrollDown
+                };
+                for _ in 0..steps.unsigned_abs() {
+                    self.scripts.send((index, action)).await.unwrap();
+                }
+            }
+            Message::PluginTooltip(index) => {
+                let tooltip = self.plugin_outputs.get(index)?.tooltip.clone()?;
+                self.set_tooltip(TooltipText::Multiline(tooltip));
+            }
+            Message::Plugin(index, action) => self.plugins.get(index)?.action(action),
+            Message::PluginScroll(index, y) => {
+                let steps = self.scroll_steps(y);
+                let action = if steps > 0 {
+                    custom::Action::ScrollUp
+                } else {
+                    custom::Action::ScrollDown
+                };
+                for _ in 0..steps.unsigned_abs() {
+                    self.plugins.get(index)?.action(action);
+                }
+            }
+            Message::CloseTooltip => self.leave(),
+            Message::Hover(message) => self.hover(*message).await?,
+            Message::Calendar => match self.tooltip {
+                Some(Tooltip {
+                    text: TooltipText::Calendar(_),
+                    ..
+                }) => self.close_tooltip(),
+                _ => {
+                    let locale = self.clocks.first()?.locale;
+                    self.open_menu(TooltipText::Calendar(Clock::now(), locale))?
                 }
             },
-            AppEvent::Battery(e) => {
-                if let Some(bat) = &mut self.battery_status {
-                    match e {
-                        BatteryEvent::PowerOnline => {
-                            if bat.charging.update(Some(true)) {
-                                self.reload_battery_icon();
-                            }
-                        }
-                        BatteryEvent::PowerOffline => {
-                            if bat.charging.update(Some(false)) {
-                                self.reload_battery_icon();
-                            }
-                        }
-                        BatteryEvent::Capacity(x) => {
-                            if bat.capacity.update(x) {
-                                self.reload_battery_icon();
-                            }
-                        }
-                        BatteryEvent::Status(x) => {
-                            if bat.status.update(x) {
-                                self.reload_battery_icon();
-                            }
-                        }
-                    };
+            Message::Processes(sort) => match self.tooltip {
+                Some(Tooltip {
+                    text: TooltipText::Processes(current, _),
+                    ..
+                }) if current == sort => self.close_tooltip(),
+                _ => {
+                    self.open_menu(TooltipText::Processes(sort, vec![]))?;
+                    self.polling
+                        .send(polling::Signal::Processes {
+                            sort,
+                            count: self.config.processes.count,
+                        })
+                        .await
+                        .unwrap();
                 }
+            },
+            Message::ClipboardHistory => match self.tooltip {
+                Some(Tooltip {
+                    text: TooltipText::ClipboardHistory { .. },
+                    ..
+                }) => self.close_tooltip(),
+                _ => {
+                    let history = self.clipboard_history(String::new())?;
+                    self.open_menu(history)?
+                }
+            },
+            Message::ClipboardPick(entry) => {
+                self.clipboard.as_mut()?.set(&mut self.wayland, entry);
+                self.close_tooltip();
             }
-            AppEvent::Polling(e) => match e {
-                polling::Event::Clock(e) => {
-                    self.date = e.date();
-                    self.time = e.time();
-                    self.weekday = e.weekday();
-                }
-                polling::Event::Battery(info) => {
-                    update_tooltip = true;
-                    if let Some(Tooltip { text, .. }) = &mut self.tooltip {
-                        *text = TooltipText::Simple(info.tooltip())
+            Message::Screenshot => self.take_screenshot(),
+            Message::Monitors => match self.tooltip {
+                Some(Tooltip {
+                    text: TooltipText::Monitors(_),
+                    ..
+                }) => self.close_tooltip(),
+                _ => self.open_menu(TooltipText::Monitors(self.outputs.monitors()))?,
+            },
+            Message::WorkspaceOverview => match self.tooltip {
+                Some(Tooltip {
+                    text: TooltipText::WorkspaceOverview(_),
+                    ..
+                }) => self.close_tooltip(),
+                _ => {
+                    let tiles = self.workspace_overview().await?;
+                    self.overview_press = None;
+                    self.open_menu(TooltipText::WorkspaceOverview(tiles))?
+                }
+            },
+            Message::OverviewPress(address, id) => self.overview_press = Some((address, id)),
+            // a window released on its own workspace was clicked, anywhere else it was dragged
+            Message::OverviewRelease(id) => match self.overview_press.take() {
+                None => {
+                    self.close_menu();
+                    self.hyprland_dispatch(hyprland::Dispatcher::Workspace(id))
+                        .await?
+                }
+                Some((address, from)) if from == id => {
+                    self.close_menu();
+                    self.hyprland_dispatch(hyprland::Dispatcher::FocusWindow(address))
+                        .await?
+                }
+                Some((address, _)) => {
+                    let dispatcher = hyprland::Dispatcher::MoveWindowToWorkspace(id, address);
+                    self.hyprland_dispatch(dispatcher).await?;
+                    let tiles = self.workspace_overview().await?;
+                    if let Some(Tooltip {
+                        text: TooltipText::WorkspaceOverview(x),
+                        ..
+                    }) = &mut self.tooltip
+                    {
+                        *x = tiles;
+                        self.tooltip_outdated = true;
                     }
                 }
             },
-            AppEvent::Tray(e) => match e {
-                TrayEvent::Registered { service, icon_name } => {
-                    let icon_name = TinyString::from_str(unsafe {
-                        str::from_utf8_unchecked(icon_name.as_bytes())
-                    });
-                    let icon = self.load_icon(&icon_name.into(), false);
-                    self.tray_items.insert(service.clone(), TrayItem { icon });
+            Message::WindowMenu => match self.tooltip {
+                Some(Tooltip {
+                    text: TooltipText::WindowMenu(_),
+                    ..
+                }) => self.close_tooltip(),
+                // nothing to act on
+                _ if self.window.app_class.is_empty() => return None,
+                _ => {
+                    let workspaces = (0..WORKSPACE_MAX)
+                        .filter(|&idx| {
+                            let persistent = self.config.workspaces.persistent.contains(&(idx + 1));
+                            idx != self.workspace_focused
+                                && (self.workspaces.get(idx) || persistent)
+                        })
+                        .map(|idx| ((idx + 1) as _, self.workspace_label(idx).into_owned()))
+                        .collect();
+                    self.open_menu(TooltipText::WindowMenu(workspaces))?
                 }
-                TrayEvent::NewIcon { service, icon_name } => {
-                    let icon_name = TinyString::from_str(unsafe {
-                        str::from_utf8_unchecked(icon_name.as_bytes())
-                    });
-                    let icon = self.load_icon(&icon_name.into(), false);
-                    if let Some(item) = self.tray_items.get_mut(&service) {
-                        item.icon = icon;
+            },
+            Message::WindowAction(dispatcher) => {
+                self.close_menu();
+                self.hyprland_dispatch(dispatcher).await?
+            }
+            Message::BatteryMenu => match self.tooltip {
+                Some(Tooltip {
+                    text: TooltipText::Battery { menu: true, .. },
+                    ..
+                }) => self.close_tooltip(),
+                _ => {
+                    let info = self.battery_status.as_ref()?.device.info();
+                    self.open_menu(TooltipText::Battery { info, menu: true })?;
+                    self.polling
+                        .send(polling::Signal::BatteryTooltip(true))
+                        .await
+                        .unwrap();
+                }
+            },
+            Message::ChargeLimit(on) => {
+                let percent = self.config.battery.charge_limit;
+                let bat = self.battery_status.as_ref()?;
+                let (path, value) = bat.device.charge_limit_file(on, percent)?;
+                match std::fs::write(&path, &value) {
+                    Ok(()) => {}
+                    // sysfs only lets root write, polkit asks for the password
+                    Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => run_shell(
+                        &format!(
+                            "printf %s {value} | pkexec tee '{}' >/dev/null",
+                            path.display()
+                        ),
+                        None,
+                    ),
+                    Err(e) => {
+                        tracing::warn!("cannot write {path:?}: {e}");
+                        return None;
                     }
                 }
-                TrayEvent::Unregistered(service) => {
-                    self.tray_items.remove(&service);
+                // through pkexec, the change only shows on a later tick
+                let info = bat.device.info();
+                if let Some(Tooltip {
+                    text: TooltipText::Battery { info: shown, .. },
+                    ..
+                }) = &mut self.tooltip
+                {
+                    *shown = info;
+                    self.tooltip_outdated = true;
                 }
-                TrayEvent::Disconnected => {
-                    self.tray_items.clear();
+            }
+            Message::BluetoothMenu => match self.tooltip {
+                Some(Tooltip {
+                    text: TooltipText::Bluetooth { .. },
+                    ..
+                }) => self.close_tooltip(),
+                _ => {
+                    let powered = self.bluetooth?;
+                    let devices = self.system.get()?.bluetooth_devices().await;
+                    self.open_menu(TooltipText::Bluetooth { powered, devices })?
                 }
             },
-        }
-        for w in self.window_manager.iter() {
-            w.state.borrow_mut().config_state.outdate();
-            match w.tag {
-                Tag::Bar => w.request_redraw(&mut self.wayland.notifier, &mut self.callbacks),
-                Tag::Tooltip => {
-                    if update_tooltip {
-                        match &w.surface().role {
-                            Role::Layer { .. } => {}
-                            Role::Popup { size, .. } => {
-                                let mut state = w.state.borrow_mut();
-                                let new_size = {
-                                    let mut view = self.view(w.tag);
-                                    let mut tree = Tree::new(&view);
-                                    let node = view.as_widget_mut().layout(
-                                        &mut tree,
-                                        &mut state.renderer,
-                                        &Limits::new(Size::ZERO, Size::INFINITE),
-                                    );
-                                    node.bounds().size()
-                                };
-                                let size = size.replace(new_size);
-                                if size.width < new_size.width || size.height < new_size.height {
-                                    state.resize(
-                                        [new_size.width, new_size.height].map(|x| x as _),
-                                        w.surface().surface,
-                                        w.tag,
-                                        self,
-                                    );
-                                }
-                            }
-                        }
-                        w.request_redraw(&mut self.wayland.notifier, &mut self.callbacks);
+            Message::BluetoothPower(on) => self.system.get()?.set_bluetooth_power(on).await,
+            Message::BluetoothConnect(path, connect) => {
+                let mut system = self.system.get()?;
+                let mut events = self.events.clone();
+                compio::runtime::spawn(async move {
+                    system.connect_bluetooth(&path, connect).await;
+                    let devices = system.bluetooth_devices().await;
+                    events
+                        .send(AppEvent::BluetoothDevices(devices).into())
+                        .await
+                        .ok();
+                })
+                .detach();
+            }
+            Message::OutputPower(output, on) => self.outputs.set_power(output, on)?,
+            Message::ConfigureOutput(name, enabled, scale) => {
+                self.outputs
+                    .configure(&mut self.wayland, &name, enabled, scale)?
+            }
+            Message::Microphone => {
+                let mic = self.microphone?;
+                let mut text = TinyString::from(if mic.muted {
+                    "Microphone muted"
+                } else {
+                    "Microphone on"
+                });
+                if mic.recording {
+                    text.push_str(", in use");
+                }
+                self.set_tooltip(TooltipText::Simple(text))?
+            }
+            Message::MicrophoneMute => {
+                spawn(
+                    "pactl",
+                    ["set-source-mute", "@DEFAULT_SOURCE@", "toggle"],
+                    None,
+                )
+                .inspect_err(|e| tracing::warn!("cannot run pactl: {e}"))
+                .ok();
+            }
+            Message::NightLight => {
+                let on = self.night_light.as_ref()?.on();
+                self.set_night_light(!on);
+            }
+            Message::Recording => {
+                self.set_tooltip(TooltipText::Simple("Recording the screen".into()))?
+            }
+            Message::StopRecording => {
+                for &pid in &self.recorders {
+                    let Some(pid) = Pid::from_raw(pid as _) else {
+                        continue;
+                    };
+                    if let Err(e) = rustix::process::kill_process(pid, Signal::INT) {
+                        tracing::warn!("cannot stop recorder {pid:?}: {e}");
                     }
                 }
             }
+            Message::Vpn => self.set_tooltip(TooltipText::Vpn {
+                text: self.vpn_tooltip(),
+                signal: self.wifi_signal.iter().copied().collect(),
+            })?,
+            Message::PublicIp => self.look_up_ip(false),
+            Message::CopyIp => match &self.public_ip {
+                Some(ip) => self.copy(ip.clone()),
+                None => self.look_up_ip(true),
+            },
+            Message::Fans => {
+                if self.fans.is_empty() {
+                    return None;
+                }
+                self.set_tooltip(TooltipText::Fans(fans_tooltip(&self.fans)));
+            }
+            Message::Gpu => {
+                let text = gpu_tooltip(self.gpu_usage.as_ref()?);
+                self.set_tooltip(TooltipText::Gpu(text));
+            }
+            Message::WorldClock => {
+                if self.world_zones.is_empty() {
+                    return None;
+                }
+                self.set_tooltip(TooltipText::WorldClock(self.world_clock(&Clock::now())));
+            }
+            Message::Click(module, button) => {
+                let message = self.action(module, button)?;
+                return Box::pin(self.update(message)).await;
+            }
+            Message::Hyprland(dispatcher) => self.hyprland_dispatch(dispatcher).await?,
+            Message::CopyDate => {
+                let [date, ..] = &self.clocks.first()?.text;
+                self.copy(date.clone());
+            }
+            Message::Copy(text) => self.copy(text),
+            Message::Exec(command) => {
+                self.launch(Box::new(move |token| run_shell(&command, token)))
+            }
+            Message::TimerToggle => {
+                self.countdown.toggle(self.config.timer.duration());
+                self.outdated = true;
+                self.sync_ticks().await;
+            }
+            Message::TimerReset => {
+                self.countdown.reset();
+                self.outdated = true;
+                self.sync_ticks().await;
+            }
+            Message::StopwatchToggle => {
+                self.stopwatch.toggle();
+                self.outdated = true;
+                self.sync_ticks().await;
+            }
+            Message::StopwatchReset => {
+                self.stopwatch.reset();
+                self.outdated = true;
+                self.sync_ticks().await;
+            }
         }
-        unsafe {
-            wayland::ffi::wl_display_flush(self.display.as_ptr());
+        None
+    }
+    /// Opens the tooltip `message` leads to once the pointer rested, or right away when one
+    /// is open already and the pointer just moved along the bar.
+    async fn hover(&mut self, message: Message) -> Option<()> {
+        self.cancel_hover();
+        if self.tooltip.is_some() {
+            return Box::pin(self.update(message)).await;
         }
+        self.hover = Hover::Opening(message);
+        self.start_hover_timer(self.config.tooltip.delay());
+        Some(())
     }
-
-    pub fn background(&self, tag: Tag) -> Color {
-        match tag {
-            Tag::Bar => self.theme.palette().background,
-            Tag::Tooltip => Color::TRANSPARENT,
+    fn leave(&mut self) {
+        self.cancel_hover();
+        // menus stay until dismissed
+        if self.tooltip.as_ref().is_some_and(|x| !x.grab) {
+            self.hover = Hover::Closing;
+            self.start_hover_timer(self.config.tooltip.grace());
         }
     }
-    fn bar(&self) -> Element<'_> {
-        let left = widget::row![
-            self.logo().into(),
-            self.workspace().into(),
-            self.title().into()
-        ]
-        .align_y(Center)
-        .spacing(7)
-        .padding(Padding::new(0.0).left(16))
-        .width(Length::Fill)
-        .height(Length::Fill);
-
-        let right = widget::row![self.tray(), self.battery(), self.clock().into()]
-            .align_y(Center)
-            .padding(Padding::new(0.0).right(13))
-            .spacing(9)
-            .height(Length::Fill);
-
-        widget::row![left, right].into()
+    fn cancel_hover(&mut self) {
+        self.hover = Hover::Idle;
+        self.hover_timer += 1;
     }
-    #[must_use]
-    fn load_icon(&mut self, key: &TinyString, symbolic: bool) -> Option<Handle> {
-        self.icon_cache
-            .get_or_insert_ref(key, || {
-                cosmic_freedesktop_icons::lookup(key)
-                    .with_size(64)
-                    // .with_theme("Adwaita")
-                    .with_theme("Tela-dracula-dark")
-                    // .with_theme("Papirus")
-                    .find()
-                    .and_then(|path| match path.extension()?.as_encoded_bytes() {
-                        b"svg" => {
-                            if symbolic {
-                                load_symbolic(path, &theme()).map(Handle::Svg)
-                            } else {
-                                load_svg(path).map(Handle::Svg)
-                            }
-                        }
-                        b"png" => load_png(path).map(Handle::Pixmap),
-                        _ => None,
-                    })
+    fn start_hover_timer(&self, duration: Duration) {
+        self.send_after(duration, AppEvent::HoverTimer(self.hover_timer));
+    }
+    fn send_after(&self, duration: Duration, event: AppEvent) {
+        let mut events = self.events.clone();
+        compio::runtime::spawn(async move {
+            compio::time::sleep(duration).await;
+            events.send(event.into()).await.ok();
+        })
+        .detach();
+    }
+    /// Runs `screenshot.command` in the background, [`AppEvent::Screenshot`] brings the image.
+    fn take_screenshot(&self) {
+        let Some((program, args)) = self.config.screenshot.command.split_first() else {
+            return;
+        };
+        let mut command = compio::process::Command::new(program);
+        command.args(args);
+        let mut events = self.events.clone();
+        compio::runtime::spawn(async move {
+            let output = match command.output().await {
+                Ok(x) => x,
+                Err(e) => {
+                    tracing::warn!("cannot take a screenshot: {e}");
+                    return;
+                }
+            };
+            // the region selection cancelled, most likely
+            if !output.status.success() {
+                return;
+            }
+            // saved to a file by the command itself otherwise
+            let image = match output.stdout.is_empty() {
+                true => None,
+                false => compio::runtime::spawn_blocking(move || clipboard::image(output.stdout))
+                    .await
+                    .ok()
+                    .flatten(),
+            };
+            events.send(AppEvent::Screenshot(image).into()).await.ok();
+        })
+        .detach();
+    }
+    /// Runs `vpn.ip_command` in the background, [`AppEvent::PublicIp`] brings the answer.
+    fn look_up_ip(&self, copy: bool) {
+        let Some((program, args)) = self.config.vpn.ip_command.split_first() else {
+            return;
+        };
+        let mut command = compio::process::Command::new(program);
+        command.args(args);
+        let mut events = self.events.clone();
+        compio::runtime::spawn(async move {
+            let output = match command.output().await {
+                Ok(x) => x,
+                Err(e) => {
+                    tracing::warn!("cannot look up the public ip: {e}");
+                    return;
+                }
+            };
+            let ip = String::from_utf8_lossy(&output.stdout).trim().to_owned();
+            if !output.status.success() || ip.is_empty() {
+                tracing::warn!("cannot look up the public ip: {}", output.status);
+                return;
+            }
+            events
+                .send(AppEvent::PublicIp { ip, copy }.into())
+                .await
+                .ok();
+        })
+        .detach();
+    }
+    fn vpn_tooltip(&self) -> String {
+        let vpn = match self.vpn.as_slice() {
+            [] => "No VPN".to_owned(),
+            up => format!("VPN  {}", up.join(", ")),
+        };
+        match &self.public_ip {
+            Some(ip) => format!("{vpn}\nPublic IP  {ip}"),
+            None => vpn,
+        }
+    }
+    fn refresh_vpn_tooltip(&mut self) {
+        let new_text = self.vpn_tooltip();
+        let new_signal = self.wifi_signal.iter().copied().collect();
+        if let Some(Tooltip {
+            text: TooltipText::Vpn { text, signal },
+            ..
+        }) = &mut self.tooltip
+        {
+            self.tooltip_outdated |= text.update(new_text) | signal.update(new_signal);
+        }
+    }
+    async fn hyprland_dispatch(&mut self, dispatcher: hyprland::Dispatcher) -> Option<()> {
+        self.hyprctl
+            .as_mut()?
+            .controller()
+            .await
+            .inspect_err(|e| tracing::warn!("cannot connect to hyprland: {e}"))
+            .ok()?
+            .dispatch(dispatcher.clone())
+            .await
+            .inspect_err(|e| tracing::warn!("cannot dispatch {dispatcher}: {e}"))
+            .ok()
+    }
+    /// Captures the focused window for its tooltip, `None` if it cannot be.
+    async fn window_preview(&mut self) -> Option<()> {
+        self.preview.as_ref()?;
+        let placement = self
+            .hyprctl
+            .as_mut()?
+            .controller()
+            .await
+            .inspect_err(|e| tracing::warn!("cannot connect to hyprland: {e}"))
+            .ok()?
+            .active_window_placement()
+            .await
+            .inspect_err(|e| tracing::warn!("cannot query active window: {e}"))
+            .ok()??;
+        let output = self.outputs.by_name(&placement.monitor)?;
+        self.preview
+            .as_mut()?
+            .capture(&mut self.wayland, output, placement.region);
+        self.set_tooltip(TooltipText::WindowPreview {
+            title: placement.title,
+            image: None,
+        });
+        Some(())
+    }
+    /// The history entries matching `query`, in a menu to open or to replace the open one.
+    fn clipboard_history(&self, query: String) -> Option<TooltipText> {
+        let needle = query.to_lowercase();
+        let entries = self
+            .clipboard
+            .as_ref()?
+            .history()
+            .iter()
+            .filter(|x| match x {
+                Entry::Text(text) => text.to_lowercase().contains(&needle),
+                // nothing to search in
+                Entry::Image(_) => needle.is_empty(),
             })
+            .cloned()
+            .collect();
+        Some(TooltipText::ClipboardHistory { query, entries })
+    }
+    fn refresh_clipboard_history(&mut self) -> Option<()> {
+        let Some(Tooltip {
+            text: TooltipText::ClipboardHistory { query, .. },
+            ..
+        }) = &self.tooltip
+        else {
+            return None;
+        };
+        let history = self.clipboard_history(query.clone())?;
+        self.tooltip.as_mut()?.text = history;
+        self.tooltip_outdated = true;
+        self.outdated = true;
+        Some(())
+    }
+    /// Typing into the open clipboard history searches it, Enter picks the first match.
+    async fn search_clipboard(&mut self, key: u32) -> Option<()> {
+        let Some(Tooltip {
+            text: TooltipText::ClipboardHistory { query, entries },
+            ..
+        }) = &mut self.tooltip
+        else {
+            return None;
+        };
+        match key {
+            KEY_ENTER => {
+                let entry = entries.first()?.clone();
+                return Box::pin(self.update(Message::ClipboardPick(entry))).await;
+            }
+            KEY_BACKSPACE => {
+                query.pop()?;
+            }
+            _ => query.push(key_char(key)?),
+        }
+        self.refresh_clipboard_history()
+    }
+    fn copy(&mut self, text: String) {
+        match &mut self.clipboard {
+            Some(clipboard) => clipboard.set(&mut self.wayland, Entry::Text(text.into())),
+            None => {
+                spawn("wl-copy", [text], None)
+                    .inspect_err(|e| tracing::warn!("cannot spawn wl-copy: {e}"))
+                    .ok();
+            }
+        }
+    }
+    fn run_custom(&mut self, index: usize, action: custom::Action) -> Option<()> {
+        let command = self.config.custom.get(index)?.command(action)?.to_owned();
+        self.launch(Box::new(move |token| run_shell(&command, token)));
+        Some(())
+    }
+    /// Starts an app for the latest press, handing it an activation token if there is any.
+    fn launch(&mut self, launch: Launch) {
+        match &mut self.activation {
+            Some(activation) => activation.request(
+                &mut self.wayland,
+                self.press,
+                self.window_manager.focused_surface(),
+                launch,
+            ),
+            None => launch(None),
+        }
+    }
+    /// The configured action for clicking `module`, or else its own.
+    fn action(&self, module: &str, button: Button) -> Option<Message> {
+        let pick = |actions: &config::Actions| match button {
+            Button::Left if self.shift => actions
+                .shift_click
+                .clone()
+                .or_else(|| actions.click.clone()),
+            Button::Left => actions.click.clone(),
+            Button::Middle => actions.middle_click.clone(),
+            Button::Right => actions.right_click.clone(),
+        };
+        let action = self
+            .config
+            .actions
+            .get(module)
+            .and_then(pick)
+            .or_else(|| pick(&default_actions(module)))?;
+        Some(match action {
+            config::Action::Exec(command) => Message::Exec(command),
+            config::Action::Calendar => Message::Calendar,
+            config::Action::CopyDate => Message::CopyDate,
+            config::Action::CloseWindow => Message::Hyprland(hyprland::Dispatcher::KillActive),
+            config::Action::Hyprland(dispatcher) => Message::Hyprland(dispatcher),
+            config::Action::RestartUnits => Message::RestartUnits,
+            config::Action::Update => Message::UpdatesAction,
+            config::Action::TimerToggle => Message::TimerToggle,
+            config::Action::TimerReset => Message::TimerReset,
+            config::Action::StopwatchToggle => Message::StopwatchToggle,
+            config::Action::StopwatchReset => Message::StopwatchReset,
+            config::Action::TopCpu => Message::Processes(Sort::Cpu),
+            config::Action::TopMemory => Message::Processes(Sort::Memory),
+            config::Action::PublicIp => Message::PublicIp,
+            config::Action::CopyIp => Message::CopyIp,
+            config::Action::ClipboardHistory => Message::ClipboardHistory,
+            config::Action::Screenshot => Message::Screenshot,
+            config::Action::StopRecording => Message::StopRecording,
+            config::Action::NightLight => Message::NightLight,
+            config::Action::MicrophoneMute => Message::MicrophoneMute,
+            config::Action::Monitors => Message::Monitors,
+            config::Action::WorkspaceOverview => Message::WorkspaceOverview,
+            config::Action::WindowMenu => Message::WindowMenu,
+            config::Action::BluetoothMenu => Message::BluetoothMenu,
+            config::Action::BatteryMenu => Message::BatteryMenu,
+        })
+    }
+    /// Whole steps scrolled so far, touchpads report many tiny deltas.
+    fn scroll_steps(&mut self, y: f32) -> i32 {
+        // a change of direction starts over
+        if self.scrolled * y < 0.0 {
+            self.scrolled = 0.0;
+        }
+        self.scrolled += y;
+        let steps = (self.scrolled / SCROLL_STEP).trunc();
+        self.scrolled -= steps * SCROLL_STEP;
+        steps as i32
+    }
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn dispatch_wayland_event(&mut self, event: wayland::Event) -> Option<()> {
+        match event {
+            wayland::Event::Resize { object, size } => {
+                self.window_manager
+                    .find_by_object(object)?
+                    .clone()
+                    .resize(size, self);
+            }
+            wayland::Event::Rescale { surface, factor } => {
+                self.window_manager
+                    .find_by_object(surface)?
+                    .clone()
+                    .rescale(factor, self);
+                // icons are resolved for the densest output showing them
+                let scale = self.window_manager.iter().map(|x| x.scale()).max();
+                if self.icons.rescale(scale.unwrap_or(1) as _) {
+                    self.resolve_icons();
+                }
+            }
+            wayland::Event::Retransform { surface, transform } => {
+                self.window_manager
+                    .find_by_object(surface)?
+                    .clone()
+                    .retransform(transform, self);
+            }
+            wayland::Event::Capabilities { seat, capabilities } => {
+                let seats = &mut self.window_manager.seats;
+                let index = match seats.iter().position(|x| x.seat == seat) {
+                    Some(index) => index,
+                    None => {
+                        seats.push(Seat::new(seat));
+                        seats.len() - 1
+                    }
+                };
+                let seat = &mut seats[index];
+                if seat.update(&mut self.wayland, capabilities) {
+                    // no leave comes from a pointer that is gone
+                    let surface = seat.focused.take()?;
+                    self.pointer_left(surface).await;
+                }
+            }
+            wayland::Event::Enter {
+                seat,
+                surface,
+                serial,
+            } => {
+                let win = self.window_manager.find_by_object(surface)?.clone();
+                self.window_manager.current = Some(seat);
+                win.mouse(iced::mouse::Event::CursorEntered, self).await;
+                win.enter(seat, serial);
+                self.window_manager.seat_mut(seat)?.focused = Some(surface);
+            }
+            wayland::Event::Pressed { seat, serial } => self.press = Some(Press { seat, serial }),
+            wayland::Event::Mouse { seat, event } => {
+                self.window_manager.current = Some(seat);
+                let window = self.window_manager.focused()?.clone();
+                // a click anywhere else closes the menu, after the click did its part
+                let menu = match event {
+                    iced::mouse::Event::ButtonPressed(_) => self
+                        .tooltip
+                        .as_ref()
+                        .filter(|x| x.grab && !Rc::ptr_eq(&x.window, &window))
+                        .map(|x| x.window.clone()),
+                    _ => None,
+                };
+                window.mouse(event, self).await;
+                if let Some(menu) = menu
+                    && self
+                        .tooltip
+                        .as_ref()
+                        .is_some_and(|x| Rc::ptr_eq(&x.window, &menu))
+                {
+                    self.close_tooltip();
+                }
+                match event {
+                    iced::mouse::Event::CursorLeft => {
+                        self.window_manager.seat_mut(seat)?.focused.take();
+                    }
+                    _ => (),
+                }
+            }
+            wayland::Event::Modifiers(depressed) => self.shift = depressed & 1 != 0,
+            wayland::Event::KeyPressed { key } => {
+                if key == KEY_ESC {
+                    self.close_menu();
+                } else {
+                    self.search_clipboard(key).await;
+                }
+            }
+            wayland::Event::PopupDone(popup) => {
+                let window = self.window_manager.find_by_object(popup)?;
+                if self
+                    .tooltip
+                    .as_ref()
+                    .is_some_and(|x| Rc::ptr_eq(&x.window, window))
+                {
+                    self.close_tooltip();
+                }
+            }
+            wayland::Event::DataOffer(offer) => self.clipboard.as_mut()?.offer(offer),
+            wayland::Event::OfferMime { offer, mime } => {
+                self.clipboard.as_mut()?.add_mime(offer, mime)
+            }
+            wayland::Event::Selection { offer, primary } => {
+                self.clipboard.as_mut()?.select(offer, primary)
+            }
+            wayland::Event::SourceSend { source, fd } => self.clipboard.as_ref()?.send(source, fd),
+            wayland::Event::SourceCancelled(source) => self.clipboard.as_mut()?.cancelled(source),
+            wayland::Event::GammaSize { control, size } => {
+                self.night_light.as_mut()?.sized(control, size)
+            }
+            wayland::Event::GammaFailed(control) => {
+                let night_light = self.night_light.as_mut()?;
+                night_light.failed(control);
+                if !night_light.on() {
+                    self.reload_night_light_icon();
+                    self.outdated = true;
+                }
+            }
+            wayland::Event::Idle(idle) => self.set_idle(idle).await,
+            wayland::Event::OutputName { output, name } => {
+                self.outputs.named(output, name);
+                self.output_named(output);
+                self.refresh_monitors();
+            }
+            wayland::Event::OutputPower { power, on } => {
+                if self.outputs.powered(power, on) {
+                    self.refresh_monitors();
+                }
+            }
+            wayland::Event::OutputPowerFailed(power) => {
+                self.outputs.power_failed(power);
+                self.refresh_monitors();
+            }
+            wayland::Event::XdgOutput { output, change } => {
+                if let Some(output) = self.outputs.xdg_output_changed(output, change) {
+                    self.output_named(output);
+                }
+                self.refresh_monitors();
+            }
+            wayland::Event::Head(head) => self.outputs.head(head),
+            wayland::Event::HeadChanged { head, change } => self.outputs.head_changed(head, change),
+            wayland::Event::ModeChanged { mode, change } => self.outputs.mode_changed(mode, change),
+            wayland::Event::HeadsDone(serial) => {
+                self.outputs.heads_done(serial);
+                self.refresh_monitors();
+            }
+            wayland::Event::Configured {
+                configuration,
+                applied,
+            } => self.outputs.configured(configuration, applied),
+            wayland::Event::CallbackDone(cb) => self.callbacks.remove(&cb).unwrap()(self),
+            wayland::Event::FrameBuffer {
+                frame,
+                format,
+                size,
+                stride,
+            } => self.preview.as_mut()?.buffer(frame, format, size, stride),
+            wayland::Event::FrameFlags { frame, y_invert } => {
+                self.preview.as_mut()?.flags(frame, y_invert)
+            }
+            wayland::Event::FrameBufferDone(frame) => {
+                self.preview.as_mut()?.buffer_done(&self.wayland, frame)
+            }
+            wayland::Event::FrameReady(frame) => {
+                let snapshot = self.preview.as_mut()?.ready(frame)?;
+                if let Some(Tooltip {
+                    text: TooltipText::WindowPreview { image, .. },
+                    ..
+                }) = &mut self.tooltip
+                {
+                    *image = Some(snapshot);
+                    self.tooltip_outdated = true;
+                    self.outdated = true;
+                }
+            }
+            wayland::Event::FrameFailed(frame) => self.preview.as_mut()?.failed(frame),
+            wayland::Event::OutputAdded { name, version } => self.output_added(name, version),
+            wayland::Event::SeatAdded { name, version } => {
+                self.wayland.bind_seat(name, version);
+            }
+            wayland::Event::GlobalRemoved(name) => {
+                if self.wayland.seat(name).is_some() {
+                    self.seat_removed(name).await?;
+                } else if self.wayland.output(name).is_some() {
+                    self.output_removed(name)?;
+                } else if self.wayland.remove_global(name) {
+                    self.drop_unsupported();
+                }
+            }
+            wayland::Event::ActivationToken { token, name } => {
+                self.activation.as_mut()?.done(token, &name)
+            }
+        }
+        Some(())
+    }
+    /// Only updates state, the windows catch up in [`Runner::refresh`] if anything shown changed.
+    pub async fn dispatch_app_event(&mut self, event: AppEvent) {
+        let changed = match event {
+            AppEvent::Hyprland(event) => match event {
+                hyprland::Event::Workspace { id } => match workspace_index(id) {
+                    Some(idx) => {
+                        self.urgent_workspaces.unset(idx) | self.workspace_focused.update(idx)
+                    }
+                    // none of those shown is focused
+                    None => self.workspace_focused.update(usize::MAX),
+                },
+                hyprland::Event::Workspaces(all) => {
+                    let mut workspaces = BitSet::new();
+                    let mut changed = false;
+                    for (id, name) in all {
+                        if let Some(idx) = workspace_index(id) {
+                            workspaces.set(idx);
+                        }
+                        changed |= self.rename_workspace(id, name);
+                    }
+                    self.workspaces.update(workspaces) | changed
+                }
+                hyprland::Event::CreateWorkspace { id, name } => {
+                    self.rename_workspace(id, name)
+                        | workspace_index(id).is_some_and(|idx| self.workspaces.set(idx))
+                }
+                hyprland::Event::RenameWorkspace { id, name } => self.rename_workspace(id, name),
+                hyprland::Event::DestroyWorkspace { id } => {
+                    workspace_index(id).is_some_and(|idx| {
+                        self.urgent_workspaces.unset(idx) | self.workspaces.unset(idx)
+                    })
+                }
+                hyprland::Event::Urgent { id } => {
+                    let urgent = workspace_index(id).is_some_and(|idx| {
+                        idx != self.workspace_focused && self.urgent_workspaces.set(idx)
+                    });
+                    if urgent {
+                        effects::fire(&self.config.effects, Trigger::Attention);
+                    }
+                    urgent
+                }
+                hyprland::Event::Submap(name) => self.submap.update(name),
+                hyprland::Event::Fullscreen(fullscreen) => {
+                    let changed = self.fullscreen.update(fullscreen);
+                    if changed {
+                        self.update_visibility();
+                    }
+                    changed
+                }
+                hyprland::Event::ActiveWindow { class, title } => {
+                    let title = truncate(title, 50, "…");
+                    if self.window.app_class == class && self.window.title == title {
+                        false
+                    } else {
+                        self.window = WindowInfo {
+                            icon: if class.is_empty() {
+                                None
+                            } else {
+                                Some(self.icons.load_app(&class, &self.theme))
+                            },
+                            class: truncate(class.clone(), 15, "…"),
+                            app_class: class,
+                            title,
+                        };
+                        true
+                    }
+                }
+            },
+            AppEvent::Battery(BatteryEvent::Plugged) => self.battery_plugged().await,
+            AppEvent::Battery(e) => {
+                let Some(bat) = &mut self.battery_status else {
+                    return;
+                };
+                let changed = match e {
+                    BatteryEvent::PowerOnline => bat.charging.update(Some(true)),
+                    BatteryEvent::PowerOffline => bat.charging.update(Some(false)),
+                    BatteryEvent::Capacity(x) => bat.capacity.update(x),
+                    BatteryEvent::Status(x) => bat.status.update(x),
+                    BatteryEvent::Plugged => unreachable!("handled above"),
+                };
+                if changed {
+                    self.reload_battery_icon();
+                    self.warn_low_battery();
+                }
+                changed
+            }
+            AppEvent::Polling(e) => match e {
+                polling::Event::Clock(e) => {
+                    let expired = self.countdown.tick();
+                    if expired {
+                        if let Some(command) = &self.config.timer.on_expire {
+                            run_shell(command, None);
+                        }
+                        effects::fire(&self.config.effects, Trigger::TimerExpired);
+                        self.sync_ticks().await;
+                    }
+                    self.schedule_night_light();
+                    let changed = self.tick_world_clock(&e)
+                        | self.countdown.running()
+                        | self.stopwatch.running()
+                        | (!self.config.bar.steady_urgency && self.urgency.blink())
+                        | expired;
+                    self.clocks.iter_mut().fold(changed, |changed, face| {
+                        let text = face.render(&e);
+                        face.text.update(text) | changed
+                    })
+                }
+                polling::Event::Fans(fans) => {
+                    if let Some(Tooltip {
+                        text: TooltipText::Fans(text),
+                        ..
+                    }) = &mut self.tooltip
+                    {
+                        self.tooltip_outdated |= text.update(fans_tooltip(&fans));
+                    }
+                    self.fans.update(fans)
+                }
+                polling::Event::Gpu(gpu) => {
+                    if let Some(Tooltip {
+                        text: TooltipText::Gpu(text),
+                        ..
+                    }) = &mut self.tooltip
+                    {
+                        self.tooltip_outdated |= text.update(gpu_tooltip(&gpu));
+                    }
+                    self.gpu_usage.update(Some(gpu))
+                }
+                polling::Event::Cpu(x) => self.cpu.update(Some(x)),
+                polling::Event::Memory(x) => self.memory.update(Some(x)),
+                polling::Event::Wifi(quality) => {
+                    const HISTORY: usize = 60;
+                    if self.wifi_signal.len() == HISTORY {
+                        self.wifi_signal.pop_front();
+                    }
+                    self.wifi_signal.push_back(quality);
+                    self.refresh_vpn_tooltip();
+                    false
+                }
+                polling::Event::Recording(pids) => {
+                    let recording = !pids.is_empty();
+                    self.pulse = recording && !self.pulse;
+                    self.recorders.update(pids) || recording
+                }
+                polling::Event::Processes(list) => match &mut self.tooltip {
+                    Some(Tooltip {
+                        text: TooltipText::Processes(_, processes),
+                        ..
+                    }) => {
+                        self.tooltip_outdated |= processes.update(list);
+                        false
+                    }
+                    // closed since, however that happened
+                    _ => {
+                        self.polling
+                            .send(polling::Signal::ProcessesStop)
+                            .await
+                            .unwrap();
+                        false
+                    }
+                },
+                polling::Event::BatteryLevel(level) => {
+                    let Some(bat) = &mut self.battery_status else {
+                        return;
+                    };
+                    let changed = bat.update(level);
+                    if changed {
+                        self.reload_battery_icon();
+                        self.warn_low_battery();
+                    }
+                    changed
+                }
+                polling::Event::Battery(info) => match &mut self.tooltip {
+                    Some(Tooltip {
+                        text: TooltipText::Battery { info: shown, .. },
+                        ..
+                    }) => {
+                        *shown = info;
+                        self.tooltip_outdated = true;
+                        true
+                    }
+                    // another tooltip took its place
+                    _ => {
+                        self.polling
+                            .send(polling::Signal::BatteryTooltip(false))
+                            .await
+                            .unwrap();
+                        false
+                    }
+                },
+            },
+            AppEvent::Tray(e) => match e {
+                TrayEvent::Registered {
+                    service,
+                    icon_name,
+                    attention_icon_name,
+                    status,
+                } => {
+                    let icon = self.load_icon(&icon_name.as_str().into(), false);
+                    let attention_icon = if attention_icon_name.is_empty() {
+                        None
+                    } else {
+                        Some(self.load_icon(&attention_icon_name.as_str().into(), false))
+                    };
+                    self.tray_items.insert(
+                        service.clone(),
+                        TrayItem {
+                            icon,
+                            attention_icon,
+                            status,
+                        },
+                    );
+                    true
+                }
+                TrayEvent::NewIcon { service, icon_name } => {
+                    let icon_name = TinyString::from_str(unsafe {
+                        str::from_utf8_unchecked(icon_name.as_bytes())
+                    });
+                    let icon = self.load_icon(&icon_name.into(), false);
+                    self.tray_items
+                        .get_mut(&service)
+                        .map(|item| item.icon = icon)
+                        .is_some()
+                }
+                TrayEvent::NewAttentionIcon { service, icon_name } => {
+                    let icon = Some(self.load_icon(&icon_name.as_str().into(), false));
+                    // only visible while the item asks for attention
+                    self.tray_items.get_mut(&service).is_some_and(|item| {
+                        item.attention_icon = icon;
+                        item.status == TrayStatus::NeedsAttention
+                    })
+                }
+                TrayEvent::NewStatus { service, status } => {
+                    let changed = self
+                        .tray_items
+                        .get_mut(&service)
+                        .is_some_and(|item| mem::replace(&mut item.status, status) != status);
+                    if changed && status == TrayStatus::NeedsAttention {
+                        effects::fire(&self.config.effects, Trigger::Attention);
+                    }
+                    changed
+                }
+                TrayEvent::Unregistered(service) => self.tray_items.remove(&service).is_some(),
+                TrayEvent::Disconnected => {
+                    let changed = !self.tray_items.is_empty();
+                    self.tray_items.clear();
+                    changed
+                }
+            },
+            AppEvent::Setting(SettingEvent::IconTheme(theme)) => {
+                // the GTK setting used until now usually names the same theme
+                let themes = icon::themes(&self.config.icons, Some(&theme));
+                self.desktop_icon_theme = Some(theme);
+                if themes != self.icons.themes() {
+                    self.reload_icons();
+                    true
+                } else {
+                    false
+                }
+            }
+            AppEvent::Setting(SettingEvent::ColorScheme(scheme)) => {
+                if self.color_scheme.update(scheme) {
+                    self.retheme();
+                    true
+                } else {
+                    false
+                }
+            }
+            AppEvent::Palette(palettes) => {
+                self.imported_palettes = palettes;
+                self.retheme();
+                true
+            }
+            AppEvent::Session(e) => match e {
+                SessionEvent::PrepareForSleep(true) => {
+                    self.polling.send(polling::Signal::Pause).await.unwrap();
+                    false
+                }
+                SessionEvent::PrepareForSleep(false) => {
+                    // uevents are lost while suspended
+                    let changed = if let Some(bat) = &mut self.battery_status {
+                        let changed = bat.update(bat.device.level());
+                        if changed {
+                            self.reload_battery_icon();
+                        }
+                        changed
+                    } else {
+                        false
+                    };
+                    self.polling.send(polling::Signal::Resume).await.unwrap();
+                    changed
+                }
+                SessionEvent::Lock => {
+                    self.lock_icon =
+                        Some(self.load_icon(&"system-lock-screen-symbolic".into(), true));
+                    true
+                }
+                SessionEvent::Unlock => self.lock_icon.take().is_some(),
+            },
+            AppEvent::Bluetooth(BluetoothEvent::Powered(on)) => {
+                if let Some(Tooltip {
+                    text: TooltipText::Bluetooth { powered, .. },
+                    ..
+                }) = &mut self.tooltip
+                {
+                    *powered = on;
+                    self.tooltip_outdated = true;
+                }
+                let changed = self.bluetooth.update(Some(on));
+                if changed {
+                    self.reload_bluetooth_icon();
+                }
+                changed
+            }
+            AppEvent::BluetoothDevices(list) => {
+                if let Some(Tooltip {
+                    text: TooltipText::Bluetooth { devices, .. },
+                    ..
+                }) = &mut self.tooltip
+                {
+                    *devices = list;
+                    self.tooltip_outdated = true;
+                }
+                false
+            }
+            AppEvent::Unit(unit) => {
+                let position = self.failed_units.iter().position(|x| *x == unit.name);
+                match (unit.failed(), position) {
+                    (true, None) => {
+                        self.failed_units.push(unit.name);
+                        true
+                    }
+                    (false, Some(idx)) => {
+                        self.failed_units.remove(idx);
+                        true
+                    }
+                    _ => false,
+                }
+            }
+            AppEvent::Script(script::Event { index, output }) => {
+                if self.script_outputs.len() <= index {
+                    self.script_outputs.resize_with(index + 1, Default::default);
+                }
+                self.script_outputs[index].update(output)
+            }
+            AppEvent::Plugin(plugin::Event { index, output }) => self
+                .plugin_outputs
+                .get_mut(index)
+                .is_some_and(|x| x.update(output)),
+            AppEvent::Custom(custom::Event { index, output }) => {
+                if self.custom.len() <= index {
+                    self.custom.resize_with(index + 1, Default::default);
+                }
+                self.custom[index].update(output)
+            }
+            AppEvent::Reload(config) => {
+                self.reload(config).await;
+                true
+            }
+            AppEvent::Ipc(command) => {
+                self.command(command).await;
+                true
+            }
+            AppEvent::Vpn(Vpn(up)) => {
+                let changed = self.vpn.update(up);
+                if changed {
+                    // another route out, most likely
+                    self.public_ip = None;
+                    self.reload_vpn_icon();
+                    self.refresh_vpn_tooltip();
+                }
+                changed
+            }
+            AppEvent::Microphone(mic) => {
+                let changed = self.microphone.update(mic);
+                if changed {
+                    self.reload_microphone_icon();
+                }
+                changed
+            }
+            AppEvent::PublicIp { ip, copy } => {
+                if copy {
+                    self.copy(ip.clone());
+                }
+                self.public_ip = Some(ip);
+                self.refresh_vpn_tooltip();
+                false
+            }
+            AppEvent::Clipboard {
+                generation,
+                content,
+            } => {
+                if let Some(clipboard) = &mut self.clipboard {
+                    clipboard.received(generation, content, self.config.clipboard.history);
+                }
+                self.refresh_clipboard_history();
+                false
+            }
+            AppEvent::Screenshot(image) => {
+                let copied = match (image, &mut self.clipboard) {
+                    (Some(image), Some(clipboard)) if self.config.screenshot.copy => {
+                        clipboard.set(&mut self.wayland, image.into());
+                        true
+                    }
+                    _ => false,
+                };
+                self.screenshot_done = Some(if copied { "Copied" } else { "Saved" });
+                self.screenshots += 1;
+                self.send_after(SCREENSHOT_DONE, AppEvent::ScreenshotShown(self.screenshots));
+                true
+            }
+            AppEvent::ScreenshotShown(generation) => {
+                generation == self.screenshots && self.screenshot_done.take().is_some()
+            }
+            AppEvent::IconLoaded => {
+                self.tooltip_outdated = true;
+                true
+            }
+            AppEvent::HoverTimer(generation) => {
+                if generation == self.hover_timer {
+                    match mem::replace(&mut self.hover, Hover::Idle) {
+                        Hover::Opening(message) => {
+                            self.update(message).await;
+                        }
+                        Hover::Closing => self.close_tooltip(),
+                        Hover::Idle => {}
+                    }
+                }
+                false
+            }
+            AppEvent::Updates(updates::Updates { packages }) => {
+                if self.pending_updates == packages {
+                    false
+                } else {
+                    self.updates_icon = if packages.is_empty() {
+                        None
+                    } else {
+                        Some(self.load_icon(&"software-update-available-symbolic".into(), true))
+                    };
+                    self.pending_updates = packages;
+                    true
+                }
+            }
+        };
+        self.outdated |= changed | self.sync_urgency().await;
+    }
+    /// Whether any module became urgent or stopped being, blinking takes second ticks.
+    async fn sync_urgency(&mut self) -> bool {
+        let battery = self.battery_status.as_ref().is_some_and(|x| x.low);
+        let tray = self
+            .tray_items
+            .values()
+            .any(|x| x.status == TrayStatus::NeedsAttention);
+        let changed = self.urgency.set("battery", battery)
+            | self.urgency.set("units", !self.failed_units.is_empty())
+            | self.urgency.set("tray", tray)
+            | self
+                .urgency
+                .set("workspaces", self.urgent_workspaces != BitSet::new());
+        if changed {
+            self.sync_ticks().await;
+        }
+        changed
+    }
+    /// Rebuilds and redraws the windows once for everything dispatched since the last call.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn refresh(&mut self) {
+        if !mem::take(&mut self.outdated) {
+            return;
+        }
+        let update_tooltip = mem::take(&mut self.tooltip_outdated);
+        for w in self.window_manager.iter() {
+            w.state.borrow_mut().config_state.outdate();
+            match w.tag {
+                Tag::Bar(_) => w.request_redraw(&mut self.wayland, &mut self.callbacks),
+                Tag::Tooltip => {
+                    if update_tooltip {
+                        match &w.surface().role {
+                            Role::Layer { .. } => {}
+                            Role::Popup { size, .. } => {
+                                let mut state = w.state.borrow_mut();
+                                let new_size = measure(self.view(w.tag), &mut state.renderer);
+                                let size = size.replace(new_size);
+                                if size.width < new_size.width || size.height < new_size.height {
+                                    state.resize(
+                                        [new_size.width, new_size.height].map(|x| x as _),
+                                        w.surface().wl_surface(),
+                                        w.tag,
+                                        self,
+                                    );
+                                }
+                            }
+                        }
+                        w.request_redraw(&mut self.wayland, &mut self.callbacks);
+                    }
+                }
+            }
+        }
+        unsafe {
+            wayland::ffi::wl_display_flush(self.display.as_ptr());
+        }
+    }
+
+    /// Both draw their own rounded backgrounds.
+    pub fn background(&self, tag: Tag) -> Color {
+        match tag {
+            Tag::Bar(_) | Tag::Tooltip => Color::TRANSPARENT,
+        }
+    }
+    fn bar(&self, profile: usize) -> Element<'_> {
+        let layout = self.config.layout(profile);
+        let section = |modules: &[String], spacing| {
+            row(modules.iter().filter_map(|x| self.module(x)))
+                .align_y(Center)
+                .spacing(spacing)
+                .height(Length::Fill)
+        };
+        let left = section(&layout.left, 7)
+            .padding(Padding::new(0.0).left(16))
+            .width(Length::Fill);
+        let right = section(&layout.right, 9).padding(Padding::new(0.0).right(13));
+        let content: Element = if layout.center.is_empty() {
+            widget::row![left, right].into()
+        } else {
+            // both sides get half of what the center leaves, which keeps it in the middle
+            let right = container(right).width(Length::Fill).align_x(Alignment::End);
+            widget::row![left, section(&layout.center, 9), right].into()
+        };
+        let style = &self.config.bar;
+        container(content)
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .style(move |theme: &Theme| container::Style {
+                background: Some(theme.palette().background.into()),
+                border: Border {
+                    color: style.border.color.unwrap_or(theme.palette().primary),
+                    width: style.border.width,
+                    radius: style.radius.into(),
+                },
+                ..Default::default()
+            })
+            .into()
+    }
+    fn module(&self, name: &str) -> Option<Element<'_>> {
+        if !self.enabled(name) {
+            return None;
+        }
+        let module = match name {
+            "logo" => Some(self.logo().into()),
+            "workspaces" => Some(self.workspace().into()),
+            "submap" => self.submap(),
+            "title" => Some(self.title().into()),
+            "custom" => Some(custom_items(
+                &self.custom,
+                Message::CustomTooltip,
+                Message::Custom,
+                Message::CustomScroll,
+            )),
+            "scripts" => Some(custom_items(
+                &self.script_outputs,
+                Message::ScriptTooltip,
+                Message::Script,
+                Message::ScriptScroll,
+            )),
+            "plugins" => Some(custom_items(
+                &self.plugin_outputs,
+                Message::PluginTooltip,
+                Message::Plugin,
+                Message::PluginScroll,
+            )),
+            "tray" => Some(self.tray()),
+            "units" => self.units(),
+            "updates" => self.updates(),
+            "lock" => self.lock(),
+            "battery" => self.battery(),
+            "clock" => Some(self.clock().into()),
+            "timer" => self.timer(),
+            "stopwatch" => self.stopwatch(),
+            "fans" => self.fans(),
+            "gpu" => self.gpu_usage(),
+            "cpu" => self.cpu(),
+            "memory" => self.memory(),
+            "vpn" => self.vpn(),
+            "microphone" => self.microphone(),
+            "bluetooth" => Some(self.bluetooth_icon.as_ref()?.get()?.load_size(17.5)),
+            "clipboard" => self.clipboard(),
+            "screenshot" => self.screenshot(),
+            "recording" => self.recording(),
+            "night_light" => Some(self.night_light_icon.as_ref()?.get()?.load_size(17.5)),
+            "monitors" => Some(self.monitors_icon.as_ref()?.get()?.load_size(17.5)),
+            _ => None,
+        }?;
+        let module = match self.urgency.get(name) {
+            true => {
+                let lit = self.urgency.lit(self.config.bar.steady_urgency);
+                container(module)
+                    .padding([2, 4])
+                    .style(move |theme: &Theme| urgency::style(theme, lit))
+                    .into()
+            }
+            false => module,
+        };
+        // items with actions of their own take their clicks first
+        let name = *MODULES.iter().find(|&&x| x == name)?;
+        Some(
+            mouse_area(module)
+                .on_press(Message::Click(name, Button::Left))
+                .on_middle_press(Message::Click(name, Button::Middle))
+                .on_right_press(Message::Click(name, Button::Right))
+                .into(),
+        )
+    }
+    async fn command(&mut self, command: ipc::Command) {
+        match command {
+            ipc::Command::ToggleVisibility => self.toggle_visibility(),
+            ipc::Command::Reload => {
+                if let Some(config) = config::try_load() {
+                    self.reload(config).await;
+                }
+            }
+            ipc::Command::SetModule { module, enabled } => {
+                let Some(&module) = MODULES.iter().find(|&&x| x == module) else {
+                    tracing::warn!("unknown module {module:?}");
+                    return;
+                };
+                if enabled {
+                    self.disabled_modules.remove(module);
+                } else {
+                    self.disabled_modules.insert(module);
+                }
+                self.update_registry();
+            }
+            ipc::Command::ToggleHud => {
+                self.hud = !self.hud;
+                self.outdated = true;
+            }
+            ipc::Command::Quit => self.quit = true,
+            ipc::Command::Hyprland(dispatcher) => {
+                self.hyprland_dispatch(dispatcher).await;
+            }
+        }
+    }
+    fn toggle_visibility(&mut self) {
+        self.hidden = !self.hidden;
+        self.update_visibility();
+    }
+    /// Hiding destroys the layer surfaces, so the exclusive zones are given back too.
+    fn update_visibility(&mut self) {
+        let visible = !self.hidden && !self.fullscreen;
+        let bars: Vec<_> = self.window_manager.bars().cloned().collect();
+        if !visible {
+            if !bars.is_empty() {
+                self.close_tooltip();
+            }
+            for bar in bars {
+                self.window_manager.close_window(bar.surface());
+            }
+            return;
+        }
+        if !bars.is_empty() {
+            return;
+        }
+        let outputs: Vec<_> = self.wayland.globals.outputs().map(Some).collect();
+        // wherever the compositor likes while there is no output yet
+        let outputs = if outputs.is_empty() {
+            vec![None]
+        } else {
+            outputs
+        };
+        for output in outputs {
+            if let Some(profile) = self.profile(output) {
+                self.create_bar(output, profile);
+            }
+        }
+    }
+    /// Which bar `output` gets, `None` if no profile names it. Without profiles, every output
+    /// gets one.
+    fn profile(&self, output: Option<Object<wayland::ffi::wl_output>>) -> Option<usize> {
+        if self.config.bars.is_empty() {
+            return Some(0);
+        }
+        let output = output?;
+        let named = |profile: &config::BarProfile| {
+            let mut names = profile.output.iter();
+            names.any(|x| self.outputs.is_named(output, x))
+        };
+        self.config.bars.iter().position(named)
+    }
+    fn create_bar(&mut self, output: Option<Object<wayland::ffi::wl_output>>, profile: usize) {
+        create_bar(
+            &mut self.wayland,
+            self.display,
+            &mut self.window_manager,
+            &self.config,
+            renderer(self.gpu.as_ref()),
+            output,
+            profile,
+        );
+    }
+    /// Gives the output a bar of its own, and the night light if it is on.
+    ///
+    /// Profiles go by the name of the output, which only comes with [`Runner::output_named`].
+    fn output_added(&mut self, name: u32, version: u32) {
+        let output = self.wayland.bind_output(name, version);
+        self.outputs.added(&mut self.wayland, output);
+        if !self.hidden
+            && !self.fullscreen
+            && let Some(profile) = self.profile(Some(output))
+        {
+            self.create_bar(Some(output), profile);
+        }
+        if self.night_light.as_ref().is_some_and(NightLight::on) {
+            self.set_night_light(true);
+        }
+    }
+    /// Creates the bar of the profile naming the output, if it has none yet.
+    fn output_named(&mut self, output: Object<wayland::ffi::wl_output>) {
+        if self.hidden || self.fullscreen || self.config.bars.is_empty() || self.bar_on(output) {
+            return;
+        }
+        if let Some(profile) = self.profile(Some(output)) {
+            self.create_bar(Some(output), profile);
+        }
+    }
+    fn bar_on(&self, output: Object<wayland::ffi::wl_output>) -> bool {
+        self.window_manager
+            .bars()
+            .any(|w| matches!(w.surface().role, Role::Layer { output: Some(x), .. } if x == output))
+    }
+    fn output_removed(&mut self, name: u32) -> Option<()> {
+        let output = self.wayland.output(name)?;
+        let bar = self
+            .window_manager
+            .bars()
+            .find(
+                |w| matches!(w.surface().role, Role::Layer { output: Some(x), .. } if x == output),
+            )
+            .cloned();
+        if let Some(bar) = bar {
+            self.close_tooltip();
+            self.window_manager.close_window(bar.surface());
+        }
+        self.outputs.removed(output);
+        self.wayland.remove_output(name);
+        self.refresh_monitors();
+        Some(())
+    }
+    /// Turns off whatever needs an optional global the compositor took away, it works on as if
+    /// the global was never there.
+    fn drop_unsupported(&mut self) {
+        let globals = &self.wayland.globals;
+        if globals.data_control_manager().is_none() {
+            self.clipboard = None;
+        }
+        if globals.idle_notifier().is_none() {
+            self.idle_notification = None;
+        }
+        if globals.screencopy_manager().is_none() {
+            self.preview = None;
+        }
+        if globals.activation().is_none() {
+            self.activation = None;
+        }
+        if globals.output_power_manager().is_none() {
+            self.outputs.drop_power();
+        }
+        if globals.output_manager().is_none() {
+            self.outputs.drop_heads();
+        }
+        if globals.xdg_output_manager().is_none() {
+            self.outputs.drop_xdg_outputs();
+        }
+        if globals.gamma_control_manager().is_none() {
+            // hides the module
+            self.night_light = None;
+            self.night_light_icon = None;
+        }
+        self.refresh_monitors();
+        self.outdated = true;
+    }
+    /// Drops the pointer, keyboard and touch of the seat bound from global `name` before the
+    /// seat itself.
+    async fn seat_removed(&mut self, name: u32) -> Option<()> {
+        let seat = self.wayland.seat(name)?.as_non_null();
+        let focused = self.window_manager.remove_seat(seat);
+        self.wayland.remove_seat(name);
+        if self.press.is_some_and(|x| x.seat == seat) {
+            self.press = None;
+        }
+        self.pointer_left(focused?).await
+    }
+    /// What the window on `surface` gets when a pointer leaves it without telling.
+    async fn pointer_left(&mut self, surface: WlSurface) -> Option<()> {
+        let window = self.window_manager.find_by_object(surface)?.clone();
+        window.mouse(iced::mouse::Event::CursorLeft, self).await;
+        Some(())
+    }
+    fn enabled(&self, module: &str) -> bool {
+        !self.disabled_modules.contains(module)
+    }
+    /// Runs the sources of the modules on any bar and not disabled, stops the rest.
+    fn update_registry(&mut self) {
+        let config = &self.config;
+        let disabled = &self.disabled_modules;
+        self.registry
+            .update(config, |name| !disabled.contains(name) && config.uses(name));
+    }
+    /// Modules which read the configuration at startup keep running with the old one, unless
+    /// their source is in the registry.
+    async fn reload(&mut self, config: Config) {
+        check_layout(&config);
+        let rebar = config.bars != self.config.bars;
+        self.registry.reload(&self.config, &config);
+        self.clocks = clock_faces(&config.clock);
+        self.world_zones = world_zones(&config.world_clock);
+        let sensors = polling::Sensors::of(&config);
+        if sensors != polling::Sensors::of(&self.config) {
+            // read again from the next tick on, if at all
+            self.fans.clear();
+            self.gpu_usage = None;
+            self.cpu = None;
+            self.memory = None;
+            self.polling
+                .send(polling::Signal::Sensors(sensors))
+                .await
+                .unwrap();
+        }
+        if config.idle.timeout != self.config.idle.timeout {
+            self.idle_notification = idle_notification(&mut self.wayland, config.idle.timeout);
+            // the new notification starts out active
+            self.set_idle(false).await;
+        }
+        self.icons.resize(config.icons.cache);
+        self.config = config;
+        self.update_registry();
+        if self.night_light.as_ref().is_some_and(NightLight::on) {
+            // at the new temperature
+            self.set_night_light(true);
+        }
+        for w in self.window_manager.iter() {
+            if let Role::Layer { layer_surface, .. } = &w.surface().role {
+                style_bar(layer_surface.as_ptr(), &self.config);
+                unsafe { wayland::ffi::wl_surface_commit(w.surface().wl_surface().as_ptr()) };
+            }
+        }
+        self.close_tooltip();
+        if rebar {
+            // other outputs, or other profiles on the same ones
+            let bars: Vec<_> = self.window_manager.bars().cloned().collect();
+            for bar in bars {
+                self.window_manager.close_window(bar.surface());
+            }
+            self.update_visibility();
+        }
+        self.retheme();
+        self.sync_ticks().await;
+    }
+    /// Pauses the sensors and update checks while idle.
+    async fn set_idle(&mut self, idle: bool) {
+        self.polling
+            .send(polling::Signal::Idle(idle))
+            .await
+            .unwrap();
+        // a sender of its own always has room, the task may be busy checking
+        self.updates
             .clone()
+            .try_send(updates::Signal::Idle(idle))
+            .unwrap();
+    }
+    /// Asks for ticks every second while anything shown needs them.
+    async fn sync_ticks(&mut self) {
+        let seconds = clock::needs_seconds(&self.config)
+            || self.countdown.running()
+            || self.stopwatch.running()
+            || (self.urgency.any() && !self.config.bar.steady_urgency);
+        if self.seconds.update(seconds) {
+            self.polling
+                .send(polling::Signal::Seconds(seconds))
+                .await
+                .unwrap();
+        }
+    }
+    fn retheme(&mut self) {
+        self.theme = theme(
+            &self.config.colors,
+            &self.imported_palettes,
+            self.color_scheme,
+        );
+        // symbolic icons are tinted with the palette
+        self.reload_icons();
+        self.tooltip_outdated = true;
+    }
+    /// Looks every shown icon up again, for a new theme or palette.
+    fn reload_icons(&mut self) {
+        self.icons.reset(icon::themes(
+            &self.config.icons,
+            self.desktop_icon_theme.as_deref(),
+        ));
+        self.resolve_icons();
+    }
+    /// Requests every shown icon again, through the cache.
+    fn resolve_icons(&mut self) {
+        let tray = self
+            .tray_items
+            .values_mut()
+            .flat_map(|item| iter::once(&mut item.icon).chain(&mut item.attention_icon));
+        let icons = self
+            .window
+            .icon
+            .iter_mut()
+            .chain(tray)
+            .chain(&mut self.battery_icon)
+            .chain(&mut self.lock_icon)
+            .chain(&mut self.updates_icon)
+            .chain(&mut self.timer_icon)
+            .chain(&mut self.stopwatch_icon)
+            .chain(&mut self.vpn_icon)
+            .chain(&mut self.microphone_icon)
+            .chain(&mut self.bluetooth_icon)
+            .chain(&mut self.clipboard_icon)
+            .chain(&mut self.screenshot_icon)
+            .chain(&mut self.night_light_icon)
+            .chain(&mut self.monitors_icon);
+        for icon in icons {
+            *icon = self.icons.reload(icon, &self.theme);
+        }
+    }
+    #[must_use]
+    fn load_icon(&mut self, name: &TinyString, symbolic: bool) -> Icon {
+        self.icons.load(name, symbolic, &self.theme)
     }
     fn logo(&self) -> impl Into<Element<'_>> {
         button(
@@ -616,20 +2720,29 @@ impl Runner {
     }
     fn workspace_item(&self, idx: usize) -> Element<'_> {
         let id = (idx + 1) as _;
-        let alive = self.workspaces.get(idx);
+        let alive =
+            self.workspaces.get(idx) || self.config.workspaces.persistent.contains(&(idx + 1));
         let focused = idx == self.workspace_focused;
+        let urgent = self.urgent_workspaces.get(idx);
+        let label = self.workspace_label(idx);
+        // names get as wide as they need, numbers and icons are all of the same width
+        let wide = label.chars().nth(1).is_some();
         let text: Element = if focused {
-            text(id % 10).size(11.5).shaping(Shaping::Basic).into()
+            text(label).size(11.5).shaping(Shaping::Basic).into()
         } else {
             match alive {
-                true => text(id % 10).size(11.5),
+                true => text(label).size(11.5),
                 false => text("𒊹")
                     .size(4.5)
                     .font(Font::with_name("Noto Sans Cuneiform")),
             }
             .into()
         };
-        let container = container(text).center_x(15).center_y(16);
+        let container = match wide {
+            true => container(text).padding([0, 4]),
+            false => container(text).center_x(15),
+        }
+        .center_y(16);
         let button = button(container)
             .style(move |theme: &Theme, status| button::Style {
                 background: match (status, focused) {
@@ -644,6 +2757,7 @@ impl Runner {
                     true => theme.palette().background.with_alpha(1.0),
                     false => match status {
                         button::Status::Hovered => theme.palette().primary,
+                        _ if urgent => theme.palette().danger,
                         _ => theme.palette().text,
                     },
                 },
@@ -654,13 +2768,87 @@ impl Runner {
             .on_press(Message::Workspace { id });
         button.into()
     }
+    /// The workspaces shown on the bar, with the windows on them as Hyprland lists them.
+    async fn workspace_overview(&mut self) -> Option<Vec<OverviewTile>> {
+        let clients = self
+            .hyprctl
+            .as_mut()?
+            .controller()
+            .await
+            .inspect_err(|e| tracing::warn!("cannot connect to hyprland: {e}"))
+            .ok()?
+            .clients()
+            .await
+            .inspect_err(|e| tracing::warn!("cannot query clients: {e}"))
+            .ok()?;
+        let mut tiles = vec![];
+        for idx in 0..WORKSPACE_MAX {
+            let persistent = self.config.workspaces.persistent.contains(&(idx + 1));
+            if !self.workspaces.get(idx) && !persistent {
+                continue;
+            }
+            let label = self.workspace_label(idx).into_owned();
+            let windows = clients
+                .iter()
+                .filter(|x| x.workspace == Some(idx + 1))
+                .map(|x| OverviewWindow {
+                    address: x.address.clone(),
+                    class: x.class.clone(),
+                    icon: (!x.class.is_empty()).then(|| self.icons.load_app(&x.class, &self.theme)),
+                })
+                .collect();
+            tiles.push(OverviewTile {
+                id: (idx + 1) as _,
+                label,
+                focused: idx == self.workspace_focused,
+                windows,
+            });
+        }
+        Some(tiles)
+    }
+    /// Whether anything changed.
+    fn rename_workspace(&mut self, id: usize, name: TinyString) -> bool {
+        match workspace_index(id) {
+            Some(idx) => self.workspace_names[idx].update(name),
+            None => false,
+        }
+    }
+    /// The configured icon for its name, the name unless it is just the id, or the id.
+    fn workspace_label(&self, idx: usize) -> Cow<'_, str> {
+        let name = self.workspace_names[idx].as_str();
+        let id = (idx + 1) % 10;
+        match self.config.workspaces.icons.get(name) {
+            Some(icon) => icon.into(),
+            None if !name.is_empty() && name != (idx + 1).to_string() => name.into(),
+            None => id.to_string().into(),
+        }
+    }
     fn workspace(&self) -> impl Into<Element<'_>> {
-        row((0..WORKSPACE_MAX).map(|idx| self.workspace_item(idx)))
+        let row = row((0..WORKSPACE_MAX).map(|idx| self.workspace_item(idx)))
             .spacing(2)
-            .align_y(Center)
+            .align_y(Center);
+        mouse_area(row).on_scroll(|delta| Message::WorkspaceScroll(scroll_y(delta)))
+    }
+    /// Hidden in the default submap.
+    fn submap(&self) -> Option<Element<'_>> {
+        if self.submap.is_empty() {
+            return None;
+        }
+        let label = text(self.submap.as_str())
+            .size(14.5)
+            .shaping(Shaping::Basic)
+            .style(|theme: &Theme| text::Style {
+                color: Some(theme.palette().primary),
+            });
+        Some(label.into())
     }
     fn title(&self) -> impl Into<Element<'_>> {
-        let icon = self.window.icon.clone().map(Handle::load);
+        let icon = self
+            .window
+            .icon
+            .as_ref()
+            .and_then(Icon::get)
+            .map(Handle::load);
         let class = text(self.window.class.as_str())
             .style(|theme: &Theme| text::Style {
                 color: Some(theme.palette().primary),
@@ -675,60 +2863,407 @@ impl Runner {
         mouse_area(row)
             // .on_enter(Signal::Message(Message::Hello))
             // .on_exit(Signal::Message(Message::Bye))
-            .on_enter(Message::WindowInfo)
+            .on_enter(Message::hover(Message::WindowInfo))
             .on_exit(Message::CloseTooltip)
     }
     fn tray(&self) -> Element<'_> {
         row(self
             .tray_items
             .iter()
-            .filter_map(|(service, TrayItem { icon })| {
-                icon.clone().map(|icon| {
-                    mouse_area(icon.load_size(22))
-                        .on_enter(Message::TrayTooltip(service.clone()))
+            .filter(|(_, item)| item.status != TrayStatus::Passive)
+            .filter_map(|(service, item)| {
+                let attention = item.status == TrayStatus::NeedsAttention;
+                let icon = match attention {
+                    true => item
+                        .attention_icon
+                        .as_ref()
+                        .and_then(Icon::get)
+                        .or_else(|| item.icon.get()),
+                    false => item.icon.get(),
+                }?;
+                // the whole tray is highlighted as urgent meanwhile
+                let icon = container(icon.load_size(22)).padding(2);
+                Some(
+                    mouse_area(icon)
+                        .on_enter(Message::hover(Message::TrayTooltip(service.clone())))
                         .on_exit(Message::CloseTooltip)
                         .on_press(Message::TrayAction(service.clone()))
-                        .into()
-                })
+                        .into(),
+                )
             }))
         .spacing(7)
         .into()
     }
     fn battery(&self) -> Option<Element<'_>> {
-        let icon = self.battery_icon.clone()?.load_size(17.5);
+        let icon = self.battery_icon.as_ref()?.get()?.load_size(17.5);
         Some(
             mouse_area(icon)
-                .on_enter(Message::Battery)
+                .on_enter(Message::hover(Message::Battery))
                 .on_exit(Message::BatteryStop)
                 .into(),
         )
     }
-    fn clock(&self) -> impl Into<Element<'_>> {
-        let date = text(unsafe { str::from_utf8_unchecked(&self.date) })
+    fn units(&self) -> Option<Element<'_>> {
+        if self.failed_units.is_empty() {
+            return None;
+        }
+        // highlighted as urgent while there are any
+        let badge = container(
+            text(self.failed_units.len())
+                .size(11.5)
+                .shaping(Shaping::Basic),
+        )
+        .center_x(16)
+        .center_y(16);
+        Some(
+            mouse_area(badge)
+                .on_enter(Message::hover(Message::Units))
+                .on_exit(Message::CloseTooltip)
+                .into(),
+        )
+    }
+    fn updates(&self) -> Option<Element<'_>> {
+        let icon = self.updates_icon.as_ref()?.get()?.load_size(17.5);
+        let count = text(self.pending_updates.len())
             .size(12.5)
-            .height(Length::Fill)
-            .align_y(Alignment::End);
-        let date = container(date)
-            .padding(Padding::default().bottom(7.5))
-            .into();
-        let time = text(unsafe { str::from_utf8_unchecked(&self.time) })
-            .size(17)
-            .height(Length::Fill)
+            .shaping(Shaping::Basic);
+        Some(
+            mouse_area(row([icon, count.into()]).align_y(Center).spacing(3))
+                .on_enter(Message::hover(Message::Updates))
+                .on_exit(Message::CloseTooltip)
+                .into(),
+        )
+    }
+    fn lock(&self) -> Option<Element<'_>> {
+        Some(self.lock_icon.as_ref()?.get()?.load_size(17.5))
+    }
+    fn clock(&self) -> impl Into<Element<'_>> {
+        mouse_area(row(self.clocks.iter().map(|face| clock_face(face).into())).spacing(14))
+            .on_enter(Message::hover(Message::WorldClock))
+            .on_exit(Message::CloseTooltip)
+    }
+    fn timer(&self) -> Option<Element<'_>> {
+        let Some(left) = self.countdown.left() else {
+            return Some(self.timer_icon.as_ref()?.get()?.load_size(17.5));
+        };
+        let expired = matches!(self.countdown, Countdown::Expired);
+        Some(counter(
+            timer::format(left),
+            self.countdown.running() || expired,
+            expired,
+        ))
+    }
+    fn stopwatch(&self) -> Option<Element<'_>> {
+        let Some(elapsed) = self.stopwatch.elapsed() else {
+            return Some(self.stopwatch_icon.as_ref()?.get()?.load_size(17.5));
+        };
+        // whole seconds counted so far, format rounds up
+        let elapsed = Duration::from_secs(elapsed.as_secs());
+        Some(counter(
+            timer::format(elapsed),
+            self.stopwatch.running(),
+            false,
+        ))
+    }
+    fn fans(&self) -> Option<Element<'_>> {
+        let rpm = self.fans.iter().map(|x| x.rpm).max()?;
+        let warning = self.config.fans.warning.is_some_and(|x| rpm >= x);
+        let label = text(format!("{rpm} rpm"))
+            .size(14.5)
             .shaping(Shaping::Basic)
-            .center()
-            .width(64)
-            .align_x(Center)
-            .into();
-        let weekday = text(self.weekday).size(15).height(Length::Fill).center();
-        let weekday = container(weekday)
-            .padding(Padding::default().bottom(4.5))
-            .into();
-        row([date, time, weekday]).spacing(7)
+            .style(move |theme: &Theme| text::Style {
+                color: warning.then(|| theme.palette().warning),
+            });
+        Some(
+            mouse_area(label)
+                .on_enter(Message::hover(Message::Fans))
+                .on_exit(Message::CloseTooltip)
+                .into(),
+        )
+    }
+    fn gpu_usage(&self) -> Option<Element<'_>> {
+        let gpu = self.gpu_usage.as_ref()?;
+        // i915 has no utilization outside of perf counters
+        let label = match (gpu.busy, gpu.frequency) {
+            (Some(busy), _) => format!("GPU {busy}%"),
+            (None, Some(mhz)) => format!("GPU {mhz} MHz"),
+            (None, None) => return None,
+        };
+        Some(
+            mouse_area(text(label).size(14.5).shaping(Shaping::Basic))
+                .on_enter(Message::hover(Message::Gpu))
+                .on_exit(Message::CloseTooltip)
+                .into(),
+        )
+    }
+    fn vpn(&self) -> Option<Element<'_>> {
+        let icon = self.vpn_icon.as_ref()?.get()?.load_size(17.5);
+        Some(
+            mouse_area(icon)
+                .on_enter(Message::hover(Message::Vpn))
+                .on_exit(Message::CloseTooltip)
+                .into(),
+        )
+    }
+    /// On a red pill while an app records.
+    fn microphone(&self) -> Option<Element<'_>> {
+        let icon = self.microphone_icon.as_ref()?.get()?.load_size(17.5);
+        let live = self.microphone?.recording;
+        let icon = container(icon)
+            .padding([2, 4])
+            .style(move |theme: &Theme| container::Style {
+                background: live.then(|| theme.palette().danger.with_alpha(0.6).into()),
+                border: Border::default().rounded(8),
+                ..Default::default()
+            });
+        Some(
+            mouse_area(icon)
+                .on_enter(Message::hover(Message::Microphone))
+                .on_exit(Message::CloseTooltip)
+                .into(),
+        )
+    }
+    /// Only with data control, there is no history to show otherwise.
+    fn clipboard(&self) -> Option<Element<'_>> {
+        self.clipboard.as_ref()?;
+        Some(self.clipboard_icon.as_ref()?.get()?.load_size(17.5))
+    }
+    fn screenshot(&self) -> Option<Element<'_>> {
+        if let Some(done) = self.screenshot_done {
+            let label = text(done).size(14.5).style(|theme: &Theme| text::Style {
+                color: Some(theme.palette().success),
+            });
+            return Some(label.into());
+        }
+        Some(self.screenshot_icon.as_ref()?.get()?.load_size(17.5))
+    }
+    fn recording(&self) -> Option<Element<'_>> {
+        const DOT: f32 = 10.0;
+        if self.recorders.is_empty() {
+            return None;
+        }
+        let alpha = if self.pulse { 0.4 } else { 1.0 };
+        let dot = container("")
+            .center(DOT)
+            .style(move |theme: &Theme| container::Style {
+                background: Some(theme.palette().danger.with_alpha(alpha).into()),
+                border: Border::default().rounded(DOT / 2.0),
+                ..Default::default()
+            });
+        Some(
+            mouse_area(container(dot).padding([0, 4]))
+                .on_enter(Message::hover(Message::Recording))
+                .on_exit(Message::CloseTooltip)
+                .into(),
+        )
+    }
+    fn cpu(&self) -> Option<Element<'_>> {
+        let label = format!("CPU {}%", self.cpu?);
+        Some(text(label).size(14.5).shaping(Shaping::Basic).into())
+    }
+    fn memory(&self) -> Option<Element<'_>> {
+        let label = format!("MEM {}%", self.memory?.percent());
+        Some(text(label).size(14.5).shaping(Shaping::Basic).into())
+    }
+    /// Keeps an open world clock tooltip going.
+    fn tick_world_clock(&mut self, clock: &Clock) -> bool {
+        if !matches!(
+            self.tooltip,
+            Some(Tooltip {
+                text: TooltipText::WorldClock(_),
+                ..
+            })
+        ) {
+            return false;
+        }
+        let now = self.world_clock(clock);
+        let Some(Tooltip {
+            text: TooltipText::WorldClock(zones),
+            ..
+        }) = &mut self.tooltip
+        else {
+            return false;
+        };
+        let changed = zones.update(now);
+        self.tooltip_outdated |= changed;
+        changed
+    }
+    fn world_clock(&self, clock: &Clock) -> Vec<[String; 2]> {
+        let locale = self.clocks.first().map_or(Locale::POSIX, |x| x.locale);
+        let format = &self.config.world_clock.format;
+        self.world_zones
+            .iter()
+            .map(|(city, tz)| {
+                let time = clock.format_in(Some(*tz), format, locale);
+                [city.clone(), time.unwrap_or_default()]
+            })
+            .collect()
+    }
+    /// Looks for a battery again, the module shows up or goes with it.
+    async fn battery_plugged(&mut self) -> bool {
+        let had = self.battery_status.is_some();
+        self.battery_status = BatteryStatus::new();
+        self.battery_icon = None;
+        self.reload_battery_icon();
+        self.polling
+            .send(polling::Signal::BatteryPlugged)
+            .await
+            .unwrap();
+        had || self.battery_status.is_some()
+    }
+    fn warn_low_battery(&mut self) {
+        let level = self.config.effects.low_battery_level;
+        if let Some(bat) = &mut self.battery_status
+            && bat.became_low(level)
+        {
+            effects::fire(&self.config.effects, Trigger::LowBattery);
+        }
+    }
+    fn reload_battery_icon(&mut self) {
+        if let Some(bat) = &self.battery_status {
+            self.battery_icon = Some(self.load_icon(&bat.icon().into(), true));
+        }
+    }
+    fn refresh_monitors(&mut self) {
+        let monitors = self.outputs.monitors();
+        if let Some(Tooltip {
+            text: TooltipText::Monitors(list),
+            ..
+        }) = &mut self.tooltip
+            && list.update(monitors)
+        {
+            self.tooltip_outdated = true;
+            self.outdated = true;
+        }
+    }
+    fn set_night_light(&mut self, on: bool) {
+        let Some(night_light) = &mut self.night_light else {
+            return;
+        };
+        if on {
+            night_light.enable(&mut self.wayland, self.config.night_light.temperature);
+        } else {
+            night_light.disable();
+        }
+        self.reload_night_light_icon();
+        self.outdated = true;
+    }
+    /// Follows the schedule whenever it turns, leaving the night light alone in between.
+    fn schedule_night_light(&mut self) {
+        let now = chrono::Local::now().time();
+        let scheduled = self.config.night_light.scheduled(now);
+        if self.night_scheduled.update(scheduled)
+            && let Some(on) = scheduled
+        {
+            self.set_night_light(on);
+        }
+    }
+    fn reload_night_light_icon(&mut self) {
+        if let Some(night_light) = &self.night_light {
+            let name = if night_light.on() {
+                "night-light-symbolic"
+            } else {
+                "night-light-disabled-symbolic"
+            };
+            self.night_light_icon = Some(self.load_icon(&name.into(), true));
+        }
+    }
+    fn reload_bluetooth_icon(&mut self) {
+        let name = if self.bluetooth == Some(true) {
+            "bluetooth-active-symbolic"
+        } else {
+            "bluetooth-disabled-symbolic"
+        };
+        self.bluetooth_icon = Some(self.load_icon(&name.into(), true));
+    }
+    fn reload_microphone_icon(&mut self) {
+        let Some(mic) = self.microphone else {
+            self.microphone_icon = None;
+            return;
+        };
+        let name = if mic.muted {
+            "microphone-sensitivity-muted-symbolic"
+        } else {
+            "audio-input-microphone-symbolic"
+        };
+        self.microphone_icon = Some(self.load_icon(&name.into(), true));
     }
-    fn reload_battery_icon(&mut self) {
-        if let Some(bat) = &self.battery_status {
-            self.battery_icon = self.load_icon(&bat.icon().into(), true);
-        }
+    fn reload_vpn_icon(&mut self) {
+        let name = if self.vpn.is_empty() {
+            "network-vpn-disconnected-symbolic"
+        } else {
+            "network-vpn-symbolic"
+        };
+        self.vpn_icon = Some(self.load_icon(&name.into(), true));
+    }
+}
+
+fn create_bar(
+    wayland: &mut wayland::Proxy,
+    display: NonNull<wayland::ffi::wl_display>,
+    wm: &mut WindowManager,
+    config: &Config,
+    renderer: Renderer,
+    output: Option<Object<wayland::ffi::wl_output>>,
+    profile: usize,
+) {
+    let surface = OwnedObject::from_raw(unsafe {
+        wayland::ffi::wl_compositor_create_surface(wayland.globals.compositer())
+    });
+    wayland.listen(&surface, &wayland::SURFACE_LISTENER);
+    let layer_surface = OwnedObject::from_raw(unsafe {
+        wayland::ffi::zwlr_layer_shell_v1_get_layer_surface(
+            wayland.globals.layer_shell(),
+            surface.as_ptr(),
+            output.map_or(ptr::null_mut(), Object::as_ptr),
+            wayland::ffi::ZWLR_LAYER_SHELL_V1_LAYER_TOP,
+            c"hyoka".as_ptr(),
+        )
+    });
+    wayland.listen(&layer_surface, &wayland::LAYER_SURFACE_LISTENER);
+    unsafe {
+        let layer_surface = layer_surface.as_ptr();
+        wayland::ffi::zwlr_layer_surface_v1_set_size(layer_surface, 0, BAR_HEIGHT);
+        wayland::ffi::zwlr_layer_surface_v1_set_anchor(
+            layer_surface,
+            wayland::ffi::ZWLR_LAYER_SURFACE_V1_ANCHOR_TOP
+                | wayland::ffi::ZWLR_LAYER_SURFACE_V1_ANCHOR_LEFT
+                | wayland::ffi::ZWLR_LAYER_SURFACE_V1_ANCHOR_RIGHT,
+        );
+        style_bar(layer_surface, config);
+        wayland::ffi::wl_surface_commit(surface.as_ptr());
+
+        wayland::ffi::wl_display_flush(display.as_ptr());
+    }
+    wm.create_window(
+        Surface {
+            role: Role::Layer {
+                layer_surface,
+                output,
+            },
+            surface,
+        },
+        Tag::Bar(profile),
+        renderer,
+    );
+}
+
+fn style_bar(layer_surface: *mut wayland::ffi::zwlr_layer_surface_v1, config: &Config) {
+    let [top, right, bottom, left] = config.bar.margin;
+    // modifiers are only sent to the surface with keyboard focus
+    let keyboard = if config.actions.values().any(|x| x.shift_click.is_some()) {
+        wayland::ffi::ZWLR_LAYER_SURFACE_V1_KEYBOARD_INTERACTIVITY_ON_DEMAND
+    } else {
+        wayland::ffi::ZWLR_LAYER_SURFACE_V1_KEYBOARD_INTERACTIVITY_NONE
+    };
+    unsafe {
+        wayland::ffi::zwlr_layer_surface_v1_set_keyboard_interactivity(layer_surface, keyboard);
+        wayland::ffi::zwlr_layer_surface_v1_set_margin(layer_surface, top, right, bottom, left);
+        // the compositor adds the top margin, the bottom one keeps windows away
+        wayland::ffi::zwlr_layer_surface_v1_set_exclusive_zone(
+            layer_surface,
+            BAR_HEIGHT as i32 + bottom,
+        );
     }
 }
 
@@ -736,20 +3271,13 @@ fn popup<'a>(
     wayland: &mut wayland::Proxy,
     wm: &'a mut WindowManager,
     display: NonNull<wayland::ffi::wl_display>,
-    mut view: Element,
+    view: Element,
     [x, y]: [u32; 2],
     parent: &Role,
+    grab: Option<Press>,
+    mut renderer: Renderer,
 ) -> Option<&'a Window> {
-    let mut renderer = renderer();
-    let Size { width, height } = {
-        let mut tree = Tree::new(&view);
-        let node = view.as_widget_mut().layout(
-            &mut tree,
-            &mut renderer,
-            &Limits::new(Size::ZERO, Size::INFINITE),
-        );
-        node.bounds().size()
-    };
+    let Size { width, height } = measure(view, &mut renderer);
     let size = width * height;
     if size == 0.0 {
         return None;
@@ -759,28 +3287,20 @@ fn popup<'a>(
         return None;
     }
 
-    let surface =
-        unsafe { wayland::ffi::wl_compositor_create_surface(wayland.globals.compositer()) };
-    unsafe {
-        wayland::ffi::wl_surface_add_listener(
-            surface,
-            &wayland::SURFACE_LISTENER,
-            &raw mut *wayland.notifier as _,
-        );
-    };
-    let xdg_surface =
-        unsafe { wayland::ffi::xdg_wm_base_get_xdg_surface(wayland.globals.wm_base(), surface) };
-    unsafe {
-        wayland::ffi::xdg_surface_add_listener(
-            xdg_surface,
-            &wayland::XDG_SURFACE_LISTENER,
-            ptr::null_mut(),
-        );
-    }
+    let wm_base = wayland.globals.wm_base()?;
+    let surface = OwnedObject::from_raw(unsafe {
+        wayland::ffi::wl_compositor_create_surface(wayland.globals.compositer())
+    });
+    wayland.listen(&surface, &wayland::SURFACE_LISTENER);
+    let xdg_surface = OwnedObject::from_raw(unsafe {
+        wayland::ffi::xdg_wm_base_get_xdg_surface(wm_base, surface.as_ptr())
+    });
+    wayland.listen(&xdg_surface, &wayland::XDG_SURFACE_LISTENER);
     let positioner =
-        unsafe { wayland::ffi::xdg_wm_base_create_positioner(wayland.globals.wm_base()) };
+        OwnedObject::from_raw(unsafe { wayland::ffi::xdg_wm_base_create_positioner(wm_base) });
 
     unsafe {
+        let positioner = positioner.as_ptr();
         wayland::ffi::xdg_positioner_set_size(positioner, width as _, height as _);
         wayland::ffi::xdg_positioner_set_anchor_rect(positioner, x as _, y as _, 1, 1);
         wayland::ffi::xdg_positioner_set_anchor(
@@ -798,39 +3318,39 @@ fn popup<'a>(
         );
     }
 
-    let popup = unsafe {
+    let popup = OwnedObject::from_raw(unsafe {
+        let get_popup = |parent| {
+            wayland::ffi::xdg_surface_get_popup(xdg_surface.as_ptr(), parent, positioner.as_ptr())
+        };
         match parent {
-            Role::Layer { layer_surface } => {
-                let popup =
-                    wayland::ffi::xdg_surface_get_popup(xdg_surface, ptr::null_mut(), positioner);
+            Role::Layer { layer_surface, .. } => {
+                let popup = get_popup(ptr::null_mut());
                 wayland::ffi::zwlr_layer_surface_v1_get_popup(layer_surface.as_ptr(), popup);
                 popup
             }
             Role::Popup {
                 xdg_surface: parent,
                 ..
-            } => wayland::ffi::xdg_surface_get_popup(xdg_surface, parent.as_ptr(), positioner),
+            } => get_popup(parent.as_ptr()),
         }
-    };
-    unsafe {
-        wayland::ffi::xdg_popup_add_listener(
-            popup,
-            &wayland::XDG_POPUP_LISTENER,
-            &raw mut *wayland.notifier as _,
-        );
-    };
+    });
+    wayland.listen(&popup, &wayland::XDG_POPUP_LISTENER);
+    if let Some(Press { seat, serial }) = grab {
+        unsafe { wayland::ffi::xdg_popup_grab(popup.as_ptr(), seat.as_ptr(), serial) };
+    }
     unsafe {
-        wayland::ffi::wl_surface_commit(surface);
+        wayland::ffi::wl_surface_commit(surface.as_ptr());
         wayland::ffi::wl_display_flush(display.as_ptr());
     };
-    let surface = NonNull::new(surface).unwrap();
     let win = wm.create_window(
-        surface,
-        Role::Popup {
-            xdg_surface: NonNull::new(xdg_surface).unwrap(),
-            popup: NonNull::new(popup).unwrap(),
-            positioner: NonNull::new(positioner).unwrap(),
-            size: Cell::new(Size::new(width, height)),
+        Surface {
+            role: Role::Popup {
+                popup,
+                xdg_surface,
+                positioner,
+                size: Cell::new(Size::new(width, height)),
+            },
+            surface,
         },
         Tag::Tooltip,
         renderer,
@@ -838,16 +3358,171 @@ fn popup<'a>(
     Some(win)
 }
 
-fn renderer() -> iced_tiny_skia::Renderer {
-    Renderer::new(
-        Font {
-            family: Family::Name("SF Pro Display"),
-            weight: Weight::Normal,
-            stretch: Stretch::Normal,
-            style: Style::Normal,
+/// The size `view` takes without constraints, popups are made to fit it.
+fn measure(mut view: Element, renderer: &mut Renderer) -> Size {
+    let mut tree = Tree::new(&view);
+    view.as_widget_mut()
+        .layout(
+            &mut tree,
+            renderer,
+            &Limits::new(Size::ZERO, Size::INFINITE),
+        )
+        .bounds()
+        .size()
+}
+
+/// Moves a popup to `[x, y]` and resizes it, the compositor answers with a configure.
+fn reposition(role: &Role, Size { width, height }: Size, [x, y]: [u32; 2]) {
+    let Role::Popup {
+        popup, positioner, ..
+    } = role
+    else {
+        return;
+    };
+    unsafe {
+        let positioner = positioner.as_ptr();
+        wayland::ffi::xdg_positioner_set_size(positioner, width as _, height as _);
+        wayland::ffi::xdg_positioner_set_anchor_rect(positioner, x as _, y as _, 1, 1);
+        wayland::ffi::xdg_popup_reposition(popup.as_ptr(), positioner, 0);
+    }
+}
+
+/// Fires once `timeout` seconds pass without input, `None` when disabled or unsupported.
+fn idle_notification(
+    wayland: &mut wayland::Proxy,
+    timeout: u32,
+) -> Option<OwnedObject<wayland::ffi::ext_idle_notification_v1>> {
+    if timeout == 0 {
+        return None;
+    }
+    let notifier = wayland.globals.idle_notifier()?;
+    // input on any seat counts, the first stands for all of them
+    let seat = wayland.globals.seats().next()?;
+    let notification = OwnedObject::from_raw(unsafe {
+        wayland::ffi::ext_idle_notifier_v1_get_idle_notification(
+            notifier,
+            timeout.saturating_mul(1000),
+            seat.as_ptr(),
+        )
+    });
+    wayland.listen(&notification, &wayland::IDLE_NOTIFICATION_LISTENER);
+    Some(notification)
+}
+
+/// What a module does when clicked unless configured otherwise.
+fn default_actions(module: &str) -> config::Actions {
+    use config::Action;
+    let click = |action| config::Actions {
+        click: Some(action),
+        ..Default::default()
+    };
+    match module {
+        "clock" => click(Action::Calendar),
+        "units" => click(Action::RestartUnits),
+        "updates" => click(Action::Update),
+        "timer" => config::Actions {
+            right_click: Some(Action::TimerReset),
+            ..click(Action::TimerToggle)
         },
-        Pixels(15.5),
-    )
+        "cpu" => click(Action::TopCpu),
+        "memory" => click(Action::TopMemory),
+        "vpn" => config::Actions {
+            middle_click: Some(Action::CopyIp),
+            ..click(Action::PublicIp)
+        },
+        "stopwatch" => config::Actions {
+            middle_click: Some(Action::StopwatchReset),
+            ..click(Action::StopwatchToggle)
+        },
+        "clipboard" => click(Action::ClipboardHistory),
+        "screenshot" => click(Action::Screenshot),
+        "recording" => click(Action::StopRecording),
+        "night_light" => click(Action::NightLight),
+        "microphone" => click(Action::MicrophoneMute),
+        "monitors" => click(Action::Monitors),
+        "title" => config::Actions {
+            right_click: Some(Action::WindowMenu),
+            ..Default::default()
+        },
+        // a left click on a workspace switches to it
+        "workspaces" => config::Actions {
+            right_click: Some(Action::WorkspaceOverview),
+            ..Default::default()
+        },
+        "bluetooth" => click(Action::BluetoothMenu),
+        "battery" => click(Action::BatteryMenu),
+        _ => config::Actions::default(),
+    }
+}
+
+/// One fan per line.
+fn fans_tooltip(fans: &[hwmon::Reading]) -> String {
+    fans.iter()
+        .map(|x| format!("{}  {} rpm", x.label, x.rpm))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn gpu_tooltip(gpu: &modules::gpu::Reading) -> String {
+    const GIB: f64 = (1 << 30) as f64;
+    let mut lines = vec![];
+    if let Some(busy) = gpu.busy {
+        lines.push(format!("Usage  {busy}%"));
+    }
+    if let Some(mhz) = gpu.frequency {
+        lines.push(format!("Frequency  {mhz} MHz"));
+    }
+    if let Some((used, total)) = gpu.vram {
+        let (used, total) = (used as f64 / GIB, total as f64 / GIB);
+        lines.push(format!("VRAM  {used:.1} / {total:.1} GiB"));
+    }
+    if let Some(millis) = gpu.temperature {
+        lines.push(format!("Temperature  {} °C", millis / 1000));
+    }
+    lines.join("\n")
+}
+
+/// The timer or stopwatch reading, dimmed while stopped.
+fn counter(label: String, running: bool, urgent: bool) -> Element<'static> {
+    let label = text(label).size(14.5).shaping(Shaping::Basic);
+    container(label)
+        .padding([0, 6])
+        .style(move |theme: &Theme| {
+            let palette = theme.palette();
+            container::Style {
+                background: urgent.then(|| palette.danger.into()),
+                text_color: Some(match () {
+                    _ if urgent => palette.background.with_alpha(1.0),
+                    _ if running => palette.primary,
+                    _ => palette.text.scale_alpha(0.6),
+                }),
+                border: Border::default().rounded(8),
+                ..Default::default()
+            }
+        })
+        .into()
+}
+
+/// Detached, like any action of a click.
+fn run_shell(command: &str, token: Option<&str>) {
+    spawn("sh", ["-c", command], token)
+        .inspect_err(|e| tracing::warn!("cannot run {command:?}: {e}"))
+        .ok();
+}
+
+const FONT: Font = Font {
+    family: Family::Name("SF Pro Display"),
+    weight: Weight::Normal,
+    stretch: Stretch::Normal,
+    style: Style::Normal,
+};
+
+/// wgpu if it came up at startup, tiny-skia otherwise.
+pub fn renderer(gpu: Option<&gpu::Gpu>) -> Renderer {
+    match gpu {
+        Some(gpu) => Renderer::Primary(gpu.renderer(FONT, Pixels(15.5))),
+        None => Renderer::Secondary(iced_tiny_skia::Renderer::new(FONT, Pixels(15.5))),
+    }
 }
 
 trait ColorExt {
@@ -861,65 +3536,61 @@ impl ColorExt for Color {
     }
 }
 
-fn load_svg(path: impl AsRef<Path>) -> Option<svg::Handle> {
-    let path = path.as_ref();
-    let fd = rustix::fs::open(path, OFlags::CLOEXEC, Mode::empty()).unwrap();
-    let mapping = Mapping::map(fd, ProtFlags::READ, MapFlags::PRIVATE).unwrap();
-    let text = unsafe { str::from_utf8_unchecked(mapping.as_bytes()) };
-    let tree = usvg::Tree::from_str(text, &usvg::Options::default())
-        .inspect_err(|err| tracing::warn!("cannot parse {path:?}: {err:?}"))
-        .ok()?;
-    Some(svg::Handle::from_tree(tree))
+/// Vertical scrolling in pixels, positive upwards.
+fn scroll_y(delta: ScrollDelta) -> f32 {
+    match delta {
+        ScrollDelta::Lines { y, .. } => y * SCROLL_STEP,
+        ScrollDelta::Pixels { y, .. } => y,
+    }
 }
 
-fn load_symbolic(path: impl AsRef<Path>, theme: &Theme) -> Option<svg::Handle> {
-    let path = path.as_ref();
-    let data = fs::read_to_string(path)
-        .unwrap()
-        .replace("currentColor", &theme.palette().text.to_string());
-    let tree = usvg::Tree::from_str(
-        &data,
-        &usvg::Options {
-            style_sheet: Some(theme.css_injection()),
-            ..Default::default()
-        },
-    )
-    .inspect_err(|err| tracing::warn!("cannot parse {path:?}: {err:?}"))
-    .ok()?;
-    Some(svg::Handle::from_tree(tree))
-}
-
-fn load_png(path: impl AsRef<Path>) -> Option<image::Handle> {
-    let path = path.as_ref();
-    let path = path.as_cow_c_str().unwrap();
-    let fd = rustix::fs::open(path.as_c_str(), OFlags::CLOEXEC, Mode::empty()).ok()?;
-    let data = Mapping::map(fd, ProtFlags::READ, MapFlags::PRIVATE).ok()?;
-    let cursor = io::Cursor::new(data.as_bytes());
-    let decoder = png::Decoder::new(cursor);
-
-    let mut reader = decoder.read_info().unwrap();
-    let len = reader.output_buffer_size().unwrap();
-    let buf = Mapping::anon(len, ProtFlags::READ | ProtFlags::WRITE, MapFlags::PRIVATE).unwrap();
-    let info = reader
-        .next_frame(buf.as_bytes_mut())
-        .inspect_err(|e| tracing::warn!("cannot decode {path:?}: {e}"))
-        .ok()?;
-    match info.color_type {
-        png::ColorType::Rgba => {}
-        x => {
-            tracing::warn!("{path:?} has unsupported color type: {x:?}");
-            return None;
-        }
-    }
-    match info.bit_depth {
-        png::BitDepth::Eight => {}
-        x => {
-            tracing::warn!("{path:?} has unsupported {}-bit depth", x as u32);
-            return None;
+/// Items printed by custom commands or sent by plugins, which take their own clicks.
+fn custom_items(
+    outputs: &[custom::Output],
+    tooltip: fn(usize) -> Message,
+    action: fn(usize, custom::Action) -> Message,
+    scroll: fn(usize, f32) -> Message,
+) -> Element<'_> {
+    row(outputs
+        .iter()
+        .enumerate()
+        .filter(|(_, output)| !output.text.is_empty())
+        .map(|(index, output)| {
+            let class = output.class.clone();
+            let text = text(output.text.as_str())
+                .style(move |theme: &Theme| text::Style {
+                    color: match class.as_deref() {
+                        Some("critical" | "urgent") => Some(theme.palette().danger),
+                        Some("warning") => Some(theme.palette().warning),
+                        Some("good" | "success") => Some(theme.palette().success),
+                        _ => None,
+                    },
+                })
+                .size(14.5);
+            mouse_area(text)
+                .on_enter(Message::hover(tooltip(index)))
+                .on_exit(Message::CloseTooltip)
+                .on_press(action(index, custom::Action::Click))
+                .on_middle_press(action(index, custom::Action::MiddleClick))
+                .on_right_press(action(index, custom::Action::RightClick))
+                .on_scroll(move |delta| scroll(index, scroll_y(delta)))
+                .into()
+        }))
+    .spacing(9)
+    .align_y(Center)
+    .into()
+}
+
+fn check_layout(config: &Config) {
+    for module in config
+        .layouts()
+        .flat_map(|x| [&x.left, &x.center, &x.right])
+        .flatten()
+    {
+        if !MODULES.contains(&module.as_str()) {
+            tracing::warn!("unknown module {module:?} in layout");
         }
     }
-    let handle = image::Handle::from_rgba(info.width, info.height, buf);
-    Some(handle)
 }
 
 fn truncate(mut s: TinyString, mut len: usize, ellipsis: &str) -> TinyString {
@@ -936,66 +3607,615 @@ fn truncate(mut s: TinyString, mut len: usize, ellipsis: &str) -> TinyString {
     }
 }
 
-const BACKGROUND: Color = Color::from_rgba8(30, 28, 34, 0.38);
+const DARK: Palette = Palette {
+    background: Color::from_rgba8(30, 28, 34, 0.38),
+    text: color!(0xcdd6f5),
+    primary: color!(0xa476f7),
+    success: color!(0x92b673),
+    warning: color!(0xe09733),
+    danger: color!(0xf25b4f),
+};
 
-const PURPLE: Color = color!(0xa476f7);
-const WHITE: Color = color!(0xcdd6f5);
-const GREEN: Color = color!(0x92b673);
-const YELLOW: Color = color!(0xe09733);
-const RED: Color = color!(0xf25b4f);
+const LIGHT: Palette = Palette {
+    background: Color::from_rgba8(239, 237, 244, 0.55),
+    text: color!(0x2e2a38),
+    primary: color!(0x7443d8),
+    success: color!(0x4f7a31),
+    warning: color!(0xa8620f),
+    danger: color!(0xc8362b),
+};
 
-fn theme() -> Theme {
-    Theme::custom(
-        "paper dark",
-        Palette {
-            background: BACKGROUND,
-            text: WHITE,
-            primary: PURPLE,
-            success: GREEN,
-            warning: YELLOW,
-            danger: RED,
-        },
-    )
+fn theme(colors: &config::Colors, imported: &palette::Palettes, scheme: ColorScheme) -> Theme {
+    let custom = |x: &config::Palette| Palette {
+        background: x.background,
+        text: x.text,
+        primary: x.primary,
+        success: x.success,
+        warning: x.warning,
+        danger: x.danger,
+    };
+    match scheme {
+        ColorScheme::Light => Theme::custom(
+            "paper light",
+            imported
+                .light
+                .or(colors.light)
+                .as_ref()
+                .map_or(LIGHT, custom),
+        ),
+        ColorScheme::Dark | ColorScheme::NoPreference => Theme::custom(
+            "paper dark",
+            imported.dark.or(colors.dark).as_ref().map_or(DARK, custom),
+        ),
+    }
 }
 
-trait StyleSheet {
-    fn css_injection(&self) -> String;
-}
-// foreground’, ‘success’, ‘warning’, ‘error’, ‘accent’
-impl StyleSheet for Theme {
-    fn css_injection(&self) -> String {
-        let Palette {
-            text,
-            primary,
-            success,
-            warning,
-            danger,
-            ..
-        } = self.palette();
-        format!(
-            concat!(
-                "* {{ fill:{} }}",
-                ".foreground {{ fill:{} }}",
-                ".success {{ fill:{} }}",
-                ".warning {{ fill:{} }}",
-                ".error {{ fill:{} }}",
-                ".accent {{ fill:{} }}",
-            ),
-            text, text, success, warning, danger, primary
-        )
+fn tooltip_style(theme: &Theme) -> container::Style {
+    container::Style {
+        background: Some(theme.palette().background.into()),
+        border: Border::default().rounded(13),
+        snap: false,
+        ..Default::default()
     }
 }
 
 fn tooltip_text(s: &str, padding: f32, shaping: Shaping) -> Element<'_> {
     let text = text(s).wrapping(text::Wrapping::None).shaping(shaping);
     container(text)
-        .style(|theme: &Theme| container::Style {
-            background: Some(theme.palette().background.into()),
-            border: Border::default().rounded(13),
-            snap: false,
+        .style(tooltip_style)
+        .padding(padding)
+        .center(Length::Shrink)
+        .into()
+}
+
+fn clock_face(face: &ClockFace) -> impl Into<Element<'_>> {
+    let [date, time, weekday] = &face.text;
+    let date = (!date.is_empty()).then(|| {
+        let date = text(date)
+            .size(12.5)
+            .height(Length::Fill)
+            .align_y(Alignment::End);
+        container(date)
+            .padding(Padding::default().bottom(7.5))
+            .into()
+    });
+    let time = (!time.is_empty()).then(|| {
+        text(time)
+            .size(17)
+            .height(Length::Fill)
+            .shaping(Shaping::Basic)
+            .center()
+            .into()
+    });
+    let weekday = (!weekday.is_empty()).then(|| {
+        let weekday = text(weekday).size(15).height(Length::Fill).center();
+        container(weekday)
+            .padding(Padding::default().bottom(4.5))
+            .into()
+    });
+    row([date, time, weekday].into_iter().flatten()).spacing(7)
+}
+
+fn world_clock(zones: &[[String; 2]]) -> Element<'_> {
+    let column =
+        |index: usize| widget::column(zones.iter().map(|x| text(&x[index]).into())).spacing(2);
+    let cities = column(0).align_x(Alignment::Start);
+    let times = column(1).align_x(Alignment::End);
+    container(widget::row![cities, times].spacing(16))
+        .style(tooltip_style)
+        .padding(12)
+        .into()
+}
+
+/// Name, CPU and memory columns, the one sorted by in the primary color.
+fn top_processes(sort: Sort, processes: &[Process]) -> Element<'static> {
+    let header = |label: &'static str, sorted: bool| -> Element<'static> {
+        text(label)
+            .size(12.5)
+            .font(Font {
+                weight: Weight::Bold,
+                ..FONT
+            })
+            .style(move |theme: &Theme| text::Style {
+                color: sorted.then(|| theme.palette().primary),
+            })
+            .into()
+    };
+    let column = |header, cells: Vec<String>| {
+        widget::column(
+            iter::once(header).chain(
+                cells
+                    .into_iter()
+                    .map(|x| text(x).size(13.5).shaping(Shaping::Basic).into()),
+            ),
+        )
+        .spacing(2)
+    };
+    let names = column(
+        header("Process", false),
+        processes.iter().map(|x| x.name.clone()).collect(),
+    );
+    let cpu = column(
+        header("CPU", sort == Sort::Cpu),
+        processes.iter().map(|x| format!("{:.1}%", x.cpu)).collect(),
+    );
+    let memory = column(
+        header("Memory", sort == Sort::Memory),
+        processes.iter().map(|x| bytes(x.rss)).collect(),
+    );
+    container(
+        widget::row![
+            names.align_x(Alignment::Start),
+            cpu.align_x(Alignment::End),
+            memory.align_x(Alignment::End)
+        ]
+        .spacing(16),
+    )
+    .style(tooltip_style)
+    .padding(12)
+    .into()
+}
+
+fn window_preview<'a>(title: &'a str, image: Option<&widget::image::Handle>) -> Element<'a> {
+    let title = text(title).size(13.5).wrapping(text::Wrapping::None);
+    let image: Option<Element> = image.map(|x| widget::image(x.clone()).into());
+    container(
+        widget::column(iter::once(title.into()).chain(image))
+            .spacing(8)
+            .align_x(Alignment::Center),
+    )
+    .style(tooltip_style)
+    .padding(10)
+    .into()
+}
+
+/// The search over the entries, newest first, each a button picking it.
+fn clipboard_history<'a>(query: &'a str, entries: &'a [Entry]) -> Element<'a> {
+    let search: Element = match query {
+        "" => text("Type to search")
+            .size(13.5)
+            .style(|theme: &Theme| text::Style {
+                color: Some(theme.palette().text.with_alpha(0.5)),
+            })
+            .into(),
+        query => text(query).size(13.5).into(),
+    };
+    let entries = entries.iter().map(|entry| {
+        let content: Element = match entry {
+            Entry::Text(s) => {
+                let line = s.trim().lines().next().unwrap_or_default();
+                text(truncate(line.into(), 60, "…"))
+                    .size(13.5)
+                    .wrapping(text::Wrapping::None)
+                    .into()
+            }
+            Entry::Image(image) => widget::image(image.handle.clone()).height(64).into(),
+        };
+        button(content)
+            .style(|theme: &Theme, status| button::Style {
+                background: matches!(status, button::Status::Hovered)
+                    .then(|| theme.palette().primary.with_alpha(0.25).into()),
+                text_color: theme.palette().text,
+                border: Border::default().rounded(8),
+                ..Default::default()
+            })
+            .padding([4, 8])
+            .on_press(Message::ClipboardPick(entry.clone()))
+            .into()
+    });
+    container(
+        widget::column(iter::once(search).chain(entries))
+            .spacing(4)
+            .align_x(Alignment::Start),
+    )
+    .style(tooltip_style)
+    .padding(12)
+    .into()
+}
+
+/// The VPN and public IP, over the Wi-Fi link quality as it went lately.
+fn network_tooltip<'a>(s: &'a str, signal: &[u8]) -> Element<'a> {
+    let Some(&quality) = signal.last() else {
+        return tooltip_text(s, 13.0, Shaping::Basic);
+    };
+    let wifi = widget::row![
+        text(format!("Wi-Fi  {quality}%")).shaping(Shaping::Basic),
+        sparkline(signal),
+    ]
+    .spacing(8)
+    .align_y(Alignment::End);
+    let column = widget::column![
+        text(s)
+            .wrapping(text::Wrapping::None)
+            .shaping(Shaping::Basic),
+        wifi,
+    ]
+    .spacing(6);
+    container(column).style(tooltip_style).padding(13).into()
+}
+
+/// A bar per sample, the latest on the right, the weak ones in the danger color.
+fn sparkline(samples: &[u8]) -> Element<'static> {
+    const HEIGHT: f32 = 16.0;
+    const WEAK: u8 = 30;
+    let bars = samples.iter().map(|&x| {
+        let height = (f32::from(x) / 100.0 * HEIGHT).max(1.0);
+        container("")
+            .width(2)
+            .height(height)
+            .style(move |theme: &Theme| {
+                let palette = theme.palette();
+                let color = if x < WEAK {
+                    palette.danger
+                } else {
+                    palette.primary
+                };
+                container::Style {
+                    background: Some(color.into()),
+                    ..Default::default()
+                }
+            })
+            .into()
+    });
+    row(bars)
+        .spacing(1)
+        .height(HEIGHT)
+        .align_y(Alignment::End)
+        .into()
+}
+
+/// Each output with its scale, and buttons turning it or just its display off or on.
+fn monitors_menu(monitors: &[Monitor]) -> Element<'_> {
+    const SCALE_STEP: f64 = 0.25;
+    let rows = monitors.iter().map(|monitor| {
+        let mode = monitor
+            .mode
+            .as_deref()
+            .map(|x| text(x).size(11.5).wrapping(text::Wrapping::None).into());
+        let name = text(&monitor.name).size(13.5).into();
+        // lined up across the rows, the popup is measured without bounds to fill
+        let name: Element = widget::column(iter::once(name).chain(mode))
+            .width(160)
+            .into();
+        let scale = monitor.scale.map(|scale| {
+            let configure =
+                |scale: f64| Message::ConfigureOutput(monitor.name.clone(), true, scale);
+            widget::row![
+                menu_button("−", configure((scale - SCALE_STEP).max(0.5))),
+                text(format!("{scale:.2}")).size(12.5),
+                menu_button("+", configure((scale + SCALE_STEP).min(4.0))),
+            ]
+            .spacing(4)
+            .align_y(Center)
+            .into()
+        });
+        let enabled = monitor.enabled.map(|enabled| {
+            let scale = monitor.scale.unwrap_or(1.0);
+            let message = Message::ConfigureOutput(monitor.name.clone(), !enabled, scale);
+            menu_button(if enabled { "Disable" } else { "Enable" }, message)
+        });
+        let power = monitor.power.map(|(output, on)| {
+            menu_button(
+                if on { "Turn off" } else { "Turn on" },
+                Message::OutputPower(output, !on),
+            )
+        });
+        row([Some(name), scale, enabled, power].into_iter().flatten())
+            .spacing(12)
+            .align_y(Center)
+            .into()
+    });
+    container(widget::column(rows).spacing(6))
+        .style(tooltip_style)
+        .padding(12)
+        .into()
+}
+
+/// The focused window's actions, then a button per workspace it can move to.
+fn window_menu(workspaces: &[(u8, String)]) -> Element<'_> {
+    use hyprland::Dispatcher;
+    let action = |label: &str, dispatcher| menu_button(label, Message::WindowAction(dispatcher));
+    let actions = widget::row![
+        action("Close", Dispatcher::KillActive),
+        action("Float", Dispatcher::ToggleFloating),
+        action("Fullscreen", Dispatcher::Fullscreen),
+    ]
+    .spacing(6);
+    let mut column = widget::column![actions].spacing(8);
+    if !workspaces.is_empty() {
+        let targets = workspaces
+            .iter()
+            .map(|(id, label)| action(label, Dispatcher::MoveToWorkspace(*id)));
+        let targets = widget::row![text("Move to").size(12.5)]
+            .extend(targets)
+            .spacing(4)
+            .align_y(Center);
+        column = column.push(targets);
+    }
+    container(column).style(tooltip_style).padding(12).into()
+}
+
+/// Five tiles a row, a window is focused by clicking it and moved by dropping it on another
+/// workspace.
+fn workspace_overview(tiles: &[OverviewTile]) -> Element<'_> {
+    let tile = |tile: &OverviewTile| -> Element<'_> {
+        let windows = tile.windows.iter().map(|window| {
+            let icon = match window.icon.as_ref().and_then(Icon::get) {
+                Some(icon) => icon.load_size(22),
+                None => text(window.class.as_str())
+                    .size(11.5)
+                    .wrapping(text::Wrapping::None)
+                    .into(),
+            };
+            mouse_area(icon)
+                .on_press(Message::OverviewPress(window.address.clone(), tile.id))
+                .into()
+        });
+        let content = widget::column![
+            text(&tile.label).size(13.5).shaping(Shaping::Basic),
+            row(windows).spacing(4).align_y(Center),
+        ]
+        .spacing(6);
+        let focused = tile.focused;
+        let area = container(content)
+            .style(move |theme: &Theme| container::Style {
+                background: Some(
+                    theme
+                        .palette()
+                        .primary
+                        .with_alpha(if focused { 0.3 } else { 0.1 })
+                        .into(),
+                ),
+                border: Border::default().rounded(8),
+                ..Default::default()
+            })
+            .padding(8)
+            .width(120)
+            .height(72);
+        mouse_area(area)
+            .on_release(Message::OverviewRelease(tile.id))
+            .into()
+    };
+    let rows = tiles
+        .chunks(5)
+        .map(|x| row(x.iter().map(tile)).spacing(6).into());
+    container(widget::column(rows).spacing(6))
+        .style(tooltip_style)
+        .padding(12)
+        .into()
+}
+
+/// As a menu, with a button turning the charge limit off or on where there is one.
+fn battery_tooltip(info: &battery::Info, menu: bool) -> Element<'static> {
+    let status = text(info.tooltip().to_string())
+        .wrapping(text::Wrapping::None)
+        .shaping(Shaping::Basic);
+    if !menu {
+        return container(status)
+            .style(tooltip_style)
+            .padding(10)
+            .center(Length::Shrink)
+            .into();
+    }
+    let toggle = info.limit.map(|limit| {
+        let label = match (limit, limit.on()) {
+            (battery::ChargeLimit::Conservation(_), true) => "Charge fully",
+            (battery::ChargeLimit::Conservation(_), false) => "Conservation mode",
+            (battery::ChargeLimit::Threshold(_), true) => "Charge to 100%",
+            (battery::ChargeLimit::Threshold(_), false) => "Limit charge",
+        };
+        menu_button(label, Message::ChargeLimit(!limit.on()))
+    });
+    container(
+        widget::column(iter::once(status.into()).chain(toggle))
+            .spacing(8)
+            .align_x(Center),
+    )
+    .style(tooltip_style)
+    .padding(12)
+    .into()
+}
+
+/// The adapter switch over the paired devices, each with its battery and a button connecting it.
+fn bluetooth_menu(powered: bool, devices: &[BluetoothDevice]) -> Element<'_> {
+    let power = widget::row![
+        text("Bluetooth").size(13.5).width(160),
+        menu_button(
+            if powered { "Turn off" } else { "Turn on" },
+            Message::BluetoothPower(!powered),
+        ),
+    ]
+    .align_y(Center)
+    .into();
+    let rows = devices.iter().map(|device| {
+        let name = text(&device.name).size(13.5).width(160).into();
+        let battery = device
+            .battery
+            .map(|x| text(format!("{x}%")).size(12.5).into());
+        // connecting needs the adapter on
+        let connect = powered.then(|| {
+            menu_button(
+                if device.connected {
+                    "Disconnect"
+                } else {
+                    "Connect"
+                },
+                Message::BluetoothConnect(device.path.clone(), !device.connected),
+            )
+        });
+        row([Some(name), battery, connect].into_iter().flatten())
+            .spacing(12)
+            .align_y(Center)
+            .into()
+    });
+    container(widget::column(iter::once(power).chain(rows)).spacing(6))
+        .style(tooltip_style)
+        .padding(12)
+        .into()
+}
+
+fn menu_button(label: &str, message: Message) -> Element<'static> {
+    button(text(label.to_owned()).size(12.5))
+        .style(|theme: &Theme, status| button::Style {
+            background: Some(
+                theme
+                    .palette()
+                    .primary
+                    .with_alpha(match status {
+                        button::Status::Hovered => 0.4,
+                        _ => 0.2,
+                    })
+                    .into(),
+            ),
+            text_color: theme.palette().text,
+            border: Border::default().rounded(8),
             ..Default::default()
         })
-        .padding(padding)
+        .padding([2, 8])
+        .on_press(message)
+        .into()
+}
+
+/// Typed with a US layout, there is no keymap to go by.
+fn key_char(key: u32) -> Option<char> {
+    const ROWS: [(u32, &str); 4] = [
+        (2, "1234567890-="),
+        (16, "qwertyuiop[]"),
+        (30, "asdfghjkl;'`"),
+        (43, "\\zxcvbnm,./"),
+    ];
+    if key == KEY_SPACE {
+        return Some(' ');
+    }
+    ROWS.iter()
+        .find_map(|&(first, keys)| keys.chars().nth(key.checked_sub(first)? as usize))
+}
+
+fn bytes(n: u64) -> String {
+    const MIB: u64 = 1 << 20;
+    const GIB: u64 = 1 << 30;
+    match n {
+        ..MIB => format!("{} KiB", n >> 10),
+        ..GIB => format!("{} MiB", n / MIB),
+        _ => format!("{:.1} GiB", n as f64 / GIB as f64),
+    }
+}
+
+fn calendar(clock: &Clock, locale: Locale) -> Element<'_> {
+    const CELL: f32 = 26.0;
+    let title: Element = text(clock.format("%B %Y", locale).unwrap_or_default())
+        .font(Font {
+            weight: Weight::Bold,
+            ..FONT
+        })
+        .into();
+    let weekdays: Element = row(clock
+        .weekdays(locale)
+        .map(|x| text(x).size(12.5).width(CELL).center().into()))
+    .into();
+    let today = clock.day_of_month();
+    let weeks = clock.weeks().map(|week| {
+        row(week.map(|day| {
+            let current = day == Some(today);
+            container(
+                text(day.map(|x| x.to_string()).unwrap_or_default())
+                    .size(13.5)
+                    .shaping(Shaping::Basic),
+            )
+            .center(CELL)
+            .style(move |theme: &Theme| container::Style {
+                background: current.then(|| theme.palette().primary.into()),
+                text_color: current.then(|| theme.palette().background.with_alpha(1.0)),
+                border: Border::default().rounded(CELL / 2.0),
+                ..Default::default()
+            })
+            .into()
+        }))
+        .into()
+    });
+    container(
+        widget::column([title, weekdays].into_iter().chain(weeks))
+            .spacing(4)
+            .align_x(Center),
+    )
+    .style(tooltip_style)
+    .padding(12)
+    .into()
+}
+
+fn tray_tooltip<'a>(icon: Option<Handle>, title: &'a str, body: &'a str) -> Element<'a> {
+    let mut lines = widget::column![].spacing(2);
+    if !title.is_empty() {
+        lines = lines.push(
+            text(title)
+                .font(Font {
+                    weight: Weight::Bold,
+                    ..FONT
+                })
+                .wrapping(text::Wrapping::None),
+        );
+    }
+    if !body.is_empty() {
+        lines = lines.push(text(body).size(13.5).wrapping(text::Wrapping::None));
+    }
+    let content = row(icon.map(|x| x.load_size(32)))
+        .push(lines)
+        .align_y(Center)
+        .spacing(10);
+    container(content)
+        .style(tooltip_style)
+        .padding(10)
         .center(Length::Shrink)
         .into()
 }
+
+/// Tooltip bodies may carry a subset of html, only the text is shown.
+fn strip_markup(s: &str) -> String {
+    let mut res = String::with_capacity(s.len());
+    let mut in_tag = false;
+    for c in s.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            _ if !in_tag => res.push(c),
+            _ => {}
+        }
+    }
+    unescape(&res)
+}
+
+/// The entities of XML, in one pass so `&amp;lt;` stays `&lt;`.
+fn unescape(s: &str) -> String {
+    const ENTITIES: [(&str, char); 5] = [
+        ("&amp;", '&'),
+        ("&lt;", '<'),
+        ("&gt;", '>'),
+        ("&quot;", '"'),
+        ("&apos;", '\''),
+    ];
+    let mut res = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(start) = rest.find('&') {
+        res.push_str(&rest[..start]);
+        rest = &rest[start..];
+        match ENTITIES.iter().find(|(x, _)| rest.starts_with(x)) {
+            Some((entity, c)) => {
+                res.push(*c);
+                rest = &rest[entity.len()..];
+            }
+            None => {
+                res.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    res.push_str(rest);
+    res
+}
+
+mod urgency;
+
+#[cfg(test)]
+mod snapshot;
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,242 @@
+//! Views rendered with tiny-skia into a pixmap, no compositor needed, against golden PNGs in
+//! `snapshots/` next to this file.
+//!
+//! Goldens are only written with `HYOKA_BLESS=1`, a missing one fails the test. Text goes
+//! through the fonts installed, so goldens are only comparable on a machine with the same ones.
+
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use iced::{Color, Rectangle, Size, mouse::Cursor};
+use iced_core::renderer::Style;
+use iced_runtime::user_interface::Cache;
+use iced_tiny_skia::graphics::Viewport;
+use tiny_skia::{Mask, Pixmap};
+
+use super::*;
+use crate::consumer::UserInterface;
+
+/// per channel, text antialiasing differs a little between builds of the same font
+const TOLERANCE: u8 = 2;
+
+fn dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("src/consumer/program/snapshots")
+}
+
+/// Lays `view` out at the size it takes and rasterizes it, as demultiplied RGBA.
+fn render(view: impl Fn() -> Element<'static>) -> (Size<u32>, Vec<u8>) {
+    let mut renderer = renderer(None);
+    let theme = theme(
+        &Default::default(),
+        &Default::default(),
+        ColorScheme::NoPreference,
+    );
+    let Size { width, height } = measure(view(), &mut renderer);
+    let size = Size::new(width.ceil() as u32, height.ceil() as u32);
+    let mut ui = UserInterface::build(
+        view(),
+        Size::new(size.width as _, size.height as _),
+        Cache::new(),
+        &mut renderer,
+    );
+    ui.draw(
+        &mut renderer,
+        &theme,
+        &Style {
+            text_color: theme.palette().text,
+        },
+        Cursor::Unavailable,
+    );
+    let Renderer::Secondary(renderer) = &mut renderer else {
+        unreachable!("without a gpu it is tiny-skia");
+    };
+    let mut pixmap = Pixmap::new(size.width, size.height).unwrap();
+    renderer.draw(
+        &mut pixmap.as_mut(),
+        &mut Mask::new(size.width, size.height).unwrap(),
+        &Viewport::with_physical_size(size, 1.0),
+        &[Rectangle::with_size(Size::new(width, height))],
+        Color::TRANSPARENT,
+    );
+    let data = pixmap
+        .pixels()
+        .iter()
+        .flat_map(|x| {
+            let x = x.demultiply();
+            [x.red(), x.green(), x.blue(), x.alpha()]
+        })
+        .collect();
+    (size, data)
+}
+
+fn write_png(path: &Path, size: Size<u32>, data: &[u8]) -> io::Result<()> {
+    fs::create_dir_all(path.parent().unwrap())?;
+    let mut encoder = png::Encoder::new(
+        io::BufWriter::new(fs::File::create(path)?),
+        size.width,
+        size.height,
+    );
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(data))
+        .map_err(io::Error::other)
+}
+
+fn read_png(path: &Path) -> io::Result<(Size<u32>, Vec<u8>)> {
+    let decoder = png::Decoder::new(io::BufReader::new(fs::File::open(path)?));
+    let mut reader = decoder.read_info().map_err(io::Error::other)?;
+    let mut data = vec![0; reader.output_buffer_size().unwrap()];
+    let info = reader.next_frame(&mut data).map_err(io::Error::other)?;
+    assert_eq!(
+        (info.color_type, info.bit_depth),
+        (png::ColorType::Rgba, png::BitDepth::Eight),
+        "{path:?} is not 8-bit RGBA",
+    );
+    data.truncate(info.buffer_size());
+    Ok((Size::new(info.width, info.height), data))
+}
+
+/// Fails with the actual rendering saved as `<name>.actual.png` beside the golden.
+fn assert_snapshot(name: &str, view: impl Fn() -> Element<'static>) {
+    let (size, data) = render(view);
+    let golden = dir().join(format!("{name}.png"));
+    if env::var_os("HYOKA_BLESS").is_some() {
+        write_png(&golden, size, &data).unwrap();
+        return;
+    }
+    if !golden.exists() {
+        let actual = golden.with_extension("actual.png");
+        write_png(&actual, size, &data).unwrap();
+        panic!("{golden:?} is missing, see {actual:?} and rerun with HYOKA_BLESS=1 to accept it");
+    }
+    let (expected_size, expected) = read_png(&golden).unwrap();
+    let matches = size == expected_size
+        && data
+            .iter()
+            .zip(&expected)
+            .all(|(x, y)| x.abs_diff(*y) <= TOLERANCE);
+    if !matches {
+        let actual = golden.with_extension("actual.png");
+        write_png(&actual, size, &data).unwrap();
+        panic!("{name} differs from {golden:?}, see {actual:?}");
+    }
+}
+
+#[test]
+fn simple_tooltip() {
+    assert_snapshot("simple-tooltip", || {
+        tooltip_text("Volume 42%", 10.0, Shaping::Basic)
+    });
+}
+
+#[test]
+fn multiline_tooltip() {
+    let text: &'static TooltipText = Box::leak(Box::new(TooltipText::Multiline(
+        "linux 6.18.1 -> 6.18.2\nmesa 25.3.0 -> 25.3.1".into(),
+    )));
+    assert_snapshot("multiline-tooltip", || text.view());
+}
+
+#[test]
+fn world_clock_tooltip() {
+    let zones: &'static [[String; 2]] = Box::leak(Box::new([
+        ["Tokyo".into(), "Fri 09:30".into()],
+        ["London".into(), "Fri 01:30".into()],
+    ]));
+    assert_snapshot("world-clock", || world_clock(zones));
+}
+
+#[test]
+fn top_processes_tooltip() {
+    let processes = [("firefox", 23.5, 1 << 30), ("hyoka", 0.4, 40 << 20)]
+        .into_iter()
+        .enumerate()
+        .map(|(pid, (name, cpu, rss))| Process {
+            pid: pid as u32 + 1,
+            name: name.into(),
+            cpu,
+            rss,
+        })
+        .collect::<Vec<_>>();
+    let processes: &'static [Process] = processes.leak();
+    assert_snapshot("top-processes", || top_processes(Sort::Cpu, processes));
+}
+
+#[test]
+fn monitors_menu_tooltip() {
+    let monitors: &'static [Monitor] = vec![Monitor {
+        name: "DP-1".into(),
+        power: None,
+        enabled: Some(true),
+        scale: Some(1.5),
+        mode: Some("2560×1440 @ 144 Hz".into()),
+    }]
+    .leak();
+    assert_snapshot("monitors-menu", || monitors_menu(monitors));
+}
+
+#[test]
+fn workspace_overview_tooltip() {
+    let window = |address: &str, class: &str| OverviewWindow {
+        address: address.into(),
+        class: class.into(),
+        icon: None,
+    };
+    let tiles: &'static [OverviewTile] = vec![
+        OverviewTile {
+            id: 1,
+            label: "1".into(),
+            focused: false,
+            windows: vec![window("55d0f3a1b0d8", "foot")],
+        },
+        OverviewTile {
+            id: 3,
+            label: "web".into(),
+            focused: true,
+            windows: vec![
+                window("55d0f3a1b0c0", "firefox"),
+                window("55d0f3a1b0e0", "mpv"),
+            ],
+        },
+    ]
+    .leak();
+    assert_snapshot("workspace-overview", || workspace_overview(tiles));
+}
+
+#[test]
+fn window_menu_tooltip() {
+    let workspaces: &'static [(u8, String)] = vec![(1, "1".into()), (3, "web".into())].leak();
+    assert_snapshot("window-menu", || window_menu(workspaces));
+}
+
+#[test]
+fn bluetooth_menu_tooltip() {
+    let devices: &'static [BluetoothDevice] = vec![BluetoothDevice {
+        path: "/org/bluez/hci0/dev_00_1B_66_AA_BB_CC".into(),
+        name: "Headphones".into(),
+        connected: true,
+        battery: Some(70),
+    }]
+    .leak();
+    assert_snapshot("bluetooth-menu", || bluetooth_menu(true, devices));
+}
+
+#[test]
+fn clipboard_history_tooltip() {
+    let entries: &'static [Entry] = vec![
+        Entry::Text(Rc::from("cargo clippy --workspace")),
+        Entry::Text(Rc::from("https://example.org")),
+    ]
+    .leak();
+    assert_snapshot("clipboard-history", || clipboard_history("c", entries));
+}
+
+#[test]
+fn counter_item() {
+    assert_snapshot("counter", || counter("04:59".into(), true, false));
+}
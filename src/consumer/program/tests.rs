@@ -0,0 +1,24 @@
+//! Tray tooltip markup reduced to its text.
+
+use super::*;
+
+#[test]
+fn tags_are_dropped() {
+    assert_eq!(
+        strip_markup("<b>Updates</b> available"),
+        "Updates available"
+    );
+}
+
+#[test]
+fn entities_are_decoded_once() {
+    assert_eq!(
+        strip_markup("&lt;b&gt; &amp;lt;b&amp;gt; &quot;x&quot; &apos;y&apos;"),
+        "<b> &lt;b&gt; \"x\" 'y'",
+    );
+}
+
+#[test]
+fn stray_ampersands_are_kept() {
+    assert_eq!(strip_markup("Tom & Jerry &nbsp;"), "Tom & Jerry &nbsp;");
+}
@@ -0,0 +1,47 @@
+//! Modules asking for attention, highlighted alike with the palette's danger color.
+
+use iced::{Border, Theme, widget::container};
+use rustc_hash::FxHashSet;
+
+/// The urgent modules by name, and whether their highlight is lit as of the latest second.
+#[derive(Default)]
+pub struct Urgency {
+    modules: FxHashSet<&'static str>,
+    /// off every other second while blinking
+    lit: bool,
+}
+
+impl Urgency {
+    /// Whether anything changed.
+    pub fn set(&mut self, module: &'static str, urgent: bool) -> bool {
+        match urgent {
+            true => self.modules.insert(module),
+            false => self.modules.remove(module),
+        }
+    }
+    pub fn get(&self, module: &str) -> bool {
+        self.modules.contains(module)
+    }
+    pub fn any(&self) -> bool {
+        !self.modules.is_empty()
+    }
+    /// Flips the highlight, whether anything shows it.
+    pub fn blink(&mut self) -> bool {
+        self.lit = !self.lit;
+        self.any()
+    }
+    /// Steady ones stay lit.
+    pub fn lit(&self, steady: bool) -> bool {
+        steady || self.lit
+    }
+}
+
+/// Behind an urgent module, faded while it blinks off.
+pub fn style(theme: &Theme, lit: bool) -> container::Style {
+    let alpha = if lit { 0.6 } else { 0.2 };
+    container::Style {
+        background: Some(theme.palette().danger.with_alpha(alpha).into()),
+        border: Border::default().rounded(8),
+        ..Default::default()
+    }
+}
@@ -0,0 +1,142 @@
+use compio::runtime::{self, Task};
+use futures::{SinkExt as _, channel::mpsc::Sender, future::LocalBoxFuture};
+
+use crate::{
+    config::{self, Config},
+    consumer::{AppEvent, BatteryEvent, Event, supervisor::supervise},
+    modules::{audio, custom, network, uevent},
+};
+
+/// A background source of events for bar modules, run only while one of them is wanted.
+pub trait Module {
+    /// As in the layout and `hyoka msg set-module`.
+    fn names(&self) -> &'static [&'static str];
+    fn run(&self, config: &Config, events: Sender<Event>) -> LocalBoxFuture<'static, ()>;
+    /// Whether it has to start over for `new` to take effect.
+    fn outdated(&self, _old: &Config, _new: &Config) -> bool {
+        false
+    }
+}
+
+/// The sources spawned from the layout, dropping a task stops its source.
+pub struct Registry {
+    modules: Vec<(Box<dyn Module>, Option<Task<()>>)>,
+    events: Sender<Event>,
+}
+
+impl Registry {
+    pub fn new(events: Sender<Event>) -> Self {
+        let modules: [Box<dyn Module>; 4] = [
+            Box::new(Battery),
+            Box::new(Vpn),
+            Box::new(Microphone),
+            Box::new(Custom),
+        ];
+        Self {
+            modules: modules.into_iter().map(|x| (x, None)).collect(),
+            events,
+        }
+    }
+    /// Starts the sources `wanted` by any of their modules and stops the others.
+    pub fn update(&mut self, config: &Config, wanted: impl Fn(&str) -> bool) {
+        for (module, task) in &mut self.modules {
+            match (module.names().iter().any(|&x| wanted(x)), &task) {
+                (true, None) => {
+                    tracing::debug!("starting {}", module.names()[0]);
+                    *task = Some(runtime::spawn(module.run(config, self.events.clone())));
+                }
+                (false, Some(_)) => {
+                    tracing::debug!("stopping {}", module.names()[0]);
+                    *task = None;
+                }
+                _ => {}
+            }
+        }
+    }
+    /// Restarts the running sources which read what changed, before [`Registry::update`].
+    pub fn reload(&mut self, old: &Config, new: &Config) {
+        for (module, task) in &mut self.modules {
+            if task.is_some() && module.outdated(old, new) {
+                *task = None;
+            }
+        }
+    }
+}
+
+struct Battery;
+
+impl Module for Battery {
+    fn names(&self) -> &'static [&'static str] {
+        &["battery"]
+    }
+    fn run(&self, _config: &Config, events: Sender<Event>) -> LocalBoxFuture<'static, ()> {
+        Box::pin(supervise("uevent", async move || {
+            let mut events = events.clone();
+            uevent::new()?
+                .serve(async move |e| {
+                    let event = match e {
+                        uevent::Event::PowerOnline => BatteryEvent::PowerOnline,
+                        uevent::Event::PowerOffline => BatteryEvent::PowerOffline,
+                        uevent::Event::BatCapacity(x) => BatteryEvent::Capacity(x),
+                        uevent::Event::BatStatus(x) => BatteryEvent::Status(x),
+                        uevent::Event::BatPlugged => BatteryEvent::Plugged,
+                    };
+                    events.send(event.into()).await.unwrap();
+                })
+                .await
+        }))
+    }
+}
+
+struct Vpn;
+
+impl Module for Vpn {
+    fn names(&self) -> &'static [&'static str] {
+        &["vpn"]
+    }
+    fn run(&self, _config: &Config, events: Sender<Event>) -> LocalBoxFuture<'static, ()> {
+        Box::pin(supervise("rtnetlink", async move || {
+            let mut events = events.clone();
+            network::run(async |vpn| events.send(AppEvent::Vpn(vpn).into()).await.unwrap()).await
+        }))
+    }
+}
+
+struct Microphone;
+
+impl Module for Microphone {
+    fn names(&self) -> &'static [&'static str] {
+        &["microphone"]
+    }
+    fn run(&self, _config: &Config, events: Sender<Event>) -> LocalBoxFuture<'static, ()> {
+        Box::pin(supervise("pactl", async move || {
+            let mut events = events.clone();
+            audio::run(async |mic| events.send(AppEvent::Microphone(mic).into()).await.unwrap())
+                .await
+        }))
+    }
+}
+
+struct Custom;
+
+impl Module for Custom {
+    fn names(&self) -> &'static [&'static str] {
+        &["custom"]
+    }
+    fn run(&self, config: &Config, events: Sender<Event>) -> LocalBoxFuture<'static, ()> {
+        let config = config.custom.clone();
+        Box::pin(async move {
+            let mut events = events;
+            custom::run(&config, async move |e| {
+                events.send(e.into()).await.unwrap();
+            })
+            .await
+        })
+    }
+    fn outdated(&self, old: &Config, new: &Config) -> bool {
+        let commands = |x: &[config::Custom]| -> Vec<(String, Option<u64>)> {
+            x.iter().map(|x| (x.exec.clone(), x.interval)).collect()
+        };
+        commands(&old.custom) != commands(&new.custom)
+    }
+}
@@ -0,0 +1,207 @@
+use std::{
+    env,
+    ffi::{CStr, CString},
+    ptr::{self, NonNull},
+};
+
+use crate::{
+    consumer::window::WlSurface,
+    wayland::{self, Object, OwnedObject, ffi},
+};
+
+pub type WlSeat = NonNull<ffi::wl_seat>;
+
+/// A button press or touch, menus grab with it and launched apps are activated by it.
+#[derive(Debug, Clone, Copy)]
+pub struct Press {
+    pub seat: WlSeat,
+    pub serial: u32,
+}
+
+/// A seat with the input devices it has right now, and where its pointer is.
+///
+/// A seat without a pointer, or one losing it when a laptop is undocked, rejects asking for one,
+/// so they are only created once the seat announces them and dropped when it takes them back.
+pub struct Seat {
+    pub seat: Object<ffi::wl_seat>,
+    pub pointer: Option<Pointer>,
+    keyboard: Option<OwnedObject<ffi::wl_keyboard>>,
+    touch: Option<OwnedObject<ffi::wl_touch>>,
+    /// the window under its pointer or touch
+    pub focused: Option<WlSurface>,
+}
+
+pub struct Pointer {
+    /// before the pointer, fields drop in order
+    cursor: Cursor,
+    pointer: OwnedObject<ffi::wl_pointer>,
+}
+
+enum Cursor {
+    Shape(OwnedObject<ffi::wp_cursor_shape_device_v1>),
+    /// without cursor shape, the images of the theme go on the surface
+    Theme {
+        surface: OwnedObject<ffi::wl_surface>,
+        theme: Theme,
+    },
+    /// nor a theme, the cursor stays as the compositor left it
+    None,
+}
+
+/// A cursor theme loaded by libwayland-cursor, `XCURSOR_THEME` at `XCURSOR_SIZE`.
+struct Theme(NonNull<ffi::wl_cursor_theme>);
+
+impl Theme {
+    fn load(shm: *mut ffi::wl_shm) -> Option<Self> {
+        let name = env::var("XCURSOR_THEME")
+            .ok()
+            .and_then(|x| CString::new(x).ok());
+        let size = env::var("XCURSOR_SIZE")
+            .ok()
+            .and_then(|x| x.parse().ok())
+            .unwrap_or(24);
+        let name = name.as_deref().map_or(ptr::null(), CStr::as_ptr);
+        let theme = NonNull::new(unsafe { ffi::wl_cursor_theme_load(name, size, shm) });
+        if theme.is_none() {
+            tracing::warn!("cannot load the cursor theme, the cursor stays as it is on the bars");
+        }
+        Some(Self(theme?))
+    }
+    /// The first frame of the cursor for `shape`, animated ones stand still.
+    fn image(&self, shape: ffi::wp_cursor_shape_device_v1_shape) -> Option<&ffi::wl_cursor_image> {
+        // older themes only have the X11 names, and some only the default cursor
+        let fallback = [c"left_ptr"];
+        names(shape).iter().chain(&fallback).find_map(|name| {
+            let cursor = unsafe { ffi::wl_cursor_theme_get_cursor(self.0.as_ptr(), name.as_ptr()) };
+            let cursor = unsafe { cursor.as_ref() }.filter(|x| x.image_count > 0)?;
+            unsafe { (*cursor.images).as_ref() }
+        })
+    }
+}
+
+impl Drop for Theme {
+    fn drop(&mut self) {
+        unsafe { ffi::wl_cursor_theme_destroy(self.0.as_ptr()) }
+    }
+}
+
+impl Pointer {
+    fn new(wayland: &mut wayland::Proxy, pointer: OwnedObject<ffi::wl_pointer>) -> Self {
+        let cursor = match wayland.globals.cursor_shape_manager() {
+            Some(manager) => Cursor::Shape(OwnedObject::from_raw(unsafe {
+                ffi::wp_cursor_shape_manager_v1_get_pointer(manager, pointer.as_ptr())
+            })),
+            None => match Theme::load(wayland.globals.shm()) {
+                Some(theme) => Cursor::Theme {
+                    surface: OwnedObject::from_raw(unsafe {
+                        ffi::wl_compositor_create_surface(wayland.globals.compositer())
+                    }),
+                    theme,
+                },
+                None => Cursor::None,
+            },
+        };
+        Self { cursor, pointer }
+    }
+    /// `serial` is of the latest enter.
+    pub fn set_shape(&self, serial: u32, shape: ffi::wp_cursor_shape_device_v1_shape) {
+        match &self.cursor {
+            Cursor::Shape(device) => unsafe {
+                ffi::wp_cursor_shape_device_v1_set_shape(device.as_ptr(), serial, shape)
+            },
+            Cursor::Theme { surface, theme } => {
+                let Some(image) = theme.image(shape) else {
+                    return;
+                };
+                unsafe {
+                    let buffer = ffi::wl_cursor_image_get_buffer(ptr::from_ref(image).cast_mut());
+                    let surface = surface.as_ptr();
+                    ffi::wl_surface_attach(surface, buffer, 0, 0);
+                    ffi::wl_surface_damage(surface, 0, 0, image.width as _, image.height as _);
+                    ffi::wl_surface_commit(surface);
+                    ffi::wl_pointer_set_cursor(
+                        self.pointer.as_ptr(),
+                        serial,
+                        surface,
+                        image.hotspot_x as _,
+                        image.hotspot_y as _,
+                    );
+                }
+            }
+            Cursor::None => {}
+        }
+    }
+    pub fn hide(&self, serial: u32) {
+        unsafe { ffi::wl_pointer_set_cursor(self.pointer.as_ptr(), serial, ptr::null_mut(), 0, 0) };
+    }
+}
+
+/// How cursor themes name the shape, after the CSS cursor it comes from, then the X11 one.
+fn names(shape: ffi::wp_cursor_shape_device_v1_shape) -> &'static [&'static CStr] {
+    match shape {
+        ffi::WP_CURSOR_SHAPE_DEVICE_V1_SHAPE_CONTEXT_MENU => &[c"context-menu"],
+        ffi::WP_CURSOR_SHAPE_DEVICE_V1_SHAPE_HELP => &[c"help", c"question_arrow"],
+        ffi::WP_CURSOR_SHAPE_DEVICE_V1_SHAPE_POINTER => &[c"pointer", c"hand2"],
+        ffi::WP_CURSOR_SHAPE_DEVICE_V1_SHAPE_PROGRESS => &[c"progress", c"left_ptr_watch"],
+        ffi::WP_CURSOR_SHAPE_DEVICE_V1_SHAPE_WAIT => &[c"wait", c"watch"],
+        ffi::WP_CURSOR_SHAPE_DEVICE_V1_SHAPE_CELL => &[c"cell", c"plus"],
+        ffi::WP_CURSOR_SHAPE_DEVICE_V1_SHAPE_CROSSHAIR => &[c"crosshair", c"cross"],
+        ffi::WP_CURSOR_SHAPE_DEVICE_V1_SHAPE_TEXT => &[c"text", c"xterm"],
+        ffi::WP_CURSOR_SHAPE_DEVICE_V1_SHAPE_ALIAS => &[c"alias", c"link"],
+        ffi::WP_CURSOR_SHAPE_DEVICE_V1_SHAPE_COPY => &[c"copy"],
+        ffi::WP_CURSOR_SHAPE_DEVICE_V1_SHAPE_MOVE => &[c"move", c"fleur"],
+        ffi::WP_CURSOR_SHAPE_DEVICE_V1_SHAPE_NO_DROP => &[c"no-drop"],
+        ffi::WP_CURSOR_SHAPE_DEVICE_V1_SHAPE_NOT_ALLOWED => &[c"not-allowed", c"crossed_circle"],
+        ffi::WP_CURSOR_SHAPE_DEVICE_V1_SHAPE_GRAB => &[c"grab", c"openhand"],
+        ffi::WP_CURSOR_SHAPE_DEVICE_V1_SHAPE_GRABBING => &[c"grabbing", c"closedhand"],
+        ffi::WP_CURSOR_SHAPE_DEVICE_V1_SHAPE_EW_RESIZE => &[c"ew-resize", c"sb_h_double_arrow"],
+        ffi::WP_CURSOR_SHAPE_DEVICE_V1_SHAPE_NS_RESIZE => &[c"ns-resize", c"sb_v_double_arrow"],
+        ffi::WP_CURSOR_SHAPE_DEVICE_V1_SHAPE_NESW_RESIZE => &[c"nesw-resize", c"fd_double_arrow"],
+        ffi::WP_CURSOR_SHAPE_DEVICE_V1_SHAPE_NWSE_RESIZE => &[c"nwse-resize", c"bd_double_arrow"],
+        ffi::WP_CURSOR_SHAPE_DEVICE_V1_SHAPE_COL_RESIZE => &[c"col-resize", c"sb_h_double_arrow"],
+        ffi::WP_CURSOR_SHAPE_DEVICE_V1_SHAPE_ROW_RESIZE => &[c"row-resize", c"sb_v_double_arrow"],
+        ffi::WP_CURSOR_SHAPE_DEVICE_V1_SHAPE_ALL_SCROLL => &[c"all-scroll", c"fleur"],
+        ffi::WP_CURSOR_SHAPE_DEVICE_V1_SHAPE_ZOOM_IN => &[c"zoom-in"],
+        ffi::WP_CURSOR_SHAPE_DEVICE_V1_SHAPE_ZOOM_OUT => &[c"zoom-out"],
+        _ => &[c"default"],
+    }
+}
+
+impl Seat {
+    pub fn new(seat: Object<ffi::wl_seat>) -> Self {
+        Self {
+            seat,
+            pointer: None,
+            keyboard: None,
+            touch: None,
+            focused: None,
+        }
+    }
+    /// Creates what `capabilities` adds and drops what it lacks, returns whether the pointer went.
+    pub fn update(&mut self, wayland: &mut wayland::Proxy, capabilities: u32) -> bool {
+        let seat = self.seat.as_ptr();
+        let had_pointer = self.pointer.is_some();
+        if capabilities & ffi::WL_SEAT_CAPABILITY_POINTER == 0 {
+            self.pointer = None;
+        } else if self.pointer.is_none() {
+            let pointer = OwnedObject::from_raw(unsafe { ffi::wl_seat_get_pointer(seat) });
+            wayland.listen_device(self.seat, &pointer, &wayland::POINTER_LISTENERL);
+            self.pointer = Some(Pointer::new(wayland, pointer));
+        }
+        if capabilities & ffi::WL_SEAT_CAPABILITY_KEYBOARD == 0 {
+            self.keyboard = None;
+        } else if self.keyboard.is_none() {
+            let keyboard = OwnedObject::from_raw(unsafe { ffi::wl_seat_get_keyboard(seat) });
+            wayland.listen(&keyboard, &wayland::KEYBOARD_LISTENER);
+            self.keyboard = Some(keyboard);
+        }
+        if capabilities & ffi::WL_SEAT_CAPABILITY_TOUCH == 0 {
+            self.touch = None;
+        } else if self.touch.is_none() {
+            let touch = OwnedObject::from_raw(unsafe { ffi::wl_seat_get_touch(seat) });
+            wayland.listen_device(self.seat, &touch, &wayland::TOUCH_LISTENER);
+            self.touch = Some(touch);
+        }
+        had_pointer && self.pointer.is_none()
+    }
+}
@@ -0,0 +1,54 @@
+use std::{
+    cell::RefCell,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use crate::error::Result;
+
+const MIN_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Runs a module again whenever it fails, waiting longer after each failure in a row.
+///
+/// `Ok` means the module is done for good.
+pub async fn supervise(name: &str, mut run: impl AsyncFnMut() -> Result<()>) {
+    let mut backoff = MIN_BACKOFF;
+    loop {
+        let start = Instant::now();
+        match run().await {
+            Ok(()) => return,
+            Err(e) => tracing::warn!("{name} failed: {e}, restarting in {backoff:?}"),
+        }
+        // it was healthy for a while, this is a new problem
+        if start.elapsed() > MAX_BACKOFF {
+            backoff = MIN_BACKOFF;
+        }
+        compio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+    }
+}
+
+/// A handle replaced by the supervisor each time its module reconnects.
+pub struct Slot<T>(Rc<RefCell<Option<T>>>);
+
+impl<T> Clone for Slot<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T> Default for Slot<T> {
+    fn default() -> Self {
+        Self(Default::default())
+    }
+}
+
+impl<T: Clone> Slot<T> {
+    pub fn get(&self) -> Option<T> {
+        self.0.borrow().clone()
+    }
+    pub fn set(&self, value: Option<T>) {
+        *self.0.borrow_mut() = value;
+    }
+}
@@ -3,13 +3,12 @@ use std::{
     ffi::c_void,
     fmt, mem,
     ops::{Deref, Index},
-    os::fd::AsRawFd as _,
+    os::fd::{AsRawFd as _, OwnedFd},
     ptr::{self, NonNull},
     rc::Rc,
     time::Instant,
 };
 
-use futures::channel::mpsc::UnboundedSender;
 use iced::{
     Rectangle, Size,
     mouse::{self, Cursor, Interaction},
@@ -20,55 +19,46 @@ use iced_core::{
     renderer::Style,
     widget::{Operation, operation::Focusable},
 };
+use iced_renderer::Renderer;
 use iced_runtime::user_interface::Cache;
-use iced_tiny_skia::{Renderer, graphics};
+use iced_tiny_skia::graphics;
 use rustc_hash::FxHashMap;
 use rustix::{
     fs::MemfdFlags,
     mm::{MapFlags, ProtFlags},
 };
-use tiny_skia::{Mask, PixmapMut};
+use tiny_skia::{Mask, Pixmap, PixmapMut};
 
 use crate::{
-    consumer::{Callbacks, Element, Runner, UserInterface, program::Message},
-    wayland::{self, Callback, Event},
+    consumer::{
+        Callbacks, Element, Runner, UserInterface, gpu,
+        hud::{Hud, Timings},
+        program::{self, Message},
+        seat::{Seat, WlSeat},
+    },
+    wayland::{self, Callback, Object, OwnedObject},
 };
 
-#[derive(Clone)]
+/// Fields drop in declaration order, which is the order the protocol wants them destroyed.
 pub enum Role {
     Layer {
-        layer_surface: NonNull<wayland::ffi::zwlr_layer_surface_v1>,
+        layer_surface: OwnedObject<wayland::ffi::zwlr_layer_surface_v1>,
+        /// `None` where the compositor picked one
+        output: Option<Object<wayland::ffi::wl_output>>,
     },
     Popup {
-        xdg_surface: NonNull<wayland::ffi::xdg_surface>,
-        popup: NonNull<wayland::ffi::xdg_popup>,
-        positioner: NonNull<wayland::ffi::xdg_positioner>,
+        popup: OwnedObject<wayland::ffi::xdg_popup>,
+        xdg_surface: OwnedObject<wayland::ffi::xdg_surface>,
+        positioner: OwnedObject<wayland::ffi::xdg_positioner>,
         size: Cell<Size>,
     },
 }
 
 impl Role {
-    fn destroy(&self) {
-        match self {
-            Role::Layer { layer_surface } => unsafe {
-                wayland::ffi::zwlr_layer_surface_v1_destroy(layer_surface.as_ptr())
-            },
-            Role::Popup {
-                xdg_surface,
-                popup,
-                positioner,
-                ..
-            } => unsafe {
-                wayland::ffi::xdg_popup_destroy(popup.as_ptr());
-                wayland::ffi::xdg_surface_destroy(xdg_surface.as_ptr());
-                wayland::ffi::xdg_positioner_destroy(positioner.as_ptr());
-            },
-        }
-    }
     fn key(&self, mut cb: impl FnMut(NonNull<c_void>)) {
         match self {
-            &Role::Layer { layer_surface } => cb(layer_surface.cast()),
-            &Role::Popup { popup, .. } => cb(popup.cast()),
+            Role::Layer { layer_surface, .. } => cb(layer_surface.as_non_null().cast()),
+            Role::Popup { popup, .. } => cb(popup.as_non_null().cast()),
         }
     }
 }
@@ -77,19 +67,87 @@ pub type WlSurface = NonNull<wayland::ffi::wl_surface>;
 
 pub enum ConfigState<'ui> {
     Configured {
-        buffer: Buffer,
-        clip_mask: tiny_skia::Mask,
+        viewport: Viewport,
+        target: Target,
         ui: Option<UserInterface<'ui>>,
-        last_layers: Option<Vec<iced_tiny_skia::Layer>>,
     },
     Unconfigured {
         scale_factor: u32,
+        transform: wayland::ffi::wl_output_transform,
     },
 }
 
+/// Where the frames go, follows the renderer of the window.
+pub enum Target {
+    Shm {
+        buffers: Buffers,
+        clip_mask: tiny_skia::Mask,
+        last_layers: Option<Vec<iced_tiny_skia::Layer>>,
+        /// the whole last frame as iced draws it, for buffers turned like the output
+        upright: Option<Pixmap>,
+    },
+    Gpu(gpu::Surface),
+}
+
+impl Target {
+    /// Falls back to shm, and the renderer to tiny-skia, if the gpu cannot serve the surface.
+    ///
+    /// The gpu presents upright, leaving `viewport` without a transform for the compositor to
+    /// turn its frames.
+    fn new(
+        renderer: &mut Renderer,
+        viewport: &mut Viewport,
+        surface: WlSurface,
+        runner: &Runner,
+    ) -> Self {
+        if let Renderer::Primary(_) = renderer {
+            let gpu = runner.gpu.as_ref().unwrap();
+            let normal = viewport.with_buffer_transform(wayland::ffi::WL_OUTPUT_TRANSFORM_NORMAL);
+            match gpu.create_surface(runner.display, surface, normal) {
+                Ok(surface) => {
+                    *viewport = normal;
+                    return Target::Gpu(surface);
+                }
+                Err(e) => {
+                    tracing::error!("{e}, falling back to software rendering");
+                    *renderer = program::renderer(None);
+                }
+            }
+        }
+        let mut buffers = Buffers::default();
+        let idx = buffers.acquire(&runner.wayland.globals, *viewport);
+        buffers.attach(idx, surface);
+        Target::Shm {
+            buffers,
+            clip_mask: viewport.mask(),
+            last_layers: None,
+            upright: viewport.upright(),
+        }
+    }
+    fn reconfigure(&mut self, viewport: Viewport) {
+        match self {
+            Target::Shm {
+                buffers,
+                clip_mask,
+                last_layers,
+                upright,
+            } => {
+                buffers.clear();
+                *clip_mask = viewport.mask();
+                *last_layers = None;
+                *upright = viewport.upright();
+            }
+            Target::Gpu(surface) => surface.configure(viewport),
+        }
+    }
+}
+
 impl Default for ConfigState<'_> {
     fn default() -> Self {
-        Self::Unconfigured { scale_factor: 1 }
+        Self::Unconfigured {
+            scale_factor: 1,
+            transform: wayland::ffi::WL_OUTPUT_TRANSFORM_NORMAL,
+        }
     }
 }
 
@@ -104,27 +162,27 @@ impl ConfigState<'_> {
     }
 }
 
-#[derive(Clone)]
+/// The role goes first, a `wl_surface` must outlive it.
 pub struct Surface {
     pub role: Role,
-    pub surface: WlSurface,
+    pub surface: OwnedObject<wayland::ffi::wl_surface>,
 }
 
-struct OwnedSurface(Surface);
-
-impl Drop for OwnedSurface {
-    fn drop(&mut self) {
-        self.0.role.destroy();
-        unsafe { wayland::ffi::wl_surface_destroy(self.0.surface.as_ptr()) };
+impl Surface {
+    pub fn wl_surface(&self) -> WlSurface {
+        self.surface.as_non_null()
     }
 }
 
 pub struct State {
     pub cursor: Cursor,
-    serial: Option<u32>,
+    /// of the latest enter, with the seat whose cursor it sets
+    serial: Option<(WlSeat, u32)>,
     shape: Option<Interaction>,
     pub config_state: ConfigState<'static>,
     pub renderer: Renderer,
+    /// bars only, while [`Runner::hud`] is on
+    hud: Option<Hud>,
 }
 
 fn rebuild_ui<'ui>(
@@ -148,8 +206,13 @@ impl State {
         runner: &Runner,
     ) {
         match mem::take(&mut self.config_state) {
-            ConfigState::Configured { buffer, ui, .. } => {
-                let viewport = buffer.viewport.with_surface_size(size);
+            ConfigState::Configured {
+                viewport,
+                mut target,
+                ui,
+            } => {
+                let viewport = viewport.with_surface_size(size);
+                target.reconfigure(viewport);
                 let ui = ui
                     .map(|ui| ui.relayout(Size::new(width as _, height as _), &mut self.renderer))
                     .unwrap_or_else(|| {
@@ -161,123 +224,207 @@ impl State {
                         )
                     });
                 self.config_state = ConfigState::Configured {
-                    buffer: runner.wayland.globals.create_buffer(viewport),
+                    viewport,
+                    target,
                     ui: Some(ui),
-                    clip_mask: viewport.mask(),
-                    last_layers: None,
                 };
             }
-            ConfigState::Unconfigured { scale_factor } => {
-                let viewport = Viewport {
+            ConfigState::Unconfigured {
+                scale_factor,
+                transform,
+            } => {
+                let mut viewport = Viewport {
                     surface_size: size,
                     buffer_scale: scale_factor,
-                    _buffer_transform: wayland::ffi::WL_OUTPUT_TRANSFORM_NORMAL,
+                    buffer_transform: transform,
                 };
 
-                let buffer = runner.wayland.globals.create_buffer(viewport);
+                let target = Target::new(&mut self.renderer, &mut viewport, surface, runner);
                 unsafe {
-                    wayland::ffi::wl_surface_attach(surface.as_ptr(), buffer.buffer.as_ptr(), 0, 0)
+                    wayland::ffi::wl_surface_set_buffer_transform(
+                        surface.as_ptr(),
+                        viewport.buffer_transform as _,
+                    )
                 };
                 self.config_state = ConfigState::Configured {
-                    buffer,
+                    viewport,
+                    target,
                     ui: Some(iced_runtime::UserInterface::build::<Element<'static>>(
                         unsafe { mem::transmute(runner.view(tag)) },
                         Size::new(width as _, height as _),
                         Cache::new(),
                         &mut self.renderer,
                     )),
-                    clip_mask: viewport.mask(),
-                    last_layers: None,
                 };
             }
         }
     }
+    #[tracing::instrument(level = "debug", skip(self, runner))]
     fn redraw(&mut self, surface: WlSurface, tag: Tag, runner: &Runner) {
-        if let ConfigState::Configured {
-            buffer,
-            clip_mask,
-            last_layers,
+        let ConfigState::Configured {
+            viewport,
+            target,
             ui,
         } = &mut self.config_state
-        {
-            let [width, height] = buffer.viewport.surface_size;
-            let ui = ui.get_or_insert_with(|| {
-                iced_runtime::UserInterface::build::<Element<'static>>(
-                    unsafe { mem::transmute(runner.view(tag)) },
-                    Size::new(width as _, height as _),
-                    Cache::new(),
-                    &mut self.renderer,
-                )
-            });
-            rebuild_ui(
-                ui,
-                unsafe { mem::transmute(runner.view(tag)) },
+        else {
+            return;
+        };
+        let hud = runner.hud && matches!(tag, Tag::Bar(_));
+        if hud != self.hud.is_some() {
+            self.hud = hud.then(Hud::default);
+        }
+        if let Some(hud) = &mut self.hud {
+            hud.icons = runner.icons.stats();
+        }
+        let start = Instant::now();
+        let view = || {
+            let view = runner.view(tag);
+            match &self.hud {
+                Some(hud) => hud.view(view),
+                None => view,
+            }
+        };
+        let viewport = *viewport;
+        let [width, height] = viewport.surface_size;
+        let ui = ui.get_or_insert_with(|| {
+            iced_runtime::UserInterface::build::<Element<'static>>(
+                unsafe { mem::transmute(view()) },
                 Size::new(width as _, height as _),
+                Cache::new(),
                 &mut self.renderer,
-            );
-            ui.update(
-                &[iced::Event::Window(iced::window::Event::RedrawRequested(
-                    Instant::now(),
-                ))],
-                self.cursor,
-                &mut self.renderer,
-                &mut Clipboard,
-                &mut vec![],
-            );
-            ui.draw(
-                &mut self.renderer,
-                &runner.theme,
-                &Style {
-                    text_color: runner.theme.palette().text,
+            )
+        });
+        rebuild_ui(
+            ui,
+            unsafe { mem::transmute(view()) },
+            Size::new(width as _, height as _),
+            &mut self.renderer,
+        );
+        ui.update(
+            &[iced::Event::Window(iced::window::Event::RedrawRequested(
+                Instant::now(),
+            ))],
+            self.cursor,
+            &mut self.renderer,
+            &mut Clipboard::new(runner),
+            &mut vec![],
+        );
+        let layout = start.elapsed();
+        ui.draw(
+            &mut self.renderer,
+            &runner.theme,
+            &Style {
+                text_color: runner.theme.palette().text,
+            },
+            self.cursor,
+        );
+        if let Some(hud) = &mut self.hud {
+            hud.outline(&mut self.renderer);
+        }
+        let mut draw = None;
+
+        match (&mut self.renderer, target) {
+            (
+                Renderer::Secondary(renderer),
+                Target::Shm {
+                    buffers,
+                    clip_mask,
+                    last_layers,
+                    upright,
                 },
-                self.cursor,
-            );
-
-            let layers = self.renderer.layers();
-            let mut damage = if let Some(last_layers) = last_layers {
-                graphics::damage::diff(
-                    &last_layers,
-                    layers,
-                    |layer| vec![layer.bounds],
-                    iced_tiny_skia::Layer::damage,
-                )
-            } else {
-                let [width, height] = buffer.viewport.surface_size.map(|x| x as _);
-                let bounds = Rectangle::with_size(Size { width, height });
-                vec![bounds]
-            };
-            *last_layers = Some(layers.to_vec());
+            ) => {
+                let layers = renderer.layers();
+                let diff = |old: &Option<Vec<iced_tiny_skia::Layer>>| match old {
+                    Some(old) => graphics::damage::diff(
+                        old,
+                        layers,
+                        |layer| vec![layer.bounds],
+                        iced_tiny_skia::Layer::damage,
+                    ),
+                    None => {
+                        let [width, height] = viewport.surface_size.map(|x| x as _);
+                        vec![Rectangle::with_size(Size { width, height })]
+                    }
+                };
+                // what the compositor has to repaint, against the last commit
+                let mut damage = diff(last_layers);
+                // the surface keeps showing the same pixels, no need to commit
+                if damage.is_empty() {
+                    return;
+                }
+                *last_layers = Some(layers.to_vec());
+                let idx = buffers.acquire(&runner.wayland.globals, viewport);
+                // what this buffer misses, against whatever frame it held before
+                let mut stale = diff(&buffers.buffers[idx].layers);
+                buffers.buffers[idx].layers = last_layers.clone();
 
-            for rect in &mut damage {
-                rect.width = rect.width.ceil();
-                rect.height = rect.height.ceil();
-            }
+                for rect in damage.iter_mut().chain(&mut stale) {
+                    rect.width = rect.width.ceil();
+                    rect.height = rect.height.ceil();
+                }
 
-            self.renderer.draw(
-                &mut buffer.pixels(),
-                clip_mask,
-                &buffer.viewport.to_iced_viewport(),
-                &damage,
-                runner.background(tag),
-            );
-
-            let surface = surface.as_ptr();
-            unsafe {
-                wayland::ffi::wl_surface_attach(surface, buffer.buffer.as_ptr(), 0, 0);
-                // let [width, height] = buffer.viewport.buffer_size().map(|x| x as _);
-                // wayland::ffi::wl_surface_damage_buffer(surface, 0, 0, width, height);
-                for rect in damage {
-                    wayland::ffi::wl_surface_damage(
-                        surface,
-                        rect.x as _,
-                        rect.y as _,
-                        rect.width as _,
-                        rect.height as _,
-                    );
+                match upright {
+                    // the upright frame is always whole, what the buffer misses is turned
+                    // out of it
+                    Some(upright) => {
+                        renderer.draw(
+                            &mut upright.as_mut(),
+                            clip_mask,
+                            &viewport.to_iced_viewport(),
+                            &damage,
+                            runner.background(tag),
+                        );
+                        let rects = damage.iter().chain(&stale);
+                        turn(upright, &mut buffers.pixels(idx), rects, viewport);
+                    }
+                    None => {
+                        // outside of the damage the front buffer is right already, copying is
+                        // much cheaper than rasterizing the unchanged widgets again
+                        let repaint = if buffers.copy_from_front(idx, &stale, viewport) {
+                            &damage
+                        } else {
+                            &stale
+                        };
+                        renderer.draw(
+                            &mut buffers.pixels(idx),
+                            clip_mask,
+                            &viewport.to_iced_viewport(),
+                            repaint,
+                            runner.background(tag),
+                        );
+                    }
                 }
-                wayland::ffi::wl_surface_commit(surface);
-                wayland::ffi::wl_display_flush(runner.display.as_ptr());
+                draw = Some(start.elapsed());
+
+                buffers.attach(idx, surface);
+                let surface = surface.as_ptr();
+                unsafe {
+                    for rect in damage {
+                        wayland::ffi::wl_surface_damage(
+                            surface,
+                            rect.x as _,
+                            rect.y as _,
+                            rect.width as _,
+                            rect.height as _,
+                        );
+                    }
+                    wayland::ffi::wl_surface_commit(surface);
+                }
+            }
+            // presenting commits the surface
+            (Renderer::Primary(renderer), Target::Gpu(target)) => {
+                target.present(renderer, viewport, runner.background(tag));
+                draw = Some(start.elapsed());
             }
+            _ => unreachable!("the target always follows the renderer"),
+        }
+        unsafe { wayland::ffi::wl_display_flush(runner.display.as_ptr()) };
+        if let (Some(hud), Some(draw)) = (&mut self.hud, draw) {
+            hud.timings = Timings {
+                layout,
+                draw: draw - layout,
+                commit: start.elapsed() - draw,
+            };
         }
     }
 }
@@ -285,21 +432,25 @@ impl State {
 #[derive(Clone)]
 pub struct Window(Rc<Inner>);
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Tag {
-    Bar,
+    /// of the profile at the index in `bars`
+    Bar(usize),
     Tooltip,
 }
 
+/// The state holds the render target, it has to go before the surface.
 pub struct Inner {
-    surface: OwnedSurface,
-    pub tag: Tag,
     pub state: RefCell<State>,
+    surface: Surface,
+    pub tag: Tag,
+    /// A `wl_surface_frame` callback is out, it draws whatever the state is when it fires.
+    frame_pending: Cell<bool>,
 }
 
 impl Inner {
     pub fn surface(&self) -> &Surface {
-        &self.surface.0
+        &self.surface
     }
 }
 
@@ -312,48 +463,83 @@ impl Deref for Window {
 
 impl fmt::Debug for Window {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{:?}", self.surface.0.surface)
+        write!(f, "{:?}", self.surface.surface)
     }
 }
 
 #[derive(Default)]
 pub struct WindowManager {
     lut: FxHashMap<NonNull<c_void>, Window>,
-    pub focused: Option<WlSurface>,
+    /// each with the window under its pointer
+    pub seats: Vec<Seat>,
+    /// of the latest input, hovering and menus follow its pointer
+    pub current: Option<WlSeat>,
 }
 
 impl WindowManager {
-    pub fn create_window(
-        &mut self,
-        surface: WlSurface,
-        role: Role,
-        tag: Tag,
-        renderer: Renderer,
-    ) -> &mut Window {
-        let window = Window::new(Surface { surface, role }, tag, renderer);
-        window.surface.0.role.key(|k| {
+    pub fn create_window(&mut self, surface: Surface, tag: Tag, renderer: Renderer) -> &mut Window {
+        let key = surface.wl_surface().cast();
+        let window = Window::new(surface, tag, renderer);
+        window.surface.role.key(|k| {
             self.lut.try_insert(k, window.clone()).unwrap();
         });
-        self.lut.try_insert(surface.cast(), window).unwrap()
+        self.lut.try_insert(key, window).unwrap()
     }
     pub fn close_window(&mut self, surface: &Surface) {
-        if Some(surface.surface) == self.focused {
-            self.focused.take();
+        for seat in &mut self.seats {
+            if Some(surface.wl_surface()) == seat.focused {
+                seat.focused.take();
+            }
         }
         surface.role.key(|k| {
             self.lut.remove(&k).unwrap();
         });
-        self.lut.remove(&surface.surface.cast());
+        self.lut.remove(&surface.wl_surface().cast());
     }
     pub fn find_by_object<T>(&self, obj: NonNull<T>) -> Option<&Window> {
         self.lut.get(&obj.cast())
     }
+    pub fn seat(&self, seat: WlSeat) -> Option<&Seat> {
+        self.seats.iter().find(|x| x.seat.as_non_null() == seat)
+    }
+    pub fn seat_mut(&mut self, seat: WlSeat) -> Option<&mut Seat> {
+        self.seats.iter_mut().find(|x| x.seat.as_non_null() == seat)
+    }
+    /// The surface under the pointer of the current seat.
+    pub fn focused_surface(&self) -> Option<WlSurface> {
+        self.seat(self.current?)?.focused
+    }
     pub fn focused(&self) -> Option<&Window> {
-        self.lut.get(&self.focused?.cast())
+        self.lut.get(&self.focused_surface()?.cast())
+    }
+    /// Forgets `seat`, returns the surface its pointer was on.
+    pub fn remove_seat(&mut self, seat: WlSeat) -> Option<WlSurface> {
+        if self.current == Some(seat) {
+            self.current = None;
+        }
+        let index = self
+            .seats
+            .iter()
+            .position(|x| x.seat.as_non_null() == seat)?;
+        self.seats.remove(index).focused
+    }
+    /// Drops the seats with their devices too.
+    pub fn clear(&mut self) {
+        self.current.take();
+        self.seats.clear();
+        self.lut.clear();
     }
     pub fn iter(&self) -> impl Iterator<Item = &Window> {
         self.lut.values()
     }
+    /// Each window with `tag` once, unlike [`WindowManager::iter`] which goes by every key.
+    /// Of every profile.
+    pub fn bars(&self) -> impl Iterator<Item = &Window> {
+        self.lut
+            .iter()
+            .filter(|(k, w)| matches!(w.tag, Tag::Bar(_)) && **k == w.surface.wl_surface().cast())
+            .map(|(_, w)| w)
+    }
 }
 
 impl<T> Index<NonNull<T>> for WindowManager {
@@ -366,50 +552,43 @@ impl<T> Index<NonNull<T>> for WindowManager {
 impl Window {
     fn new(surface: Surface, tag: Tag, renderer: Renderer) -> Self {
         Self(Rc::new(Inner {
-            surface: OwnedSurface(surface),
+            surface,
             tag,
+            frame_pending: Cell::new(false),
             state: RefCell::new(State {
                 cursor: Cursor::Unavailable,
                 serial: None,
                 shape: None,
                 config_state: ConfigState::default(),
                 renderer,
+                hud: None,
             }),
         }))
     }
     pub fn resize(&self, size: [u32; 2], runner: &mut Runner) {
         let mut window = self.state.borrow_mut();
-        window.resize(size, self.surface.0.surface, self.tag, runner);
-        self.request_redraw(&mut runner.wayland.notifier, &mut runner.callbacks);
+        window.resize(size, self.surface.wl_surface(), self.tag, runner);
+        self.request_redraw(&mut runner.wayland, &mut runner.callbacks);
         unsafe { wayland::ffi::wl_display_flush(runner.display.as_ptr()) };
     }
 
     pub fn rescale(&self, scale: u32, runner: &mut Runner) {
         let mut window = self.state.borrow_mut();
-        let surface = self.surface.0.surface;
+        let surface = self.surface.wl_surface();
         match &mut window.config_state {
             ConfigState::Configured {
-                buffer,
-                clip_mask,
-                last_layers,
-                ..
+                viewport, target, ..
             } => {
                 // for hyprland sends scale event when scale does not change
-                if buffer.viewport.buffer_scale == scale {
+                if viewport.buffer_scale == scale {
                     return;
                 }
-                *buffer = runner
-                    .wayland
-                    .globals
-                    .create_buffer(buffer.viewport.with_buffer_scale(scale));
-
-                let [width, height] = buffer.viewport.buffer_size();
-                *clip_mask = Mask::new(width, height).unwrap();
-                *last_layers = None;
+                *viewport = viewport.with_buffer_scale(scale);
+                target.reconfigure(*viewport);
 
-                self.request_redraw(&mut runner.wayland.notifier, &mut runner.callbacks);
+                self.request_redraw(&mut runner.wayland, &mut runner.callbacks);
             }
-            ConfigState::Unconfigured { scale_factor } => {
+            ConfigState::Unconfigured { scale_factor, .. } => {
                 *scale_factor = scale;
             }
         }
@@ -417,16 +596,50 @@ impl Window {
         unsafe { wayland::ffi::wl_display_flush(runner.display.as_ptr()) };
     }
 
-    pub fn request_redraw(&self, notifier: &mut UnboundedSender<Event>, callbacks: &mut Callbacks) {
-        let surface = self.surface.0.surface.as_ptr();
-        let callback = unsafe { wayland::ffi::wl_surface_frame(surface) };
-        unsafe {
-            wayland::ffi::wl_callback_add_listener(
-                callback,
-                &wayland::CALLBACK_LISTENER,
-                notifier as *mut _ as _,
-            )
-        };
+    /// The buffer scale, the one announced before the first configure until then.
+    pub fn scale(&self) -> u32 {
+        match &self.state.borrow().config_state {
+            ConfigState::Configured { viewport, .. } => viewport.buffer_scale,
+            ConfigState::Unconfigured { scale_factor, .. } => *scale_factor,
+        }
+    }
+
+    pub fn retransform(&self, transform: wayland::ffi::wl_output_transform, runner: &mut Runner) {
+        let mut window = self.state.borrow_mut();
+        let surface = self.surface.wl_surface();
+        match &mut window.config_state {
+            ConfigState::Configured {
+                viewport, target, ..
+            } => {
+                // the gpu only presents upright
+                if viewport.buffer_transform == transform || matches!(target, Target::Gpu(_)) {
+                    return;
+                }
+                *viewport = viewport.with_buffer_transform(transform);
+                target.reconfigure(*viewport);
+
+                self.request_redraw(&mut runner.wayland, &mut runner.callbacks);
+            }
+            ConfigState::Unconfigured {
+                transform: pending, ..
+            } => {
+                // set along with the first buffer, once the target is known
+                *pending = transform;
+                return;
+            }
+        }
+        unsafe { wayland::ffi::wl_surface_set_buffer_transform(surface.as_ptr(), transform as _) }
+        unsafe { wayland::ffi::wl_display_flush(runner.display.as_ptr()) };
+    }
+
+    /// Schedules a redraw for the next frame, requests made until then share it.
+    pub fn request_redraw(&self, wayland: &mut wayland::Proxy, callbacks: &mut Callbacks) {
+        if self.frame_pending.replace(true) {
+            return;
+        }
+        let surface = self.surface.surface.as_ptr();
+        let callback = Callback::from_raw(unsafe { wayland::ffi::wl_surface_frame(surface) });
+        wayland.listen(&callback, &wayland::CALLBACK_LISTENER);
         unsafe {
             wayland::ffi::wl_surface_commit(surface);
         };
@@ -434,20 +647,22 @@ impl Window {
         let window = self.clone();
         callbacks
             .try_insert(
-                Callback::from_raw(callback),
+                callback,
                 Box::new(move |runner| {
-                    window
-                        .state
-                        .borrow_mut()
-                        .redraw(window.surface.0.surface, window.tag, runner)
+                    window.frame_pending.set(false);
+                    window.state.borrow_mut().redraw(
+                        window.surface.wl_surface(),
+                        window.tag,
+                        runner,
+                    )
                 }),
             )
             .map_err(|e| e.entry.remove_entry().0)
             .unwrap();
     }
 
-    pub fn enter(&self, serial: u32) {
-        self.state.borrow_mut().serial = Some(serial);
+    pub fn enter(&self, seat: WlSeat, serial: u32) {
+        self.state.borrow_mut().serial = Some((seat, serial));
     }
 
     pub async fn mouse(&self, event: mouse::Event, runner: &mut Runner) {
@@ -458,12 +673,14 @@ impl Window {
                 ref mut shape,
                 ref mut config_state,
                 ref mut renderer,
+                hud: _,
             } = *self.state.borrow_mut();
 
             let mut messages = vec![];
+            let mut clipboard = Clipboard::new(runner);
 
-            if let ConfigState::Configured { ui, buffer, .. } = config_state {
-                let [width, height] = buffer.viewport.buffer_size();
+            if let ConfigState::Configured { ui, viewport, .. } = config_state {
+                let [width, height] = viewport.buffer_size();
                 match event {
                     mouse::Event::CursorMoved { position } => {
                         *cursor = Cursor::Available(position);
@@ -490,9 +707,10 @@ impl Window {
                     &[iced::Event::Mouse(event)],
                     *cursor,
                     renderer,
-                    &mut Clipboard,
+                    &mut clipboard,
                     &mut messages,
                 );
+                messages.extend(clipboard.copied.map(Message::Copy));
                 if let iced_runtime::user_interface::State::Updated {
                     mouse_interaction,
                     redraw_request,
@@ -500,28 +718,18 @@ impl Window {
                 } = state
                 {
                     if let RedrawRequest::NextFrame = redraw_request {
-                        self.request_redraw(&mut runner.wayland.notifier, &mut runner.callbacks);
+                        self.request_redraw(&mut runner.wayland, &mut runner.callbacks);
                     }
-                    if let Some(serial) = *serial
+                    if let Some((seat, serial)) = *serial
+                        && let Some(pointer) = runner
+                            .window_manager
+                            .seat(seat)
+                            .and_then(|x| x.pointer.as_ref())
                         && Some(mouse_interaction) != *shape
                     {
                         match cursor_shape(mouse_interaction) {
-                            CursorShape::Shape(shape) => unsafe {
-                                wayland::ffi::wp_cursor_shape_device_v1_set_shape(
-                                    runner.cursor_shape_device.as_ptr(),
-                                    serial,
-                                    shape,
-                                )
-                            },
-                            CursorShape::Hide => unsafe {
-                                wayland::ffi::wl_pointer_set_cursor(
-                                    runner.pointer.as_ptr(),
-                                    serial,
-                                    ptr::null_mut(),
-                                    0,
-                                    0,
-                                );
-                            },
+                            CursorShape::Shape(shape) => pointer.set_shape(serial, shape),
+                            CursorShape::Hide => pointer.hide(serial),
                         }
                     }
                 }
@@ -553,86 +761,206 @@ impl Operation for Leave {
     }
 }
 
-pub struct Buffer {
-    pub buffer: NonNull<wayland::ffi::wl_buffer>,
-    pub viewport: Viewport,
-    pub ptr: NonNull<u8>,
+/// Every frame draws into a buffer the compositor is done with, so it never reads a half
+/// drawn one. Usually two of them, a third when the compositor holds on to the others.
+#[derive(Default)]
+pub struct Buffers {
+    buffers: Vec<Buffer>,
+    /// the one attached last, it holds the complete last frame
+    front: Option<usize>,
+    /// kept across resizes, buffers are carved out of it
+    pool: Option<ShmPool>,
 }
 
-impl Buffer {
-    fn pixels(&self) -> tiny_skia::PixmapMut<'_> {
-        PixmapMut::from_bytes(
-            self.data(),
-            self.viewport.buffer_width(),
-            self.viewport.buffer_height(),
-        )
-        .unwrap()
+impl Buffers {
+    /// Drops the buffers of the old size, the pool stays.
+    fn clear(&mut self) {
+        self.buffers.clear();
+        self.front = None;
     }
-    fn data(&self) -> &mut [u8] {
-        unsafe {
-            std::slice::from_raw_parts_mut(self.ptr.as_ptr(), self.viewport.buffer_byte_size())
+    fn acquire(&mut self, globals: &wayland::Globals, viewport: Viewport) -> usize {
+        if let Some(idx) = self.buffers.iter().position(|x| !x.busy.get()) {
+            return idx;
         }
+        let len = viewport.buffer_byte_size();
+        let offset = self.buffers.len() * len;
+        // room for two right away, that is what it usually settles at
+        let pool = self
+            .pool
+            .get_or_insert_with(|| ShmPool::new(globals, 2 * len));
+        pool.grow(offset + len);
+        self.buffers.push(pool.create_buffer(offset, viewport));
+        self.buffers.len() - 1
+    }
+    fn pixels(&self, idx: usize) -> PixmapMut<'_> {
+        self.pool.as_ref().unwrap().pixels(&self.buffers[idx])
+    }
+    fn attach(&mut self, idx: usize, surface: WlSurface) {
+        self.buffers[idx].attach(surface);
+        self.front = Some(idx);
+    }
+    /// Brings `rects` of buffer `idx` up to date by copying the pixels of the front buffer,
+    /// which has them rasterized already. False if there is nothing to copy from.
+    fn copy_from_front(&self, idx: usize, rects: &[Rectangle], viewport: Viewport) -> bool {
+        let Some(front) = self.front else {
+            return false;
+        };
+        if front == idx || rects.is_empty() {
+            return true;
+        }
+        let pool = self.pool.as_ref().unwrap();
+        let [width, height] = viewport.buffer_size().map(|x| x as usize);
+        let scale = viewport.buffer_scale as f32;
+        let clamp = |x: f32, max| (x.max(0.0) as usize).min(max);
+        let [src, dst] =
+            [front, idx].map(|x| unsafe { pool.ptr.as_ptr().add(self.buffers[x].offset) });
+        for rect in rects {
+            let [x0, x1] =
+                [rect.x.floor(), (rect.x + rect.width).ceil()].map(|x| clamp(x * scale, width));
+            let [y0, y1] =
+                [rect.y.floor(), (rect.y + rect.height).ceil()].map(|y| clamp(y * scale, height));
+            for y in y0..y1 {
+                let at = (y * width + x0) * 4;
+                let len = x1.saturating_sub(x0) * 4;
+                unsafe { ptr::copy_nonoverlapping(src.add(at), dst.add(at), len) };
+            }
+        }
+        true
     }
 }
 
-impl Drop for Buffer {
-    fn drop(&mut self) {
-        unsafe {
-            wayland::ffi::wl_buffer_destroy(self.buffer.as_ptr());
-            rustix::mm::munmap(self.ptr.as_ptr() as _, self.viewport.buffer_byte_size()).unwrap();
-        }
+pub struct Buffer {
+    pub buffer: OwnedObject<wayland::ffi::wl_buffer>,
+    pub viewport: Viewport,
+    offset: usize,
+    /// attached and not released yet, flipped back by [`wayland::BUFFER_LISTENER`]
+    busy: Box<Cell<bool>>,
+    /// the frame the pixels currently show
+    layers: Option<Vec<iced_tiny_skia::Layer>>,
+}
+
+impl Buffer {
+    fn attach(&self, surface: WlSurface) {
+        self.busy.set(true);
+        unsafe { wayland::ffi::wl_surface_attach(surface.as_ptr(), self.buffer.as_ptr(), 0, 0) };
     }
 }
 
-impl wayland::Globals {
-    fn create_buffer(&self, viewport: Viewport) -> Buffer {
-        let len = viewport.buffer_byte_size();
-        let fd = rustix::fs::memfd_create(c"", MemfdFlags::empty()).unwrap();
-        rustix::fs::ftruncate(&fd, len as _).unwrap();
+/// A memfd shared with the compositor, it only ever grows.
+struct ShmPool {
+    pool: OwnedObject<wayland::ffi::wl_shm_pool>,
+    fd: OwnedFd,
+    ptr: NonNull<u8>,
+    len: usize,
+}
 
-        let ptr = unsafe {
-            rustix::mm::mmap(
-                ptr::null_mut(),
-                len,
-                ProtFlags::READ | ProtFlags::WRITE,
-                MapFlags::SHARED,
-                &fd,
-                0,
-            )
+impl ShmPool {
+    fn new(globals: &wayland::Globals, len: usize) -> Self {
+        let fd = rustix::fs::memfd_create(c"hyoka", MemfdFlags::CLOEXEC).unwrap();
+        rustix::fs::ftruncate(&fd, len as _).unwrap();
+        let pool = OwnedObject::from_raw(unsafe {
+            wayland::ffi::wl_shm_create_pool(globals.shm(), fd.as_raw_fd(), len as _)
+        });
+        Self {
+            ptr: map(&fd, len),
+            pool,
+            fd,
+            len,
         }
-        .unwrap() as _;
-
-        let pool =
-            unsafe { wayland::ffi::wl_shm_create_pool(self.shm(), fd.as_raw_fd(), len as _) };
-        let buffer = unsafe {
+    }
+    fn grow(&mut self, len: usize) {
+        if len <= self.len {
+            return;
+        }
+        rustix::fs::ftruncate(&self.fd, len as _).unwrap();
+        unsafe {
+            wayland::ffi::wl_shm_pool_resize(self.pool.as_ptr(), len as _);
+            rustix::mm::munmap(self.ptr.as_ptr() as _, self.len).unwrap();
+        }
+        self.ptr = map(&self.fd, len);
+        self.len = len;
+    }
+    fn create_buffer(&self, offset: usize, viewport: Viewport) -> Buffer {
+        let buffer = OwnedObject::from_raw(unsafe {
             wayland::ffi::wl_shm_pool_create_buffer(
-                pool,
-                0,
+                self.pool.as_ptr(),
+                offset as _,
                 viewport.buffer_width() as _,
                 viewport.buffer_height() as _,
                 viewport.buffer_width() as i32 * 4,
                 wayland::ffi::WL_SHM_FORMAT_ARGB8888,
             )
-        };
-        unsafe { wayland::ffi::wl_shm_pool_destroy(pool) };
+        });
+        let busy = Box::new(Cell::new(false));
+        unsafe { buffer.add_listener(&wayland::BUFFER_LISTENER, ptr::from_ref(&*busy) as _) };
         Buffer {
-            buffer: NonNull::new(buffer).unwrap(),
+            buffer,
             viewport,
-            ptr: NonNull::new(ptr).unwrap(),
+            offset,
+            busy,
+            layers: None,
         }
     }
+    fn pixels(&self, buffer: &Buffer) -> PixmapMut<'_> {
+        let viewport = buffer.viewport;
+        let data = unsafe {
+            std::slice::from_raw_parts_mut(
+                self.ptr.as_ptr().add(buffer.offset),
+                viewport.buffer_byte_size(),
+            )
+        };
+        PixmapMut::from_bytes(data, viewport.buffer_width(), viewport.buffer_height()).unwrap()
+    }
+}
+
+impl Drop for ShmPool {
+    fn drop(&mut self) {
+        unsafe { rustix::mm::munmap(self.ptr.as_ptr() as _, self.len).unwrap() };
+    }
+}
+
+fn map(fd: &OwnedFd, len: usize) -> NonNull<u8> {
+    let ptr = unsafe {
+        rustix::mm::mmap(
+            ptr::null_mut(),
+            len,
+            ProtFlags::READ | ProtFlags::WRITE,
+            MapFlags::SHARED,
+            fd,
+            0,
+        )
+    }
+    .unwrap();
+    NonNull::new(ptr.cast()).unwrap()
+}
+
+/// Lends widgets the selection as last read, what they copy comes back as [`Message::Copy`].
+struct Clipboard {
+    text: Option<Rc<str>>,
+    copied: Option<String>,
 }
 
-struct Clipboard;
+impl Clipboard {
+    fn new(runner: &Runner) -> Self {
+        Self {
+            text: runner.clipboard.as_ref().and_then(|x| x.text()),
+            copied: None,
+        }
+    }
+}
 
 impl clipboard::Clipboard for Clipboard {
     fn read(&self, kind: clipboard::Kind) -> Option<String> {
-        _ = kind;
-        None
+        match kind {
+            clipboard::Kind::Standard => self.text.as_deref().map(ToOwned::to_owned),
+            clipboard::Kind::Primary => None,
+        }
     }
 
     fn write(&mut self, kind: clipboard::Kind, contents: String) {
-        _ = (kind, contents);
+        if let clipboard::Kind::Standard = kind {
+            self.copied = Some(contents);
+        }
     }
 }
 
@@ -640,7 +968,7 @@ impl clipboard::Clipboard for Clipboard {
 pub struct Viewport {
     surface_size: [u32; 2],
     buffer_scale: u32,
-    _buffer_transform: wayland::ffi::wl_output_transform,
+    buffer_transform: wayland::ffi::wl_output_transform,
 }
 
 impl Viewport {
@@ -656,28 +984,101 @@ impl Viewport {
             ..self
         }
     }
+    fn with_buffer_transform(self, buffer_transform: wayland::ffi::wl_output_transform) -> Self {
+        Self {
+            buffer_transform,
+            ..self
+        }
+    }
     fn buffer_width(&self) -> u32 {
-        self.surface_size[0] * self.buffer_scale
+        self.buffer_size()[0]
     }
     fn buffer_height(&self) -> u32 {
-        self.surface_size[1] * self.buffer_scale
+        self.buffer_size()[1]
     }
-    fn buffer_size(&self) -> [u32; 2] {
+    /// What iced draws, before the transform.
+    fn pixel_size(&self) -> [u32; 2] {
         self.surface_size.map(|x| x * self.buffer_scale)
     }
+    /// Width and height swap on outputs turned sideways.
+    pub fn buffer_size(&self) -> [u32; 2] {
+        let [width, height] = self.pixel_size();
+        match self.buffer_transform {
+            wayland::ffi::WL_OUTPUT_TRANSFORM_90
+            | wayland::ffi::WL_OUTPUT_TRANSFORM_270
+            | wayland::ffi::WL_OUTPUT_TRANSFORM_FLIPPED_90
+            | wayland::ffi::WL_OUTPUT_TRANSFORM_FLIPPED_270 => [height, width],
+            _ => [width, height],
+        }
+    }
     fn buffer_byte_size(&self) -> usize {
         let [width, height] = self.buffer_size().map(|x| x as usize);
         width * height * 4
     }
-    fn to_iced_viewport(&self) -> graphics::Viewport {
-        let [width, height] = self.buffer_size();
+    pub fn to_iced_viewport(&self) -> graphics::Viewport {
+        let [width, height] = self.pixel_size();
         graphics::Viewport::with_physical_size(Size { width, height }, self.buffer_scale as _)
     }
     fn mask(&self) -> Mask {
-        Mask::new(self.buffer_width(), self.buffer_height()).unwrap()
+        let [width, height] = self.pixel_size();
+        Mask::new(width, height).unwrap()
+    }
+    /// Where to draw before turning into the buffers, none without a transform.
+    fn upright(&self) -> Option<Pixmap> {
+        let [width, height] = self.pixel_size();
+        (self.buffer_transform != wayland::ffi::WL_OUTPUT_TRANSFORM_NORMAL)
+            .then(|| Pixmap::new(width, height).unwrap())
+    }
+}
+
+/// Copies `rects` of the upright frame into `buffer`, turned as the transform of `viewport` says.
+fn turn<'a>(
+    upright: &Pixmap,
+    buffer: &mut PixmapMut,
+    rects: impl IntoIterator<Item = &'a Rectangle>,
+    viewport: Viewport,
+) {
+    let size @ [width, height] = viewport.pixel_size().map(|x| x as usize);
+    let buffer_width = viewport.buffer_width() as usize;
+    let scale = viewport.buffer_scale as f32;
+    let clamp = |x: f32, max| (x.max(0.0) as usize).min(max);
+    let (src, dst) = (upright.data(), buffer.data_mut());
+    for rect in rects {
+        let [x0, x1] =
+            [rect.x.floor(), (rect.x + rect.width).ceil()].map(|x| clamp(x * scale, width));
+        let [y0, y1] =
+            [rect.y.floor(), (rect.y + rect.height).ceil()].map(|y| clamp(y * scale, height));
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let [bx, by] = transformed(viewport.buffer_transform, size, [x, y]);
+                let [from, to] = [y * width + x, by * buffer_width + bx].map(|i| i * 4);
+                dst[to..to + 4].copy_from_slice(&src[from..from + 4]);
+            }
+        }
+    }
+}
+
+/// Where pixel `[x, y]` of an upright frame of `size` goes in a buffer with `transform`, the
+/// compositor undoes it as weston's `weston_transformed_coord` does.
+fn transformed(
+    transform: wayland::ffi::wl_output_transform,
+    [width, height]: [usize; 2],
+    [x, y]: [usize; 2],
+) -> [usize; 2] {
+    let [right, bottom] = [width - 1 - x, height - 1 - y];
+    match transform {
+        wayland::ffi::WL_OUTPUT_TRANSFORM_90 => [bottom, x],
+        wayland::ffi::WL_OUTPUT_TRANSFORM_180 => [right, bottom],
+        wayland::ffi::WL_OUTPUT_TRANSFORM_270 => [y, right],
+        wayland::ffi::WL_OUTPUT_TRANSFORM_FLIPPED => [right, y],
+        wayland::ffi::WL_OUTPUT_TRANSFORM_FLIPPED_90 => [bottom, right],
+        wayland::ffi::WL_OUTPUT_TRANSFORM_FLIPPED_180 => [x, bottom],
+        wayland::ffi::WL_OUTPUT_TRANSFORM_FLIPPED_270 => [y, x],
+        _ => [x, y],
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum CursorShape {
     Shape(wayland::ffi::wp_cursor_shape_device_v1_shape),
     Hide,
@@ -685,11 +1086,11 @@ enum CursorShape {
 
 fn cursor_shape(interaction: Interaction) -> CursorShape {
     match interaction {
-        Interaction::None => {
+        // idle is what iced gives over what does not react to the cursor, like none
+        Interaction::None | Interaction::Idle => {
             CursorShape::Shape(wayland::ffi::WP_CURSOR_SHAPE_DEVICE_V1_SHAPE_DEFAULT)
         }
         Interaction::Hidden => CursorShape::Hide,
-        Interaction::Idle => todo!(), // what is idle ?
         Interaction::ContextMenu => {
             CursorShape::Shape(wayland::ffi::WP_CURSOR_SHAPE_DEVICE_V1_SHAPE_CONTEXT_MENU)
         }
@@ -721,10 +1122,18 @@ fn cursor_shape(interaction: Interaction) -> CursorShape {
         Interaction::Grabbing => {
             CursorShape::Shape(wayland::ffi::WP_CURSOR_SHAPE_DEVICE_V1_SHAPE_GRABBING)
         }
-        Interaction::ResizingHorizontally => todo!(),
-        Interaction::ResizingVertically => todo!(),
-        Interaction::ResizingDiagonallyUp => todo!(),
-        Interaction::ResizingDiagonallyDown => todo!(),
+        Interaction::ResizingHorizontally => {
+            CursorShape::Shape(wayland::ffi::WP_CURSOR_SHAPE_DEVICE_V1_SHAPE_EW_RESIZE)
+        }
+        Interaction::ResizingVertically => {
+            CursorShape::Shape(wayland::ffi::WP_CURSOR_SHAPE_DEVICE_V1_SHAPE_NS_RESIZE)
+        }
+        Interaction::ResizingDiagonallyUp => {
+            CursorShape::Shape(wayland::ffi::WP_CURSOR_SHAPE_DEVICE_V1_SHAPE_NESW_RESIZE)
+        }
+        Interaction::ResizingDiagonallyDown => {
+            CursorShape::Shape(wayland::ffi::WP_CURSOR_SHAPE_DEVICE_V1_SHAPE_NWSE_RESIZE)
+        }
         Interaction::ResizingColumn => {
             CursorShape::Shape(wayland::ffi::WP_CURSOR_SHAPE_DEVICE_V1_SHAPE_COL_RESIZE)
         }
@@ -742,3 +1151,6 @@ fn cursor_shape(interaction: Interaction) -> CursorShape {
         }
     }
 }
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,183 @@
+//! The cursor shown for every interaction iced reports, and frames turned into the buffers of
+//! rotated outputs.
+
+use super::*;
+
+/// Every variant, [`listed`] fails to build when iced adds one missing here.
+const ALL: [Interaction; 27] = [
+    Interaction::None,
+    Interaction::Hidden,
+    Interaction::Idle,
+    Interaction::ContextMenu,
+    Interaction::Help,
+    Interaction::Pointer,
+    Interaction::Progress,
+    Interaction::Wait,
+    Interaction::Cell,
+    Interaction::Crosshair,
+    Interaction::Text,
+    Interaction::Alias,
+    Interaction::Copy,
+    Interaction::Move,
+    Interaction::NoDrop,
+    Interaction::NotAllowed,
+    Interaction::Grab,
+    Interaction::Grabbing,
+    Interaction::ResizingHorizontally,
+    Interaction::ResizingVertically,
+    Interaction::ResizingDiagonallyUp,
+    Interaction::ResizingDiagonallyDown,
+    Interaction::ResizingColumn,
+    Interaction::ResizingRow,
+    Interaction::AllScroll,
+    Interaction::ZoomIn,
+    Interaction::ZoomOut,
+];
+
+/// Its place in [`ALL`], without a wildcard arm.
+fn listed(interaction: Interaction) -> usize {
+    match interaction {
+        Interaction::None => 0,
+        Interaction::Hidden => 1,
+        Interaction::Idle => 2,
+        Interaction::ContextMenu => 3,
+        Interaction::Help => 4,
+        Interaction::Pointer => 5,
+        Interaction::Progress => 6,
+        Interaction::Wait => 7,
+        Interaction::Cell => 8,
+        Interaction::Crosshair => 9,
+        Interaction::Text => 10,
+        Interaction::Alias => 11,
+        Interaction::Copy => 12,
+        Interaction::Move => 13,
+        Interaction::NoDrop => 14,
+        Interaction::NotAllowed => 15,
+        Interaction::Grab => 16,
+        Interaction::Grabbing => 17,
+        Interaction::ResizingHorizontally => 18,
+        Interaction::ResizingVertically => 19,
+        Interaction::ResizingDiagonallyUp => 20,
+        Interaction::ResizingDiagonallyDown => 21,
+        Interaction::ResizingColumn => 22,
+        Interaction::ResizingRow => 23,
+        Interaction::AllScroll => 24,
+        Interaction::ZoomIn => 25,
+        Interaction::ZoomOut => 26,
+    }
+}
+
+#[test]
+fn every_interaction_has_a_shape() {
+    for (i, interaction) in ALL.into_iter().enumerate() {
+        assert_eq!(listed(interaction), i, "{interaction:?} out of place");
+        let shape = cursor_shape(interaction);
+        assert_eq!(
+            shape == CursorShape::Hide,
+            interaction == Interaction::Hidden,
+            "{interaction:?}",
+        );
+    }
+}
+
+#[test]
+fn closest_shapes() {
+    use wayland::ffi::*;
+    for (interaction, shape) in [
+        (
+            Interaction::ResizingHorizontally,
+            WP_CURSOR_SHAPE_DEVICE_V1_SHAPE_EW_RESIZE,
+        ),
+        (
+            Interaction::ResizingVertically,
+            WP_CURSOR_SHAPE_DEVICE_V1_SHAPE_NS_RESIZE,
+        ),
+        (
+            Interaction::ResizingDiagonallyUp,
+            WP_CURSOR_SHAPE_DEVICE_V1_SHAPE_NESW_RESIZE,
+        ),
+        (
+            Interaction::ResizingDiagonallyDown,
+            WP_CURSOR_SHAPE_DEVICE_V1_SHAPE_NWSE_RESIZE,
+        ),
+        (Interaction::Idle, WP_CURSOR_SHAPE_DEVICE_V1_SHAPE_DEFAULT),
+    ] {
+        assert_eq!(
+            cursor_shape(interaction),
+            CursorShape::Shape(shape),
+            "{interaction:?}"
+        );
+    }
+}
+
+const TRANSFORMS: [wayland::ffi::wl_output_transform; 8] = [
+    wayland::ffi::WL_OUTPUT_TRANSFORM_NORMAL,
+    wayland::ffi::WL_OUTPUT_TRANSFORM_90,
+    wayland::ffi::WL_OUTPUT_TRANSFORM_180,
+    wayland::ffi::WL_OUTPUT_TRANSFORM_270,
+    wayland::ffi::WL_OUTPUT_TRANSFORM_FLIPPED,
+    wayland::ffi::WL_OUTPUT_TRANSFORM_FLIPPED_90,
+    wayland::ffi::WL_OUTPUT_TRANSFORM_FLIPPED_180,
+    wayland::ffi::WL_OUTPUT_TRANSFORM_FLIPPED_270,
+];
+
+fn viewport(transform: wayland::ffi::wl_output_transform) -> Viewport {
+    Viewport {
+        surface_size: [3, 2],
+        buffer_scale: 2,
+        buffer_transform: transform,
+    }
+}
+
+#[test]
+fn sideways_buffers_swap_sides() {
+    for transform in TRANSFORMS {
+        let viewport = viewport(transform);
+        let sideways = transform % 2 == 1;
+        let expected = if sideways { [4, 6] } else { [6, 4] };
+        assert_eq!(viewport.buffer_size(), expected, "{transform}");
+        // iced keeps drawing upright
+        assert_eq!(viewport.pixel_size(), [6, 4], "{transform}");
+        assert_eq!(viewport.upright().is_some(), transform != 0, "{transform}");
+    }
+}
+
+#[test]
+fn every_pixel_lands_once_in_the_buffer() {
+    for transform in TRANSFORMS {
+        let viewport = viewport(transform);
+        let size @ [width, height] = viewport.pixel_size().map(|x| x as usize);
+        let [buffer_width, buffer_height] = viewport.buffer_size().map(|x| x as usize);
+        let mut hit = vec![false; width * height];
+        for y in 0..height {
+            for x in 0..width {
+                let [bx, by] = transformed(transform, size, [x, y]);
+                assert!(bx < buffer_width && by < buffer_height, "{transform}");
+                assert!(!hit[by * buffer_width + bx], "{transform} hits twice");
+                hit[by * buffer_width + bx] = true;
+            }
+        }
+    }
+}
+
+#[test]
+fn top_left_corner() {
+    let size = [3, 2];
+    for (transform, expected) in [
+        (wayland::ffi::WL_OUTPUT_TRANSFORM_NORMAL, [0, 0]),
+        // turned a quarter clockwise into the buffer, the compositor turns it back
+        (wayland::ffi::WL_OUTPUT_TRANSFORM_90, [1, 0]),
+        (wayland::ffi::WL_OUTPUT_TRANSFORM_180, [2, 1]),
+        (wayland::ffi::WL_OUTPUT_TRANSFORM_270, [0, 2]),
+        (wayland::ffi::WL_OUTPUT_TRANSFORM_FLIPPED, [2, 0]),
+        (wayland::ffi::WL_OUTPUT_TRANSFORM_FLIPPED_90, [1, 2]),
+        (wayland::ffi::WL_OUTPUT_TRANSFORM_FLIPPED_180, [0, 1]),
+        (wayland::ffi::WL_OUTPUT_TRANSFORM_FLIPPED_270, [0, 0]),
+    ] {
+        assert_eq!(
+            transformed(transform, size, [0, 0]),
+            expected,
+            "{transform}"
+        );
+    }
+}
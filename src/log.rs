@@ -0,0 +1,60 @@
+//! Where tracing goes, set up once at startup from `[log]` in the config.
+
+use std::env;
+
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::{
+    EnvFilter, fmt::format::FmtSpan, layer::SubscriberExt as _, util::SubscriberInitExt as _,
+};
+
+use crate::config::{self, LogOutput};
+
+/// `filter` from the command line wins over `RUST_LOG`, which wins over the config.
+///
+/// Closed spans are logged with how long they took, wherever they are enabled.
+pub fn init(filter: Option<&str>, config: &config::Log) {
+    let directives = filter
+        .map(Into::into)
+        .or_else(|| env::var("RUST_LOG").ok())
+        .unwrap_or_else(|| config.filter.clone());
+    let (filter, invalid) = match EnvFilter::try_new(&directives) {
+        Ok(x) => (x, None),
+        Err(e) => (EnvFilter::new("info"), Some(e)),
+    };
+
+    let journal = match config.output {
+        LogOutput::Journal => tracing_journald::layer()
+            .inspect_err(|e| eprintln!("cannot log to journald, logging to stderr: {e}"))
+            .ok(),
+        _ => None,
+    };
+    let file = match config.output {
+        LogOutput::File => RollingFileAppender::builder()
+            .rotation(Rotation::DAILY)
+            .filename_prefix("hyoka")
+            .filename_suffix("log")
+            .max_log_files(config.keep.max(1))
+            .build(config::xdg_state_home().join("hyoka"))
+            .inspect_err(|e| eprintln!("cannot log to a file, logging to stderr: {e}"))
+            .ok(),
+        _ => None,
+    };
+    let stderr = (journal.is_none() && file.is_none())
+        .then(|| tracing_subscriber::fmt::layer().with_span_events(FmtSpan::CLOSE));
+    let file = file.map(|x| {
+        tracing_subscriber::fmt::layer()
+            .with_ansi(false)
+            .with_span_events(FmtSpan::CLOSE)
+            .with_writer(x)
+    });
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(journal)
+        .with(file)
+        .with(stderr)
+        .init();
+    if let Some(e) = invalid {
+        tracing::warn!("invalid log filter {directives:?}, using \"info\": {e}");
+    }
+}
@@ -11,13 +11,27 @@
     str_as_str
 )]
 
-use std::async_iter::AsyncIterator;
+use std::time::Duration;
 
 use compio::{driver::ProactorBuilder, runtime::Runtime};
-use smallstr::SmallString;
+use hyoka_core::{TinyString, error, mapping, stream, wayland};
 
 fn main() {
-    tracing_subscriber::fmt::init();
+    let args: Vec<_> = std::env::args().skip(1).collect();
+    if let Some((cmd, args)) = args.split_first()
+        && cmd == "msg"
+    {
+        std::process::exit(modules::ipc::send(args));
+    }
+    let filter = args
+        .iter()
+        .position(|x| x == "--log")
+        .and_then(|i| args.get(i + 1));
+    log::init(filter.map(String::as_str), &config::load().log);
+    let replace = args.iter().any(|x| x == "--replace");
+    let Some(_lock) = modules::ipc::lock(replace) else {
+        std::process::exit(1);
+    };
     let rt = Runtime::builder()
         .with_proactor({
             let mut builder = ProactorBuilder::new();
@@ -27,47 +41,27 @@ fn main() {
         })
         .build()
         .unwrap();
-    rt.block_on(consumer::run());
+    rt.block_on(async {
+        loop {
+            match consumer::run().await {
+                Ok(()) => break,
+                Err(e @ (wayland::Error::Connect(_) | wayland::Error::Unsupported(_))) => {
+                    tracing::error!("{e}");
+                    std::process::exit(1);
+                }
+                Err(e) => {
+                    tracing::error!("wayland connection failed: {e}, reconnecting");
+                    compio::time::sleep(Duration::from_secs(1)).await;
+                }
+            }
+        }
+    });
 }
 
+mod config;
 mod consumer;
-mod mapping;
+mod log;
 mod modules;
 mod program;
 mod shell;
-mod wayland;
-
-pub type TinyString = SmallString<[u8; 16]>;
-
-trait Split {
-    type R;
-    fn split(self) -> Self::R;
-}
-
-impl<X, Y> Split for Option<(X, Y)> {
-    type R = (Option<X>, Option<Y>);
-
-    fn split(self) -> Self::R {
-        match self {
-            Some((x, y)) => (Some(x), Some(y)),
-            None => (None, None),
-        }
-    }
-}
-
-pub fn stream<T: AsyncIterator>(iterator: T) -> impl futures::Stream<Item = T::Item> {
-    #[repr(transparent)]
-    struct Stream<T>(T);
-    impl<T: AsyncIterator> futures::Stream for Stream<T> {
-        type Item = <T as AsyncIterator>::Item;
-
-        fn poll_next(
-            self: std::pin::Pin<&mut Self>,
-            cx: &mut std::task::Context<'_>,
-        ) -> std::task::Poll<Option<Self::Item>> {
-            let inner: std::pin::Pin<&mut T> = unsafe { std::mem::transmute(self) };
-            AsyncIterator::poll_next(std::pin::pin!(inner), cx)
-        }
-    }
-    Stream(iterator)
-}
+mod spawn;
@@ -1,6 +1,20 @@
+pub mod audio;
 pub mod battery;
 pub mod clock;
+pub mod custom;
 pub mod dbus;
-pub mod hyprland;
-pub mod uevent;
+pub mod gpu;
+pub mod hwmon;
+pub mod ipc;
+pub mod network;
+pub mod palette;
+pub mod plugin;
 pub mod polling;
+pub mod procfs;
+pub mod reload;
+pub mod script;
+pub mod timer;
+pub mod uevent;
+pub mod updates;
+
+pub use hyoka_core::hyprland;
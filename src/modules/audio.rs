@@ -0,0 +1,73 @@
+use std::process::Stdio;
+
+use compio::{BufResult, io::AsyncRead as _, process::Command};
+
+use crate::error::{Error, Result};
+
+/// The default source, through the PulseAudio API, which PipeWire speaks as well.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Microphone {
+    pub muted: bool,
+    /// some app has a stream open on any source
+    pub recording: bool,
+}
+
+/// Reports the microphone now and after every change of sources or their streams, `None`
+/// while there is no default source.
+pub async fn run(mut dispatch: impl AsyncFnMut(Option<Microphone>)) -> Result<()> {
+    let mut command = Command::new("pactl");
+    command.arg("subscribe").stdout(Stdio::piped()).unwrap();
+    let mut child = command.spawn()?;
+    let mut stdout = child.stdout.take().unwrap();
+    dispatch(microphone().await).await;
+    let mut pending = Vec::new();
+    let mut buf = Vec::with_capacity(4096);
+    loop {
+        let n;
+        BufResult(n, buf) = stdout.read(buf).await;
+        if n? == 0 {
+            child.wait().await.ok();
+            return Err(Error::Closed("pactl"));
+        }
+        pending.extend_from_slice(&buf);
+        buf.clear();
+        // a burst of events, queried once
+        let mut changed = false;
+        while let Some(pos) = pending.iter().position(|&x| x == b'\n') {
+            let line: Vec<u8> = pending.drain(..=pos).collect();
+            changed |= concerns_microphone(&String::from_utf8_lossy(&line));
+        }
+        if changed {
+            dispatch(microphone().await).await;
+        }
+    }
+}
+
+/// `Event 'change' on source #55`, streams are `source-output`, the default source changes on
+/// the server.
+fn concerns_microphone(line: &str) -> bool {
+    line.contains(" on source") || line.contains(" on server")
+}
+
+async fn microphone() -> Option<Microphone> {
+    let mute = output(&["get-source-mute", "@DEFAULT_SOURCE@"]).await?;
+    let streams = output(&["list", "short", "source-outputs"]).await?;
+    Some(Microphone {
+        muted: mute.trim_end().ends_with("yes"),
+        recording: streams.lines().any(|x| !x.trim().is_empty()),
+    })
+}
+
+async fn output(args: &[&str]) -> Option<String> {
+    let output = Command::new("pactl")
+        .args(args)
+        .output()
+        .await
+        .inspect_err(|e| tracing::warn!("cannot run pactl {args:?}: {e}"))
+        .ok()?;
+    // without a default source
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).into_owned())
+}
@@ -1,9 +1,10 @@
 use std::{
     cell::OnceCell,
-    ffi::CStr,
+    ffi::{CStr, CString},
     fmt::Write as _,
     mem::MaybeUninit,
     os::fd::{AsFd, OwnedFd},
+    path::PathBuf,
 };
 
 use rustix::fs::{Mode, OFlags};
@@ -16,6 +17,35 @@ pub struct Info {
     power_now: u32,
     energy_now: u32,
     energy_full: u32,
+    pub limit: Option<ChargeLimit>,
+}
+
+/// Where the firmware stops charging, to spare a battery that mostly sits on AC.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChargeLimit {
+    /// `charge_control_end_threshold`, percent
+    Threshold(u8),
+    /// of Lenovo ideapads, which stop at about 60% while it is on
+    Conservation(bool),
+}
+
+impl ChargeLimit {
+    pub fn on(self) -> bool {
+        match self {
+            Self::Threshold(x) => x < 100,
+            Self::Conservation(x) => x,
+        }
+    }
+}
+
+const CONSERVATION_MODE: &str =
+    "/sys/bus/platform/drivers/ideapad_acpi/VPC2004:00/conservation_mode";
+
+/// What the bar shows, read whether or not a uevent told about it.
+#[derive(Debug, Clone, Copy)]
+pub struct Level {
+    pub capacity: u8,
+    pub status: Status,
 }
 
 #[derive(Default, Debug, PartialEq, Clone, Copy)]
@@ -39,6 +69,8 @@ impl Status {
 
 pub struct Battery {
     fd: OwnedFd,
+    /// under `/sys/class/power_supply`
+    name: CString,
     energy_full: OnceCell<u32>,
 }
 impl Battery {
@@ -63,6 +95,7 @@ impl Battery {
             if r#type.starts_with(b"Battery") {
                 return Some(Self {
                     fd: device,
+                    name: name.into(),
                     energy_full: OnceCell::new(),
                 });
             }
@@ -86,12 +119,45 @@ impl Battery {
             .energy_full
             .get_or_init(|| Attr::get(&self.fd, c"energy_full"))
     }
+    pub fn level(&self) -> Level {
+        Level {
+            capacity: self.capacity(),
+            status: self.status(),
+        }
+    }
     pub fn info(&self) -> Info {
         Info {
             status: self.status(),
             power_now: self.power_now(),
             energy_now: self.energy_now(),
             energy_full: self.energy_full(),
+            limit: self.charge_limit(),
+        }
+    }
+    /// `None` when neither the battery nor the laptop has a control for it.
+    pub fn charge_limit(&self) -> Option<ChargeLimit> {
+        let mut buf = [MaybeUninit::uninit(); 8];
+        let threshold = read(&self.fd, c"charge_control_end_threshold", &mut buf);
+        if let Ok(x) = u8::from_ascii(threshold) {
+            return Some(ChargeLimit::Threshold(x));
+        }
+        let mode = std::fs::read(CONSERVATION_MODE).ok()?;
+        Some(ChargeLimit::Conservation(mode.starts_with(b"1")))
+    }
+    /// The file turning the limit on or off, at `percent` where it takes one, and what to
+    /// write to it. Only root can, usually.
+    pub fn charge_limit_file(&self, on: bool, percent: u8) -> Option<(PathBuf, String)> {
+        match self.charge_limit()? {
+            ChargeLimit::Threshold(_) => {
+                let mut path = PathBuf::from("/sys/class/power_supply");
+                path.push(self.name.to_str().ok()?);
+                path.push("charge_control_end_threshold");
+                let percent = if on { percent.clamp(1, 100) } else { 100 };
+                Some((path, percent.to_string()))
+            }
+            ChargeLimit::Conservation(_) => {
+                Some((CONSERVATION_MODE.into(), u8::from(on).to_string()))
+            }
         }
     }
 }
@@ -100,33 +166,37 @@ trait Attr {
     fn get(dev: impl AsFd, path: &CStr) -> Self;
 }
 
+/// The attribute at `path` without its newline, empty when it cannot be read, like while the
+/// battery is being removed.
+fn read<'a>(dev: impl AsFd, path: &CStr, buf: &'a mut [MaybeUninit<u8>]) -> &'a [u8] {
+    let Ok(file) = rustix::fs::openat(dev, path, OFlags::empty(), Mode::empty()) else {
+        return &[];
+    };
+    let Ok((value, _)) = rustix::io::read(file, buf) else {
+        return &[];
+    };
+    let value: &[u8] = value;
+    value.strip_suffix(b"\n").unwrap_or(value)
+}
+
 impl Attr for u32 {
     fn get(dev: impl AsFd, path: &CStr) -> Self {
         let mut buf = [MaybeUninit::uninit(); 1024];
-        let value = rustix::fs::openat(dev, path, OFlags::empty(), Mode::empty()).unwrap();
-        let (value, _) = rustix::io::read(value, &mut buf).unwrap();
-        let value = u32::from_ascii(unsafe { value.get_unchecked(..value.len() - 1) }).unwrap();
-        value
+        u32::from_ascii(read(dev, path, &mut buf)).unwrap_or_default()
     }
 }
 
 impl Attr for u8 {
     fn get(dev: impl AsFd, path: &CStr) -> Self {
         let mut buf = [MaybeUninit::uninit(); 1024];
-        let value = rustix::fs::openat(dev, path, OFlags::empty(), Mode::empty()).unwrap();
-        let (value, _) = rustix::io::read(value, &mut buf).unwrap();
-        let value = u8::from_ascii(unsafe { value.get_unchecked(..value.len() - 1) }).unwrap();
-        value
+        u8::from_ascii(read(dev, path, &mut buf)).unwrap_or_default()
     }
 }
 
 impl Attr for Status {
     fn get(dev: impl AsFd, path: &CStr) -> Self {
         let mut buf = [MaybeUninit::uninit(); 1024];
-        let value = rustix::fs::openat(dev, path, OFlags::empty(), Mode::empty()).unwrap();
-        let (value, _) = rustix::io::read(value, &mut buf).unwrap();
-        let value = Status::from_bytes(unsafe { value.get_unchecked(..value.len() - 1) });
-        value
+        Status::from_bytes(read(dev, path, &mut buf))
     }
 }
 
@@ -160,6 +230,12 @@ impl Info {
         let m = self.minutes_remaining() % 60;
         let mut result = TinyString::new();
         write!(&mut result, "{cap}% {power}.{power_frac:0>2}W {h}h{m}").unwrap();
+        match self.limit {
+            Some(ChargeLimit::Threshold(x)) if x < 100 => write!(&mut result, " limit {x}%"),
+            Some(ChargeLimit::Conservation(true)) => write!(&mut result, " conservation"),
+            _ => Ok(()),
+        }
+        .unwrap();
         result
     }
 }
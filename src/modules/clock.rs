@@ -1,5 +1,12 @@
-use arrayvec::ArrayVec;
-use chrono::{DateTime, Datelike, Local, Timelike};
+use std::{array, env, fmt::Write as _};
+
+use chrono::{
+    DateTime, Datelike, Days, Local, Locale,
+    format::{Item, Numeric, StrftimeItems},
+};
+use chrono_tz::Tz;
+
+use crate::config::{self, Config};
 
 #[derive(Debug)]
 pub struct Clock {
@@ -18,48 +25,102 @@ impl Clock {
             date_time: Local::now(),
         }
     }
-    pub fn year(&self) -> [u8; 4] {
-        let year = self.date_time.year();
-        [year / 1000, (year / 100) % 10, (year / 10) % 10, year % 10].map(|x| x as u8 + b'0')
+    /// `format` in strftime syntax, `None` when it is malformed.
+    pub fn format(&self, format: &str, locale: Locale) -> Option<String> {
+        self.format_in(None, format, locale)
     }
-    pub fn month(&self) -> &'static str {
-        [
-            "JAN", "FÉV", "MAR", "AVR", "MAI", "JUN", "JUL", "AOU", "SÉP", "OCT", "NOV", "DÉC",
-        ][self.date_time.month0() as usize]
+    /// Like [`format`](Self::format) for the time in `timezone`, the local one without.
+    pub fn format_in(&self, timezone: Option<Tz>, format: &str, locale: Locale) -> Option<String> {
+        let mut s = String::new();
+        match timezone {
+            Some(tz) => write!(
+                s,
+                "{}",
+                self.date_time
+                    .with_timezone(&tz)
+                    .format_localized(format, locale)
+            ),
+            None => write!(s, "{}", self.date_time.format_localized(format, locale)),
+        }
+        .ok()?;
+        Some(s)
     }
-    pub fn day(&self) -> [u8; 2] {
-        let day = self.date_time.day();
-        [day / 10, day % 10].map(|x| x as u8 + b'0')
+    pub fn day_of_month(&self) -> u32 {
+        self.date_time.day()
     }
-    pub fn date(&self) -> ArrayVec<u8, 12> {
-        self.year()
-            .into_iter()
-            .chain([b' '])
-            .chain(self.month().bytes())
-            .chain([b' '])
-            .chain(self.day().into_iter())
-            .collect()
+    /// Abbreviated names of the days of the week, from Sunday.
+    pub fn weekdays(&self, locale: Locale) -> [String; 7] {
+        let date = self.date_time.date_naive();
+        let sunday = date - Days::new(date.weekday().num_days_from_sunday().into());
+        array::from_fn(|x| {
+            (sunday + Days::new(x as u64))
+                .format_localized("%a", locale)
+                .to_string()
+        })
     }
-    pub fn time(&self) -> [u8; 8] {
-        let [h, m, s] = [
-            self.date_time.hour(),
-            self.date_time.minute(),
-            self.date_time.second(),
-        ]
-        .map(|x| x as u8);
-        [
-            h / 10 + b'0',
-            h % 10 + b'0',
-            b':',
-            m / 10 + b'0',
-            m % 10 + b'0',
-            b':',
-            s / 10 + b'0',
-            s % 10 + b'0',
-        ]
+    /// The current month as weeks from Sunday, `None` for the days of the months around it.
+    pub fn weeks(&self) -> impl Iterator<Item = [Option<u32>; 7]> {
+        let date = self.date_time.date_naive();
+        let len = (29..=31)
+            .rev()
+            .find(|&x| date.with_day(x).is_some())
+            .unwrap_or(28);
+        let first = (self.date_time.weekday().num_days_from_sunday() + 36 - date.day()) % 7;
+        (0..(first + len).div_ceil(7)).map(move |week| {
+            array::from_fn(|x| {
+                (week * 7 + x as u32 + 1)
+                    .checked_sub(first)
+                    .filter(|day| (1..=len).contains(day))
+            })
+        })
     }
-    pub fn weekday(&self) -> &'static str {
-        ["日", "月", "火", "水", "木", "金", "土"]
-            [self.date_time.weekday().number_from_monday() as usize % 7]
+}
+
+pub fn valid_format(format: &str) -> bool {
+    !StrftimeItems::new(format).any(|x| x == Item::Error)
+}
+
+/// Whether any configured format changes more often than once a minute.
+pub fn needs_seconds(config: &Config) -> bool {
+    let default = [config::Clock::default()];
+    let clocks = match config.clock.as_slice() {
+        [] => &default[..],
+        clocks => clocks,
+    };
+    let world = &config.world_clock;
+    clocks
+        .iter()
+        .flat_map(|x| [&x.date, &x.time, &x.weekday])
+        .chain((!world.zones.is_empty()).then_some(&world.format))
+        .any(|format| {
+            // composite specifiers like %T come expanded
+            StrftimeItems::new(format)
+                .any(|x| matches!(x, Item::Numeric(Numeric::Second | Numeric::Timestamp, _)))
+        })
+}
+
+/// `name` from the tz database, like "Asia/Tokyo".
+pub fn timezone(name: &str) -> Option<Tz> {
+    name.parse()
+        .inspect_err(|e| tracing::warn!("unknown timezone {name:?}: {e}"))
+        .ok()
+}
+
+/// `name` like "ja_JP", the environment's `LC_TIME` otherwise.
+pub fn locale(name: Option<&str>) -> Locale {
+    let env = ["LC_ALL", "LC_TIME", "LANG"]
+        .into_iter()
+        .find_map(|x| env::var(x).ok().filter(|x| !x.is_empty()));
+    let Some(name) = name.map(ToOwned::to_owned).or(env) else {
+        return Locale::POSIX;
+    };
+    // "de_DE.UTF-8@euro"
+    let bare = name.split(['.', '@']).next().unwrap_or_default();
+    if bare == "C" {
+        return Locale::POSIX;
     }
+    Locale::try_from(bare).unwrap_or_else(|_| {
+        tracing::warn!("unknown locale {name:?}");
+        Locale::POSIX
+    })
 }
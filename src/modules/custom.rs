@@ -0,0 +1,150 @@
+use std::{process::Stdio, time::Duration};
+
+use compio::{
+    BufResult,
+    io::AsyncRead as _,
+    process::{Command, Output as ProcessOutput},
+};
+use futures::future;
+use serde::Deserialize;
+
+use crate::config;
+
+#[derive(Debug)]
+pub struct Event {
+    pub index: usize,
+    pub output: Output,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct Output {
+    pub text: String,
+    pub tooltip: Option<String>,
+    pub class: Option<String>,
+}
+
+impl Output {
+    /// Either a json object `{text, tooltip, class}` or up to three plain lines in that order.
+    pub fn parse(s: &str) -> Self {
+        let s = s.trim();
+        if s.starts_with('{') {
+            if let Ok(x) = serde_json::from_str(s)
+                .inspect_err(|e| tracing::warn!("cannot parse custom module output {s:?}: {e}"))
+            {
+                return x;
+            }
+        }
+        let mut lines = s.lines();
+        let text = lines.next().unwrap_or_default().into();
+        let tooltip = lines.next().map(Into::into);
+        let class = lines.next().map(Into::into);
+        Self {
+            text,
+            tooltip,
+            class,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Action {
+    Click,
+    MiddleClick,
+    RightClick,
+    ScrollUp,
+    ScrollDown,
+}
+
+impl config::Custom {
+    pub fn command(&self, action: Action) -> Option<&str> {
+        match action {
+            Action::Click => self.on_click.as_deref(),
+            Action::MiddleClick => self.on_middle_click.as_deref(),
+            Action::RightClick => self.on_right_click.as_deref(),
+            Action::ScrollUp => self.on_scroll_up.as_deref(),
+            Action::ScrollDown => self.on_scroll_down.as_deref(),
+        }
+    }
+}
+
+pub async fn run(config: &[config::Custom], dispatch: impl AsyncFnMut(Event) + Clone) {
+    future::join_all(config.iter().enumerate().map(|(index, config)| {
+        let dispatch = dispatch.clone();
+        async move {
+            match config.interval {
+                Some(interval) => {
+                    interval_mode(index, &config.exec, Duration::from_secs(interval), dispatch)
+                        .await
+                }
+                None => continuous_mode(index, &config.exec, dispatch).await,
+            }
+        }
+    }))
+    .await;
+}
+
+async fn interval_mode(
+    index: usize,
+    exec: &str,
+    interval: Duration,
+    mut dispatch: impl AsyncFnMut(Event),
+) {
+    let mut timer = compio::time::interval(interval);
+    loop {
+        timer.tick().await;
+        let output = match Command::new("sh").args(["-c", exec]).output().await {
+            Ok(ProcessOutput { stdout, .. }) => stdout,
+            Err(e) => {
+                tracing::warn!("cannot run {exec:?}: {e}");
+                continue;
+            }
+        };
+        let output = Output::parse(&String::from_utf8_lossy(&output));
+        dispatch(Event { index, output }).await;
+    }
+}
+
+async fn continuous_mode(index: usize, exec: &str, mut dispatch: impl AsyncFnMut(Event)) {
+    const RESTART_DELAY: Duration = Duration::from_secs(5);
+    loop {
+        let mut command = Command::new("sh");
+        command.args(["-c", exec]).stdout(Stdio::piped()).unwrap();
+        let mut child = match command.spawn() {
+            Ok(x) => x,
+            Err(e) => {
+                tracing::warn!("cannot run {exec:?}: {e}");
+                compio::time::sleep(RESTART_DELAY).await;
+                continue;
+            }
+        };
+        let mut stdout = child.stdout.take().unwrap();
+        let mut pending = Vec::new();
+        let mut buf = Vec::with_capacity(4096);
+        loop {
+            let n;
+            BufResult(n, buf) = stdout.read(buf).await;
+            match n {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {}
+            }
+            pending.extend_from_slice(&buf);
+            buf.clear();
+            // only complete lines are outputs, keep the tail for the next read
+            while let Some(pos) = pending.iter().position(|&x| x == b'\n') {
+                let line = String::from_utf8_lossy(&pending[..pos]).into_owned();
+                pending.drain(..=pos);
+                if !line.trim().is_empty() {
+                    dispatch(Event {
+                        index,
+                        output: Output::parse(&line),
+                    })
+                    .await;
+                }
+            }
+        }
+        child.wait().await.ok();
+        tracing::warn!("{exec:?} exited, restarting in {RESTART_DELAY:?}");
+        compio::time::sleep(RESTART_DELAY).await;
+    }
+}
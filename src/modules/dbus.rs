@@ -1,146 +1,68 @@
 use std::{
-    borrow::Cow,
     cell::RefCell,
-    env,
-    ffi::OsStr,
     fmt::{self, Debug},
     hash::Hash,
-    io,
-    mem::MaybeUninit,
-    path::Path,
-    ptr,
+    mem::{self, MaybeUninit},
+    pin, ptr,
     rc::Rc,
-    result,
-    time::Duration,
 };
 
-use compio::{
-    io::{AsyncRead as _, AsyncWrite as _},
-    net::UnixStream,
-};
 use dbus::{
-    self, Fields, Flags, MessageIterator, MessageType, OwnedMessage, Serial,
-    authentication::Io,
-    marshal::Marshal,
-    signature::{MultiSignature, SignatureProxy},
+    self,
+    signature::SignatureProxy,
     unmarshal::{self, ArrayIter, Unmarshal},
 };
-use derive_more::From;
+use derive_more::{Deref, DerefMut, From};
 use futures::{
     StreamExt,
-    channel::mpsc::{self, UnboundedSender},
+    channel::mpsc::{self, UnboundedReceiver, UnboundedSender},
+    future::{self, Either},
 };
-use thiserror::Error;
-
-pub use cookie::*;
-
-type Raw = OwnedMessage<Box<[u8]>>;
-type Return = Result<Raw>;
-
-#[derive(Clone)]
-pub struct Connection<D> {
-    stream: UnixStream,
-    serial: Rc<RefCell<Serial>>,
-    cookie: Cookie,
+use hyoka_core::dbus::{Connection, MatchRule, NameOwner, Property, Result, SubscriptionId};
+use rustc_hash::FxHashMap;
+
+pub use bluetooth::*;
+pub use watcher::*;
+
+/// The tray host, session and unit watching on top of a bus connection.
+#[derive(Clone, Deref, DerefMut)]
+struct Client<D> {
+    #[deref]
+    #[deref_mut]
+    connection: Connection,
+    tasks: UnboundedSender<Task>,
+    /// subscriptions made on behalf of each tray item, dropped with the item
+    items: Rc<RefCell<FxHashMap<Tray, Vec<SubscriptionId>>>>,
+    watcher: Watcher,
     events: D,
 }
 
-#[derive(Debug, Error)]
-pub enum Error {
-    #[error("address not found")]
-    AddrNotFound,
-    #[error("failed to parse address")]
-    FailedParseAddr,
-    #[error(transparent)]
-    Io(#[from] io::Error),
-    #[error(transparent)]
-    Authentication(#[from] dbus::authentication::Error<io::Error>),
-    #[error(transparent)]
-    Unmarshal(#[from] dbus::unmarshal::Error),
-    #[error("{name}{}", if let Some(desc) = desc { ": {desc}" } else { "" })]
-    ErrorMessage {
-        name: Box<dbus::String>,
-        desc: Option<Box<dbus::String>>,
-    },
-    #[error("deadline has elapsed")]
-    Elapsed,
-}
-
-pub type Result<T> = result::Result<T, Error>;
-
-fn address(addr: &[u8]) -> Option<Cow<'_, [u8]>> {
-    for addr in addr.get("unix:".len()..)?.split(|&x| x == b',') {
-        if let Some((k, v)) = addr.split_once(|&x| x == b'=') {
-            match k {
-                b"path" => return Some(v.into()),
-                b"abstract" => {
-                    return Some({
-                        let mut res = Vec::with_capacity(v.len() + 1);
-                        res.push(0);
-                        res.extend_from_slice(v);
-                        res.into()
-                    });
-                }
-                _ => continue,
-            }
-        }
-    }
-    None
-}
-
-async fn connect(addr: &[u8]) -> Result<UnixStream> {
-    let addr = address(addr).ok_or(Error::FailedParseAddr)?;
-    let path = Path::new(unsafe { OsStr::from_encoded_bytes_unchecked(addr.as_ref()) });
-    Ok(UnixStream::connect(path).await?)
-}
-
-impl<D> dbus::authentication::Io for Connection<D> {
-    type Error = io::Error;
-
-    #[allow(refining_impl_trait)]
-    async fn read(&mut self) -> result::Result<impl AsRef<[u8]> + 'static, Self::Error> {
-        let buf = Vec::with_capacity(4096);
-        let (n, buf) = self.stream.read(buf).await?;
-        if n == 0 {
-            Err(io::Error::new(io::ErrorKind::BrokenPipe, "peer closed"))?
-        }
-        Ok(buf)
-    }
-    async fn write(&mut self, data: impl AsRef<[u8]> + 'static) -> result::Result<(), Self::Error> {
-        struct Buf<T>(T);
-
-        impl<T: AsRef<[u8]> + 'static> compio::buf::IoBuf for Buf<T> {
-            fn as_init(&self) -> &[u8] {
-                self.0.as_ref()
-            }
-        }
-        let _ = self.stream.write(Buf(data)).await?;
-        Ok(())
-    }
-}
-
-pub const DBUS: dbus::Proxy = dbus::Proxy {
-    name: "org.freedesktop.DBus".into(),
-    path: "/org/freedesktop/DBus".into(),
-    interface: "org.freedesktop.DBus".into(),
-};
-
-pub trait Dispatcher: Clone {
+pub trait Dispatcher: Clone + 'static {
     async fn dispatch(&mut self, e: impl Into<Event>);
 }
 
 #[derive(Debug)]
 enum Task {
     NewItem(Tray),
+    RemoveItem(Tray),
     IconName(Tray),
+    AttentionIconName(Tray),
     NewWatcher,
+    WatcherGone,
+    OwnWatcher,
+    Session,
+    Settings,
+    Units(Vec<String>),
+    SystemdSubscribe,
+    Bluetooth,
 }
 impl Task {
-    async fn execute<D: Dispatcher>(self, conn: &mut Connection<D>) {
+    async fn execute<D: Dispatcher>(self, conn: &mut Client<D>) {
         match self {
             Task::NewItem(service) => {
                 conn.new_item(service).await;
             }
+            Task::RemoveItem(service) => conn.remove_item(service).await,
             Task::IconName(service) => {
                 if let Some(icon_name) = conn.icon_name(service.proxy()).await {
                     conn.events
@@ -148,282 +70,382 @@ impl Task {
                         .await;
                 }
             }
+            Task::AttentionIconName(service) => {
+                if let Some(icon_name) = conn
+                    .string_property(service.proxy(), "AttentionIconName")
+                    .await
+                {
+                    conn.events
+                        .dispatch(TrayEvent::NewAttentionIcon { service, icon_name })
+                        .await;
+                }
+            }
             Task::NewWatcher => conn.new_watcher().await,
+            Task::WatcherGone => conn.watcher_gone().await,
+            Task::OwnWatcher => conn.own_watcher().await,
+            Task::Session => conn.watch_session().await,
+            Task::Settings => conn.watch_settings().await,
+            Task::Units(units) => conn.watch_units(units).await,
+            Task::Bluetooth => conn.watch_bluetooth().await,
+            Task::SystemdSubscribe => {
+                // systemd only emits signals while somebody is subscribed
+                conn.method_call_silent(SYSTEMD, "Subscribe", dbus::Empty)
+                    .await
+                    .ok();
+            }
         }
     }
 }
 
-impl<D: Dispatcher> Connection<D> {
-    pub async fn session(dispatch: D) -> Result<Self> {
-        let addr = env::var_os("DBUS_SESSION_BUS_ADDRESS").ok_or(Error::AddrNotFound)?;
-        let addr = addr.as_encoded_bytes();
-        Ok(Self::new(connect(addr).await?, dispatch).await?)
+impl<D: Dispatcher> Client<D> {
+    fn new(connection: Connection, events: D, tasks: UnboundedSender<Task>) -> Self {
+        Self {
+            connection,
+            tasks,
+            items: Default::default(),
+            watcher: Default::default(),
+            events,
+        }
     }
-    async fn new(stream: UnixStream, dispatch: D) -> Result<Self> {
-        let mut this = Self {
-            stream,
-            serial: Default::default(),
-            cookie: Default::default(),
-            events: dispatch,
+
+    /// Without a logind session, in a container or on a bare seat, there is no lock state and
+    /// the lock module stays hidden.
+    async fn watch_session(&mut self) {
+        let session = match self.method_call(LOGIN1, "GetSession", "auto").await {
+            Ok(reply) => reply.await,
+            Err(e) => Err(e),
+        };
+        let session = match session {
+            Ok(session) => session,
+            Err(e) => {
+                tracing::warn!("cannot find logind session, lock state is unavailable: {e}");
+                return;
+            }
+        };
+        let Ok(path) = session.as_ref().parse::<&dbus::ObjectPath>() else {
+            tracing::warn!("logind answered GetSession without a path");
+            return;
         };
-        this.authenticate().await?;
-        this.stream
-            .write(this.serial.borrow_mut().method_call(
-                Flags::empty().with_no_reply_expected(),
-                DBUS,
-                "Hello",
-                dbus::Empty,
-            ))
-            .await?;
-        Ok(this)
-    }
-    async fn authenticate(&mut self) -> Result<()> {
-        dbus::authentication::authenticate(self, rustix::process::getuid().as_raw()).await?;
-        Ok(())
-    }
-
-    pub async fn method_call<'a>(
-        &mut self,
-        proxy: dbus::Proxy<'_>,
-        member: impl Into<&'a dbus::String>,
-        arguments: impl Marshal + MultiSignature,
-    ) -> Result<Notifier> {
-        let mut serial = self.serial.borrow_mut();
-        self.stream
-            .write(serial.method_call(Flags::empty(), proxy, member, arguments))
-            .await?;
-        Ok(self.cookie.wait(serial.clone(), Duration::from_secs(1)))
-    }
-
-    pub async fn get_property<'a>(
-        &mut self,
-        proxy: dbus::Proxy<'_>,
-        prop: impl Into<&'a dbus::String>,
-    ) -> Result<Notifier> {
-        self.method_call(
-            dbus::Proxy {
-                interface: "org.freedesktop.DBus.Properties".into(),
-                ..proxy
+        let events = self.events.clone();
+        self.subscribe(
+            MatchRule::signal()
+                .sender("org.freedesktop.login1")
+                .path(path.to_string())
+                .interface("org.freedesktop.login1.Session"),
+            move |msg| {
+                let mut events = events.clone();
+                async move {
+                    match msg
+                        .as_ref()
+                        .header
+                        .fields
+                        .member
+                        .map(dbus::String::as_bytes)
+                    {
+                        Some(b"Lock") => events.dispatch(SessionEvent::Lock).await,
+                        Some(b"Unlock") => events.dispatch(SessionEvent::Unlock).await,
+                        _ => {}
+                    }
+                }
             },
-            "Get",
-            dbus::multiple_new!(proxy.interface, prop.into()),
         )
         .await
+        .ok();
     }
 
-    pub async fn method_call_silent<'a>(
-        &mut self,
-        proxy: dbus::Proxy<'_>,
-        member: impl Into<&'a dbus::String>,
-        arguments: impl Marshal + MultiSignature,
-    ) -> Result<()> {
-        self.stream
-            .write(self.serial.borrow_mut().method_call(
-                Flags::empty().with_no_reply_expected(),
-                proxy,
-                member,
-                arguments,
-            ))
-            .await?;
-        Ok(())
-    }
-
-    async fn read_dispatch(&mut self, tasks: &mut UnboundedSender<Task>) -> Result<()> {
-        let buf = self.read().await?;
-        for msg in MessageIterator::new(buf.as_ref()) {
-            let msg = msg?;
-            let fields = msg.header.fields;
-            match msg.header.message_type {
-                MessageType::MethodCall => {
-                    self.stream
-                        .write(self.serial.borrow_mut().error(
-                            "org.freedesktop.DBus.Error.UnknownMethod",
-                            &msg.header,
-                            "Unknown method",
-                        ))
-                        .await?;
-                }
-                MessageType::MethodReturn => self.cookie.notify(
-                    Serial::from_raw(
-                        fields
-                            .reply_serial
-                            .ok_or(Error::Unmarshal(unmarshal::Error::InvalidHeader))?,
-                    ),
-                    Ok(msg.to_owned()),
-                ),
-                MessageType::Error => self.cookie.notify(
-                    Serial::from_raw(
-                        fields
-                            .reply_serial
-                            .ok_or(Error::Unmarshal(unmarshal::Error::InvalidHeader))?,
-                    ),
-                    Err(Error::ErrorMessage {
-                        name: fields
-                            .error_name
-                            .ok_or(unmarshal::Error::InvalidHeader)?
-                            .to_owned(),
-                        desc: msg.parse::<&dbus::String>().map(ToOwned::to_owned).ok(),
-                    }),
-                ),
-                MessageType::Signal => {
-                    let Fields {
-                        path,
-                        interface,
-                        member,
-                        sender,
-                        ..
-                    } = fields;
-
-                    match interface.map(dbus::String::as_bytes) {
-                        Some(b"org.kde.StatusNotifierItem") => {
-                            match member.unwrap().as_bytes() {
-                                b"NewIcon" => tasks
-                                    .unbounded_send(Task::IconName(Tray::new(
-                                        sender.unwrap(),
-                                        path.unwrap(),
-                                    )))
-                                    .unwrap(),
-                                _ => {}
-                            };
-                        }
-                        Some(b"org.kde.StatusNotifierWatcher") => {
-                            match member.unwrap().as_bytes() {
-                                b"StatusNotifierItemRegistered" => {
-                                    let service = msg.parse::<&dbus::String>().unwrap();
-                                    if let Some(service) = Tray::try_from_string(service) {
-                                        tasks.unbounded_send(Task::NewItem(service)).unwrap()
-                                    }
-                                }
-                                b"StatusNotifierItemUnregistered" => {
-                                    let service = msg.parse::<&dbus::String>().unwrap();
-                                    if let Some(service) = Tray::try_from_string(service) {
-                                        self.events.dispatch(TrayEvent::Unregistered(service)).await
-                                    }
-                                }
-                                _ => {}
-                            }
-                        }
-                        Some(b"org.freedesktop.DBus") => match member.unwrap().as_bytes() {
-                            b"NameOwnerChanged" => {
-                                let dbus::multiple_match!(name, old, new): dbus::multiple_type!(
-                                    &dbus::String,
-                                    &dbus::String,
-                                    &dbus::String,
-                                ) = msg.parse().unwrap();
-                                if name.as_bytes() == b"org.kde.StatusNotifierWatcher" {
-                                    if old.is_empty() && !new.is_empty() {
-                                        tasks.unbounded_send(Task::NewWatcher).unwrap();
-                                    } else if new.is_empty() && !old.is_empty() {
-                                        self.events.dispatch(TrayEvent::Disconnected).await;
-                                    }
-                                }
-                            }
-                            _ => {}
-                        },
-                        _ => {}
-                    }
+    async fn watch_settings(&mut self) {
+        let events = self.events.clone();
+        self.subscribe(
+            MatchRule::signal()
+                .sender("org.freedesktop.portal.Desktop")
+                .interface("org.freedesktop.portal.Settings")
+                .member("SettingChanged"),
+            move |msg| {
+                let mut events = events.clone();
+                async move {
+                    let msg = msg.as_ref();
+                    let event =
+                        if let Ok(dbus::multiple_match!(namespace, key, value)) = msg
+                            .parse::<dbus::multiple_type!(
+                                &dbus::String,
+                                &dbus::String,
+                                dbus::Variant<&dbus::String>
+                            )>()
+                            && namespace.as_bytes() == b"org.gnome.desktop.interface"
+                            && key.as_bytes() == b"icon-theme"
+                        {
+                            let theme = unsafe { String::from_utf8_unchecked(value.0.to_vec()) };
+                            SettingEvent::IconTheme(theme)
+                        } else if let Ok(dbus::multiple_match!(namespace, key, value)) =
+                            msg.parse::<dbus::multiple_type!(
+                                &dbus::String,
+                                &dbus::String,
+                                dbus::Variant<u32>
+                            )>()
+                            && namespace.as_bytes() == b"org.freedesktop.appearance"
+                            && key.as_bytes() == b"color-scheme"
+                        {
+                            SettingEvent::ColorScheme(ColorScheme::from_raw(value.0))
+                        } else {
+                            return;
+                        };
+                    events.dispatch(event).await
                 }
-            }
+            },
+        )
+        .await
+        .ok();
+        if let Some(theme) = self
+            .read_setting::<String>("org.gnome.desktop.interface", "icon-theme")
+            .await
+        {
+            self.events.dispatch(SettingEvent::IconTheme(theme)).await
+        }
+        if let Some(scheme) = self
+            .read_setting::<u32>("org.freedesktop.appearance", "color-scheme")
+            .await
+        {
+            self.events
+                .dispatch(SettingEvent::ColorScheme(ColorScheme::from_raw(scheme)))
+                .await
         }
-        Ok(())
     }
-
-    #[allow(dead_code)]
-    async fn sync(&mut self, timeout: Duration, tasks: &mut UnboundedSender<Task>) -> Result<()> {
-        while !self.cookie.is_empty() {
-            match compio::time::timeout(timeout, self.read_dispatch(tasks)).await {
-                Ok(x) => x?,
-                Err(_) => self.cookie.cancel_all(),
+    async fn read_setting<T: Property>(&mut self, namespace: &str, key: &str) -> Option<T> {
+        let reply = self
+            .method_call(
+                PORTAL_SETTINGS,
+                "ReadOne",
+                dbus::multiple_new!(namespace, key),
+            )
+            .await
+            .ok()?;
+        match reply.await {
+            Ok(reply) => T::from_reply(&reply),
+            Err(e) => {
+                tracing::debug!("cannot read {namespace} {key} from the settings portal: {e}");
+                None
             }
         }
-        Ok(())
     }
 
-    async fn serve(&mut self, tasks: &mut UnboundedSender<Task>) {
-        loop {
-            self.read_dispatch(tasks).await.unwrap();
+    async fn watch_units(&mut self, units: Vec<String>) {
+        let tasks = self.tasks.clone();
+        self.track_name("org.freedesktop.systemd1", move |owner| {
+            if owner == NameOwner::Appeared {
+                tasks.unbounded_send(Task::SystemdSubscribe).unwrap();
+            }
+        })
+        .await
+        .ok();
+        self.tasks.unbounded_send(Task::SystemdSubscribe).unwrap();
+        for unit in units {
+            let Ok(path) = self.method_call(SYSTEMD, "LoadUnit", unit.as_str()).await else {
+                continue;
+            };
+            let path = match path.await {
+                Ok(x) => x,
+                Err(e) => {
+                    tracing::warn!("cannot load unit {unit}: {e}");
+                    continue;
+                }
+            };
+            let Ok(path) = path.as_ref().parse::<&dbus::ObjectPath>() else {
+                continue;
+            };
+            let Ok(states) = self
+                .watch_property::<String>(
+                    dbus::Proxy {
+                        path,
+                        ..SYSTEMD_UNIT
+                    },
+                    "ActiveState",
+                )
+                .await
+            else {
+                continue;
+            };
+            let mut events = self.events.clone();
+            compio::runtime::spawn(async move {
+                let mut states = pin::pin!(states);
+                while let Some(active_state) = states.next().await {
+                    events
+                        .dispatch(UnitState {
+                            name: unit.clone(),
+                            active_state,
+                        })
+                        .await;
+                }
+            })
+            .detach();
         }
     }
 
     async fn icon_name(&mut self, proxy: dbus::Proxy<'_>) -> Option<String> {
-        let icon_name = self.get_property(proxy, "IconName").await.unwrap();
-
-        let icon_name = icon_name.await.unwrap();
-        let icon_name = unsafe {
-            String::from_utf8_unchecked(
-                icon_name
-                    .as_ref()
-                    .parse::<dbus::Variant<&dbus::String>>()
-                    .unwrap()
-                    .0
-                    .to_vec(),
-            )
-        };
-        Some(icon_name)
-    }
-    async fn tooltip(&mut self, proxy: dbus::Proxy<'_>) -> Option<String> {
-        let tooltip = self.get_property(proxy, "ToolTip").await.unwrap();
-
-        let tooltip = tooltip.await.unwrap();
-        let tooltip = unsafe {
-            String::from_utf8_unchecked(
-                tooltip
-                    .as_ref()
-                    .parse::<dbus::Variant<Tooltip>>()
-                    .unwrap()
-                    .0
-                    .content
-                    .to_vec(),
-            )
-        };
-        Some(tooltip)
+        self.string_property(proxy, "IconName").await
+    }
+    async fn string_property(&mut self, proxy: dbus::Proxy<'_>, prop: &str) -> Option<String> {
+        self.read_property(proxy, prop).await
+    }
+    async fn tooltip(&mut self, proxy: dbus::Proxy<'_>) -> Option<TrayTooltip> {
+        let tooltip = self.get_property(proxy, "ToolTip").await.ok()?;
+
+        let tooltip = tooltip.await.ok()?;
+        let Tooltip {
+            icon_name,
+            title,
+            body,
+        } = tooltip.as_ref().parse::<dbus::Variant<Tooltip>>().ok()?.0;
+        let string = |x: &dbus::String| unsafe { String::from_utf8_unchecked(x.to_vec()) };
+        Some(TrayTooltip {
+            icon_name: string(icon_name),
+            title: string(title),
+            body: string(body),
+        })
     }
 
     async fn new_item(&mut self, service: Tray) -> Option<()> {
         let (name, path) = service.item();
-        self.method_call_silent(
-            DBUS,
-            "AddMatch",
-            format!(
-                concat!(
-                    "type='signal',",
-                    "sender='{}',",
-                    "interface='org.kde.StatusNotifierItem',",
-                    "path='{}'"
-                ),
-                name, path
+        let mut subscriptions = vec![];
+        let tasks = self.tasks.clone();
+        let item = service.clone();
+        subscriptions.extend(
+            self.subscribe(
+                MatchRule::signal()
+                    .sender(name.to_string())
+                    .path(path.to_string())
+                    .interface("org.kde.StatusNotifierItem")
+                    .member("NewIcon"),
+                move |_| {
+                    tasks.unbounded_send(Task::IconName(item.clone())).unwrap();
+                    async {}
+                },
             )
-            .as_str(),
-        )
-        .await
-        .ok();
+            .await
+            .ok(),
+        );
+        let tasks = self.tasks.clone();
+        let item = service.clone();
+        subscriptions.extend(
+            self.subscribe(
+                MatchRule::signal()
+                    .sender(name.to_string())
+                    .path(path.to_string())
+                    .interface("org.kde.StatusNotifierItem")
+                    .member("NewAttentionIcon"),
+                move |_| {
+                    tasks
+                        .unbounded_send(Task::AttentionIconName(item.clone()))
+                        .unwrap();
+                    async {}
+                },
+            )
+            .await
+            .ok(),
+        );
+        let events = self.events.clone();
+        let item = service.clone();
+        subscriptions.extend(
+            self.subscribe(
+                MatchRule::signal()
+                    .sender(name.to_string())
+                    .path(path.to_string())
+                    .interface("org.kde.StatusNotifierItem")
+                    .member("NewStatus"),
+                move |msg| {
+                    let mut events = events.clone();
+                    let service = item.clone();
+                    async move {
+                        if let Ok(status) = msg.as_ref().parse::<&dbus::String>() {
+                            let status = TrayStatus::from_bytes(status.as_bytes());
+                            events
+                                .dispatch(TrayEvent::NewStatus { service, status })
+                                .await
+                        }
+                    }
+                },
+            )
+            .await
+            .ok(),
+        );
+        // items which crash never unregister themselves
+        let tasks = self.tasks.clone();
+        let item = service.clone();
+        subscriptions.extend(
+            self.track_name(&name.to_string(), move |owner| {
+                if owner == NameOwner::Vanished {
+                    tasks
+                        .unbounded_send(Task::RemoveItem(item.clone()))
+                        .unwrap();
+                }
+            })
+            .await
+            .ok(),
+        );
+        if let Some(old) = self
+            .items
+            .borrow_mut()
+            .insert(service.clone(), subscriptions)
+        {
+            for id in old {
+                self.unsubscribe(id).await.ok();
+            }
+        }
         let icon_name = self.icon_name(service.proxy()).await?;
+        let status = self
+            .string_property(service.proxy(), "Status")
+            .await
+            .map_or(TrayStatus::Active, |x| TrayStatus::from_bytes(x.as_bytes()));
+        let attention_icon_name = self
+            .string_property(service.proxy(), "AttentionIconName")
+            .await
+            .unwrap_or_default();
         self.events
-            .dispatch(TrayEvent::Registered { icon_name, service })
+            .dispatch(TrayEvent::Registered {
+                service,
+                icon_name,
+                attention_icon_name,
+                status,
+            })
             .await;
         Some(())
     }
 
+    async fn remove_item(&mut self, service: Tray) {
+        let Some(subscriptions) = self.items.borrow_mut().remove(&service) else {
+            return;
+        };
+        for id in subscriptions {
+            self.unsubscribe(id).await.ok();
+        }
+        self.unregister_item(&service).await.ok();
+        self.events.dispatch(TrayEvent::Unregistered(service)).await
+    }
+
+    /// A new watcher registers every item again, so forget all of them.
+    async fn watcher_gone(&mut self) {
+        let items = mem::take(&mut *self.items.borrow_mut());
+        for id in items.into_values().flatten() {
+            self.unsubscribe(id).await.ok();
+        }
+        self.events.dispatch(TrayEvent::Disconnected).await;
+        // the other bar went away, keep the tray working on our own
+        self.own_watcher().await;
+    }
+
     async fn new_watcher(&mut self) {
-        let registered = self
-            .method_call(
-                dbus::Proxy {
-                    name: "org.kde.StatusNotifierWatcher".into(),
-                    path: "/StatusNotifierWatcher".into(),
-                    interface: "org.freedesktop.DBus.Properties".into(),
-                },
-                "Get",
-                dbus::multiple_new!(
-                    "org.kde.StatusNotifierWatcher",
-                    "RegisteredStatusNotifierItems"
-                ),
-            )
+        let Ok(registered) = self
+            .get_property(WATCHER, "RegisteredStatusNotifierItems")
             .await
-            .unwrap();
-        if let Ok(msg) = registered.await {
-            let arr: dbus::Variant<ArrayIter<&dbus::String>> = msg.as_ref().parse().unwrap();
-            for item in arr.0 {
-                if let Some(service) = Tray::try_from_string(item.unwrap()) {
+        else {
+            return;
+        };
+        if let Ok(msg) = registered.await
+            && let Ok(arr) = msg
+                .as_ref()
+                .parse::<dbus::Variant<ArrayIter<&dbus::String>>>()
+        {
+            for item in arr.0.flatten() {
+                if let Some(service) = Tray::try_from_string(item) {
                     self.new_item(service).await;
                 }
             }
@@ -434,123 +456,312 @@ impl<D: Dispatcher> Connection<D> {
 #[derive(Debug, From)]
 pub enum Event {
     Tray(TrayEvent),
+    Session(SessionEvent),
+    Setting(SettingEvent),
+    Unit(UnitState),
+    Bluetooth(BluetoothEvent),
 }
 
 #[derive(Debug)]
 pub enum TrayEvent {
-    Registered { service: Tray, icon_name: String },
-    NewIcon { service: Tray, icon_name: String },
+    Registered {
+        service: Tray,
+        icon_name: String,
+        attention_icon_name: String,
+        status: TrayStatus,
+    },
+    NewIcon {
+        service: Tray,
+        icon_name: String,
+    },
+    NewAttentionIcon {
+        service: Tray,
+        icon_name: String,
+    },
+    NewStatus {
+        service: Tray,
+        status: TrayStatus,
+    },
     Unregistered(Tray),
     Disconnected,
 }
 
-pub async fn new<D: Dispatcher>(dispatch: D) -> Option<(Daemon<D>, Proxy<D>)> {
-    let connection = Connection::session(dispatch).await.ok()?;
-    Some((
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrayStatus {
+    /// nothing interesting going on, the item may be hidden
+    Passive,
+    Active,
+    NeedsAttention,
+}
+
+impl TrayStatus {
+    fn from_bytes(status: &[u8]) -> Self {
+        match status {
+            b"Passive" => Self::Passive,
+            b"NeedsAttention" => Self::NeedsAttention,
+            _ => Self::Active,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct TrayTooltip {
+    pub icon_name: String,
+    pub title: String,
+    /// may contain a subset of html markup
+    pub body: String,
+}
+
+#[derive(Debug)]
+pub enum SessionEvent {
+    /// `true` right before suspending, `false` after resuming
+    PrepareForSleep(bool),
+    Lock,
+    Unlock,
+}
+
+/// Desktop settings read through xdg-desktop-portal.
+#[derive(Debug)]
+pub enum SettingEvent {
+    IconTheme(String),
+    ColorScheme(ColorScheme),
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ColorScheme {
+    #[default]
+    NoPreference,
+    Dark,
+    Light,
+}
+
+impl ColorScheme {
+    fn from_raw(value: u32) -> Self {
+        match value {
+            1 => Self::Dark,
+            2 => Self::Light,
+            _ => Self::NoPreference,
+        }
+    }
+}
+
+pub async fn new<D: Dispatcher>(dispatch: D) -> Result<(Daemon<D>, Proxy<D>)> {
+    let (sender, tasks) = mpsc::unbounded();
+    let connection = Client::new(Connection::session().await?, dispatch, sender);
+    Ok((
         Daemon {
             connection: connection.clone(),
+            bus: Bus::Session,
+            tasks,
         },
         Proxy { connection },
     ))
 }
 
+#[derive(Debug)]
+pub struct UnitState {
+    pub name: String,
+    pub active_state: String,
+}
+
+impl UnitState {
+    pub fn failed(&self) -> bool {
+        self.active_state == "failed"
+    }
+}
+
+/// logind and systemd live on the system bus, so it gets a connection of its own.
+pub async fn system<D: Dispatcher>(
+    dispatch: D,
+    units: Vec<String>,
+) -> Result<(Daemon<D>, Proxy<D>)> {
+    let (sender, tasks) = mpsc::unbounded();
+    let connection = Client::new(Connection::system().await?, dispatch, sender);
+    Ok((
+        Daemon {
+            connection: connection.clone(),
+            bus: Bus::System { units },
+            tasks,
+        },
+        Proxy { connection },
+    ))
+}
+
+pub const LOGIN1: dbus::Proxy = dbus::Proxy {
+    name: "org.freedesktop.login1".into(),
+    path: "/org/freedesktop/login1".into(),
+    interface: "org.freedesktop.login1.Manager".into(),
+};
+
+pub const SYSTEMD: dbus::Proxy = dbus::Proxy {
+    name: "org.freedesktop.systemd1".into(),
+    path: "/org/freedesktop/systemd1".into(),
+    interface: "org.freedesktop.systemd1.Manager".into(),
+};
+
+pub const PORTAL_SETTINGS: dbus::Proxy = dbus::Proxy {
+    name: "org.freedesktop.portal.Desktop".into(),
+    path: "/org/freedesktop/portal/desktop".into(),
+    interface: "org.freedesktop.portal.Settings".into(),
+};
+
+const SYSTEMD_UNIT: dbus::Proxy = dbus::Proxy {
+    name: "org.freedesktop.systemd1".into(),
+    path: "/org/freedesktop/systemd1/unit".into(),
+    interface: "org.freedesktop.systemd1.Unit".into(),
+};
+
+enum Bus {
+    Session,
+    System { units: Vec<String> },
+}
+
 pub struct Daemon<D> {
-    connection: Connection<D>,
+    connection: Client<D>,
+    bus: Bus,
+    tasks: UnboundedReceiver<Task>,
 }
 
 impl<D: Dispatcher> Daemon<D> {
-    async fn init(&mut self, tasks: &mut UnboundedSender<Task>) {
-        self.connection
-            .method_call_silent(
-                DBUS,
-                "AddMatch",
-                concat!(
-                    "type='signal',",
-                    "sender='org.kde.StatusNotifierWatcher',",
-                    "interface='org.kde.StatusNotifierWatcher',",
-                    "member='StatusNotifierItemRegistered'",
-                ),
-            )
-            .await
-            .ok();
-        self.connection
-            .method_call_silent(
-                DBUS,
-                "AddMatch",
-                concat!(
-                    "type='signal',",
-                    "sender='org.kde.StatusNotifierWatcher',",
-                    "interface='org.kde.StatusNotifierWatcher',",
-                    "member='StatusNotifierItemUnregistered'",
-                ),
-            )
-            .await
-            .ok();
-        self.connection
-            .method_call_silent(
-                DBUS,
-                "AddMatch",
-                concat!(
-                    "type='signal',",
-                    "sender='org.freedesktop.DBus',",
-                    "interface='org.freedesktop.DBus',",
-                    "member='NameOwnerChanged',",
-                    "arg0='org.kde.StatusNotifierWatcher'",
-                ),
-            )
-            .await
-            .unwrap();
-        tasks.unbounded_send(Task::NewWatcher).unwrap();
+    async fn init(&mut self) {
+        match &mut self.bus {
+            Bus::Session => self.connection.init_tray().await,
+            Bus::System { units } => {
+                let units = std::mem::take(units);
+                self.connection.init_system(units).await;
+            }
+        }
     }
-    pub async fn serve(mut self) {
-        let (mut sender, mut receiver) = mpsc::unbounded();
-        self.init(&mut sender).await;
+    /// Returns once the connection breaks, the caller may connect again.
+    pub async fn serve(mut self) -> Result<()> {
+        self.init().await;
         let mut connection = self.connection.clone();
-        let daemon = connection.serve(&mut sender);
+        let daemon = connection.serve();
         let mut connection = self.connection.clone();
+        let mut receiver = self.tasks;
         let tasks = async {
-            loop {
-                receiver
-                    .next()
-                    .await
-                    .unwrap()
-                    .execute(&mut connection)
-                    .await;
+            while let Some(task) = receiver.next().await {
+                task.execute(&mut connection).await;
             }
         };
-        std::future::join!(daemon, tasks).await;
+        match future::select(pin::pin!(daemon), pin::pin!(tasks)).await {
+            Either::Left((res, _)) => res,
+            Either::Right(((), _)) => Ok(()),
+        }
     }
 }
 
+impl<D: Dispatcher> Client<D> {
+    async fn init_system(&mut self, units: Vec<String>) {
+        let events = self.events.clone();
+        self.subscribe(
+            MatchRule::signal()
+                .sender("org.freedesktop.login1")
+                .interface("org.freedesktop.login1.Manager")
+                .member("PrepareForSleep"),
+            move |msg| {
+                let mut events = events.clone();
+                async move {
+                    if let Ok(start) = msg.as_ref().parse::<bool>() {
+                        events.dispatch(SessionEvent::PrepareForSleep(start)).await
+                    }
+                }
+            },
+        )
+        .await
+        .ok();
+        self.tasks.unbounded_send(Task::Session).unwrap();
+        self.tasks.unbounded_send(Task::Bluetooth).unwrap();
+        if !units.is_empty() {
+            self.tasks.unbounded_send(Task::Units(units)).unwrap();
+        }
+    }
+    async fn init_tray(&mut self) {
+        let tasks = self.tasks.clone();
+        self.subscribe(
+            MatchRule::signal()
+                .sender("org.kde.StatusNotifierWatcher")
+                .interface("org.kde.StatusNotifierWatcher")
+                .member("StatusNotifierItemRegistered"),
+            move |msg| {
+                if let Ok(service) = msg.as_ref().parse::<&dbus::String>()
+                    && let Some(service) = Tray::try_from_string(service)
+                {
+                    tasks.unbounded_send(Task::NewItem(service)).unwrap()
+                }
+                async {}
+            },
+        )
+        .await
+        .ok();
+        let tasks = self.tasks.clone();
+        self.subscribe(
+            MatchRule::signal()
+                .sender("org.kde.StatusNotifierWatcher")
+                .interface("org.kde.StatusNotifierWatcher")
+                .member("StatusNotifierItemUnregistered"),
+            move |msg| {
+                if let Ok(service) = msg.as_ref().parse::<&dbus::String>()
+                    && let Some(service) = Tray::try_from_string(service)
+                {
+                    tasks.unbounded_send(Task::RemoveItem(service)).unwrap()
+                }
+                async {}
+            },
+        )
+        .await
+        .ok();
+        let tasks = self.tasks.clone();
+        self.track_name("org.kde.StatusNotifierWatcher", move |owner| {
+            tasks
+                .unbounded_send(match owner {
+                    NameOwner::Appeared => Task::NewWatcher,
+                    NameOwner::Vanished => Task::WatcherGone,
+                })
+                .unwrap()
+        })
+        .await
+        .ok();
+        self.serve_watcher();
+        self.tasks.unbounded_send(Task::OwnWatcher).unwrap();
+        self.tasks.unbounded_send(Task::NewWatcher).unwrap();
+        self.tasks.unbounded_send(Task::Settings).unwrap();
+    }
+}
+
+#[derive(Clone)]
 pub struct Proxy<D> {
-    connection: Connection<D>,
+    connection: Client<D>,
 }
 
 impl<D: Dispatcher> Proxy<D> {
-    pub async fn tray_tooltip(&mut self, service: Tray) -> Option<String> {
+    pub async fn tray_tooltip(&mut self, service: Tray) -> Option<TrayTooltip> {
         self.connection.tooltip(service.proxy()).await
     }
-    pub async fn tray_action(&mut self, service: Tray) {
+    pub async fn restart_unit(&mut self, name: &str) {
+        self.connection
+            .method_call_silent(SYSTEMD, "RestartUnit", dbus::multiple_new!(name, "replace"))
+            .await
+            .inspect_err(|e| tracing::warn!("cannot restart {name}: {e}"))
+            .ok();
+    }
+    /// Passes `token` on first, for the item to focus the window it may open with it.
+    pub async fn tray_action(&mut self, service: Tray, token: Option<&str>) {
+        if let Some(token) = token {
+            self.connection
+                .method_call_silent(service.proxy(), "ProvideXdgActivationToken", token)
+                .await
+                .inspect_err(|e| tracing::debug!("{service:?} takes no activation token: {e}"))
+                .ok();
+        }
         self.connection
             .method_call_silent(service.proxy(), "Activate", dbus::multiple_new!(0i32, 0i32))
             .await
-            .unwrap();
+            .inspect_err(|e| tracing::warn!("cannot activate {service:?}: {e}"))
+            .ok();
     }
 }
 
-#[allow(dead_code)]
-fn show_bytes(xs: &[u8]) -> impl Debug {
-    fmt::from_fn(move |f| {
-        Ok(for &x in xs {
-            if x.is_ascii_graphic() {
-                write!(f, "{}", x as char)?;
-            } else {
-                write!(f, "\\{x}")?;
-            }
-        })
-    })
-}
-
 #[derive(Clone, Eq)]
 pub struct Tray {
     data: Box<dbus::String>,
@@ -618,7 +829,9 @@ impl Debug for Tray {
 }
 
 struct Tooltip<'a> {
-    content: &'a dbus::String,
+    icon_name: &'a dbus::String,
+    title: &'a dbus::String,
+    body: &'a dbus::String,
 }
 impl<'a> SignatureProxy for Tooltip<'a> {
     type Proxy = dbus::struct_type!(
@@ -630,9 +843,15 @@ impl<'a> SignatureProxy for Tooltip<'a> {
 }
 impl<'a> Unmarshal<'a> for Tooltip<'a> {
     fn unmarshal(r: &mut unmarshal::Reader<'a>) -> unmarshal::Result<Self> {
-        let dbus::struct_match!(_, _, content, _): <Self as SignatureProxy>::Proxy = r.read()?;
-        Ok(Self { content })
+        let dbus::struct_match!(icon_name, _, title, body): <Self as SignatureProxy>::Proxy =
+            r.read()?;
+        Ok(Self {
+            icon_name,
+            title,
+            body,
+        })
     }
 }
 
-mod cookie;
+mod bluetooth;
+mod watcher;
@@ -0,0 +1,124 @@
+use std::{pin, time::Duration};
+
+use futures::StreamExt as _;
+
+use super::{Client, Dispatcher, Proxy};
+
+/// The first adapter, which is the only one on most machines.
+const ADAPTER: &str = "/org/bluez/hci0";
+
+#[derive(Debug)]
+pub enum BluetoothEvent {
+    /// the adapter turned on or off
+    Powered(bool),
+}
+
+/// A paired device of the adapter.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BluetoothDevice {
+    /// object path under the adapter
+    pub path: String,
+    pub name: String,
+    pub connected: bool,
+    /// percent, from devices reporting it through `org.bluez.Battery1`
+    pub battery: Option<u8>,
+}
+
+impl<D: Dispatcher> Client<D> {
+    pub(super) async fn watch_bluetooth(&mut self) {
+        let adapter = bluez(ADAPTER, "org.bluez.Adapter1");
+        let Ok(powered) = self.watch_property::<bool>(adapter, "Powered").await else {
+            return;
+        };
+        let mut events = self.events.clone();
+        compio::runtime::spawn(async move {
+            let mut powered = pin::pin!(powered);
+            while let Some(x) = powered.next().await {
+                events.dispatch(BluetoothEvent::Powered(x)).await;
+            }
+        })
+        .detach();
+    }
+    async fn bluetooth_device(&mut self, path: &str) -> Option<BluetoothDevice> {
+        let device = || bluez(path, "org.bluez.Device1");
+        if !self.read_property::<bool>(device(), "Paired").await? {
+            return None;
+        }
+        Some(BluetoothDevice {
+            path: path.into(),
+            name: self.read_property(device(), "Alias").await?,
+            connected: self.read_property(device(), "Connected").await?,
+            battery: self
+                .read_property(bluez(path, "org.bluez.Battery1"), "Percentage")
+                .await,
+        })
+    }
+}
+
+impl<D: Dispatcher> Proxy<D> {
+    /// Paired devices by name, empty without an adapter.
+    pub async fn bluetooth_devices(&mut self) -> Vec<BluetoothDevice> {
+        let introspectable = bluez(ADAPTER, "org.freedesktop.DBus.Introspectable");
+        let Ok(reply) = self
+            .connection
+            .method_call(introspectable, "Introspect", dbus::Empty)
+            .await
+        else {
+            return vec![];
+        };
+        let Ok(reply) = reply.await else {
+            return vec![];
+        };
+        let Ok(xml) = reply.as_ref().parse::<&dbus::String>() else {
+            return vec![];
+        };
+        let xml = String::from_utf8_lossy(xml.as_bytes()).into_owned();
+        let mut devices = vec![];
+        for node in device_nodes(&xml) {
+            let path = format!("{ADAPTER}/{node}");
+            devices.extend(self.connection.bluetooth_device(&path).await);
+        }
+        devices.sort_by(|a, b| a.name.cmp(&b.name));
+        devices
+    }
+    pub async fn set_bluetooth_power(&mut self, on: bool) {
+        let properties = bluez(ADAPTER, "org.freedesktop.DBus.Properties");
+        self.connection
+            .method_call_silent(
+                properties,
+                "Set",
+                dbus::multiple_new!("org.bluez.Adapter1", "Powered", dbus::Variant(on)),
+            )
+            .await
+            .inspect_err(|e| tracing::warn!("cannot power the bluetooth adapter: {e}"))
+            .ok();
+    }
+    /// Returns once the device had time to answer, BlueZ replies to `Connect` only after the
+    /// link is up, later than calls wait for.
+    pub async fn connect_bluetooth(&mut self, path: &str, connect: bool) {
+        let device = bluez(path, "org.bluez.Device1");
+        let member = if connect { "Connect" } else { "Disconnect" };
+        self.connection
+            .method_call_silent(device, member, dbus::Empty)
+            .await
+            .inspect_err(|e| tracing::warn!("cannot {member} {path}: {e}"))
+            .ok();
+        compio::time::sleep(Duration::from_secs(3)).await;
+    }
+}
+
+fn bluez<'a>(path: &'a str, interface: &'static str) -> dbus::Proxy<'a> {
+    dbus::Proxy {
+        name: "org.bluez".into(),
+        path: path.as_bytes().into(),
+        interface: interface.into(),
+    }
+}
+
+/// The `dev_AA_BB_CC_DD_EE_FF` children in the introspection of the adapter.
+fn device_nodes(xml: &str) -> impl Iterator<Item = &str> {
+    xml.split("<node name=\"")
+        .skip(1)
+        .filter_map(|x| Some(x.split_once('"')?.0))
+        .filter(|x| x.starts_with("dev_"))
+}
@@ -0,0 +1,160 @@
+use std::{cell::RefCell, rc::Rc};
+
+use hyoka_core::dbus::{Call, DBUS, Raw, Result};
+
+use super::{Client, Dispatcher, Tray};
+
+pub const WATCHER: dbus::Proxy = dbus::Proxy {
+    name: "org.kde.StatusNotifierWatcher".into(),
+    path: "/StatusNotifierWatcher".into(),
+    interface: "org.kde.StatusNotifierWatcher".into(),
+};
+
+/// `DBUS_NAME_FLAG_DO_NOT_QUEUE`
+const DO_NOT_QUEUE: u32 = 4;
+/// `DBUS_REQUEST_NAME_REPLY_PRIMARY_OWNER`
+const PRIMARY_OWNER: u32 = 1;
+
+/// Items registered with our own `org.kde.StatusNotifierWatcher`.
+///
+/// `None` as long as somebody else owns the name, usually another bar.
+#[derive(Default, Clone)]
+pub struct Watcher(Rc<RefCell<Option<Vec<Tray>>>>);
+
+impl Watcher {
+    fn is_active(&self) -> bool {
+        self.0.borrow().is_some()
+    }
+    fn insert(&self, item: &Tray) -> bool {
+        match &mut *self.0.borrow_mut() {
+            Some(items) if !items.contains(item) => {
+                items.push(item.clone());
+                true
+            }
+            _ => false,
+        }
+    }
+    fn remove(&self, item: &Tray) -> bool {
+        let mut inner = self.0.borrow_mut();
+        let Some(items) = &mut *inner else {
+            return false;
+        };
+        let Some(idx) = items.iter().position(|x| x == item) else {
+            return false;
+        };
+        items.remove(idx);
+        true
+    }
+}
+
+impl<D: Dispatcher> Client<D> {
+    /// Answers as the watcher while we own its name.
+    pub(super) fn serve_watcher(&self) {
+        let client = self.clone();
+        self.register("/StatusNotifierWatcher", move |msg| {
+            let mut client = client.clone();
+            async move { client.method_called(&msg).await }
+        });
+    }
+
+    /// Takes over the watcher name if nobody provides it, so tray icons work without another bar
+    /// running.
+    pub(super) async fn own_watcher(&mut self) {
+        if self.watcher.is_active() {
+            return;
+        }
+        let Ok(reply) = self
+            .method_call(
+                DBUS,
+                "RequestName",
+                dbus::multiple_new!("org.kde.StatusNotifierWatcher", DO_NOT_QUEUE),
+            )
+            .await
+        else {
+            return;
+        };
+        match reply.await.map(|x| x.as_ref().parse::<u32>()) {
+            Ok(Ok(PRIMARY_OWNER)) => {
+                tracing::info!("no StatusNotifierWatcher found, acting as one");
+                *self.watcher.0.borrow_mut() = Some(vec![]);
+            }
+            Ok(_) => {}
+            Err(e) => tracing::warn!("cannot request StatusNotifierWatcher name: {e}"),
+        }
+    }
+
+    /// Forgets an item registered with our watcher and tells the hosts about it.
+    pub(super) async fn unregister_item(&mut self, item: &Tray) -> Result<()> {
+        if self.watcher.remove(item) {
+            self.signal(WATCHER, "StatusNotifierItemUnregistered", &*item.data)
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn method_called(&mut self, msg: &Raw) -> Result<Call> {
+        let message = msg.as_ref();
+        let fields = message.header.fields;
+        let interface = fields.interface.map(|x| x.as_bytes());
+        let member = fields.member.map(|x| x.as_bytes());
+        if !self.watcher.is_active() {
+            return Ok(Call::UnknownMethod);
+        }
+        match (interface, member) {
+            (Some(b"org.kde.StatusNotifierWatcher"), Some(b"RegisterStatusNotifierItem")) => {
+                let (Ok(service), Some(sender)) = (message.parse::<&dbus::String>(), fields.sender)
+                else {
+                    return Ok(Call::InvalidArgs);
+                };
+                // items may pass either their bus name or just their object path
+                let item = if service.as_bytes().starts_with(b"/") {
+                    Tray::new(sender, service.as_bytes().into())
+                } else {
+                    Tray::new(service, "/StatusNotifierItem".into())
+                };
+                self.reply(msg, dbus::Empty).await?;
+                // our own host picks the signal up like any other one
+                if self.watcher.insert(&item) {
+                    self.signal(WATCHER, "StatusNotifierItemRegistered", &*item.data)
+                        .await?;
+                }
+            }
+            (Some(b"org.kde.StatusNotifierWatcher"), Some(b"RegisterStatusNotifierHost")) => {
+                self.reply(msg, dbus::Empty).await?;
+                self.signal(WATCHER, "StatusNotifierHostRegistered", dbus::Empty)
+                    .await?;
+            }
+            (Some(b"org.freedesktop.DBus.Properties"), Some(b"Get")) => {
+                let Ok(dbus::multiple_match!(_, prop)) =
+                    message.parse::<dbus::multiple_type!(&dbus::String, &dbus::String)>()
+                else {
+                    return Ok(Call::InvalidArgs);
+                };
+                match prop.as_bytes() {
+                    b"RegisteredStatusNotifierItems" => {
+                        let items: Vec<Box<dbus::String>> = self
+                            .watcher
+                            .0
+                            .borrow()
+                            .iter()
+                            .flatten()
+                            .map(|x| x.data.clone())
+                            .collect();
+                        let items: Vec<&dbus::String> = items.iter().map(|x| &**x).collect();
+                        self.reply(msg, dbus::Variant(items.as_slice())).await?
+                    }
+                    b"IsStatusNotifierHostRegistered" => {
+                        self.reply(msg, dbus::Variant(true)).await?
+                    }
+                    b"ProtocolVersion" => self.reply(msg, dbus::Variant(0i32)).await?,
+                    _ => return Ok(Call::InvalidArgs),
+                }
+            }
+            _ => return Ok(Call::UnknownMethod),
+        }
+        Ok(Call::Answered)
+    }
+}
+
+#[cfg(test)]
+mod tests;
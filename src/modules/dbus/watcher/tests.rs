@@ -0,0 +1,179 @@
+//! The watcher against the fake bus of `hyoka_core::dbus::mock`, with items registering.
+
+use compio::runtime::{self, Runtime};
+use dbus::unmarshal::ArrayIter;
+use futures::{channel::mpsc, future};
+use hyoka_core::dbus::{
+    Connection,
+    mock::{Bus, Incoming, Message, Peer},
+};
+
+use super::{super::Event, *};
+
+/// `DBUS_REQUEST_NAME_REPLY_EXISTS`
+const EXISTS: u32 = 3;
+
+#[derive(Clone)]
+struct Ignore;
+
+impl Dispatcher for Ignore {
+    async fn dispatch(&mut self, _: impl Into<Event>) {}
+}
+
+/// Connects a client serving the watcher object, which asked for the name and got `reply`.
+fn run(name: &str, reply: u32, f: impl AsyncFnOnce(Client<Ignore>, Peer)) {
+    Runtime::new().unwrap().block_on(async {
+        let bus = Bus::new(&format!("watcher-{name}")).await.unwrap();
+        let address = bus.address();
+        let (connection, peer) =
+            future::join(Connection::at(address.as_bytes()), bus.accept()).await;
+        let (mut client, mut peer) = (
+            Client::new(connection.unwrap(), Ignore, mpsc::unbounded().0),
+            peer.unwrap(),
+        );
+        let mut connection = client.connection.clone();
+        runtime::spawn(async move { connection.serve().await }).detach();
+        client.serve_watcher();
+
+        let request = async {
+            let call = peer.receive().await.unwrap();
+            assert_eq!(call.member(), Some(&b"RequestName"[..]));
+            peer.send(Message::method_return(&call).arg(reply))
+                .await
+                .unwrap();
+        };
+        future::join(client.own_watcher(), request).await;
+        f(client, peer).await
+    })
+}
+
+fn register<'a>(sender: &'a str, service: &'a str) -> Message<'a> {
+    Message::method_call(
+        "/StatusNotifierWatcher",
+        "org.kde.StatusNotifierWatcher",
+        "RegisterStatusNotifierItem",
+    )
+    .sender(sender)
+    .arg(service)
+}
+
+fn string(msg: &Incoming) -> String {
+    let s = msg.message.as_ref().parse::<&dbus::String>().unwrap();
+    String::from_utf8(s.as_bytes().into()).unwrap()
+}
+
+/// The next message, which has to be a signal of the watcher.
+async fn signal(peer: &mut Peer, member: &str) -> String {
+    let signal = peer.receive().await.unwrap();
+    assert_eq!(signal.path(), Some(&b"/StatusNotifierWatcher"[..]));
+    assert_eq!(signal.member(), Some(member.as_bytes()));
+    string(&signal)
+}
+
+/// The next message, which has to be a plain reply.
+async fn method_return(peer: &mut Peer) -> Incoming {
+    let reply = peer.receive().await.unwrap();
+    assert_eq!(reply.error_name(), None);
+    assert_eq!(reply.member(), None, "expected a reply");
+    reply
+}
+
+#[test]
+fn registers_items_once() {
+    run("register", PRIMARY_OWNER, async |mut client, mut peer| {
+        peer.send(register(":1.42", "/org/ayatana/NotificationItem/app"))
+            .await
+            .unwrap();
+        method_return(&mut peer).await;
+        assert_eq!(
+            signal(&mut peer, "StatusNotifierItemRegistered").await,
+            ":1.42/org/ayatana/NotificationItem/app",
+        );
+
+        // known already, nothing to tell the hosts
+        peer.send(register(":1.42", "/org/ayatana/NotificationItem/app"))
+            .await
+            .unwrap();
+        method_return(&mut peer).await;
+
+        peer.send(register(":1.43", "org.kde.StatusNotifierItem-99-1"))
+            .await
+            .unwrap();
+        method_return(&mut peer).await;
+        assert_eq!(
+            signal(&mut peer, "StatusNotifierItemRegistered").await,
+            "org.kde.StatusNotifierItem-99-1/StatusNotifierItem",
+        );
+
+        peer.send(
+            Message::method_call(
+                "/StatusNotifierWatcher",
+                "org.freedesktop.DBus.Properties",
+                "Get",
+            )
+            .arg("org.kde.StatusNotifierWatcher")
+            .arg("RegisteredStatusNotifierItems"),
+        )
+        .await
+        .unwrap();
+        let reply = method_return(&mut peer).await;
+        let items = reply
+            .message
+            .as_ref()
+            .parse::<dbus::Variant<ArrayIter<&dbus::String>>>()
+            .unwrap();
+        let items: Vec<_> = items
+            .0
+            .flatten()
+            .map(|x| String::from_utf8(x.as_bytes().into()).unwrap())
+            .collect();
+        assert_eq!(
+            items,
+            [
+                ":1.42/org/ayatana/NotificationItem/app",
+                "org.kde.StatusNotifierItem-99-1/StatusNotifierItem",
+            ],
+        );
+
+        let item = Tray::new(":1.42".into(), "/org/ayatana/NotificationItem/app".into());
+        client.unregister_item(&item).await.unwrap();
+        assert_eq!(
+            signal(&mut peer, "StatusNotifierItemUnregistered").await,
+            ":1.42/org/ayatana/NotificationItem/app",
+        );
+    });
+}
+
+#[test]
+fn registers_hosts() {
+    run("host", PRIMARY_OWNER, async |_, mut peer| {
+        peer.send(
+            Message::method_call(
+                "/StatusNotifierWatcher",
+                "org.kde.StatusNotifierWatcher",
+                "RegisterStatusNotifierHost",
+            )
+            .sender(":1.7")
+            .arg("org.kde.StatusNotifierHost-7"),
+        )
+        .await
+        .unwrap();
+        method_return(&mut peer).await;
+        let signal = peer.receive().await.unwrap();
+        assert_eq!(signal.member(), Some(&b"StatusNotifierHostRegistered"[..]));
+    });
+}
+
+#[test]
+fn leaves_items_to_the_owner() {
+    run("owner", EXISTS, async |_, mut peer| {
+        peer.send(register(":1.42", "/org/ayatana/NotificationItem/app"))
+            .await
+            .unwrap();
+        let reply = peer.receive().await.unwrap();
+        assert_eq!(
+            reply.error_name(),
+            Some(&b"org.freedesktop.DBus.Error.UnknownMethod"[..]),
+        );
+    });
+}
@@ -0,0 +1,93 @@
+use std::{fs, path::PathBuf, str::FromStr};
+
+use crate::modules::hwmon::read;
+
+const DRM: &str = "/sys/class/drm";
+
+/// The first card driven by amdgpu or i915.
+#[derive(Debug)]
+pub struct Gpu {
+    card: PathBuf,
+    driver: Driver,
+    hwmon: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Driver {
+    Amdgpu,
+    I915,
+}
+
+/// Whatever the driver reports.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reading {
+    /// percent
+    pub busy: Option<u8>,
+    /// MHz
+    pub frequency: Option<u32>,
+    /// bytes used and in total
+    pub vram: Option<(u64, u64)>,
+    /// millidegrees Celsius
+    pub temperature: Option<i32>,
+}
+
+impl Gpu {
+    pub fn scan() -> Option<Self> {
+        let mut cards: Vec<_> = fs::read_dir(DRM)
+            .ok()?
+            .flatten()
+            .map(|x| x.path())
+            // connectors like card0-DP-1 live next to the cards
+            .filter(|x| {
+                x.file_name()
+                    .and_then(|x| x.to_str())
+                    .is_some_and(|x| x.starts_with("card") && !x.contains('-'))
+            })
+            .collect();
+        cards.sort_unstable();
+        cards.into_iter().find_map(|card| {
+            let device = card.join("device");
+            let driver = fs::read_link(device.join("driver")).ok()?;
+            let driver = match driver.file_name()?.to_str()? {
+                "amdgpu" => Driver::Amdgpu,
+                "i915" => Driver::I915,
+                _ => return None,
+            };
+            let hwmon = fs::read_dir(device.join("hwmon"))
+                .ok()
+                .and_then(|mut x| x.next()?.ok())
+                .map(|x| x.path());
+            Some(Self {
+                card,
+                driver,
+                hwmon,
+            })
+        })
+    }
+    pub fn read(&self) -> Reading {
+        let temperature = self
+            .hwmon
+            .as_ref()
+            .and_then(|x| read(&x.join("temp1_input"))?.parse().ok());
+        match self.driver {
+            Driver::Amdgpu => Reading {
+                busy: self.attr("device/gpu_busy_percent"),
+                frequency: None,
+                vram: self
+                    .attr("device/mem_info_vram_used")
+                    .zip(self.attr("device/mem_info_vram_total")),
+                temperature,
+            },
+            Driver::I915 => Reading {
+                busy: None,
+                frequency: self.attr("gt_act_freq_mhz"),
+                // integrated, it shares the system memory
+                vram: None,
+                temperature,
+            },
+        }
+    }
+    fn attr<T: FromStr>(&self, path: &str) -> Option<T> {
+        read(&self.card.join(path))?.parse().ok()
+    }
+}
@@ -0,0 +1,72 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+const HWMON: &str = "/sys/class/hwmon";
+
+/// The `fan*_input` files of every hwmon device.
+#[derive(Debug, Default)]
+pub struct Fans {
+    inputs: Vec<(Rc<str>, PathBuf)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reading {
+    pub label: Rc<str>,
+    pub rpm: u32,
+}
+
+impl Fans {
+    pub fn scan() -> Self {
+        let Ok(devices) = fs::read_dir(HWMON) else {
+            return Self::default();
+        };
+        let mut inputs = vec![];
+        for device in devices.flatten() {
+            let dir = device.path();
+            let Ok(entries) = fs::read_dir(&dir) else {
+                continue;
+            };
+            let name = read(&dir.join("name")).unwrap_or_default();
+            for entry in entries.flatten() {
+                let file = entry.file_name();
+                let Some(n) = file
+                    .to_str()
+                    .and_then(|x| x.strip_prefix("fan")?.strip_suffix("_input"))
+                else {
+                    continue;
+                };
+                // drivers without labels number their fans from 1
+                let label = read(&dir.join(format!("fan{n}_label")))
+                    .unwrap_or_else(|| format!("{name} fan{n}"));
+                inputs.push((label.into(), entry.path()));
+            }
+        }
+        inputs.sort_unstable();
+        Self { inputs }
+    }
+    pub fn is_empty(&self) -> bool {
+        self.inputs.is_empty()
+    }
+    /// Fans that cannot be read, e.g. of a powered down GPU, are left out.
+    pub fn read(&self) -> Vec<Reading> {
+        self.inputs
+            .iter()
+            .filter_map(|(label, path)| {
+                Some(Reading {
+                    label: label.clone(),
+                    rpm: read(path)?.parse().ok()?,
+                })
+            })
+            .collect()
+    }
+}
+
+/// A sysfs attribute without its trailing newline.
+pub fn read(path: &Path) -> Option<String> {
+    let mut text = fs::read_to_string(path).ok()?;
+    text.truncate(text.trim_end().len());
+    Some(text)
+}
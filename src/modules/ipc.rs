@@ -0,0 +1,200 @@
+use std::{
+    fmt, fs,
+    io::{self, BufRead as _, BufReader, Write as _},
+    os::{linux::net::SocketAddrExt as _, unix::net},
+    path::{Path, PathBuf},
+    str::FromStr,
+    thread,
+    time::Duration,
+};
+
+use compio::{
+    BufResult,
+    io::{AsyncRead as _, AsyncWriteExt as _},
+    net::{UnixListener, UnixStream},
+};
+
+use hyoka_core::xdg_runtime_dir;
+
+use crate::modules::hyprland::Dispatcher;
+
+pub fn path() -> PathBuf {
+    xdg_runtime_dir().join("hyoka.sock")
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    ToggleVisibility,
+    /// frame timings and damage over the bars
+    ToggleHud,
+    Reload,
+    SetModule {
+        module: String,
+        enabled: bool,
+    },
+    Quit,
+    /// passed on as it is, like `hyprland movefocus l`
+    Hyprland(Dispatcher),
+}
+
+#[derive(Debug)]
+pub struct ParseError(String);
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl FromStr for Command {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut words = s.split_whitespace();
+        let command = match words.next() {
+            Some("toggle-visibility") => Command::ToggleVisibility,
+            Some("toggle-hud") => Command::ToggleHud,
+            Some("reload") => Command::Reload,
+            Some("set-module") => {
+                let (Some(module), Some(state)) = (words.next(), words.next()) else {
+                    return Err(ParseError(
+                        "usage: set-module <module> enabled|disabled".into(),
+                    ));
+                };
+                let enabled = match state {
+                    "enabled" => true,
+                    "disabled" => false,
+                    _ => return Err(ParseError(format!("unknown module state {state:?}"))),
+                };
+                Command::SetModule {
+                    module: module.into(),
+                    enabled,
+                }
+            }
+            Some("quit") => Command::Quit,
+            Some("hyprland") => {
+                let dispatcher = words.by_ref().collect::<Vec<_>>().join(" ");
+                if dispatcher.is_empty() {
+                    return Err(ParseError("usage: hyprland <dispatcher> [args]".into()));
+                }
+                Command::Hyprland(Dispatcher::Raw(dispatcher))
+            }
+            Some(command) => return Err(ParseError(format!("unknown command {command:?}"))),
+            None => return Err(ParseError("empty command".into())),
+        };
+        match words.next() {
+            Some(extra) => Err(ParseError(format!("unexpected argument {extra:?}"))),
+            None => Ok(command),
+        }
+    }
+}
+
+/// Reads one line, commands are tiny and a client that never finishes must not hold the socket.
+async fn read_command(stream: &mut UnixStream) -> io::Result<String> {
+    let mut line = Vec::new();
+    while !line.contains(&b'\n') && line.len() < 4096 {
+        let BufResult(n, buf) =
+            compio::time::timeout(Duration::from_secs(1), stream.read(Vec::with_capacity(256)))
+                .await
+                .map_err(|_| io::ErrorKind::TimedOut)?;
+        if n? == 0 {
+            break;
+        }
+        line.extend_from_slice(&buf);
+    }
+    String::from_utf8(line).map_err(|_| io::ErrorKind::InvalidData.into())
+}
+
+/// Serves the control socket at [`path`], each connection carries a single command.
+pub async fn serve(mut dispatch: impl AsyncFnMut(Command)) {
+    let path = path();
+    // whatever is left belongs to a dead instance, see `lock`
+    fs::remove_file(&path).ok();
+    let listener = match UnixListener::bind(&path).await {
+        Ok(x) => x,
+        Err(e) => {
+            tracing::warn!("cannot bind {path:?}: {e}");
+            return;
+        }
+    };
+    loop {
+        let mut stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(e) => {
+                tracing::warn!("cannot accept ipc connection: {e}");
+                continue;
+            }
+        };
+        let reply = match read_command(&mut stream).await {
+            Ok(line) => match line.parse::<Command>() {
+                Ok(command) => {
+                    dispatch(command).await;
+                    "ok\n".to_owned()
+                }
+                Err(e) => format!("error: {e}\n"),
+            },
+            Err(e) => format!("error: {e}\n"),
+        };
+        stream.write_all(reply.into_bytes()).await.0.ok();
+    }
+}
+
+fn request(path: &Path, command: &str) -> io::Result<String> {
+    let mut stream = net::UnixStream::connect(path)?;
+    writeln!(stream, "{command}")?;
+    let mut reply = String::new();
+    BufReader::new(stream).read_line(&mut reply)?;
+    Ok(reply)
+}
+
+/// `hyoka msg <command>`, sends `command` to the running instance and prints its reply.
+pub fn send(args: &[String]) -> i32 {
+    let path = path();
+    match request(&path, &args.join(" ")) {
+        Ok(reply) if reply.starts_with("ok") => 0,
+        Ok(reply) => {
+            eprint!("{reply}");
+            1
+        }
+        Err(e) => {
+            eprintln!("cannot talk to hyoka at {path:?}: {e}");
+            1
+        }
+    }
+}
+
+/// Held for the lifetime of the process, the kernel drops it together with the process.
+pub struct Lock(#[allow(dead_code)] net::UnixListener);
+
+fn try_lock() -> io::Result<Lock> {
+    let name = format!("hyoka-{}", rustix::process::getuid().as_raw());
+    let addr = net::SocketAddr::from_abstract_name(name)?;
+    Ok(Lock(net::UnixListener::bind_addr(&addr)?))
+}
+
+/// Makes sure only one bar runs per user, with `replace` the running one is asked to quit.
+pub fn lock(replace: bool) -> Option<Lock> {
+    match try_lock() {
+        Ok(lock) => return Some(lock),
+        Err(e) if e.kind() != io::ErrorKind::AddrInUse => {
+            tracing::error!("cannot take instance lock: {e}");
+            return None;
+        }
+        Err(_) if !replace => {
+            tracing::error!("hyoka is already running, use --replace to take over");
+            return None;
+        }
+        Err(_) => {}
+    }
+    if let Err(e) = request(&path(), "quit") {
+        tracing::warn!("cannot ask the running instance to quit: {e}");
+    }
+    for _ in 0..50 {
+        thread::sleep(Duration::from_millis(40));
+        if let Ok(lock) = try_lock() {
+            return Some(lock);
+        }
+    }
+    tracing::error!("running instance did not quit");
+    None
+}
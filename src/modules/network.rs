@@ -0,0 +1,81 @@
+use std::{fs, io};
+
+use compio::{BufResult, io::AsyncRead, net::UnixStream};
+use rustix::net::{
+    AddressFamily, SocketType,
+    netlink::{self, SocketAddrNetlink},
+};
+
+use crate::{
+    error::{Error, Result},
+    mapping::Mapping,
+};
+
+/// Interface name prefixes of WireGuard and OpenVPN style tunnels.
+const VPN_PREFIXES: [&str; 2] = ["wg", "tun"];
+/// `RTMGRP_LINK`, interfaces appearing, going up or down and disappearing
+const LINK_GROUP: u32 = 1;
+/// `IFF_UP`
+const UP: u32 = 1;
+
+/// The VPN interfaces that are up, by name.
+#[derive(Debug, PartialEq)]
+pub struct Vpn(pub Vec<String>);
+
+fn rtnetlink() -> io::Result<UnixStream> {
+    let fd = rustix::net::socket(
+        AddressFamily::NETLINK,
+        SocketType::RAW,
+        Some(netlink::ROUTE),
+    )?;
+    rustix::net::bind(&fd, &SocketAddrNetlink::new(0, LINK_GROUP))?;
+    let stream = std::os::unix::net::UnixStream::from(fd);
+    let stream = compio::net::UnixStream::from_std(stream)?;
+    Ok(stream)
+}
+
+/// Reports the VPN interfaces now and after every link change.
+pub async fn run(mut dispatch: impl AsyncFnMut(Vpn)) -> Result<()> {
+    let mut stream = rtnetlink()?;
+    let mut buf = Mapping::page()?;
+    loop {
+        dispatch(Vpn(vpn_interfaces())).await;
+        // any message is reason to look again, sysfs has the same in an easier shape
+        let BufResult(n, b) = stream.read(buf).await;
+        buf = b;
+        if n? == 0 {
+            return Err(Error::Closed("rtnetlink"));
+        }
+    }
+}
+
+/// Link quality of the first wireless interface, percent, `None` without one.
+pub fn wifi_quality() -> Option<u8> {
+    let table = fs::read_to_string("/proc/net/wireless").ok()?;
+    // two header lines, then `wlan0: 0000   54.  -56.  -256 ...`
+    let line = table.lines().nth(2)?;
+    let link = line.split_whitespace().nth(2)?;
+    let link: f32 = link.trim_end_matches('.').parse().ok()?;
+    // out of 70 for every cfg80211 driver
+    Some((link * 100.0 / 70.0).clamp(0.0, 100.0) as u8)
+}
+
+fn vpn_interfaces() -> Vec<String> {
+    let Ok(entries) = fs::read_dir("/sys/class/net") else {
+        return vec![];
+    };
+    let mut up: Vec<_> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            if !VPN_PREFIXES.iter().any(|x| name.starts_with(x)) {
+                return None;
+            }
+            let flags = fs::read_to_string(entry.path().join("flags")).ok()?;
+            let flags = u32::from_str_radix(flags.trim().trim_start_matches("0x"), 16).ok()?;
+            (flags & UP != 0).then_some(name)
+        })
+        .collect();
+    up.sort_unstable();
+    up
+}
@@ -0,0 +1,129 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    pin,
+};
+
+use futures::{StreamExt as _, stream};
+use iced::Color;
+use rustc_hash::FxHashMap;
+use serde::Deserialize;
+
+use crate::{
+    config::{self, Import, Palette},
+    modules::reload::file_changes,
+};
+
+/// Imported colors, they take precedence over the configured palettes.
+#[derive(Debug, Clone, Default)]
+pub struct Palettes {
+    pub dark: Option<Palette>,
+    pub light: Option<Palette>,
+}
+
+/// Generated files come with opaque backgrounds, the bar stays as translucent as the built-in
+/// palettes.
+const DARK_ALPHA: f32 = 0.38;
+const LIGHT_ALPHA: f32 = 0.55;
+
+/// Loads the imported palettes and again whenever the generator rewrites the file.
+pub async fn run(import: Option<&Import>, mut dispatch: impl AsyncFnMut(Palettes)) {
+    let Some(import) = import else {
+        return;
+    };
+    let path = import.path();
+    let changes = file_changes(&path)
+        .inspect_err(|e| tracing::warn!("cannot watch {path:?}: {e}"))
+        .ok();
+    let mut changes = pin::pin!(stream::iter(changes).flatten());
+    loop {
+        if let Some(palettes) = load(import, &path) {
+            dispatch(palettes).await;
+        }
+        if changes.next().await.is_none() {
+            return;
+        }
+    }
+}
+
+fn load(import: &Import, path: &Path) -> Option<Palettes> {
+    let data = fs::read(path)
+        .inspect_err(|e| tracing::warn!("cannot read {path:?}: {e}"))
+        .ok()?;
+    let res = match import {
+        Import::Pywal => serde_json::from_slice(&data).map(pywal),
+        Import::Matugen(_) => serde_json::from_slice(&data).map(matugen),
+    };
+    res.inspect_err(|e| tracing::warn!("cannot parse {path:?}: {e}"))
+        .ok()
+        .flatten()
+}
+
+/// `~/.cache/wal/colors.json`, one scheme used for both.
+#[derive(Deserialize)]
+struct Pywal {
+    special: FxHashMap<String, String>,
+    colors: FxHashMap<String, String>,
+}
+
+fn pywal(wal: Pywal) -> Option<Palettes> {
+    let special = |x: &str| Color::parse(wal.special.get(x)?);
+    let color = |x: &str| Color::parse(wal.colors.get(x)?);
+    let palette = Palette {
+        background: special("background")?,
+        text: special("foreground")?,
+        primary: color("color5")?,
+        success: color("color2")?,
+        warning: color("color3")?,
+        danger: color("color1")?,
+    };
+    Some(Palettes {
+        dark: Some(Palette {
+            background: palette.background.scale_alpha(DARK_ALPHA),
+            ..palette
+        }),
+        light: Some(Palette {
+            background: palette.background.scale_alpha(LIGHT_ALPHA),
+            ..palette
+        }),
+    })
+}
+
+/// As printed by `matugen --json hex`.
+#[derive(Deserialize)]
+struct Matugen {
+    colors: MatugenSchemes,
+}
+
+#[derive(Deserialize)]
+struct MatugenSchemes {
+    dark: FxHashMap<String, String>,
+    light: FxHashMap<String, String>,
+}
+
+fn matugen(matugen: Matugen) -> Option<Palettes> {
+    let palette = |scheme: &FxHashMap<String, String>, alpha| {
+        let color = |x: &str| Color::parse(scheme.get(x)?);
+        Some(Palette {
+            background: color("surface")?.scale_alpha(alpha),
+            text: color("on_surface")?,
+            primary: color("primary")?,
+            success: color("tertiary")?,
+            warning: color("secondary")?,
+            danger: color("error")?,
+        })
+    };
+    Some(Palettes {
+        dark: palette(&matugen.colors.dark, DARK_ALPHA),
+        light: palette(&matugen.colors.light, LIGHT_ALPHA),
+    })
+}
+
+impl Import {
+    fn path(&self) -> PathBuf {
+        match self {
+            Import::Pywal => config::xdg_cache_home().join("wal").join("colors.json"),
+            Import::Matugen(path) => path.clone(),
+        }
+    }
+}
@@ -0,0 +1,181 @@
+use std::{
+    ffi::{CStr, c_char, c_void},
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use futures::{
+    StreamExt as _,
+    channel::mpsc::{self, UnboundedReceiver, UnboundedSender},
+};
+use libloading::Library;
+use thiserror::Error;
+
+use crate::{
+    config::xdg_config_home,
+    modules::custom::{Action, Output},
+};
+
+/// Of the structs below, as in `include/hyoka_plugin.h`.
+const ABI: u32 = 1;
+
+/// An update from the plugin at `index` in the bar.
+#[derive(Debug)]
+pub struct Event {
+    pub index: usize,
+    pub output: Output,
+}
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error(transparent)]
+    Load(#[from] libloading::Error),
+    #[error("built for plugin ABI {0}, this is {ABI}")]
+    Abi(u32),
+    #[error("failed to initialize")]
+    Init,
+}
+
+/// Handed to the plugin, which may keep it until `deinit`.
+#[repr(C)]
+struct Host {
+    data: *const c_void,
+    /// Thread-safe, `output` is NUL-terminated and read like a custom module prints it.
+    update: unsafe extern "C" fn(data: *const c_void, output: *const c_char),
+}
+
+/// Returned by `hyoka_plugin`, static for as long as the library is loaded.
+#[repr(C)]
+struct VTable {
+    abi: u32,
+    name: *const c_char,
+    /// Returns the state passed to the others, null on failure.
+    init: unsafe extern "C" fn(host: *const Host) -> *mut c_void,
+    action: unsafe extern "C" fn(state: *mut c_void, action: u32),
+    /// `update` must not be called once it returns.
+    deinit: unsafe extern "C" fn(state: *mut c_void),
+}
+
+struct Updates {
+    index: usize,
+    sender: UnboundedSender<Event>,
+}
+
+/// A bar item from a shared library in [`dir`].
+///
+/// Fields drop in declaration order, the library is unloaded last.
+pub struct Plugin {
+    pub name: String,
+    vtable: *const VTable,
+    state: *mut c_void,
+    _host: Box<Host>,
+    _updates: Box<Updates>,
+    _library: Library,
+}
+
+impl Plugin {
+    /// # Safety
+    /// Runs the initializers of the library and its `init`, which can do anything.
+    unsafe fn load(
+        path: &Path,
+        index: usize,
+        sender: UnboundedSender<Event>,
+    ) -> Result<Self, Error> {
+        let library = unsafe { Library::new(path) }?;
+        let vtable =
+            unsafe { library.get::<unsafe extern "C" fn() -> *const VTable>(b"hyoka_plugin")?() };
+        let abi = unsafe { (*vtable).abi };
+        if abi != ABI {
+            return Err(Error::Abi(abi));
+        }
+        let name = unsafe { CStr::from_ptr((*vtable).name) }
+            .to_string_lossy()
+            .into_owned();
+        let updates = Box::new(Updates { index, sender });
+        let host = Box::new(Host {
+            data: (&raw const *updates).cast(),
+            update,
+        });
+        let state = unsafe { ((*vtable).init)(&*host) };
+        if state.is_null() {
+            return Err(Error::Init);
+        }
+        Ok(Self {
+            name,
+            vtable,
+            state,
+            _host: host,
+            _updates: updates,
+            _library: library,
+        })
+    }
+    pub fn action(&self, action: Action) {
+        let action = match action {
+            Action::Click => 0,
+            Action::MiddleClick => 1,
+            Action::RightClick => 2,
+            Action::ScrollUp => 3,
+            Action::ScrollDown => 4,
+        };
+        unsafe { ((*self.vtable).action)(self.state, action) };
+    }
+}
+
+impl Drop for Plugin {
+    fn drop(&mut self) {
+        unsafe { ((*self.vtable).deinit)(self.state) };
+    }
+}
+
+unsafe extern "C" fn update(data: *const c_void, output: *const c_char) {
+    let updates = unsafe { &*data.cast::<Updates>() };
+    let output = unsafe { CStr::from_ptr(output) }.to_string_lossy();
+    updates
+        .sender
+        .unbounded_send(Event {
+            index: updates.index,
+            output: Output::parse(&output),
+        })
+        .ok();
+}
+
+pub fn dir() -> PathBuf {
+    xdg_config_home().join("hyoka").join("plugins")
+}
+
+/// Every `.so` in [`dir`] which loads, by file name, with the updates they send.
+pub fn load() -> (Vec<Plugin>, UnboundedReceiver<Event>) {
+    let (sender, receiver) = mpsc::unbounded();
+    let dir = dir();
+    let mut paths: Vec<_> = match fs::read_dir(&dir) {
+        Ok(entries) => entries
+            .flatten()
+            .map(|x| x.path())
+            .filter(|x| x.extension().is_some_and(|x| x == "so"))
+            .collect(),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => vec![],
+        Err(e) => {
+            tracing::warn!("cannot read {dir:?}: {e}");
+            vec![]
+        }
+    };
+    paths.sort();
+    let mut plugins = vec![];
+    for path in paths {
+        match unsafe { Plugin::load(&path, plugins.len(), sender.clone()) } {
+            Ok(plugin) => {
+                tracing::info!("loaded plugin {:?} from {path:?}", plugin.name);
+                plugins.push(plugin);
+            }
+            Err(e) => tracing::warn!("cannot load plugin {path:?}: {e}"),
+        }
+    }
+    (plugins, receiver)
+}
+
+/// Passes on updates until every plugin is gone.
+pub async fn run(mut updates: UnboundedReceiver<Event>, mut dispatch: impl AsyncFnMut(Event)) {
+    while let Some(event) = updates.next().await {
+        dispatch(event).await;
+    }
+}
@@ -1,56 +1,202 @@
 use std::{
-    cell::RefCell,
-    rc::Rc,
-    time::{Duration, Instant},
+    pin,
+    time::{Duration, SystemTime},
 };
 
 use derive_more::From;
-use futures::{StreamExt as _, channel::mpsc::Receiver};
+use futures::{
+    StreamExt as _,
+    channel::mpsc::Receiver,
+    future::{self, Either},
+};
 
-use crate::modules::{
-    battery::{self, Battery},
-    clock::Clock,
+use crate::{
+    config::Config,
+    modules::{
+        battery::{self, Battery},
+        clock::Clock,
+        gpu::{self, Gpu},
+        hwmon::{self, Fans},
+        network,
+        procfs::{self, Cpu, Memory, Top},
+    },
 };
 
 #[derive(From, Debug)]
 pub enum Event {
     Clock(Clock),
     Battery(battery::Info),
+    BatteryLevel(battery::Level),
+    Fans(Vec<hwmon::Reading>),
+    Gpu(gpu::Reading),
+    /// percent
+    #[from(skip)]
+    Cpu(u8),
+    Memory(Memory),
+    Processes(Vec<procfs::Process>),
+    /// pids of the screen recorders running
+    #[from(skip)]
+    Recording(Vec<u32>),
+    /// link quality, percent
+    #[from(skip)]
+    Wifi(u8),
 }
 
 pub enum Signal {
-    Battery(Rc<Battery>),
-    BatteryStop,
+    /// the battery tooltip refreshes every second while open
+    BatteryTooltip(bool),
+    /// one was plugged in or out, the battery sensor looks for it again
+    BatteryPlugged,
+    /// stop ticking, e.g. while the system is suspended
+    Pause,
+    Resume,
+    /// no input for a while, the sensors are left alone meanwhile
+    Idle(bool),
+    /// whether any clock shows seconds, it ticks once a minute otherwise
+    Seconds(bool),
+    Sensors(Sensors),
+    /// the top processes popup refreshes every second while open
+    Processes {
+        sort: procfs::Sort,
+        count: usize,
+    },
+    ProcessesStop,
 }
 
-pub async fn run(signals: &mut Receiver<Signal>, mut dispatch: impl AsyncFnMut(Event)) {
-    let interval = Duration::from_secs(1);
-    let mut timer = compio::time::interval_at(Instant::now() + interval, interval);
-    let battery = Rc::new(RefCell::new(None));
-    let bat = battery.clone();
-    std::future::join!(
-        async {
-            loop {
-                match signals.next().await.unwrap() {
-                    Signal::Battery(x) => {
-                        battery.replace(Some(x));
-                    }
-                    Signal::BatteryStop => {
-                        battery.replace(None);
-                    }
+/// What is read on every tick besides the clock, for the modules on any bar.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sensors {
+    /// its level is sampled on every tick, not every battery sends uevents as it drains
+    pub battery: bool,
+    pub fans: bool,
+    pub gpu: bool,
+    pub cpu: bool,
+    pub memory: bool,
+    /// looked for every second, ticking that often as well
+    pub recording: bool,
+    /// for the signal history in the network tooltip
+    pub wifi: bool,
+}
+
+impl Sensors {
+    pub fn of(config: &Config) -> Self {
+        Self {
+            battery: config.uses("battery"),
+            fans: config.uses("fans"),
+            gpu: config.uses("gpu"),
+            cpu: config.uses("cpu"),
+            memory: config.uses("memory"),
+            recording: config.uses("recording"),
+            wifi: config.uses("vpn"),
+        }
+    }
+}
+
+const SECOND: Duration = Duration::from_secs(1);
+const MINUTE: Duration = Duration::from_secs(60);
+
+pub async fn run(
+    seconds: bool,
+    sensors: Sensors,
+    signals: &mut Receiver<Signal>,
+    mut dispatch: impl AsyncFnMut(Event),
+) {
+    let mut sensors_battery = sensors.battery;
+    let mut battery = sensors.battery.then(Battery::new).flatten();
+    let mut battery_tooltip = false;
+    let mut paused = false;
+    let mut idle = false;
+    let mut seconds = seconds;
+    let mut fans = sensors.fans.then(Fans::scan);
+    let mut gpu = sensors.gpu.then(Gpu::scan).flatten();
+    let mut cpu = sensors.cpu.then(Cpu::default);
+    let mut memory = sensors.memory;
+    let mut recording = sensors.recording;
+    let mut wifi = sensors.wifi;
+    let mut top = None;
+    loop {
+        let period = if seconds
+            || (battery_tooltip && battery.is_some())
+            || top.is_some()
+            || (recording && !idle)
+        {
+            SECOND
+        } else {
+            MINUTE
+        };
+        let tick = pin::pin!(compio::time::sleep(until_next(period)));
+        match future::select(signals.next(), tick).await {
+            Either::Left((signal, _)) => match signal.unwrap() {
+                Signal::BatteryTooltip(x) => battery_tooltip = x,
+                Signal::BatteryPlugged => {
+                    battery = sensors_battery.then(Battery::new).flatten();
+                }
+                Signal::Pause => paused = true,
+                Signal::Resume => paused = false,
+                Signal::Idle(x) => idle = x,
+                Signal::Seconds(x) => seconds = x,
+                Signal::Sensors(x) => {
+                    sensors_battery = x.battery;
+                    battery = x.battery.then(Battery::new).flatten();
+                    fans = x.fans.then(Fans::scan);
+                    gpu = x.gpu.then(Gpu::scan).flatten();
+                    cpu = x.cpu.then(Cpu::default);
+                    memory = x.memory;
+                    recording = x.recording;
+                    wifi = x.wifi;
+                }
+                Signal::Processes { sort, count } => top = Some(Top::new(sort, count)),
+                Signal::ProcessesStop => top = None,
+            },
+            Either::Right(((), _)) => {
+                if paused {
+                    continue;
                 }
-            }
-        },
-        async {
-            loop {
-                timer.tick().await;
                 dispatch(Clock::now().into()).await;
 
-                if let Some(bat) = bat.borrow().as_ref() {
-                    dispatch(bat.info().into()).await;
+                if let Some(bat) = &battery {
+                    dispatch(bat.level().into()).await;
+                    if battery_tooltip {
+                        dispatch(bat.info().into()).await;
+                    }
+                }
+                if idle {
+                    continue;
+                }
+                if let Some(fans) = &fans
+                    && !fans.is_empty()
+                {
+                    dispatch(fans.read().into()).await;
+                }
+                if let Some(gpu) = &gpu {
+                    dispatch(gpu.read().into()).await;
+                }
+                if let Some(percent) = cpu.as_mut().and_then(Cpu::sample) {
+                    dispatch(Event::Cpu(percent)).await;
+                }
+                if memory && let Some(memory) = Memory::read() {
+                    dispatch(memory.into()).await;
+                }
+                if let Some(top) = &mut top {
+                    dispatch(top.sample().into()).await;
+                }
+                if recording {
+                    dispatch(Event::Recording(procfs::recorders())).await;
+                }
+                if wifi && let Some(quality) = network::wifi_quality() {
+                    dispatch(Event::Wifi(quality)).await;
                 }
             }
-        },
-    )
-    .await;
+        }
+    }
+}
+
+/// Until the wall clock crosses the next multiple of `period`, a little past it so the clock
+/// never shows the second or minute just ending.
+fn until_next(period: Duration) -> Duration {
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default();
+    let elapsed = now.as_nanos() % period.as_nanos();
+    period - Duration::from_nanos(elapsed as u64) + Duration::from_millis(2)
 }
@@ -0,0 +1,183 @@
+use std::{fs, time::Instant};
+
+use rustc_hash::FxHashMap;
+
+/// Screen recorders stopped by SIGINT, which makes them finish the file first.
+const RECORDERS: [&str; 3] = ["wf-recorder", "wl-screenrec", "gpu-screen-recorder"];
+
+/// Share of all CPUs busy between two samples of `/proc/stat`.
+#[derive(Debug, Default)]
+pub struct Cpu {
+    busy: u64,
+    total: u64,
+}
+
+impl Cpu {
+    /// Percent since the previous sample, since boot for the first one.
+    pub fn sample(&mut self) -> Option<u8> {
+        let stat = fs::read_to_string("/proc/stat").ok()?;
+        let times: Vec<u64> = stat
+            .lines()
+            .next()?
+            .strip_prefix("cpu ")?
+            .split_ascii_whitespace()
+            // guest and guest_nice are counted in user and nice already
+            .take(8)
+            .filter_map(|x| x.parse().ok())
+            .collect();
+        // idle and iowait
+        let idle = times.get(3)? + times.get(4).unwrap_or(&0);
+        let total = times.iter().sum::<u64>();
+        let busy = total - idle;
+        // iowait may go backwards, see proc(5)
+        let busy_delta = busy.saturating_sub(self.busy);
+        let total_delta = total.saturating_sub(self.total);
+        self.busy = busy;
+        self.total = total;
+        (total_delta > 0).then(|| (busy_delta * 100 / total_delta).min(100) as u8)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Memory {
+    /// bytes neither free nor reclaimable
+    pub used: u64,
+    pub total: u64,
+}
+
+impl Memory {
+    pub fn read() -> Option<Self> {
+        let meminfo = fs::read_to_string("/proc/meminfo").ok()?;
+        let field = |name: &str| -> Option<u64> {
+            let line = meminfo.lines().find_map(|x| x.strip_prefix(name))?;
+            let kib = line.trim_start_matches(':').trim().trim_end_matches(" kB");
+            Some(kib.parse::<u64>().ok()? * 1024)
+        };
+        let total = field("MemTotal")?;
+        Some(Self {
+            used: total - field("MemAvailable")?,
+            total,
+        })
+    }
+    pub fn percent(&self) -> u8 {
+        (self.used * 100 / self.total.max(1)) as u8
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Sort {
+    Cpu,
+    Memory,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Process {
+    pub pid: u32,
+    pub name: String,
+    /// percent of one CPU since the previous sample
+    pub cpu: f32,
+    /// resident set size in bytes
+    pub rss: u64,
+}
+
+/// The processes using the most CPU or memory, from `/proc/*/stat`.
+#[derive(Debug)]
+pub struct Top {
+    sort: Sort,
+    count: usize,
+    /// user and system ticks by pid, as of `sampled`
+    ticks: FxHashMap<u32, u64>,
+    sampled: Instant,
+}
+
+impl Top {
+    pub fn new(sort: Sort, count: usize) -> Self {
+        Self {
+            sort,
+            count,
+            ticks: FxHashMap::default(),
+            sampled: Instant::now(),
+        }
+    }
+    /// CPU usage is unknown for processes the previous sample missed, and reads 0.
+    pub fn sample(&mut self) -> Vec<Process> {
+        let Ok(entries) = fs::read_dir("/proc") else {
+            return vec![];
+        };
+        let now = Instant::now();
+        let hz = rustix::param::clock_ticks_per_second() as f32;
+        let elapsed = now.duration_since(self.sampled).as_secs_f32() * hz;
+        let page = rustix::param::page_size() as u64;
+        let mut ticks = FxHashMap::default();
+        let mut processes: Vec<_> = entries
+            .flatten()
+            .filter_map(|entry| {
+                let pid = entry.file_name().to_str()?.parse().ok()?;
+                // gone meanwhile
+                let stat = fs::read_to_string(entry.path().join("stat")).ok()?;
+                let stat = parse(&stat)?;
+                ticks.insert(pid, stat.ticks);
+                let cpu = match self.ticks.get(&pid) {
+                    Some(&previous) if elapsed > 0.0 => {
+                        stat.ticks.saturating_sub(previous) as f32 * 100.0 / elapsed
+                    }
+                    _ => 0.0,
+                };
+                Some(Process {
+                    pid,
+                    name: stat.name.to_owned(),
+                    cpu,
+                    rss: stat.rss * page,
+                })
+            })
+            .collect();
+        self.ticks = ticks;
+        self.sampled = now;
+        match self.sort {
+            Sort::Cpu => processes.sort_unstable_by(|a, b| b.cpu.total_cmp(&a.cpu)),
+            Sort::Memory => processes.sort_unstable_by_key(|x| u64::MAX - x.rss),
+        }
+        processes.truncate(self.count);
+        processes
+    }
+}
+
+struct Stat<'a> {
+    name: &'a str,
+    ticks: u64,
+    /// pages
+    rss: u64,
+}
+
+/// `pid (comm) state ppid ...`, comm may contain anything up to the last parenthesis.
+fn parse(stat: &str) -> Option<Stat<'_>> {
+    let (name, rest) = stat.split_once('(')?.1.rsplit_once(')')?;
+    // from the state on, the third field of proc_pid_stat(5)
+    let fields: Vec<&str> = rest.split_ascii_whitespace().collect();
+    let field = |n: usize| fields.get(n - 3)?.parse::<u64>().ok();
+    Some(Stat {
+        name,
+        ticks: field(14)? + field(15)?,
+        rss: field(24)?,
+    })
+}
+
+/// Pids of the running screen recorders.
+pub fn recorders() -> Vec<u32> {
+    let Ok(entries) = fs::read_dir("/proc") else {
+        return vec![];
+    };
+    entries
+        .flatten()
+        .filter_map(|entry| {
+            let pid = entry.file_name().to_str()?.parse().ok()?;
+            let comm = fs::read_to_string(entry.path().join("comm")).ok()?;
+            let comm = comm.trim_end();
+            // cut to 15 bytes by the kernel
+            RECORDERS
+                .iter()
+                .any(|x| x.get(..comm.len()) == Some(comm) && comm.len() >= x.len().min(15))
+                .then_some(pid)
+        })
+        .collect()
+}
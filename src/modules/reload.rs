@@ -0,0 +1,87 @@
+use std::{io, mem, path::Path, pin};
+
+use compio::{BufResult, io::AsyncRead as _, runtime::fd::AsyncFd};
+use futures::{Stream, StreamExt as _, stream};
+use rustix::{
+    fs::inotify::{self, CreateFlags, WatchFlags},
+    process::Signal,
+};
+
+use crate::config::{self, Config};
+
+/// Size of `struct inotify_event` without the trailing name.
+const EVENT_HEADER: usize = 16;
+
+fn sighup() -> impl Stream<Item = ()> {
+    crate::stream(async gen {
+        while compio::signal::unix::signal(Signal::HUP.as_raw())
+            .await
+            .inspect_err(|e| tracing::warn!("cannot wait for SIGHUP: {e}"))
+            .is_ok()
+        {
+            yield;
+        }
+    })
+}
+
+/// Watches the directory instead of the file, editors tend to replace files by renaming.
+pub fn file_changes(path: &Path) -> io::Result<impl Stream<Item = ()> + use<>> {
+    let (Some(dir), Some(name)) = (path.parent(), path.file_name()) else {
+        return Err(io::ErrorKind::InvalidInput.into());
+    };
+    let name = name.as_encoded_bytes().to_vec();
+    let fd = inotify::init(CreateFlags::CLOEXEC)?;
+    inotify::add_watch(
+        &fd,
+        dir,
+        WatchFlags::CLOSE_WRITE | WatchFlags::MOVED_TO | WatchFlags::CREATE,
+    )?;
+    let mut fd = AsyncFd::new(fd)?;
+    Ok(crate::stream(async gen move {
+        let mut buf = Vec::with_capacity(4096);
+        loop {
+            let BufResult(n, b) = fd.read(mem::take(&mut buf)).await;
+            buf = b;
+            let n = match n {
+                Ok(n) => n,
+                Err(e) => {
+                    tracing::warn!("cannot read inotify events: {e}");
+                    break;
+                }
+            };
+            let mut events = &buf[..n];
+            let mut changed = false;
+            while events.len() >= EVENT_HEADER {
+                let len = u32::from_ne_bytes(events[12..16].try_into().unwrap()) as usize;
+                let Some(file) = events.get(EVENT_HEADER..EVENT_HEADER + len) else {
+                    break;
+                };
+                // the name is padded with nul bytes
+                let file = file.split(|&x| x == 0).next().unwrap_or_default();
+                changed |= file == name.as_slice();
+                events = &events[EVENT_HEADER + len..];
+            }
+            if changed {
+                yield;
+            }
+            buf.clear();
+        }
+    }))
+}
+
+/// Loads the configuration again on `SIGHUP` and whenever the file is written.
+///
+/// A broken file is reported and otherwise ignored, the running configuration stays.
+pub async fn run(mut dispatch: impl AsyncFnMut(Config)) {
+    let path = config::path();
+    let changes = file_changes(&path)
+        .inspect_err(|e| tracing::warn!("cannot watch {path:?}: {e}"))
+        .ok();
+    let mut events = pin::pin!(stream::select(sighup(), stream::iter(changes).flatten()));
+    while let Some(()) = events.next().await {
+        if let Some(config) = config::try_load() {
+            tracing::info!("reloading {path:?}");
+            dispatch(config).await;
+        }
+    }
+}
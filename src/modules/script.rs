@@ -0,0 +1,164 @@
+use std::{
+    fs,
+    pin::pin,
+    time::{Duration, Instant},
+};
+
+use futures::{
+    StreamExt as _,
+    channel::mpsc::Receiver,
+    future::{self, Either},
+};
+use rhai::{AST, CallFnOptions, Dynamic, Engine, EvalAltResult, Map, Scope};
+
+use crate::{
+    config,
+    modules::custom::{Action, Output},
+    spawn,
+};
+
+/// Per call into a script, one that runs away gets stopped instead of freezing the bar.
+const MAX_OPERATIONS: u64 = 1_000_000;
+
+/// What `view` of the script at `index` returned.
+#[derive(Debug)]
+pub struct Event {
+    pub index: usize,
+    pub output: Output,
+}
+
+struct Script {
+    ast: AST,
+    /// `this` of every call, where the script keeps its state
+    state: Dynamic,
+    interval: Option<Duration>,
+    due: Option<Instant>,
+}
+
+impl Script {
+    fn call(&mut self, engine: &Engine, name: &str) -> Option<Dynamic> {
+        if !self.ast.iter_functions().any(|x| x.name == name) {
+            return None;
+        }
+        let options = CallFnOptions::new()
+            .eval_ast(false)
+            .bind_this_ptr(&mut self.state);
+        engine
+            .call_fn_with_options(options, &mut Scope::new(), &self.ast, name, ())
+            .inspect_err(|e| tracing::warn!("script {name} failed: {e}"))
+            .ok()
+    }
+    fn view(&mut self, engine: &Engine) -> Option<Output> {
+        let output = self.call(engine, "view")?;
+        if output.is_string() {
+            return Some(Output {
+                text: output.into_string().ok()?,
+                ..Default::default()
+            });
+        }
+        rhai::serde::from_dynamic(&output)
+            .inspect_err(|e| tracing::warn!("cannot read what a script view returned: {e}"))
+            .ok()
+    }
+}
+
+fn engine() -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+    engine.register_fn("spawn", |command: &str| {
+        spawn::spawn("sh", ["-c", command], None)
+            .inspect_err(|e| tracing::warn!("cannot spawn {command:?}: {e}"))
+            .ok();
+    });
+    engine.register_fn(
+        "read_file",
+        |path: &str| -> Result<String, Box<EvalAltResult>> {
+            fs::read_to_string(path).map_err(|e| format!("cannot read {path:?}: {e}").into())
+        },
+    );
+    engine
+}
+
+fn load(engine: &Engine, config: &config::Script) -> Option<Script> {
+    let path = config::path().with_file_name(&config.path);
+    let ast = engine
+        .compile_file(path.clone())
+        .inspect_err(|e| tracing::warn!("cannot load script {path:?}: {e}"))
+        .ok()?;
+    let interval = config.interval.map(Duration::from_secs);
+    let mut script = Script {
+        ast,
+        state: Map::new().into(),
+        interval,
+        // right after the first view
+        due: interval.map(|x| Instant::now() + x),
+    };
+    script.call(engine, "init");
+    Some(script)
+}
+
+/// Calls `view` of each script every `interval`, and after any of its handlers.
+///
+/// An action on the script at `index` calls `on_click`, `on_middle_click`, `on_right_click`,
+/// `on_scroll_up` or `on_scroll_down`, whichever it defines.
+pub async fn run(
+    config: &[config::Script],
+    actions: &mut Receiver<(usize, Action)>,
+    mut dispatch: impl AsyncFnMut(Event),
+) {
+    let engine = engine();
+    let mut scripts: Vec<_> = config.iter().map(|x| load(&engine, x)).collect();
+    for (index, script) in scripts.iter_mut().enumerate() {
+        if let Some(output) = script.as_mut().and_then(|x| x.view(&engine)) {
+            dispatch(Event { index, output }).await;
+        }
+    }
+    loop {
+        let next = scripts.iter().flatten().filter_map(|x| x.due).min();
+        let tick = async {
+            match next {
+                Some(due) => {
+                    compio::time::sleep(due.saturating_duration_since(Instant::now())).await
+                }
+                None => future::pending().await,
+            }
+        };
+        match future::select(pin!(tick), actions.next()).await {
+            Either::Left(_) => {
+                let now = Instant::now();
+                for (index, script) in scripts.iter_mut().enumerate() {
+                    let Some(script) = script else {
+                        continue;
+                    };
+                    let (Some(due), Some(interval)) = (script.due, script.interval) else {
+                        continue;
+                    };
+                    if due > now {
+                        continue;
+                    }
+                    script.due = Some(now + interval);
+                    if let Some(output) = script.view(&engine) {
+                        dispatch(Event { index, output }).await;
+                    }
+                }
+            }
+            Either::Right((Some((index, action)), _)) => {
+                let Some(Some(script)) = scripts.get_mut(index) else {
+                    continue;
+                };
+                let handler = match action {
+                    Action::Click => "on_click",
+                    Action::MiddleClick => "on_middle_click",
+                    Action::RightClick => "on_right_click",
+                    Action::ScrollUp => "on_scroll_up",
+                    Action::ScrollDown => "on_scroll_down",
+                };
+                script.call(&engine, handler);
+                if let Some(output) = script.view(&engine) {
+                    dispatch(Event { index, output }).await;
+                }
+            }
+            Either::Right((None, _)) => return,
+        }
+    }
+}
@@ -0,0 +1,99 @@
+use std::time::{Duration, Instant};
+
+/// Counts a configured duration down, pomodoro style.
+#[derive(Debug, Default)]
+pub enum Countdown {
+    #[default]
+    Idle,
+    Running {
+        until: Instant,
+    },
+    Paused {
+        left: Duration,
+    },
+    /// until reset or started again
+    Expired,
+}
+
+impl Countdown {
+    /// Starts counting `duration` down, or pauses and resumes once started.
+    pub fn toggle(&mut self, duration: Duration) {
+        let now = Instant::now();
+        *self = match *self {
+            Countdown::Idle | Countdown::Expired => Countdown::Running {
+                until: now + duration,
+            },
+            Countdown::Running { until } => Countdown::Paused {
+                left: until.saturating_duration_since(now),
+            },
+            Countdown::Paused { left } => Countdown::Running { until: now + left },
+        };
+    }
+    pub fn reset(&mut self) {
+        *self = Countdown::Idle;
+    }
+    pub fn running(&self) -> bool {
+        matches!(self, Countdown::Running { .. })
+    }
+    /// Whether the time ran out since the last call.
+    pub fn tick(&mut self) -> bool {
+        match *self {
+            Countdown::Running { until } if until <= Instant::now() => {
+                *self = Countdown::Expired;
+                true
+            }
+            _ => false,
+        }
+    }
+    /// `None` before it is started.
+    pub fn left(&self) -> Option<Duration> {
+        match *self {
+            Countdown::Idle => None,
+            Countdown::Running { until } => Some(until.saturating_duration_since(Instant::now())),
+            Countdown::Paused { left } => Some(left),
+            Countdown::Expired => Some(Duration::ZERO),
+        }
+    }
+}
+
+/// `m:ss`, or `h:mm:ss` from an hour on.
+pub fn format(duration: Duration) -> String {
+    // rounded up, a fresh 25 minute countdown shows 25:00 rather than 24:59
+    let secs = duration.as_millis().div_ceil(1000);
+    let (h, m, s) = (secs / 3600, secs / 60 % 60, secs % 60);
+    if h > 0 {
+        format!("{h}:{m:02}:{s:02}")
+    } else {
+        format!("{m}:{s:02}")
+    }
+}
+
+/// Counts up while running, keeping what it counted while stopped.
+#[derive(Debug, Default)]
+pub struct Stopwatch {
+    since: Option<Instant>,
+    /// before `since`
+    elapsed: Duration,
+}
+
+impl Stopwatch {
+    pub fn toggle(&mut self) {
+        match self.since.take() {
+            Some(since) => self.elapsed += since.elapsed(),
+            None => self.since = Some(Instant::now()),
+        }
+    }
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+    pub fn running(&self) -> bool {
+        self.since.is_some()
+    }
+    /// `None` before it is started.
+    pub fn elapsed(&self) -> Option<Duration> {
+        match self.since {
+            Some(since) => Some(self.elapsed + since.elapsed()),
+            None => (!self.elapsed.is_zero()).then_some(self.elapsed),
+        }
+    }
+}
@@ -1,6 +1,7 @@
 use std::{io, mem::MaybeUninit, os::fd::OwnedFd};
 
-use compio::{io::AsyncRead, net::UnixStream};
+use compio::{BufResult, io::AsyncRead, net::UnixStream};
+use hyoka_core::uevent::Message;
 use rustix::{
     fs::{Mode, OFlags},
     net::{
@@ -9,7 +10,11 @@ use rustix::{
     },
 };
 
-use crate::{mapping::Mapping, modules::battery};
+use crate::{
+    error::{Error, Result},
+    mapping::Mapping,
+    modules::battery,
+};
 
 #[derive(Debug)]
 pub enum Event {
@@ -17,6 +22,8 @@ pub enum Event {
     PowerOffline,
     BatCapacity(u8),
     BatStatus(battery::Status),
+    /// one was plugged in or out, like an external battery
+    BatPlugged,
 }
 
 fn uevent() -> io::Result<UnixStream> {
@@ -27,41 +34,50 @@ fn uevent() -> io::Result<UnixStream> {
     )?;
     rustix::net::bind(&fd, &SocketAddrNetlink::new(0, 1))?;
     let stream = std::os::unix::net::UnixStream::from(fd);
-    let stream = compio::net::UnixStream::from_std(stream).unwrap();
+    let stream = compio::net::UnixStream::from_std(stream)?;
     Ok(stream)
 }
 
-pub fn new() -> Listener {
-    Listener {
-        stream: uevent().unwrap(),
-    }
+pub fn new() -> io::Result<Listener> {
+    Ok(Listener {
+        stream: uevent()?,
+        battery: battery::Battery::new().is_some(),
+    })
 }
 
 pub struct Listener {
     stream: UnixStream,
+    /// the adapter going online or offline only matters with a battery, desktops have none
+    battery: bool,
 }
 
 impl Listener {
-    pub async fn serve(mut self, dispatch: impl AsyncFnMut(Event) + Clone) {
+    pub async fn serve(mut self, dispatch: impl AsyncFnMut(Event) + Clone) -> Result<()> {
         #[derive(Debug)]
         enum Subsystem {
             Backlight,
             PowerSupply,
         }
-        let mut buf = Mapping::page().unwrap();
+        let mut buf = Mapping::page()?;
         let cb = dispatch;
         let mut dispatch = cb.clone();
         loop {
-            let n;
-            (n, buf) = self.stream.read(buf).await.unwrap();
-            let msg = unsafe { buf.as_bytes().get_unchecked(..n) };
-            let (_, body) = parse_message(msg).unwrap();
+            let BufResult(n, b) = self.stream.read(buf).await;
+            buf = b;
+            let n = n?;
+            if n == 0 {
+                return Err(Error::Closed("uevent"));
+            }
+            let Some(message) = Message::parse(&buf.as_bytes()[..n]) else {
+                continue;
+            };
+            let action = message.header.split_once('@').map(|(action, _)| action);
             let mut subsystem = None;
             let mut is_battery = false;
             let mut ac_online = None;
             let mut capacity = None;
             let mut status = None;
-            for (k, v) in body {
+            for (k, v) in message.properties() {
                 match k {
                     "SUBSYSTEM" => match v {
                         "backlight" => subsystem = Some(Subsystem::Backlight),
@@ -77,7 +93,7 @@ impl Listener {
                         _ => ac_online = Some(true),
                     },
                     "POWER_SUPPLY_CAPACITY" => {
-                        capacity = Some(u8::from_ascii(v.as_bytes()).unwrap());
+                        capacity = u8::from_ascii(v.as_bytes()).ok();
                     }
                     "POWER_SUPPLY_STATUS" => {
                         status = Some(battery::Status::from_bytes(v.as_bytes()));
@@ -87,14 +103,17 @@ impl Listener {
             }
             match subsystem {
                 Some(Subsystem::PowerSupply) => {
-                    if is_battery {
+                    if is_battery && let Some("add" | "remove") = action {
+                        self.battery = battery::Battery::new().is_some();
+                        dispatch(Event::BatPlugged).await
+                    } else if is_battery {
                         if let Some(x) = capacity {
                             dispatch(Event::BatCapacity(x)).await
                         }
                         if let Some(x) = status {
                             dispatch(Event::BatStatus(x)).await
                         }
-                    } else {
+                    } else if self.battery {
                         if let Some(x) = ac_online {
                             dispatch(if x {
                                 Event::PowerOnline
@@ -113,15 +132,6 @@ impl Listener {
     }
 }
 
-fn parse_message(data: &[u8]) -> Option<(&str, impl Iterator<Item = (&str, &str)>)> {
-    let mut lines = data.split(|&x| x == 0);
-    let head = unsafe { str::from_utf8_unchecked(lines.next()?) };
-    let lines = lines
-        .map_while(|x| x.split_once(|&x| x == b'='))
-        .map(|(k, v)| unsafe { (str::from_utf8_unchecked(k), str::from_utf8_unchecked(v)) });
-    Some((head, lines))
-}
-
 #[allow(dead_code)]
 fn backlight() -> Option<OwnedFd> {
     let fd = rustix::fs::open(c"/sys/class/backlight", OFlags::empty(), Mode::empty()).ok()?;
@@ -0,0 +1,76 @@
+use std::{mem, pin::pin};
+
+use compio::process::Command;
+use futures::{
+    StreamExt as _,
+    channel::mpsc::Receiver,
+    future::{self, Either},
+};
+
+use crate::config;
+
+#[derive(Debug)]
+pub struct Updates {
+    /// one `name old -> new` line per package
+    pub packages: Vec<String>,
+}
+
+pub enum Signal {
+    Refresh,
+    /// no input for a while, checks wait until there is again
+    Idle(bool),
+}
+
+pub async fn run(
+    config: &config::Updates,
+    signals: &mut Receiver<Signal>,
+    mut dispatch: impl AsyncFnMut(Updates),
+) {
+    let mut timer = compio::time::interval(config.interval());
+    let mut idle = false;
+    // a check skipped while idle, made up for on return
+    let mut missed = false;
+    loop {
+        match future::select(pin!(timer.tick()), signals.next()).await {
+            Either::Left(_) if idle => {
+                missed = true;
+                continue;
+            }
+            Either::Left(_) | Either::Right((Some(Signal::Refresh), _)) => {}
+            Either::Right((Some(Signal::Idle(x)), _)) => {
+                idle = x;
+                if idle || !mem::take(&mut missed) {
+                    continue;
+                }
+            }
+            Either::Right((None, _)) => return,
+        }
+        let mut packages = vec![];
+        lines("checkupdates", &[], &mut packages).await;
+        if let Some(aur) = &config.aur {
+            lines(aur, &["-Qua"], &mut packages).await;
+        }
+        dispatch(Updates { packages }).await;
+    }
+}
+
+async fn lines(program: &str, args: &[&str], result: &mut Vec<String>) {
+    // checkupdates exits with 2 and `-Qua` with 1 when there is nothing to update,
+    // so only the output matters
+    let output = match Command::new(program).args(args).output().await {
+        Ok(x) => x,
+        Err(e) => {
+            tracing::warn!("cannot run {program}: {e}");
+            return;
+        }
+    };
+    let Ok(stdout) = String::from_utf8(output.stdout) else {
+        return;
+    };
+    result.extend(
+        stdout
+            .lines()
+            .filter(|x| !x.is_empty())
+            .map(ToOwned::to_owned),
+    );
+}
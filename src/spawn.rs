@@ -0,0 +1,93 @@
+//! Starting programs apart from the bar, so they outlive it and keep nothing of it.
+
+use std::{
+    env,
+    ffi::OsStr,
+    io,
+    os::unix::process::CommandExt as _,
+    process::{Child, Command, Stdio},
+};
+
+/// Ours only, or stale for anything we start.
+const SCRUBBED: [&str; 13] = [
+    "DESKTOP_STARTUP_ID",
+    "XDG_ACTIVATION_TOKEN",
+    "WAYLAND_SOCKET",
+    "NOTIFY_SOCKET",
+    "LISTEN_PID",
+    "LISTEN_FDS",
+    "LISTEN_FDNAMES",
+    "WATCHDOG_PID",
+    "WATCHDOG_USEC",
+    "JOURNAL_STREAM",
+    "INVOCATION_ID",
+    "RUST_LOG",
+    "RUST_BACKTRACE",
+];
+
+/// In a session of its own, from the home directory, with stdio on `/dev/null`, no fds of ours
+/// and `token` from xdg activation under both names apps look for it.
+fn command(
+    program: impl AsRef<OsStr>,
+    args: impl IntoIterator<Item = impl AsRef<OsStr>>,
+    token: Option<&str>,
+) -> Command {
+    let mut command = Command::new(program);
+    command
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    for name in SCRUBBED {
+        command.env_remove(name);
+    }
+    if let Some(token) = token {
+        command
+            .env("XDG_ACTIVATION_TOKEN", token)
+            .env("DESKTOP_STARTUP_ID", token);
+    }
+    if let Some(home) = env::var_os("HOME") {
+        command.current_dir(home);
+    }
+    // between fork and exec, only async-signal-safe calls
+    unsafe {
+        command.pre_exec(|| {
+            if libc::setsid() == -1 {
+                return Err(io::Error::last_os_error());
+            }
+            // std reports a failed exec through a pipe marked close-on-exec already
+            libc::close_range(3, u32::MAX, libc::CLOSE_RANGE_CLOEXEC as _);
+            Ok(())
+        })
+    };
+    command
+}
+
+/// Starts `program` detached, it is reparented away from the bar right away.
+///
+/// Forks twice, the child in between exits once the grandchild got to exec.
+pub fn spawn(
+    program: impl AsRef<OsStr>,
+    args: impl IntoIterator<Item = impl AsRef<OsStr>>,
+    token: Option<&str>,
+) -> io::Result<()> {
+    let mut command = command(program, args, token);
+    unsafe {
+        command.pre_exec(|| match libc::fork() {
+            -1 => Err(io::Error::last_os_error()),
+            0 => Ok(()),
+            _ => libc::_exit(0),
+        })
+    };
+    command.spawn()?.wait()?;
+    Ok(())
+}
+
+/// Like [`spawn`], but it stays our child for someone to wait on.
+pub fn spawn_attached(
+    program: impl AsRef<OsStr>,
+    args: impl IntoIterator<Item = impl AsRef<OsStr>>,
+    token: Option<&str>,
+) -> io::Result<Child> {
+    command(program, args, token).spawn()
+}